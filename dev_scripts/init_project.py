@@ -28,11 +28,13 @@ def init_env_file() -> None:
         "SECRET_KEY",
         "SECRET_KEY_SALT",
         "CSRF_KEY_SALT",
+        "SECRET_KEYRING",
         "BLOG_ADMIN_USERNAME",
         "BLOG_ADMIN_EMAIL",
         "BLOG_ADMIN_PASSWORD",
         "DB_ENCRYPTION_KEY",
         "DB_ENCRYPTION_KEY_AAD",
+        "DB_ENCRYPTION_KEYRING",
     ]
     with open(env_file_path, "w") as f:
         for key in env_keys:
@@ -1,17 +1,27 @@
 use crate::constants;
 use crate::database::db;
 use crate::errors::blog::BlogError;
+use crate::middleware::auth::get_user_claim;
+use crate::models::audit_event::AuditAction;
 use crate::models::{
-    blog, blog::Blog, blog_identifier::BlogIdentifier, blog_preview::BlogPreview,
-    new_blog::NewBlog, update_blog::UpdateBlog, uploaded_files::UploadedFiles,
+    blog, blog::Blog, blog_identifier::BlogIdentifier, blog_operation::BlogOperationKind,
+    blog_preview::BlogPreview, new_blog::NewBlog, update_blog::UpdateBlog,
+    uploaded_files::UploadedFiles,
 };
+use crate::utils::audit::{new_audit_event, record_audit_event};
+use crate::utils::auth::check_blog_author;
+use crate::utils::backup::back_up_blog;
 use crate::utils::blog::file_utils;
-use crate::utils::blog::file_utils::{back_up_blog, delete_blog_backup, process_file_logic};
+use crate::utils::blog::file_utils::process_file_logic;
+use crate::utils::blog::operation_utils::BlogOperationJournal;
 use crate::utils::blog::publish_utils;
+use crate::utils::conflict::get_blog_conflict;
 use crate::utils::datetime;
+use crate::utils::draft::delete_blog_draft;
 use crate::utils::html::minify_html;
-use crate::utils::io::get_temp_file_path;
+use crate::utils::lint::check_blog_lint;
 use crate::utils::md::convert_to_html;
+use crate::utils::preview::revoke_blog_preview_links;
 use crate::utils::storage;
 use crate::utils::validations::validate_id;
 
@@ -28,7 +38,6 @@ use mime::{Mime, IMAGE_GIF, IMAGE_JPEG, IMAGE_PNG};
 use mongodb::bson;
 use mongodb::bson::doc;
 use mongodb::options::FindOneOptions;
-use std::path::Path as std_Path;
 use std::str::FromStr;
 
 #[post("/api/admin/ws/blog/preview")]
@@ -48,10 +57,10 @@ async fn preview_blog(data: Form<BlogPreview>) -> HttpResponse {
 async fn new_blog(
     client: Data<db::DbClient>,
     s3_client: Data<s3::Client>,
+    req: HttpRequest,
     blog: Json<NewBlog>,
 ) -> Result<HttpResponse, BlogError> {
     let mut blog_op = blog.into_inner();
-    let blog_col = client.into_inner().get_blog_collection();
 
     let title = blog_op.title;
     if title.is_empty() {
@@ -63,6 +72,9 @@ async fn new_blog(
     if blog_op.content.is_empty() {
         return Err(BlogError::EmptyContent);
     }
+    if !blog_op.ignore_lint {
+        check_blog_lint(&client, &blog_op.content, &blog_op.seo_desc).await?;
+    }
 
     let mut blog = Blog::new(
         title,
@@ -72,22 +84,37 @@ async fn new_blog(
         &vec![],
         blog_op.is_public,
     );
+    blog.author_id = Some(get_user_claim(&req).user_id);
     let blog_id = blog.get_id_string();
 
+    let mut journal =
+        BlogOperationJournal::begin(&client, &s3_client, blog.id, BlogOperationKind::Create)
+            .await?;
     for file in blog_op.files.iter_mut() {
-        file_utils::process_file!(&blog_id, file, &mut blog_op.content, &s3_client);
+        file_utils::process_file!(&blog_id, file, &mut blog_op.content, journal);
+    }
+    // the same media may have been uploaded more than once for this blog post
+    for file in blog_op.files.into_iter() {
+        if !blog.files.contains(&file) {
+            blog.files.push(file);
+        }
     }
-    blog.files = blog_op.files;
     blog.content = blog_op.content;
 
-    match blog_col.insert_one(&blog).await {
+    match client.get_blog_collection().insert_one(&blog).await {
         Ok(result) => {
+            journal.commit().await;
             let id = result.inserted_id.as_object_id().unwrap();
             back_up_blog(&s3_client, &blog).await;
+            let event = new_audit_event(&req, AuditAction::PostCreated)
+                .with_target(id)
+                .with_details(&blog.title);
+            record_audit_event(&client, event).await;
             Ok(HttpResponse::Ok().body(id.to_hex()))
         }
         Err(err) => {
             log::error!("Failed to create api in database: {:?}", err);
+            journal.rollback().await;
             Err(BlogError::PublishBlogError)
         }
     }
@@ -97,14 +124,16 @@ async fn new_blog(
 async fn update_blog(
     client: Data<db::DbClient>,
     s3_client: Data<s3::Client>,
+    req: HttpRequest,
     update_blog: Json<UpdateBlog>,
 ) -> Result<HttpResponse, BlogError> {
     let blog: UpdateBlog = update_blog.into_inner();
     let blog_id = validate_id(&blog.id)?;
+    check_blog_author(&client, &req, &blog_id).await?;
     let blog_id_str = blog_id.to_hex();
 
     let updating_tags = !blog.tags.is_none();
-    let new_tags = blog.tags.unwrap_or(vec![]);
+    let new_tags = blog.tags.clone().unwrap_or(vec![]);
     if updating_tags {
         if new_tags.len() > constants::MAX_TAGS {
             return Err(BlogError::TooManyTags);
@@ -127,6 +156,21 @@ async fn update_blog(
     }
 
     let blog_in_db = client.get_blog_post(&blog_id, None).await?;
+    if blog_in_db.version != blog.version {
+        let conflict = get_blog_conflict(&s3_client, &blog, &blog_in_db).await;
+        return Err(BlogError::VersionConflict(Box::new(conflict)));
+    }
+    if !blog.ignore_lint {
+        let content = blog.content.as_deref().unwrap_or(&blog_in_db.content);
+        let seo_desc = blog.seo_desc.as_deref().unwrap_or(&blog_in_db.seo_desc);
+        check_blog_lint(&client, content, seo_desc).await?;
+    }
+    // kept to report a conflict if the blog post is updated while the files are processed
+    let submitted = blog.clone();
+
+    let mut journal =
+        BlogOperationJournal::begin(&client, &s3_client, blog_id, BlogOperationKind::Update)
+            .await?;
 
     let mut is_updating = false;
     let last_modified = bson::DateTime::parse_rfc3339_str(datetime::get_dtnow_str())
@@ -137,6 +181,7 @@ async fn update_blog(
         blog::LAST_MODIFIED_KEY: last_modified,
     };
     blog_to_backup.last_modified = Some(chrono::DateTime::from(last_modified));
+    blog_to_backup.version += 1;
 
     let mut blog_content = blog.content.unwrap_or_default();
     if updating_files {
@@ -147,21 +192,30 @@ async fn update_blog(
         if new_files.len() > 0 {
             update_file_flag = true;
             for file in new_files.iter_mut() {
-                file_utils::process_file!(&blog_id_str, file, &mut blog_content, &s3_client);
+                file_utils::process_file!(&blog_id_str, file, &mut blog_content, journal);
+            }
+            for file in new_files.into_iter() {
+                if !files_to_put_in_db.contains(&file) {
+                    files_to_put_in_db.push(file);
+                }
             }
-            files_to_put_in_db = new_files;
         }
 
         // check if the old_files are in the content
         for file in old_files.into_iter() {
             if blog_content.contains(&file.url) {
-                files_to_put_in_db.push(file);
+                if !files_to_put_in_db.contains(&file) {
+                    files_to_put_in_db.push(file);
+                }
                 continue;
             }
             if !update_file_flag {
                 update_file_flag = true;
             }
-            file_utils::delete_blob!(&s3_client, &file.url);
+            if let Err(err) = journal.release_file(&file.url).await {
+                journal.rollback().await;
+                return Err(err);
+            }
         }
 
         if update_file_flag {
@@ -207,19 +261,42 @@ async fn update_blog(
     }
 
     if !is_updating {
-        return Ok(HttpResponse::Ok().body(old_blog_content));
+        // nothing was stored or released
+        journal.rollback().await;
+        return Ok(HttpResponse::Ok()
+            .insert_header((constants::BLOG_VERSION_HEADER, blog_in_db.version))
+            .body(old_blog_content));
     }
 
-    let query = doc! { "_id": blog_id };
-    let update = doc! { "$set": set_doc };
-    let blog_col = client.into_inner().get_blog_collection();
-    match blog_col.update_one(query, update).await {
+    // only update the blog post if it is still at the version that was edited
+    let query = doc! { "_id": blog_id, blog::VERSION_KEY: submitted.version };
+    let update = doc! {
+        "$set": set_doc,
+        "$inc": {blog::VERSION_KEY: 1},
+    };
+    match client.get_blog_collection().update_one(query, update).await {
+        Ok(result) if result.matched_count == 0 => {
+            journal.rollback().await;
+            let current = client.get_blog_post(&blog_id, None).await?;
+            let conflict = get_blog_conflict(&s3_client, &submitted, &current).await;
+            Err(BlogError::VersionConflict(Box::new(conflict)))
+        }
         Ok(_) => {
+            journal.commit().await;
+            // the draft was published
+            delete_blog_draft(&client, &blog_id).await;
             back_up_blog(&s3_client, &blog_to_backup).await;
-            Ok(HttpResponse::Ok().body(blog_content))
+            let event = new_audit_event(&req, AuditAction::PostUpdated)
+                .with_target(blog_id)
+                .with_details(format!("version {}", blog_to_backup.version));
+            record_audit_event(&client, event).await;
+            Ok(HttpResponse::Ok()
+                .insert_header((constants::BLOG_VERSION_HEADER, blog_to_backup.version))
+                .body(blog_content))
         }
         Err(err) => {
             log::error!("Failed to update api in database: {:?}", err);
+            journal.rollback().await;
             Err(BlogError::UpdateBlogError)
         }
     }
@@ -229,9 +306,11 @@ async fn update_blog(
 async fn delete_blog(
     client: Data<db::DbClient>,
     s3_client: Data<s3::Client>,
+    req: HttpRequest,
     blog_identifier: Path<BlogIdentifier>,
 ) -> Result<HttpResponse, BlogError> {
     let blog_id = validate_id(&blog_identifier.into_inner().id)?;
+    check_blog_author(&client, &req, &blog_id).await?;
 
     let options = FindOneOptions::builder()
         .projection(doc! {blog::FILES_KEY: 1})
@@ -240,19 +319,37 @@ async fn delete_blog(
         .get_projected_blog_post(&blog_id, Some(options))
         .await?;
 
+    let mut journal =
+        BlogOperationJournal::begin(&client, &s3_client, blog_id, BlogOperationKind::Delete)
+            .await?;
     let files = blog_data.files.unwrap_or(vec![]);
     for file in files.iter() {
-        file_utils::delete_blob!(&s3_client, &file.url);
+        if let Err(err) = journal.release_file(&file.url).await {
+            journal.rollback().await;
+            return Err(err);
+        }
     }
 
-    let blog_col = client.into_inner().get_blog_collection();
-    match blog_col.delete_one(doc! { "_id": blog_id }).await {
+    match client
+        .get_blog_collection()
+        .delete_one(doc! { "_id": blog_id })
+        .await
+    {
+        // the backups are kept so that the content can still be restored, but the media
+        // is deleted with it unless another blog post uses it, see `restore_blog`.
         Ok(_) => {
-            delete_blog_backup(&s3_client, &blog_id).await;
+            journal.commit().await;
+            tokio::join!(
+                delete_blog_draft(&client, &blog_id),
+                revoke_blog_preview_links(&client, &blog_id),
+            );
+            let event = new_audit_event(&req, AuditAction::PostDeleted).with_target(blog_id);
+            record_audit_event(&client, event).await;
             Ok(HttpResponse::Ok().body("Blog deleted successfully".to_string()))
         }
         Err(err) => {
             log::error!("Failed to delete api from database: {:?}", err);
+            journal.rollback().await;
             Err(BlogError::InternalServerError)
         }
     }
@@ -261,21 +358,34 @@ async fn delete_blog(
 #[patch("/api/blogs/{id}/publish")]
 async fn publish_blog_post(
     client: Data<db::DbClient>,
+    req: HttpRequest,
     blog_identifier: Path<BlogIdentifier>,
 ) -> Result<HttpResponse, BlogError> {
-    publish_utils::configure_blog_post_bool(client, &blog_identifier.into_inner().id, true).await
+    let blog_id = blog_identifier.into_inner().id;
+    check_blog_author(&client, &req, &validate_id(&blog_id)?).await?;
+    let response = publish_utils::configure_blog_post_bool(client.clone(), &blog_id, true).await?;
+    let event = new_audit_event(&req, AuditAction::PostPublished).with_target(&blog_id);
+    record_audit_event(&client, event).await;
+    Ok(response)
 }
 
 #[patch("/api/blogs/{id}/unpublish")]
 async fn unpublish_blog_post(
     client: Data<db::DbClient>,
+    req: HttpRequest,
     blog_identifier: Path<BlogIdentifier>,
 ) -> Result<HttpResponse, BlogError> {
-    publish_utils::configure_blog_post_bool(client, &blog_identifier.into_inner().id, false).await
+    let blog_id = blog_identifier.into_inner().id;
+    check_blog_author(&client, &req, &validate_id(&blog_id)?).await?;
+    let response = publish_utils::configure_blog_post_bool(client.clone(), &blog_id, false).await?;
+    let event = new_audit_event(&req, AuditAction::PostUnpublished).with_target(&blog_id);
+    record_audit_event(&client, event).await;
+    Ok(response)
 }
 
 #[post("/api/blog/upload/files")]
 async fn upload_blog_files(
+    client: Data<db::DbClient>,
     s3_client: Data<s3::Client>,
     mut payload: Multipart,
     req: HttpRequest,
@@ -328,13 +438,6 @@ async fn upload_blog_files(
             "mp4"
         };
 
-        let destination = format!(
-            "{}{}.{}",
-            constants::TEMP_OBJ_PREFIX,
-            get_temp_file_path(),
-            file_ext
-        );
-
         let headers = field.headers();
         let content_length: usize = match headers.get(CONTENT_LENGTH) {
             Some(v) => v.to_str().unwrap_or("0").parse().unwrap(),
@@ -346,6 +449,17 @@ async fn upload_blog_files(
             data.extend_from_slice(&chunk);
         }
 
+        // name the file after its contents so that re-uploads of the same file are deduplicated
+        let file_name = format!("{}.{}", file_utils::hash_file_data(&data), file_ext);
+        let media_obj_name = file_utils::get_media_obj_name(&file_name);
+        if storage::blob_exists(&s3_client, constants::BUCKET, &media_obj_name).await {
+            log::info!("File already stored, {}", media_obj_name);
+            let url = file_utils::get_public_url(&media_obj_name);
+            files.append(file_name, url.clone(), url);
+            continue;
+        }
+        let destination = format!("{}/{}", constants::TEMP_OBJ_PREFIX, file_name);
+
         log::info!("Uploading file, {}", destination);
         file_utils::upload_blob!(&s3_client, constants::BUCKET_FOR_TEMP, &destination, data);
         let url = format!(
@@ -354,14 +468,15 @@ async fn upload_blog_files(
             constants::get_r2_acc_id(),
             destination
         );
-        let file_name = std_Path::new(&destination)
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap();
         let signed_url =
             storage::get_signed_url(&s3_client, constants::BUCKET_FOR_TEMP, &destination).await;
-        files.append(file_name.to_string(), url, signed_url);
+        files.append(file_name, url, signed_url);
+    }
+    let file_names = files.get_names();
+    if !file_names.is_empty() {
+        let event =
+            new_audit_event(&req, AuditAction::FilesUploaded).with_details(file_names.join(", "));
+        record_audit_event(&client, event).await;
     }
     return Ok(Json(files));
 }
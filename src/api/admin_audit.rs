@@ -0,0 +1,30 @@
+use crate::database::db;
+use crate::errors::auth::AuthError;
+use crate::models::audit_event::AuditLogQuery;
+use crate::utils::audit::export_audit_events;
+
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{
+    get,
+    web::{Data, Query},
+    HttpResponse,
+};
+
+#[get("/api/admin/audit-log/export")]
+async fn export_audit_log(
+    client: Data<db::DbClient>,
+    query: Query<AuditLogQuery>,
+) -> Result<HttpResponse, AuthError> {
+    let json_lines = export_audit_events(&client, &query).await?;
+    let file_name = format!(
+        "audit-log-{}.jsonl",
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name)],
+        })
+        .streaming(json_lines))
+}
@@ -0,0 +1,114 @@
+use crate::constants;
+use crate::database::db;
+use crate::errors::backup::BackupError;
+use crate::middleware::auth::get_user_claim;
+use crate::models::backup_manifest::RestoreOptions;
+use crate::models::blog_identifier::BlogIdentifier;
+use crate::models::restore_report::RestoreReport;
+use crate::utils::backup::{
+    export_site, get_blog_backups, import_site, restore_all_blogs, restore_blog,
+};
+use crate::utils::validations::validate_id;
+
+use actix_multipart::Multipart;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{
+    get, post,
+    web::{Data, Path, Query},
+    HttpRequest, HttpResponse,
+};
+use aws_sdk_s3 as s3;
+use futures_util::TryStreamExt;
+
+#[post("/api/admin/backups/restore")]
+async fn restore_blog_backups(
+    client: Data<db::DbClient>,
+    s3_client: Data<s3::Client>,
+) -> Result<HttpResponse, BackupError> {
+    let report = restore_all_blogs(&client, &s3_client).await?;
+    log::info!(
+        "Restored {} blog(s) from backups, {} failed",
+        report.restored.len(),
+        report.failed.len()
+    );
+    Ok(HttpResponse::Ok().json(report))
+}
+
+#[get("/api/admin/backups/{id}")]
+async fn list_blog_backups(
+    s3_client: Data<s3::Client>,
+    blog_identifier: Path<BlogIdentifier>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let blog_id = validate_id(&blog_identifier.into_inner().id)?;
+    let manifest = get_blog_backups(&s3_client, &blog_id).await?;
+    Ok(HttpResponse::Ok().json(manifest))
+}
+
+#[post("/api/admin/backups/{id}/restore")]
+async fn restore_blog_backup(
+    client: Data<db::DbClient>,
+    s3_client: Data<s3::Client>,
+    blog_identifier: Path<BlogIdentifier>,
+    options: Query<RestoreOptions>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let blog_id = validate_id(&blog_identifier.into_inner().id)?;
+    let missing_files =
+        restore_blog(&client, &s3_client, &blog_id, options.version.as_deref()).await?;
+    let report = RestoreReport {
+        restored: vec![blog_id.to_hex()],
+        failed: vec![],
+        missing_files,
+    };
+    Ok(HttpResponse::Ok().json(report))
+}
+
+#[get("/api/admin/export")]
+async fn export_site_archive(
+    client: Data<db::DbClient>,
+    s3_client: Data<s3::Client>,
+) -> Result<HttpResponse, BackupError> {
+    let archive = export_site(&client, &s3_client).await?;
+    let file_name = format!(
+        "{}-export-{}.zip",
+        constants::BUCKET,
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name)],
+        })
+        .body(archive))
+}
+
+#[post("/api/admin/import")]
+async fn import_site_archive(
+    client: Data<db::DbClient>,
+    s3_client: Data<s3::Client>,
+    req: HttpRequest,
+    mut payload: Multipart,
+) -> Result<HttpResponse, BackupError> {
+    let mut archive = Vec::new();
+    if let Ok(Some(mut field)) = payload.try_next().await {
+        while let Ok(Some(chunk)) = field.try_next().await {
+            if archive.len() + chunk.len() > constants::MAX_FILE_SIZE {
+                return Err(BackupError::InvalidArchive);
+            }
+            archive.extend_from_slice(&chunk);
+        }
+    }
+    if archive.is_empty() {
+        return Err(BackupError::InvalidArchive);
+    }
+
+    let imported_by = get_user_claim(&req).user_id;
+    let report = import_site(&client, &s3_client, archive, Some(imported_by)).await?;
+    log::info!(
+        "Imported {} blog(s), {} new user(s) and {} media file(s)",
+        report.blogs,
+        report.users_created,
+        report.media
+    );
+    Ok(HttpResponse::Ok().json(report))
+}
@@ -0,0 +1,80 @@
+use crate::constants;
+use crate::database::db;
+use crate::errors::blog::BlogError;
+use crate::models::blog_draft::{BlogDraft, SaveDraft, SavedDraft};
+use crate::models::blog_identifier::BlogIdentifier;
+use crate::utils::auth::check_blog_author;
+use crate::utils::draft::delete_blog_draft;
+use crate::utils::validations::validate_id;
+
+use actix_web::web::{Data, Json, Path};
+use actix_web::{delete, put, HttpRequest, HttpResponse};
+use mongodb::bson::doc;
+use mongodb::options::ReplaceOptions;
+
+#[put("/api/blogs/{id}/draft")]
+async fn save_blog_draft(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    blog_identifier: Path<BlogIdentifier>,
+    draft: Json<SaveDraft>,
+) -> Result<Json<SavedDraft>, BlogError> {
+    let blog_id = validate_id(&blog_identifier.into_inner().id)?;
+    check_blog_author(&client, &req, &blog_id).await?;
+    let draft = draft.into_inner();
+    if draft.title.len() > constants::TITLE_MAX_LENGTH {
+        return Err(BlogError::TitleTooLong);
+    }
+    if draft.tags.len() > constants::MAX_TAGS {
+        return Err(BlogError::TooManyTags);
+    }
+
+    let blog_count = client
+        .get_blog_collection()
+        .count_documents(doc! {"_id": blog_id})
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blog from database: {:?}", e);
+            BlogError::InternalServerError
+        })?;
+    if blog_count == 0 {
+        return Err(BlogError::BlogNotFound);
+    }
+
+    let draft = BlogDraft {
+        blog_id,
+        title: draft.title,
+        seo_desc: draft.seo_desc,
+        tags: draft.tags,
+        content: draft.content,
+        is_public: draft.is_public,
+        new_files: draft.new_files,
+        base_version: draft.base_version,
+        saved: chrono::Utc::now(),
+    };
+    let options = ReplaceOptions::builder().upsert(true).build();
+    client
+        .get_blog_draft_collection()
+        .replace_one(doc! {"_id": blog_id}, &draft)
+        .with_options(options)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to save blog draft: {:?}", e);
+            BlogError::InternalServerError
+        })?;
+    Ok(Json(SavedDraft {
+        saved: draft.saved.to_rfc3339(),
+    }))
+}
+
+#[delete("/api/blogs/{id}/draft")]
+async fn discard_blog_draft(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    blog_identifier: Path<BlogIdentifier>,
+) -> Result<HttpResponse, BlogError> {
+    let blog_id = validate_id(&blog_identifier.into_inner().id)?;
+    check_blog_author(&client, &req, &blog_id).await?;
+    delete_blog_draft(&client, &blog_id).await;
+    Ok(HttpResponse::Ok().body("Draft discarded"))
+}
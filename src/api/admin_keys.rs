@@ -0,0 +1,18 @@
+use crate::database::db;
+use crate::errors::auth::AuthError;
+use crate::jobs::key_rotation::reencrypt_totp_secrets;
+
+use actix_web::web::Data;
+use actix_web::{post, HttpResponse};
+
+#[post("/api/admin/keys/reencrypt")]
+async fn reencrypt_keys(client: Data<db::DbClient>) -> Result<HttpResponse, AuthError> {
+    let report = reencrypt_totp_secrets(&client).await?;
+    log::info!(
+        "Re-encrypted {} TOTP secret(s) with the DB key {:?}, {} failed",
+        report.reencrypted,
+        report.key_id,
+        report.failed.len()
+    );
+    Ok(HttpResponse::Ok().json(report))
+}
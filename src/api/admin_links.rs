@@ -0,0 +1,38 @@
+use crate::database::db;
+use crate::templates::alerts::{ErrAlert, SuccessAlert};
+use crate::utils::html::render_template;
+use crate::utils::link_checker::{check_all_links, is_link_check_running, HttpLinkClient};
+
+use actix_web::http::StatusCode;
+use actix_web::web::Data;
+use actix_web::{post, rt, HttpResponse};
+
+#[post("/api/admin/links/check")]
+async fn check_links(client: Data<db::DbClient>) -> HttpResponse {
+    if is_link_check_running() {
+        let template = ErrAlert {
+            err: "The links are already being checked",
+        };
+        return render_template(template, StatusCode::CONFLICT);
+    }
+
+    // checking every link can take a while due to the rate limits so it runs in the background
+    let db_client = client.get_ref().clone();
+    rt::spawn(async move {
+        match check_all_links(&db_client, HttpLinkClient::new()).await {
+            Ok(Some(summary)) => log::info!(
+                "Checked {} outbound link(s): {} ok, {} broken, {} skipped",
+                summary.checked,
+                summary.ok,
+                summary.broken,
+                summary.skipped
+            ),
+            Ok(None) => {}
+            Err(_) => log::error!("Failed to check the outbound links"),
+        }
+    });
+    let template = SuccessAlert {
+        msg: "Started checking the links, refresh the page later for the results",
+    };
+    render_template(template, StatusCode::OK)
+}
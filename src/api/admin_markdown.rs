@@ -0,0 +1,149 @@
+use crate::constants;
+use crate::database::db;
+use crate::errors::backup::BackupError;
+use crate::middleware::auth::get_user_claim;
+use crate::models::blog_identifier::BlogIdentifier;
+use crate::models::front_matter::{ImportOptions, MarkdownImportReport};
+use crate::utils::markdown_files::{export_markdown, import_markdown};
+use crate::utils::migration::{import_content_dir, import_wordpress};
+use crate::utils::validations::validate_id;
+
+use actix_multipart::Multipart;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{
+    get, post,
+    web::{Data, Path, Query},
+    HttpRequest, HttpResponse,
+};
+use aws_sdk_s3 as s3;
+use futures_util::TryStreamExt;
+
+#[inline]
+fn zip_response(archive: Vec<u8>, file_name: String) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name)],
+        })
+        .body(archive)
+}
+
+#[get("/api/admin/blogs/{id}/export/markdown")]
+async fn export_blog_markdown(
+    client: Data<db::DbClient>,
+    s3_client: Data<s3::Client>,
+    blog_identifier: Path<BlogIdentifier>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let blog_id = validate_id(&blog_identifier.into_inner().id)?;
+    let archive = export_markdown(&client, &s3_client, Some(&blog_id)).await?;
+    Ok(zip_response(archive, format!("{}-markdown.zip", blog_id)))
+}
+
+#[get("/api/admin/export/markdown")]
+async fn export_blogs_markdown(
+    client: Data<db::DbClient>,
+    s3_client: Data<s3::Client>,
+) -> Result<HttpResponse, BackupError> {
+    let archive = export_markdown(&client, &s3_client, None).await?;
+    let file_name = format!(
+        "{}-markdown-{}.zip",
+        constants::BUCKET,
+        chrono::Utc::now().format("%Y%m%d%H%M%S")
+    );
+    Ok(zip_response(archive, file_name))
+}
+
+/// Reads every uploaded file in the form with its file name.
+async fn read_uploaded_files(
+    mut payload: Multipart,
+) -> Result<Vec<(String, Vec<u8>)>, BackupError> {
+    let mut files = Vec::new();
+    let mut total_size = 0;
+    while let Ok(Some(mut field)) = payload.try_next().await {
+        let file_name = field
+            .content_disposition()
+            .and_then(|cd| cd.get_filename())
+            .unwrap_or_default()
+            .to_string();
+        let mut data = Vec::new();
+        while let Ok(Some(chunk)) = field.try_next().await {
+            total_size += chunk.len();
+            if total_size > constants::MAX_FILE_SIZE {
+                return Err(BackupError::InvalidArchive);
+            }
+            data.extend_from_slice(&chunk);
+        }
+        if !file_name.is_empty() && !data.is_empty() {
+            files.push((file_name, data));
+        }
+    }
+    if files.is_empty() {
+        return Err(BackupError::InvalidArchive);
+    }
+    Ok(files)
+}
+
+#[inline]
+fn report_response(source: &str, report: MarkdownImportReport) -> HttpResponse {
+    log::info!(
+        "Imported {}{}: {} created, {} updated, {} unchanged, {} failed",
+        source,
+        if report.dry_run { " (dry run)" } else { "" },
+        report.created.len(),
+        report.updated.len(),
+        report.unchanged.len(),
+        report.failed.len()
+    );
+    HttpResponse::Ok().json(report)
+}
+
+#[post("/api/admin/import/markdown")]
+async fn import_blogs_markdown(
+    client: Data<db::DbClient>,
+    s3_client: Data<s3::Client>,
+    req: HttpRequest,
+    options: Query<ImportOptions>,
+    payload: Multipart,
+) -> Result<HttpResponse, BackupError> {
+    let imported_by = Some(get_user_claim(&req).user_id);
+    // the markdown files, the media they embed or zip archives of both
+    let files = read_uploaded_files(payload).await?;
+    let report = import_markdown(&client, &s3_client, files, imported_by, options.dry_run).await?;
+    Ok(report_response("markdown", report))
+}
+
+#[post("/api/admin/import/wordpress")]
+async fn import_wordpress_export(
+    client: Data<db::DbClient>,
+    s3_client: Data<s3::Client>,
+    req: HttpRequest,
+    options: Query<ImportOptions>,
+    payload: Multipart,
+) -> Result<HttpResponse, BackupError> {
+    let imported_by = Some(get_user_claim(&req).user_id);
+    let xml = read_uploaded_files(payload)
+        .await?
+        .into_iter()
+        .next()
+        .map(|(_, data)| data)
+        .unwrap_or_default();
+    let report = import_wordpress(&client, &s3_client, xml, imported_by, options.dry_run).await?;
+    Ok(report_response("WordPress export", report))
+}
+
+#[post("/api/admin/import/content-dir")]
+async fn import_content_directory(
+    client: Data<db::DbClient>,
+    s3_client: Data<s3::Client>,
+    req: HttpRequest,
+    options: Query<ImportOptions>,
+    payload: Multipart,
+) -> Result<HttpResponse, BackupError> {
+    let imported_by = Some(get_user_claim(&req).user_id);
+    // the files of a Hugo or Jekyll site or a zip archive of it
+    let files = read_uploaded_files(payload).await?;
+    let report =
+        import_content_dir(&client, &s3_client, files, imported_by, options.dry_run).await?;
+    Ok(report_response("content directory", report))
+}
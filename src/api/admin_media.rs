@@ -0,0 +1,89 @@
+use crate::constants;
+use crate::database::db;
+use crate::errors::blog::BlogError;
+use crate::models::media;
+use crate::models::media_query::MediaQuery;
+use crate::models::update_media::UpdateMedia;
+use crate::templates::admin::MediaGrid;
+use crate::templates::alerts::SuccessAlert;
+use crate::utils::html::render_template;
+use crate::utils::media::get_media_library;
+use crate::utils::security::get_csrf_header_json;
+use crate::utils::storage;
+
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Form, Query};
+use actix_web::{get, patch, HttpRequest, HttpResponse};
+use aws_sdk_s3 as s3;
+use mongodb::bson::doc;
+use mongodb::options::UpdateOptions;
+
+const MAX_ALT_TEXT_LENGTH: usize = 250;
+
+#[get("/api/admin/media")]
+async fn search_media(
+    client: Data<db::DbClient>,
+    s3_client: Data<s3::Client>,
+    req: HttpRequest,
+    query: Query<MediaQuery>,
+) -> Result<HttpResponse, BlogError> {
+    let media_list =
+        get_media_library(&client, &s3_client, query.q.as_deref().unwrap_or_default()).await?;
+    let template = MediaGrid {
+        csrf_header_json: get_csrf_header_json(&req, None),
+        media_list,
+        picker: query.picker.unwrap_or_default(),
+    };
+    Ok(render_template(template, StatusCode::OK))
+}
+
+#[patch("/api/admin/media/alt-text")]
+async fn update_media_alt_text(
+    client: Data<db::DbClient>,
+    s3_client: Data<s3::Client>,
+    data: Form<UpdateMedia>,
+) -> Result<HttpResponse, BlogError> {
+    let data = data.into_inner();
+    if data.name.is_empty() {
+        return Err(BlogError::FileIsEmpty);
+    }
+    let alt_text = data.alt_text.trim();
+    if alt_text.len() > MAX_ALT_TEXT_LENGTH {
+        return Err(BlogError::AltTextTooLong);
+    }
+    // only the media listed in the library can be described, otherwise
+    // the upsert would store alt texts for arbitrary object names.
+    let prefix = format!("{}/", constants::get_blog_obj_prefix());
+    if !data.name.starts_with(&prefix)
+        || data
+            .name
+            .split('/')
+            .any(|part| part.is_empty() || part == "..")
+        || !storage::blob_exists(&s3_client, constants::BUCKET, &data.name).await
+    {
+        return Err(BlogError::MediaNotFound);
+    }
+
+    let last_modified = bson::DateTime::from_chrono(chrono::Utc::now());
+    let options = UpdateOptions::builder().upsert(true).build();
+    client
+        .get_media_collection()
+        .update_one(
+            doc! {"_id": &data.name},
+            doc! {"$set": {
+                media::ALT_TEXT_KEY: alt_text,
+                media::LAST_MODIFIED_KEY: last_modified,
+            }},
+        )
+        .with_options(options)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to update media alt text: {:?}", e);
+            BlogError::InternalServerError
+        })?;
+
+    let template = SuccessAlert {
+        msg: "Alt text saved",
+    };
+    Ok(render_template(template, StatusCode::OK))
+}
@@ -0,0 +1,73 @@
+use crate::constants;
+use crate::database::db;
+use crate::errors::blog::BlogError;
+use crate::models::blog_identifier::BlogIdentifier;
+use crate::models::preview_link::{NewPreviewLink, PreviewLinkIdentifier};
+use crate::templates::admin::PreviewLinks;
+use crate::utils::auth::check_blog_author;
+use crate::utils::html::render_template;
+use crate::utils::preview::{create_preview_link, get_preview_links, revoke_preview_link};
+use crate::utils::security::get_csrf_header_json;
+use crate::utils::validations::validate_id;
+
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Form, Path};
+use actix_web::{delete, get, post, HttpRequest, HttpResponse};
+use bson::oid::ObjectId;
+
+async fn render_preview_links(
+    client: &db::DbClient,
+    req: &HttpRequest,
+    blog_id: &ObjectId,
+) -> Result<HttpResponse, BlogError> {
+    let template = PreviewLinks {
+        csrf_header_json: get_csrf_header_json(req, None),
+        blog_id: blog_id.to_hex(),
+        links: get_preview_links(client, req, blog_id).await?,
+        max_days: constants::PREVIEW_LINK_MAX_DAYS,
+        default_days: constants::PREVIEW_LINK_DEFAULT_DAYS,
+    };
+    Ok(render_template(template, StatusCode::OK))
+}
+
+#[get("/api/blogs/{id}/preview-links")]
+async fn list_preview_links(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    blog_identifier: Path<BlogIdentifier>,
+) -> Result<HttpResponse, BlogError> {
+    let blog_id = validate_id(&blog_identifier.into_inner().id)?;
+    check_blog_author(&client, &req, &blog_id).await?;
+    render_preview_links(&client, &req, &blog_id).await
+}
+
+#[post("/api/blogs/{id}/preview-links")]
+async fn new_preview_link(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    blog_identifier: Path<BlogIdentifier>,
+    data: Form<NewPreviewLink>,
+) -> Result<HttpResponse, BlogError> {
+    let blog_id = validate_id(&blog_identifier.into_inner().id)?;
+    check_blog_author(&client, &req, &blog_id).await?;
+    let days = data
+        .days
+        .unwrap_or(constants::PREVIEW_LINK_DEFAULT_DAYS)
+        .clamp(1, constants::PREVIEW_LINK_MAX_DAYS);
+    create_preview_link(&client, blog_id, days).await?;
+    render_preview_links(&client, &req, &blog_id).await
+}
+
+#[delete("/api/blogs/{id}/preview-links/{link_id}")]
+async fn delete_preview_link(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    identifier: Path<PreviewLinkIdentifier>,
+) -> Result<HttpResponse, BlogError> {
+    let identifier = identifier.into_inner();
+    let blog_id = validate_id(&identifier.id)?;
+    check_blog_author(&client, &req, &blog_id).await?;
+    let link_id = validate_id(&identifier.link_id)?;
+    revoke_preview_link(&client, &blog_id, &link_id).await?;
+    render_preview_links(&client, &req, &blog_id).await
+}
@@ -1,22 +1,35 @@
+use crate::constants;
 use crate::database::db;
 use crate::errors::auth::AuthError;
 use crate::middleware::auth::get_user_claim;
+use crate::models::audit_event::AuditAction;
 use crate::models::change_password::ChangePassword;
+use crate::models::login_attempt::UnlockLogin;
+use crate::models::passkey::{NewPasskey, Passkey, PasskeyCeremony};
+use crate::models::recovery_codes::RegenerateRecoveryCodes;
 use crate::models::remove_2fa::Remove2fa;
+use crate::models::session;
 use crate::models::setup_2fa::Setup2fa;
+use crate::models::totp_config::TotpConfig;
 use crate::models::user;
 use crate::security::cf_turnstile;
+use crate::security::rate_limiter::DefaultLoginRateLimiter;
 use crate::security::totp;
+use crate::security::webauthn;
 use crate::security::{chacha_crypto, pw_hasher};
-use crate::templates::admin_profile::{Disable2FA, Enable2FA};
+use crate::templates::admin_profile::{
+    Disable2FA, Enable2FA, LoginAttemptList, PasskeyList, RecoveryCodes, SessionList,
+};
 use crate::templates::alerts::SuccessAlert;
+use crate::utils::audit::{new_audit_event, record_audit_event};
 use crate::utils::auth::cf_turnstile::verify_captcha;
 use crate::utils::html::render_template;
+use crate::utils::passkey::get_creation_options;
 use crate::utils::security::get_csrf_header_json;
 
 use actix_web::http::StatusCode;
-use actix_web::web::{Data, Form};
-use actix_web::{get, patch, post, web, HttpRequest, HttpResponse};
+use actix_web::web::{Data, Form, Json, Query};
+use actix_web::{delete, get, patch, post, web, HttpRequest, HttpResponse};
 use bson::doc;
 use mongodb::options::FindOneOptions;
 
@@ -24,7 +37,11 @@ use mongodb::options::FindOneOptions;
 async fn generate_2fa(
     client: Data<db::DbClient>,
     req: HttpRequest,
+    totp_config: Query<TotpConfig>,
 ) -> Result<HttpResponse, AuthError> {
+    if !totp_config.is_valid() {
+        return Err(AuthError::InvalidTotpConfig);
+    }
     let user_info = get_user_claim(&req);
 
     let options = FindOneOptions::builder()
@@ -34,7 +51,7 @@ async fn generate_2fa(
         .get_projected_user_by_id(&user_info.user_id, Some(options))
         .await?;
 
-    let generated_totp = totp::generate_totp(&user.email.unwrap_or_default());
+    let generated_totp = totp::generate_totp(&user.email.unwrap_or_default(), &totp_config);
     Ok(HttpResponse::Ok().json(generated_totp))
 }
 
@@ -49,10 +66,19 @@ async fn setup_2fa(
     let user_info = get_user_claim(&req);
     let secret = &setup_data.secret;
     let totp_code = &setup_data.totp_code;
-    let valid_totp = totp::verify_totp(totp_code, secret);
-    if !valid_totp {
-        return Err(AuthError::InvalidTotp);
+    let totp_config = setup_data.get_totp_config();
+    if !totp_config.is_valid() {
+        return Err(AuthError::InvalidTotpConfig);
     }
+    // the time step is saved so that the same code cannot be used to login afterwards
+    let totp_step = match totp::verify_totp(totp_code, secret, &totp_config, None) {
+        Some(totp_step) => totp_step,
+        None => return Err(AuthError::InvalidTotp),
+    };
+    let totp_config = bson::to_bson(&totp_config).map_err(|e| {
+        log::error!("Failed to serialise totp config: {:?}", e);
+        AuthError::InternalServerError
+    })?;
 
     let options = FindOneOptions::builder()
         .projection(doc! {user::TOTP_SECRET_KEY: 1, user::PASSWORD_KEY: 1})
@@ -68,7 +94,9 @@ async fn setup_2fa(
     let user_password_hash = user_doc.password.unwrap_or_default();
     let login_data_password = setup_data.current_password.to_string();
     let secret_bytes = secret.as_bytes().to_vec();
-    let encrypted_secret = web::block(move || {
+    let recovery_codes = totp::generate_recovery_codes();
+    let recovery_codes_clone = recovery_codes.clone();
+    let (encrypted_secret, recovery_code_hashes) = web::block(move || {
         pw_hasher::verify_user_password(&login_data_password, &user_password_hash, false)?;
 
         let encrypted_secret = match chacha_crypto::encrypt_with_db_key(&secret_bytes) {
@@ -82,7 +110,8 @@ async fn setup_2fa(
             subtype: bson::spec::BinarySubtype::Generic,
             bytes: encrypted_secret,
         };
-        Ok(encrypted_secret)
+        let recovery_code_hashes = totp::hash_recovery_codes(&recovery_codes_clone)?;
+        Ok((encrypted_secret, recovery_code_hashes))
     })
     .await
     .map_err(|e| {
@@ -97,16 +126,32 @@ async fn setup_2fa(
         .get_user_collection()
         .update_one(
             doc! {"_id": user_info.user_id},
-            doc! {"$set": {user::TOTP_SECRET_KEY: encrypted_secret}},
+            doc! {"$set": {
+                user::TOTP_SECRET_KEY: encrypted_secret,
+                user::TOTP_CONFIG_KEY: totp_config,
+                user::TOTP_LAST_STEP_KEY: totp_step,
+                user::RECOVERY_CODES_KEY: recovery_code_hashes,
+            }},
         )
         .await
         .map_err(|e| {
             log::error!("Failed to set user's totp secret: {:?}", e);
             AuthError::InternalServerError
         })?;
+    client
+        .revoke_other_sessions(&user_info.user_id, &user_info.session_id)
+        .await?;
+
+    record_audit_event(
+        &client,
+        new_audit_event(&req, AuditAction::TwoFactorEnabled),
+    )
+    .await;
 
     let template = Disable2FA {
         csrf_header_json: get_csrf_header_json(&req, None),
+        recovery_codes_left: recovery_codes.len(),
+        recovery_codes,
     };
     Ok(render_template(template, StatusCode::OK))
 }
@@ -148,13 +193,25 @@ async fn remove_2fa(
         .get_user_collection()
         .update_one(
             doc! {"_id": user_info.user_id},
-            doc! {"$set": {user::TOTP_SECRET_KEY: bson::Bson::Null}},
+            doc! {
+                "$set": {user::TOTP_SECRET_KEY: bson::Bson::Null, user::RECOVERY_CODES_KEY: []},
+                "$unset": {user::TOTP_CONFIG_KEY: "", user::TOTP_LAST_STEP_KEY: ""},
+            },
         )
         .await
         .map_err(|e| {
             log::error!("Failed to remove user's totp secret: {:?}", e);
             AuthError::InternalServerError
         })?;
+    client
+        .revoke_other_sessions(&user_info.user_id, &user_info.session_id)
+        .await?;
+
+    record_audit_event(
+        &client,
+        new_audit_event(&req, AuditAction::TwoFactorDisabled),
+    )
+    .await;
 
     let template = Enable2FA {
         csrf_header_json: get_csrf_header_json(&req, None),
@@ -162,6 +219,68 @@ async fn remove_2fa(
     Ok(render_template(template, StatusCode::OK))
 }
 
+#[post("/api/admin/recovery-codes")]
+async fn regenerate_recovery_codes(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    regenerate_data: Form<RegenerateRecoveryCodes>,
+) -> Result<HttpResponse, AuthError> {
+    verify_captcha!(&req, &regenerate_data.cf_turnstile_res);
+
+    let user_info = get_user_claim(&req);
+    let options = FindOneOptions::builder()
+        .projection(doc! {user::TOTP_SECRET_KEY: 1, user::PASSWORD_KEY: 1})
+        .build();
+    let user_doc = client
+        .get_projected_user_by_id(&user_info.user_id, Some(options))
+        .await?;
+    if user_doc.totp_secret.unwrap_or_default().is_empty() {
+        return Err(AuthError::AlreadyDisabled2fa);
+    }
+
+    let password_hash = user_doc.password.unwrap_or_default();
+    let current_password = regenerate_data.current_password.to_string();
+    let recovery_codes = totp::generate_recovery_codes();
+    let recovery_codes_clone = recovery_codes.clone();
+    let recovery_code_hashes = web::block(move || {
+        pw_hasher::verify_user_password(&current_password, &password_hash, false)?;
+        totp::hash_recovery_codes(&recovery_codes_clone)
+    })
+    .await
+    .map_err(|e| {
+        log::error!(
+            "Blocking Error when trying to verify user's password and hashing recovery codes: {:?}",
+            e
+        );
+        AuthError::InternalServerError
+    })??;
+
+    // the previous recovery codes can no longer be used
+    client
+        .get_user_collection()
+        .update_one(
+            doc! {"_id": user_info.user_id},
+            doc! {"$set": {user::RECOVERY_CODES_KEY: recovery_code_hashes}},
+        )
+        .await
+        .map_err(|e| {
+            log::error!("Failed to set user's recovery codes: {:?}", e);
+            AuthError::InternalServerError
+        })?;
+    client
+        .revoke_other_sessions(&user_info.user_id, &user_info.session_id)
+        .await?;
+
+    let event = new_audit_event(&req, AuditAction::RecoveryCodesRegenerated);
+    record_audit_event(&client, event).await;
+
+    let template = RecoveryCodes {
+        recovery_codes_left: recovery_codes.len(),
+        recovery_codes,
+    };
+    Ok(render_template(template, StatusCode::OK))
+}
+
 #[patch("/api/admin/change-password")]
 async fn change_password(
     client: Data<db::DbClient>,
@@ -216,9 +335,261 @@ async fn change_password(
             log::error!("Failed to update user's password: {:?}", e);
             AuthError::InternalServerError
         })?;
+    // the other devices have to sign in again with the new credentials
+    client
+        .revoke_other_sessions(&user_info.user_id, &user_info.session_id)
+        .await?;
+
+    record_audit_event(&client, new_audit_event(&req, AuditAction::PasswordChanged)).await;
 
     let template = SuccessAlert {
         msg: "Password changed successfully",
     };
     Ok(render_template(template, StatusCode::OK))
 }
+
+// https://www.w3.org/TR/webauthn-3/#enum-transport
+const PASSKEY_TRANSPORTS: [&str; 6] = ["usb", "nfc", "ble", "smart-card", "hybrid", "internal"];
+
+async fn render_passkey_list(
+    client: &db::DbClient,
+    req: &HttpRequest,
+) -> Result<HttpResponse, AuthError> {
+    let user_info = get_user_claim(req);
+    let options = FindOneOptions::builder()
+        .projection(doc! {user::TOTP_SECRET_KEY: 1, user::PASSKEYS_KEY: 1})
+        .build();
+    let user_doc = client
+        .get_projected_user_by_id(&user_info.user_id, Some(options))
+        .await?;
+
+    let template = PasskeyList {
+        csrf_header_json: get_csrf_header_json(req, None),
+        passkeys: user_doc.passkeys.unwrap_or_default(),
+    };
+    Ok(render_template(template, StatusCode::OK))
+}
+
+#[post("/api/admin/passkeys/options")]
+async fn passkey_options(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AuthError> {
+    let user_info = get_user_claim(&req);
+    let options = FindOneOptions::builder()
+        .projection(doc! {user::USERNAME_KEY: 1, user::TOTP_SECRET_KEY: 1, user::PASSKEYS_KEY: 1})
+        .build();
+    let user_doc = client
+        .get_projected_user_by_id(&user_info.user_id, Some(options))
+        .await?;
+
+    let options = get_creation_options(
+        &client,
+        user_info.user_id,
+        &user_doc.username.unwrap_or_default(),
+        &user_doc.passkeys.unwrap_or_default(),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(options))
+}
+
+#[post("/api/admin/passkeys")]
+async fn register_passkey(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    new_passkey: Json<NewPasskey>,
+) -> Result<HttpResponse, AuthError> {
+    verify_captcha!(&req, &new_passkey.cf_turnstile_res);
+
+    let name = new_passkey.name.trim();
+    if name.is_empty() || name.chars().count() > constants::PASSKEY_NAME_MAX_LENGTH {
+        return Err(AuthError::InvalidPasskeyName);
+    }
+
+    let user_info = get_user_claim(&req);
+    let challenge = client
+        .take_passkey_challenge(&new_passkey.challenge_id, PasskeyCeremony::Registration)
+        .await?;
+    if challenge.user_id != Some(user_info.user_id) {
+        return Err(AuthError::PasskeyChallengeExpired);
+    }
+
+    let options = FindOneOptions::builder()
+        .projection(doc! {user::TOTP_SECRET_KEY: 1, user::PASSWORD_KEY: 1})
+        .build();
+    let user_doc = client
+        .get_projected_user_by_id(&user_info.user_id, Some(options))
+        .await?;
+    let password_hash = user_doc.password.unwrap_or_default();
+    let current_password = new_passkey.current_password.to_string();
+    web::block(move || pw_hasher::verify_user_password(&current_password, &password_hash, false))
+        .await
+        .map_err(|e| {
+            log::error!(
+                "Blocking Error when trying to verify user's password: {:?}",
+                e
+            );
+            AuthError::InternalServerError
+        })??;
+
+    let credential = webauthn::verify_registration(
+        &challenge.challenge,
+        &new_passkey.id,
+        &webauthn::decode(&new_passkey.client_data_json)?,
+        &webauthn::decode(&new_passkey.attestation_object)?,
+    )?;
+    match client.get_user_by_passkey_id(&credential.id).await {
+        Ok(_) => return Err(AuthError::PasskeyAlreadyRegistered),
+        Err(AuthError::UserNotFound) => {}
+        Err(e) => return Err(e),
+    }
+
+    let passkey = Passkey {
+        id: credential.id,
+        name: name.to_string(),
+        public_key: credential.public_key,
+        sign_count: i64::from(credential.sign_count),
+        transports: new_passkey
+            .transports
+            .iter()
+            .filter(|transport| PASSKEY_TRANSPORTS.contains(&transport.as_str()))
+            .cloned()
+            .collect(),
+        created: chrono::Utc::now(),
+        last_used: None,
+    };
+    let event = new_audit_event(&req, AuditAction::PasskeyAdded)
+        .with_target(&passkey.id)
+        .with_details(&passkey.name);
+    let passkey = bson::to_bson(&passkey).map_err(|e| {
+        log::error!("Failed to serialise passkey: {:?}", e);
+        AuthError::InternalServerError
+    })?;
+
+    // the user cannot have more than MAX_PASSKEYS passkeys if the one at the last index does not exist
+    let result = client
+        .get_user_collection()
+        .update_one(
+            doc! {
+                "_id": user_info.user_id,
+                format!("{}.{}", user::PASSKEYS_KEY, constants::MAX_PASSKEYS - 1): {"$exists": false},
+            },
+            doc! {"$push": {user::PASSKEYS_KEY: passkey}},
+        )
+        .await
+        .map_err(|e| {
+            log::error!("Failed to add user's passkey: {:?}", e);
+            AuthError::InternalServerError
+        })?;
+    if result.matched_count == 0 {
+        return Err(AuthError::TooManyPasskeys);
+    }
+    record_audit_event(&client, event).await;
+
+    render_passkey_list(&client, &req).await
+}
+
+#[delete("/api/admin/passkeys/{id}")]
+async fn delete_passkey(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    passkey_id: web::Path<String>,
+) -> Result<HttpResponse, AuthError> {
+    let user_info = get_user_claim(&req);
+    let result = client
+        .get_user_collection()
+        .update_one(
+            doc! {"_id": user_info.user_id},
+            doc! {"$pull": {user::PASSKEYS_KEY: {"id": passkey_id.as_str()}}},
+        )
+        .await
+        .map_err(|e| {
+            log::error!("Failed to remove user's passkey: {:?}", e);
+            AuthError::InternalServerError
+        })?;
+    if result.modified_count == 0 {
+        return Err(AuthError::PasskeyNotFound);
+    }
+    let event = new_audit_event(&req, AuditAction::PasskeyRemoved).with_target(passkey_id.as_str());
+    record_audit_event(&client, event).await;
+
+    render_passkey_list(&client, &req).await
+}
+
+#[post("/api/admin/login-attempts/unlock")]
+async fn unlock_login(
+    client: Data<db::DbClient>,
+    limiter: Data<DefaultLoginRateLimiter>,
+    req: HttpRequest,
+    unlock_data: Form<UnlockLogin>,
+) -> Result<HttpResponse, AuthError> {
+    if !limiter.unlock(&unlock_data.key).await? {
+        return Err(AuthError::LoginAttemptNotFound(unlock_data.key.clone()));
+    }
+    let event = new_audit_event(&req, AuditAction::LoginUnlocked).with_target(&unlock_data.key);
+    record_audit_event(&client, event).await;
+
+    let template = LoginAttemptList {
+        csrf_header_json: get_csrf_header_json(&req, None),
+        login_attempts: limiter.get_blocked().await?,
+        now: chrono::Utc::now(),
+    };
+    Ok(render_template(template, StatusCode::OK))
+}
+
+async fn render_session_list(
+    client: &db::DbClient,
+    req: &HttpRequest,
+) -> Result<HttpResponse, AuthError> {
+    let user_info = get_user_claim(req);
+    let template = SessionList {
+        csrf_header_json: get_csrf_header_json(req, None),
+        sessions: client.get_user_sessions(&user_info.user_id).await?,
+        current_session_id: user_info.session_id.to_hex(),
+    };
+    Ok(render_template(template, StatusCode::OK))
+}
+
+#[delete("/api/admin/sessions/{id}")]
+async fn revoke_session(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    session_id: web::Path<String>,
+) -> Result<HttpResponse, AuthError> {
+    let user_info = get_user_claim(&req);
+    let session_id = bson::oid::ObjectId::parse_str(session_id.as_str())
+        .map_err(|_| AuthError::SessionNotFound)?;
+    if session_id == user_info.session_id {
+        return Err(AuthError::CannotRevokeCurrentSession);
+    }
+
+    let result = client
+        .get_session_collection()
+        .delete_one(doc! {"_id": session_id, session::USER_ID_KEY: user_info.user_id})
+        .await
+        .map_err(|e| {
+            log::error!("Failed to revoke user's session: {:?}", e);
+            AuthError::InternalServerError
+        })?;
+    if result.deleted_count == 0 {
+        return Err(AuthError::SessionNotFound);
+    }
+    let event = new_audit_event(&req, AuditAction::SessionRevoked).with_target(session_id);
+    record_audit_event(&client, event).await;
+
+    render_session_list(&client, &req).await
+}
+
+#[post("/api/admin/sessions/revoke-others")]
+async fn revoke_other_sessions(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AuthError> {
+    let user_info = get_user_claim(&req);
+    client
+        .revoke_other_sessions(&user_info.user_id, &user_info.session_id)
+        .await?;
+    let event = new_audit_event(&req, AuditAction::OtherSessionsRevoked);
+    record_audit_event(&client, event).await;
+    render_session_list(&client, &req).await
+}
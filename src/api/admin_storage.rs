@@ -0,0 +1,25 @@
+use crate::database::db;
+use crate::errors::blog::BlogError;
+use crate::jobs::storage_gc::reconcile_blog_files;
+use crate::models::reconcile_report::ReconcileOptions;
+
+use actix_web::web::{Data, Query};
+use actix_web::{post, HttpResponse};
+use aws_sdk_s3 as s3;
+
+#[post("/api/admin/storage/reconcile")]
+async fn reconcile_storage(
+    client: Data<db::DbClient>,
+    s3_client: Data<s3::Client>,
+    options: Query<ReconcileOptions>,
+) -> Result<HttpResponse, BlogError> {
+    let report = reconcile_blog_files(&client, &s3_client, options.dry_run).await?;
+    if !report.dry_run {
+        log::info!(
+            "Storage reconciliation deleted {} orphaned blob(s), {} failed",
+            report.deleted.len(),
+            report.failed.len()
+        );
+    }
+    Ok(HttpResponse::Ok().json(report))
+}
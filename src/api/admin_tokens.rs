@@ -0,0 +1,62 @@
+use crate::database::db;
+use crate::errors::auth::AuthError;
+use crate::middleware::auth::get_user_claim;
+use crate::models::api_token::NewApiToken;
+use crate::models::audit_event::AuditAction;
+use crate::templates::admin_profile::ApiTokenList;
+use crate::utils::api_tokens::{create_api_token, get_api_tokens, revoke_api_token};
+use crate::utils::audit::{new_audit_event, record_audit_event};
+use crate::utils::html::render_template;
+use crate::utils::security::get_csrf_header_json;
+
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Form, Path};
+use actix_web::{delete, post, HttpRequest, HttpResponse};
+use bson::oid::ObjectId;
+
+async fn render_api_token_list(
+    client: &db::DbClient,
+    req: &HttpRequest,
+    new_token: Option<String>,
+) -> Result<HttpResponse, AuthError> {
+    let user_info = get_user_claim(req);
+    let template = ApiTokenList {
+        csrf_header_json: get_csrf_header_json(req, None),
+        api_tokens: get_api_tokens(client, &user_info.user_id).await?,
+        new_token,
+    };
+    Ok(render_template(template, StatusCode::OK))
+}
+
+#[post("/api/admin/tokens")]
+async fn new_api_token(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    data: Form<NewApiToken>,
+) -> Result<HttpResponse, AuthError> {
+    let user_info = get_user_claim(&req);
+    let token = create_api_token(&client, user_info.user_id, &data).await?;
+    let scopes: Vec<String> = data.get_scopes().iter().map(ToString::to_string).collect();
+    let event = new_audit_event(&req, AuditAction::ApiTokenCreated).with_details(format!(
+        "{} ({})",
+        data.name.trim(),
+        scopes.join(", ")
+    ));
+    record_audit_event(&client, event).await;
+    render_api_token_list(&client, &req, Some(token)).await
+}
+
+#[delete("/api/admin/tokens/{id}")]
+async fn delete_api_token(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    token_id: Path<String>,
+) -> Result<HttpResponse, AuthError> {
+    let user_info = get_user_claim(&req);
+    let token_id =
+        ObjectId::parse_str(token_id.as_str()).map_err(|_| AuthError::ApiTokenNotFound)?;
+    revoke_api_token(&client, &user_info.user_id, &token_id).await?;
+    let event = new_audit_event(&req, AuditAction::ApiTokenRevoked).with_target(token_id);
+    record_audit_event(&client, event).await;
+    render_api_token_list(&client, &req, None).await
+}
@@ -0,0 +1,147 @@
+use crate::constants;
+use crate::database::db;
+use crate::errors::auth::AuthError;
+use crate::middleware::auth::get_user_claim;
+use crate::models::audit_event::AuditAction;
+use crate::models::invite::NewInvite;
+use crate::models::manage_user::{NewUser, UpdateUserRole};
+use crate::templates::admin_profile::{InviteList, UserList};
+use crate::utils::audit::{new_audit_event, record_audit_event};
+use crate::utils::html::render_template;
+use crate::utils::security::get_csrf_header_json;
+use crate::utils::users::{
+    create_invite, create_user, get_invites, get_users, revoke_invite, set_user_disabled,
+    set_user_role,
+};
+
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Form, Path};
+use actix_web::{delete, patch, post, HttpRequest, HttpResponse};
+use bson::oid::ObjectId;
+
+#[inline]
+fn parse_user_id(user_id: &str) -> Result<ObjectId, AuthError> {
+    ObjectId::parse_str(user_id).map_err(|_| AuthError::UserNotFound)
+}
+
+async fn render_user_list(
+    client: &db::DbClient,
+    req: &HttpRequest,
+) -> Result<HttpResponse, AuthError> {
+    let template = UserList {
+        csrf_header_json: get_csrf_header_json(req, None),
+        users: get_users(client).await?,
+        current_user_id: get_user_claim(req).user_id.to_hex(),
+    };
+    Ok(render_template(template, StatusCode::OK))
+}
+
+async fn render_invite_list(
+    client: &db::DbClient,
+    req: &HttpRequest,
+) -> Result<HttpResponse, AuthError> {
+    let template = InviteList {
+        csrf_header_json: get_csrf_header_json(req, None),
+        invites: get_invites(client, req).await?,
+    };
+    Ok(render_template(template, StatusCode::OK))
+}
+
+#[post("/api/admin/users")]
+async fn new_user(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    data: Form<NewUser>,
+) -> Result<HttpResponse, AuthError> {
+    let user = create_user(
+        &client,
+        &data.username,
+        &data.email,
+        &data.password,
+        data.role,
+    )
+    .await?;
+    let event = new_audit_event(&req, AuditAction::UserCreated)
+        .with_target(user._id)
+        .with_details(format!("{} role", data.role));
+    record_audit_event(&client, event).await;
+    render_user_list(&client, &req).await
+}
+
+#[patch("/api/admin/users/{id}/role")]
+async fn update_user_role(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    user_id: Path<String>,
+    data: Form<UpdateUserRole>,
+) -> Result<HttpResponse, AuthError> {
+    let user_id = parse_user_id(&user_id)?;
+    let actor_id = get_user_claim(&req).user_id;
+    set_user_role(&client, &actor_id, &user_id, data.role).await?;
+    let event = new_audit_event(&req, AuditAction::UserRoleChanged)
+        .with_target(user_id)
+        .with_details(format!("{} role", data.role));
+    record_audit_event(&client, event).await;
+    render_user_list(&client, &req).await
+}
+
+#[post("/api/admin/users/{id}/disable")]
+async fn disable_user(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    user_id: Path<String>,
+) -> Result<HttpResponse, AuthError> {
+    let user_id = parse_user_id(&user_id)?;
+    let actor_id = get_user_claim(&req).user_id;
+    set_user_disabled(&client, &actor_id, &user_id, true).await?;
+    let event = new_audit_event(&req, AuditAction::UserDisabled).with_target(user_id);
+    record_audit_event(&client, event).await;
+    render_user_list(&client, &req).await
+}
+
+#[post("/api/admin/users/{id}/enable")]
+async fn enable_user(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    user_id: Path<String>,
+) -> Result<HttpResponse, AuthError> {
+    let user_id = parse_user_id(&user_id)?;
+    let actor_id = get_user_claim(&req).user_id;
+    set_user_disabled(&client, &actor_id, &user_id, false).await?;
+    let event = new_audit_event(&req, AuditAction::UserEnabled).with_target(user_id);
+    record_audit_event(&client, event).await;
+    render_user_list(&client, &req).await
+}
+
+#[post("/api/admin/invites")]
+async fn new_invite(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    data: Form<NewInvite>,
+) -> Result<HttpResponse, AuthError> {
+    let days = data
+        .days
+        .unwrap_or(constants::INVITE_DEFAULT_DAYS)
+        .clamp(1, constants::INVITE_MAX_DAYS);
+    let created_by = get_user_claim(&req).user_id;
+    let invite = create_invite(&client, created_by, data.role, days, None).await?;
+    let event = new_audit_event(&req, AuditAction::InviteCreated)
+        .with_target(invite._id)
+        .with_details(format!("{} role", data.role));
+    record_audit_event(&client, event).await;
+    render_invite_list(&client, &req).await
+}
+
+#[delete("/api/admin/invites/{id}")]
+async fn delete_invite(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    invite_id: Path<String>,
+) -> Result<HttpResponse, AuthError> {
+    let invite_id =
+        ObjectId::parse_str(invite_id.as_str()).map_err(|_| AuthError::InviteNotFound)?;
+    revoke_invite(&client, &invite_id).await?;
+    let event = new_audit_event(&req, AuditAction::InviteRevoked).with_target(invite_id);
+    record_audit_event(&client, event).await;
+    render_invite_list(&client, &req).await
+}
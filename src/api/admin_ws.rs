@@ -0,0 +1,138 @@
+use crate::constants;
+use crate::database::db;
+use crate::errors::session::SessionError;
+use crate::middleware::auth::{get_user_claim, UserClaim};
+use crate::models::live_preview::{PreviewEdit, PreviewUpdate};
+use crate::utils::live_preview::PreviewDocument;
+
+use actix_web::http::header::{HeaderValue, SEC_WEBSOCKET_PROTOCOL};
+use actix_web::{get, rt, web, Error, HttpRequest, HttpResponse};
+use actix_ws::{AggregatedMessage, CloseCode, CloseReason, Session};
+use std::time::Instant;
+
+// the editor reconnects on its next edit after an idle close
+const IDLE_CLOSE_CODE: u16 = 4000;
+
+async fn send_update(session: &mut Session, update: &PreviewUpdate) -> bool {
+    let msg = match serde_json::to_string(update) {
+        Ok(msg) => msg,
+        Err(e) => {
+            log::error!("Failed to serialise live preview update: {:?}", e);
+            return false;
+        }
+    };
+    session.text(msg).await.is_ok()
+}
+
+/// Returns false if the session was revoked, e.g. by logging out, or has expired.
+async fn is_session_valid(client: &db::DbClient, user_claim: &UserClaim) -> bool {
+    match client.get_session_by_id(&user_claim.session_id).await {
+        Ok(session) => !session.is_expired() && session.user_id == user_claim.user_id,
+        Err(SessionError::NotFound) => false,
+        // the connection is kept open if the database is temporarily unavailable
+        Err(_) => true,
+    }
+}
+
+#[inline]
+fn get_close_reason(code: CloseCode, description: &str) -> Option<CloseReason> {
+    Some(CloseReason {
+        code,
+        description: Some(description.to_string()),
+    })
+}
+
+/// Renders the markdown in the editor as it is being typed.
+///
+/// The editor sends its edits as they happen and only the blocks
+/// that changed are sent back together with any content warnings.
+///
+/// The connection is closed when the browser stops answering the pings,
+/// after no edits for a while or once the session is no longer valid.
+#[get("/api/admin/ws/blog/preview")]
+async fn live_preview(
+    client: web::Data<db::DbClient>,
+    req: HttpRequest,
+    body: web::Payload,
+) -> Result<HttpResponse, Error> {
+    let user_claim = get_user_claim(&req);
+    let (mut res, mut session, stream) = actix_ws::handle(&req, body)?;
+    let mut stream = stream
+        .max_frame_size(constants::WS_PREVIEW_MAX_FRAME_SIZE)
+        .aggregate_continuations()
+        .max_continuation_size(constants::WS_PREVIEW_MAX_FRAME_SIZE);
+
+    // the browser closes the connection unless one of its requested subprotocols is selected
+    res.headers_mut().insert(
+        SEC_WEBSOCKET_PROTOCOL,
+        HeaderValue::from_static(constants::WS_PREVIEW_PROTOCOL),
+    );
+
+    rt::spawn(async move {
+        let mut document = PreviewDocument::default();
+        let mut last_heartbeat = Instant::now();
+        let mut last_edit = Instant::now();
+        let mut heartbeat = tokio::time::interval(constants::WS_PREVIEW_HEARTBEAT_INTERVAL);
+        let session_check_period = constants::WS_PREVIEW_SESSION_CHECK_INTERVAL;
+        let mut session_check = tokio::time::interval_at(
+            tokio::time::Instant::now() + session_check_period,
+            session_check_period,
+        );
+
+        let reason = loop {
+            tokio::select! {
+                msg = stream.recv() => {
+                    let Some(msg) = msg else {
+                        break None;
+                    };
+                    match msg {
+                        Ok(AggregatedMessage::Text(text)) => {
+                            last_heartbeat = Instant::now();
+                            last_edit = Instant::now();
+                            let update = match serde_json::from_str::<PreviewEdit>(&text) {
+                                Ok(edit) => document.apply(edit),
+                                Err(_) => PreviewUpdate::Error {
+                                    message: "Invalid live preview message".to_string(),
+                                },
+                            };
+                            if !send_update(&mut session, &update).await {
+                                return;
+                            }
+                        }
+                        Ok(AggregatedMessage::Ping(bytes)) => {
+                            last_heartbeat = Instant::now();
+                            if session.pong(&bytes).await.is_err() {
+                                return;
+                            }
+                        }
+                        Ok(AggregatedMessage::Pong(_)) => last_heartbeat = Instant::now(),
+                        Ok(AggregatedMessage::Close(reason)) => break reason,
+                        Ok(_) => {}
+                        Err(e) => {
+                            log::error!("Live preview connection error: {:?}", e);
+                            break None;
+                        }
+                    }
+                }
+                _ = heartbeat.tick() => {
+                    if last_heartbeat.elapsed() > constants::WS_PREVIEW_CLIENT_TIMEOUT {
+                        break None;
+                    }
+                    if last_edit.elapsed() > constants::WS_PREVIEW_IDLE_TIMEOUT {
+                        break get_close_reason(CloseCode::Other(IDLE_CLOSE_CODE), "Idle");
+                    }
+                    if session.ping(b"").await.is_err() {
+                        return;
+                    }
+                }
+                _ = session_check.tick() => {
+                    if !is_session_valid(&client, &user_claim).await {
+                        break get_close_reason(CloseCode::Policy, "Session is no longer valid");
+                    }
+                }
+            }
+        };
+        let _ = session.close(reason).await;
+    });
+    Ok(res)
+}
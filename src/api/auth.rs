@@ -2,27 +2,38 @@ use crate::constants;
 use crate::database::db;
 use crate::errors::auth::AuthError;
 use crate::middleware::auth;
+use crate::models::audit_event::AuditAction;
+use crate::models::invite::AcceptInvite;
+use crate::models::passkey::{self, PasskeyAssertion, PasskeyCeremony, PasskeyLoginData};
+use crate::models::user::{self, User};
 use crate::models::{login_data::LoginData, session::Session};
 use crate::security::cf_turnstile;
 use crate::security::chacha_crypto::decrypt_with_db_key;
 use crate::security::pw_hasher;
+use crate::security::rate_limiter::{get_ip_key, get_user_key, DefaultLoginRateLimiter};
 use crate::security::totp;
+use crate::security::webauthn;
 use crate::templates;
+use crate::utils::audit::{new_audit_event, record_audit_event, record_rate_limited_audit_event};
 use crate::utils::auth::{cf_turnstile::verify_captcha, is_logged_in};
 use crate::utils::html::render_template;
+use crate::utils::passkey::get_request_options;
+use crate::utils::users;
 
-use actix_web::cookie::{time as cookie_time, Cookie, SameSite};
-use actix_web::http::header::ContentType;
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::http::header::{self, ContentType};
 use actix_web::http::StatusCode;
-use actix_web::{post, web, web::Data, web::Form, HttpRequest, HttpResponse};
+use actix_web::{post, web, web::Data, web::Form, web::Json, HttpRequest, HttpResponse};
 use askama::Template;
 use bson::doc;
+use bson::oid::ObjectId;
 use rand::Rng;
 use tokio::time as tokio_time;
 
 #[post("/api/admin")]
 async fn admin_honeypot(
     req: HttpRequest,
+    client: Data<db::DbClient>,
     login_data: Form<LoginData>,
 ) -> Result<HttpResponse, AuthError> {
     log::warn!(
@@ -31,15 +42,62 @@ async fn admin_honeypot(
         login_data.username,
         login_data.password
     );
+    // recorded before the captcha since bots rarely solve it
+    let event =
+        new_audit_event(&req, AuditAction::HoneypotHit).with_actor_name(&login_data.username);
+    record_rate_limited_audit_event(&client, event, constants::HONEYPOT_AUDIT_WINDOW).await;
     verify_captcha!(&req, &login_data.cf_turnstile_res);
     let sleep_time = rand::rng().random_range(2000..4000);
     tokio_time::sleep(tokio_time::Duration::from_millis(sleep_time)).await;
     Err(AuthError::InvalidCredentials)
 }
 
+#[inline]
+fn get_login_ip_key(req: &HttpRequest) -> String {
+    get_ip_key(&cf_turnstile::get_ip_addr(req).unwrap_or("unknown".to_string()))
+}
+
+/// Records the failed login attempt against every key, e.g. the IP address and the account.
+async fn record_login_failure(
+    limiter: &DefaultLoginRateLimiter,
+    keys: &[&str],
+) -> Result<(), AuthError> {
+    for key in keys {
+        limiter.record_failure(key).await?;
+    }
+    Ok(())
+}
+
+async fn record_failed_login_event(
+    client: &db::DbClient,
+    req: &HttpRequest,
+    username: &str,
+    user_id: Option<ObjectId>,
+    reason: &str,
+) {
+    let mut event = new_audit_event(req, AuditAction::LoginFailure)
+        .with_actor_name(username)
+        .with_details(reason);
+    if let Some(user_id) = user_id {
+        event = event.with_actor(user_id);
+    }
+    record_audit_event(client, event).await;
+}
+
+async fn clear_login_failures(
+    limiter: &DefaultLoginRateLimiter,
+    keys: &[&str],
+) -> Result<(), AuthError> {
+    for key in keys {
+        limiter.clear(key).await?;
+    }
+    Ok(())
+}
+
 pub async fn login(
     req: HttpRequest,
     client: Data<db::DbClient>,
+    limiter: Data<DefaultLoginRateLimiter>,
     login_data: Form<LoginData>,
 ) -> Result<HttpResponse, AuthError> {
     match req.cookie(constants::AUTH_COOKIE_NAME) {
@@ -48,23 +106,41 @@ pub async fn login(
         }
         None => {}
     }
+    let ip_key = get_login_ip_key(&req);
+    limiter.check(&ip_key).await?;
     verify_captcha!(&req, &login_data.cf_turnstile_res);
 
     let login_data_password = &login_data.password;
     if login_data_password.len() > 64 {
         return Err(AuthError::InvalidCredentials);
     }
+    // the input can either be a TOTP code or one of the user's recovery codes
     let login_data_totp_input = match &login_data.totp_input {
-        Some(totp_input) => totp_input.to_string(),
+        Some(totp_input) => totp_input.trim().to_string(),
         None => "".to_string(),
     };
-    if login_data_totp_input.len() > 6 {
+    if login_data_totp_input.len() > totp::RECOVERY_CODE_MAX_INPUT_LEN {
         return Err(AuthError::InvalidCredentials);
     }
 
-    let user = client
+    let user = match client
         .get_user_by_username_or_email(&login_data.username)
-        .await?;
+        .await
+    {
+        Ok(user) => user,
+        Err(AuthError::UserNotFound) => {
+            // unknown usernames are throttled like existing accounts to avoid enumeration attacks
+            let user_key = get_user_key(&login_data.username);
+            limiter.check(&user_key).await?;
+            record_login_failure(&limiter, &[&ip_key, &user_key]).await?;
+            record_failed_login_event(&client, &req, &login_data.username, None, "unknown user")
+                .await;
+            return Err(AuthError::UserNotFound);
+        }
+        Err(e) => return Err(e),
+    };
+    let user_key = get_user_key(&user._id.to_hex());
+    limiter.check(&user_key).await?;
 
     // Initialise the variables to be consumed by the blocking operation
     let login_data_password = login_data_password.to_string();
@@ -81,10 +157,17 @@ pub async fn login(
     } else {
         vec![]
     };
+    let user_recovery_codes = user.get_recovery_codes().to_vec();
+    let user_totp_config = *user.get_totp_config();
+    let user_totp_last_step = user.get_totp_last_step();
 
-    web::block(move || {
+    // the TOTP code is only needed if the user has not registered any passkeys
+    // or has chosen to enter it instead of using one of their passkeys
+    let user_has_passkeys = user.has_passkeys();
+    let use_totp = user_has_totp && (!login_data_totp_input.is_empty() || !user_has_passkeys);
+    let verification = web::block(move || {
         pw_hasher::verify_user_password(&login_data_password, &user_password_hash, true)?;
-        if user_has_totp {
+        if use_totp {
             let decrypted_totp = match decrypt_with_db_key(&user_totp_secret) {
                 Ok(decrypted_totp) => {
                     String::from_utf8(decrypted_totp).expect("totp secret should be valid utf-8")
@@ -98,26 +181,191 @@ pub async fn login(
             if login_data_totp_input.is_empty() {
                 return Err(AuthError::MissingTotp);
             }
-            if !totp::verify_totp(&login_data_totp_input, &decrypted_totp) {
-                return Err(AuthError::InvalidTotp);
+            if !totp::is_totp_code(&login_data_totp_input) {
+                return match totp::find_recovery_code(&login_data_totp_input, &user_recovery_codes)?
+                {
+                    Some(recovery_code) => Ok((None, Some(recovery_code))),
+                    None => Err(AuthError::InvalidTotp),
+                };
             }
+            return match totp::verify_totp(
+                &login_data_totp_input,
+                &decrypted_totp,
+                &user_totp_config,
+                user_totp_last_step,
+            ) {
+                Some(totp_step) => Ok((Some(totp_step), None)),
+                None => Err(AuthError::InvalidTotp),
+            };
         }
-        Ok(())
+        Ok((None, None))
     })
     .await
     .map_err(|e| {
         log::error!("Blocking Error when verifying password and TOTP: {:?}", e);
         AuthError::InternalServerError
-    })??;
+    })?;
+    let (totp_step, used_recovery_code) = match verification {
+        Ok(verification) => verification,
+        Err(e @ (AuthError::InvalidCredentials | AuthError::InvalidTotp)) => {
+            record_login_failure(&limiter, &[&ip_key, &user_key]).await?;
+            let reason = match e {
+                AuthError::InvalidTotp => "invalid TOTP or recovery code",
+                _ => "invalid password",
+            };
+            record_failed_login_event(&client, &req, &login_data.username, Some(user._id), reason)
+                .await;
+            return Err(e);
+        }
+        Err(e) => return Err(e),
+    };
+    // only checked after the password to avoid revealing which accounts exist
+    if user.is_disabled() {
+        return Err(AuthError::AccountDisabled);
+    }
+
+    // the time step must be newer than the last accepted one in case a concurrent login used the same code
+    if let Some(totp_step) = totp_step {
+        let result = client
+            .get_user_collection()
+            .update_one(
+                doc! {"_id": user._id, "$or": [
+                    {user::TOTP_LAST_STEP_KEY: null},
+                    {user::TOTP_LAST_STEP_KEY: {"$lt": totp_step}},
+                ]},
+                doc! {"$set": {user::TOTP_LAST_STEP_KEY: totp_step}},
+            )
+            .await
+            .map_err(|e| {
+                log::error!("Failed to update the last TOTP time step: {:?}", e);
+                AuthError::InternalServerError
+            })?;
+        if result.matched_count == 0 {
+            log::warn!("Rejected a reused TOTP code for user {}", user._id);
+            record_login_failure(&limiter, &[&ip_key, &user_key]).await?;
+            record_failed_login_event(
+                &client,
+                &req,
+                &login_data.username,
+                Some(user._id),
+                "reused TOTP code",
+            )
+            .await;
+            return Err(AuthError::InvalidTotp);
+        }
+    }
+
+    // the recovery code is only accepted if it has not been used by a concurrent login
+    let used_recovery_code_login = used_recovery_code.is_some();
+    if let Some(recovery_code) = used_recovery_code {
+        let result = client
+            .get_user_collection()
+            .update_one(
+                doc! {"_id": user._id, user::RECOVERY_CODES_KEY: &recovery_code},
+                doc! {"$pull": {user::RECOVERY_CODES_KEY: &recovery_code}},
+            )
+            .await
+            .map_err(|e| {
+                log::error!("Failed to remove used recovery code: {:?}", e);
+                AuthError::InternalServerError
+            })?;
+        if result.modified_count == 0 {
+            record_login_failure(&limiter, &[&ip_key, &user_key]).await?;
+            record_failed_login_event(
+                &client,
+                &req,
+                &login_data.username,
+                Some(user._id),
+                "reused recovery code",
+            )
+            .await;
+            return Err(AuthError::InvalidTotp);
+        }
+        log::warn!("User {} signed in with a recovery code", user._id);
+    }
 
-    let exp_sec = if login_data.remember_session() {
-        constants::SESSION_TIMEOUT_REMEMBER
+    // the failed attempts are only cleared once the passkey has also been verified
+    if user_has_passkeys && !use_totp {
+        return request_passkey(&client, &user, login_data.remember_session(), user_has_totp).await;
+    }
+    clear_login_failures(&limiter, &[&ip_key, &user_key]).await?;
+    let method = if used_recovery_code_login {
+        "password and recovery code"
+    } else if use_totp {
+        "password and TOTP"
     } else {
-        constants::SESSION_TIMEOUT
+        "password"
+    };
+    let event = new_audit_event(&req, AuditAction::LoginSuccess)
+        .with_actor(user._id)
+        .with_details(method);
+    record_audit_event(&client, event).await;
+    create_session(&req, &client, &user, login_data.remember_session()).await
+}
+
+/// Asks the browser to confirm the sign in with one of the user's passkeys
+/// after their password has been verified.
+async fn request_passkey(
+    client: &db::DbClient,
+    user: &User,
+    remember: bool,
+    has_totp: bool,
+) -> Result<HttpResponse, AuthError> {
+    let options = get_request_options(client, Some(user), remember).await?;
+    let options = serde_json::to_string(&options).map_err(|e| {
+        log::error!("Failed to serialise passkey request options: {:?}", e);
+        AuthError::InternalServerError
+    })?;
+
+    let template = templates::alerts::ErrAlert {
+        err: if has_totp {
+            "Use your passkey or enter your TOTP code to continue"
+        } else {
+            "Use your passkey to continue"
+        },
     };
+    let mut response = render_template(template, StatusCode::UNAUTHORIZED);
+    let headers = response.headers_mut();
+    headers.insert(
+        "X-Login-Error".parse().unwrap(),
+        "MissingPasskey".parse().unwrap(),
+    );
+    headers.insert(
+        "X-Passkey-Options".parse().unwrap(),
+        options.parse().unwrap(),
+    );
+    if has_totp {
+        headers.insert("X-Totp-Enabled".parse().unwrap(), "true".parse().unwrap());
+    }
+    Ok(response)
+}
+
+#[inline]
+fn get_user_agent(req: &HttpRequest) -> String {
+    req.headers()
+        .get(header::USER_AGENT)
+        .and_then(|user_agent| user_agent.to_str().ok())
+        .unwrap_or_default()
+        .chars()
+        .take(constants::SESSION_USER_AGENT_MAX_LENGTH)
+        .collect()
+}
+
+async fn create_session(
+    req: &HttpRequest,
+    client: &db::DbClient,
+    user: &User,
+    remember: bool,
+) -> Result<HttpResponse, AuthError> {
     let session_col = client.get_session_collection();
-    let session = Session::new(user._id, exp_sec);
-    let session_expiry = session.expiry.timestamp_millis();
+    let session = Session::new(
+        user._id,
+        user.get_role(),
+        remember,
+        get_user_agent(req),
+        cf_turnstile::get_ip_addr(req).unwrap_or_default(),
+    );
+    let session_expiry = session.expiry;
     let result = match session_col.insert_one(session).await {
         Ok(result) => result,
         Err(e) => {
@@ -126,24 +374,12 @@ pub async fn login(
         }
     };
 
-    let claims = auth::create_user_claim(user._id, result.inserted_id.as_object_id().unwrap());
-    let token = auth::sign_payload(&claims);
-    let max_age = if login_data.remember_session() {
-        // offset_dt is 10 seconds before the EXPIRY time for extra leeway
-        let offset_dt = cookie_time::OffsetDateTime::from_unix_timestamp(session_expiry - 10_000);
-        Some(offset_dt.unwrap())
-    } else {
-        None
-    };
-
-    let c = Cookie::build(constants::AUTH_COOKIE_NAME, token.clone())
-        .domain(constants::get_domain())
-        .path("/")
-        .same_site(SameSite::Lax)
-        .http_only(true)
-        .secure(!constants::get_debug_mode())
-        .expires(max_age)
-        .finish();
+    let claims = auth::create_user_claim(
+        user._id,
+        result.inserted_id.as_object_id().unwrap(),
+        session_expiry,
+    );
+    let c = auth::build_auth_cookie(&claims, remember);
     let template = templates::alerts::SuccessAlert {
         msg: "You have logged in",
     };
@@ -152,7 +388,152 @@ pub async fn login(
     response
         .headers_mut()
         .insert("HX-Redirect".parse().unwrap(), "/".parse().unwrap());
-    return Ok(response);
+    Ok(response)
+}
+
+/// Starts a passwordless sign in with a discoverable passkey.
+pub async fn passkey_login_options(
+    req: HttpRequest,
+    client: Data<db::DbClient>,
+    limiter: Data<DefaultLoginRateLimiter>,
+    login_data: Form<PasskeyLoginData>,
+) -> Result<HttpResponse, AuthError> {
+    if req.cookie(constants::AUTH_COOKIE_NAME).is_some() {
+        return Err(AuthError::AlreadyLoggedIn);
+    }
+    limiter.check(&get_login_ip_key(&req)).await?;
+    verify_captcha!(&req, &login_data.cf_turnstile_res);
+
+    let options = get_request_options(&client, None, login_data.remember_session()).await?;
+    Ok(HttpResponse::Ok().json(options))
+}
+
+/// Finishes signing in with a passkey, either as a second factor or without a password.
+///
+/// Since a passkey cannot be guessed, a valid one also lifts the lockout of the account
+/// so that the owner can still sign in while someone is guessing their password.
+pub async fn passkey_login(
+    req: HttpRequest,
+    client: Data<db::DbClient>,
+    limiter: Data<DefaultLoginRateLimiter>,
+    assertion: Json<PasskeyAssertion>,
+) -> Result<HttpResponse, AuthError> {
+    if req.cookie(constants::AUTH_COOKIE_NAME).is_some() {
+        return Err(AuthError::AlreadyLoggedIn);
+    }
+    let ip_key = get_login_ip_key(&req);
+    limiter.check(&ip_key).await?;
+
+    let (user, remember) = match verify_passkey_login(&client, &assertion).await {
+        Ok(result) => result,
+        Err(AuthError::InvalidPasskey) => {
+            record_login_failure(&limiter, &[&ip_key]).await?;
+            let event =
+                new_audit_event(&req, AuditAction::LoginFailure).with_details("invalid passkey");
+            record_audit_event(&client, event).await;
+            return Err(AuthError::InvalidPasskey);
+        }
+        Err(e) => return Err(e),
+    };
+    if user.is_disabled() {
+        return Err(AuthError::AccountDisabled);
+    }
+    clear_login_failures(&limiter, &[&ip_key, &get_user_key(&user._id.to_hex())]).await?;
+    let event = new_audit_event(&req, AuditAction::LoginSuccess)
+        .with_actor(user._id)
+        .with_details("passkey");
+    record_audit_event(&client, event).await;
+    create_session(&req, &client, &user, remember).await
+}
+
+/// Verifies the passkey assertion and returns the user and whether to remember the session.
+async fn verify_passkey_login(
+    client: &db::DbClient,
+    assertion: &PasskeyAssertion,
+) -> Result<(User, bool), AuthError> {
+    let challenge = client
+        .take_passkey_challenge(&assertion.challenge_id, PasskeyCeremony::Authentication)
+        .await?;
+    let user = match client.get_user_by_passkey_id(&assertion.id).await {
+        Ok(user) => user,
+        Err(AuthError::UserNotFound) => return Err(AuthError::InvalidPasskey),
+        Err(e) => return Err(e),
+    };
+    if challenge.user_id.is_some_and(|user_id| user_id != user._id) {
+        log::warn!(
+            "Passkey {} does not belong to the user signing in",
+            assertion.id
+        );
+        return Err(AuthError::InvalidPasskey);
+    }
+    if let Some(user_handle) = &assertion.user_handle {
+        if webauthn::decode(user_handle)? != user._id.bytes() {
+            log::warn!("The user handle of passkey {} does not match", assertion.id);
+            return Err(AuthError::InvalidPasskey);
+        }
+    }
+    let passkey = user
+        .get_passkeys()
+        .iter()
+        .find(|passkey| passkey.id == assertion.id)
+        .ok_or(AuthError::InvalidPasskey)?;
+
+    let sign_count = webauthn::verify_assertion(
+        passkey,
+        &challenge.challenge,
+        &webauthn::decode(&assertion.client_data_json)?,
+        &webauthn::decode(&assertion.authenticator_data)?,
+        &webauthn::decode(&assertion.signature)?,
+        challenge.user_id.is_none(),
+    )?;
+
+    // the stored sign counter is part of the filter so that
+    // the same assertion cannot be used twice concurrently
+    let result = client
+        .get_user_collection()
+        .update_one(
+            doc! {
+                "_id": user._id,
+                user::PASSKEYS_KEY: {"$elemMatch": {
+                    passkey::ID_KEY: &passkey.id,
+                    passkey::SIGN_COUNT_KEY: passkey.sign_count,
+                }},
+            },
+            doc! {"$set": {
+                format!("{}.$.{}", user::PASSKEYS_KEY, passkey::SIGN_COUNT_KEY): i64::from(sign_count),
+                format!("{}.$.{}", user::PASSKEYS_KEY, passkey::LAST_USED_KEY): bson::DateTime::now(),
+            }},
+        )
+        .await
+        .map_err(|e| {
+            log::error!("Failed to update passkey sign counter: {:?}", e);
+            AuthError::InternalServerError
+        })?;
+    if result.matched_count == 0 {
+        log::warn!("Passkey {} was used concurrently", passkey.id);
+        return Err(AuthError::InvalidPasskey);
+    }
+    Ok((user, challenge.remember))
+}
+
+/// Creates the account of an invite link and signs the new user in.
+#[post("/api/invite")]
+async fn accept_invite(
+    req: HttpRequest,
+    client: Data<db::DbClient>,
+    invite_data: Form<AcceptInvite>,
+) -> Result<HttpResponse, AuthError> {
+    if req.cookie(constants::AUTH_COOKIE_NAME).is_some() {
+        return Err(AuthError::AlreadyLoggedIn);
+    }
+    verify_captcha!(&req, &invite_data.cf_turnstile_res);
+
+    let user = users::accept_invite(&client, &invite_data).await?;
+    let event = new_audit_event(&req, AuditAction::InviteAccepted)
+        .with_actor(user._id)
+        .with_details(format!("{} role", user.get_role()));
+    record_audit_event(&client, event).await;
+    create_session(&req, &client, &user, false).await
 }
 
 #[post("/api/logout")]
@@ -173,6 +554,8 @@ async fn logout(req: HttpRequest, client: Data<db::DbClient>) -> HttpResponse {
             log::error!("Failed to delete session from db: {:?}", e);
         }
     }
+    let event = new_audit_event(&req, AuditAction::Logout).with_target(session_id);
+    record_audit_event(&client, event).await;
 
     let mut auth_cookie = Cookie::build(constants::AUTH_COOKIE_NAME, "")
         .domain(constants::get_domain())
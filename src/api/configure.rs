@@ -2,8 +2,33 @@ use crate::api::admin::{
     delete_blog, new_blog, preview_blog, publish_blog_post, unpublish_blog_post, update_blog,
     upload_blog_files,
 };
-use crate::api::admin_profile::{change_password, generate_2fa, remove_2fa, setup_2fa};
-use crate::api::auth::{admin_honeypot, login, logout};
+use crate::api::admin_audit::export_audit_log;
+use crate::api::admin_backup::{
+    export_site_archive, import_site_archive, list_blog_backups, restore_blog_backup,
+    restore_blog_backups,
+};
+use crate::api::admin_draft::{discard_blog_draft, save_blog_draft};
+use crate::api::admin_keys::reencrypt_keys;
+use crate::api::admin_links::check_links;
+use crate::api::admin_markdown::{
+    export_blog_markdown, export_blogs_markdown, import_blogs_markdown, import_content_directory,
+    import_wordpress_export,
+};
+use crate::api::admin_media::{search_media, update_media_alt_text};
+use crate::api::admin_preview::{delete_preview_link, list_preview_links, new_preview_link};
+use crate::api::admin_profile::{
+    change_password, delete_passkey, generate_2fa, passkey_options, regenerate_recovery_codes,
+    register_passkey, remove_2fa, revoke_other_sessions, revoke_session, setup_2fa, unlock_login,
+};
+use crate::api::admin_storage::reconcile_storage;
+use crate::api::admin_tokens::{delete_api_token, new_api_token};
+use crate::api::admin_users::{
+    delete_invite, disable_user, enable_user, new_invite, new_user, update_user_role,
+};
+use crate::api::admin_ws::live_preview;
+use crate::api::auth::{
+    accept_invite, admin_honeypot, login, logout, passkey_login, passkey_login_options,
+};
 use crate::api::csrf::get_csrf_token;
 use crate::api::general::{api_health, api_index};
 use crate::constants;
@@ -13,7 +38,19 @@ use actix_web::web;
 #[inline]
 pub fn add_api_routes(cfg: &mut web::ServiceConfig) {
     add_admin_routes(cfg);
+    add_admin_audit_routes(cfg);
+    add_admin_backup_routes(cfg);
+    add_admin_draft_routes(cfg);
+    add_admin_keys_routes(cfg);
+    add_admin_links_routes(cfg);
+    add_admin_markdown_routes(cfg);
+    add_admin_media_routes(cfg);
+    add_admin_preview_routes(cfg);
     add_admin_profile_routes(cfg);
+    add_admin_storage_routes(cfg);
+    add_admin_tokens_routes(cfg);
+    add_admin_users_routes(cfg);
+    add_admin_ws_routes(cfg);
     add_auth_routes(cfg);
     add_general_routes(cfg);
 }
@@ -29,12 +66,94 @@ fn add_admin_routes(cfg: &mut web::ServiceConfig) {
         .service(upload_blog_files);
 }
 
+#[inline]
+fn add_admin_audit_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(export_audit_log);
+}
+
+#[inline]
+fn add_admin_backup_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(restore_blog_backups)
+        .service(list_blog_backups)
+        .service(restore_blog_backup)
+        .service(export_site_archive)
+        .service(import_site_archive);
+}
+
+#[inline]
+fn add_admin_draft_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(save_blog_draft).service(discard_blog_draft);
+}
+
+#[inline]
+fn add_admin_keys_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(reencrypt_keys);
+}
+
+#[inline]
+fn add_admin_links_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(check_links);
+}
+
+#[inline]
+fn add_admin_markdown_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(export_blog_markdown)
+        .service(export_blogs_markdown)
+        .service(import_blogs_markdown)
+        .service(import_wordpress_export)
+        .service(import_content_directory);
+}
+
+#[inline]
+fn add_admin_media_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(search_media).service(update_media_alt_text);
+}
+
+#[inline]
+fn add_admin_preview_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_preview_links)
+        .service(new_preview_link)
+        .service(delete_preview_link);
+}
+
 #[inline]
 fn add_admin_profile_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(generate_2fa)
         .service(setup_2fa)
         .service(remove_2fa)
-        .service(change_password);
+        .service(regenerate_recovery_codes)
+        .service(change_password)
+        .service(passkey_options)
+        .service(register_passkey)
+        .service(delete_passkey)
+        .service(revoke_session)
+        .service(revoke_other_sessions)
+        .service(unlock_login);
+}
+
+#[inline]
+fn add_admin_storage_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(reconcile_storage);
+}
+
+#[inline]
+fn add_admin_tokens_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(new_api_token).service(delete_api_token);
+}
+
+#[inline]
+fn add_admin_users_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(new_user)
+        .service(update_user_role)
+        .service(disable_user)
+        .service(enable_user)
+        .service(new_invite)
+        .service(delete_invite);
+}
+
+#[inline]
+fn add_admin_ws_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(live_preview);
 }
 
 #[inline]
@@ -44,6 +163,15 @@ fn add_auth_routes(cfg: &mut web::ServiceConfig) {
             &format!("/api{}", constants::get_login_uri_path()),
             web::post().to(login),
         )
+        .route(
+            &format!("/api{}/passkey/options", constants::get_login_uri_path()),
+            web::post().to(passkey_login_options),
+        )
+        .route(
+            &format!("/api{}/passkey", constants::get_login_uri_path()),
+            web::post().to(passkey_login),
+        )
+        .service(accept_invite)
         .service(logout);
 }
 
@@ -1,5 +1,17 @@
 pub(crate) mod admin;
+pub(crate) mod admin_audit;
+pub(crate) mod admin_backup;
+pub(crate) mod admin_draft;
+pub(crate) mod admin_keys;
+pub(crate) mod admin_links;
+pub(crate) mod admin_markdown;
+pub(crate) mod admin_media;
+pub(crate) mod admin_preview;
 pub(crate) mod admin_profile;
+pub(crate) mod admin_storage;
+pub(crate) mod admin_tokens;
+pub(crate) mod admin_users;
+pub(crate) mod admin_ws;
 pub(crate) mod auth;
 pub(crate) mod configure;
 pub(crate) mod csrf;
@@ -0,0 +1,231 @@
+use crate::database::db::DbClient;
+use crate::errors::auth::AuthError;
+use crate::jobs::key_rotation::reencrypt_totp_secrets;
+use crate::security::rate_limiter::{
+    get_ip_key, get_user_key, DefaultLoginRateLimiter, MongoLoginAttemptStore,
+};
+use crate::utils::backup::{
+    export_site, get_blog_backups, import_site, restore_all_blogs, restore_blog,
+};
+use crate::utils::markdown_files::{export_markdown, import_markdown};
+use crate::utils::migration::{import_content_dir, import_wordpress};
+use crate::utils::static_site::export_static_site;
+
+use aws_sdk_s3 as s3;
+use bson::oid::ObjectId;
+use serde::Serialize;
+use std::io::{Error, ErrorKind};
+
+const USAGE: &str = "Usage:
+    backups <blog_id>             list the backups of a blog post
+    restore [blog_id [version]]   restore one or all blog posts from their backups
+    export <file>                 export the whole site into a zip archive
+    import <file>                 import a zip archive created by export
+    export-md <file> [blog_id]    export one or all blog posts as markdown into a zip archive
+    import-md [--dry-run] <file>...
+                                  import markdown files, their media or zip archives of them
+    import-wxr [--dry-run] <file> import the posts of a WordPress export
+    import-dir [--dry-run] <path> import the posts of a Hugo or Jekyll site directory or zip archive
+    export-static <dir>           render the public site into a directory of static HTML
+    unlock <username|ip>          lift the sign in backoff and lockout of an account or IP address
+    reencrypt-totp                re-encrypt the TOTP secrets with the newest DB encryption key";
+
+const DRY_RUN_FLAG: &str = "--dry-run";
+
+fn print_report<T: Serialize>(report: &T) -> std::io::Result<()> {
+    let report = serde_json::to_string_pretty(report)?;
+    println!("{}", report);
+    Ok(())
+}
+
+fn invalid_input(message: &str) -> Error {
+    eprintln!("{}", USAGE);
+    Error::new(ErrorKind::InvalidInput, message)
+}
+
+/// Returns the paths after the command and whether the dry run flag was given.
+fn get_import_paths(args: &[String]) -> (Vec<&String>, bool) {
+    let dry_run = args.iter().skip(1).any(|arg| arg == DRY_RUN_FLAG);
+    let paths = args
+        .iter()
+        .skip(1)
+        .filter(|arg| *arg != DRY_RUN_FLAG)
+        .collect();
+    (paths, dry_run)
+}
+
+/// Reads every file under the directory with its path relative to the root.
+fn read_dir_files(
+    root: &std::path::Path,
+    dir: &std::path::Path,
+    files: &mut Vec<(String, Vec<u8>)>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            read_dir_files(root, &path, files)?;
+        } else if let Ok(relative_path) = path.strip_prefix(root) {
+            let relative_path = relative_path.to_string_lossy().replace('\\', "/");
+            files.push((relative_path, std::fs::read(&path)?));
+        }
+    }
+    Ok(())
+}
+
+/// Lifts the backoff and lockout of the account or IP address and returns the number of keys unlocked.
+async fn unlock_login(db_client: &DbClient, target: &str) -> std::io::Result<usize> {
+    let limiter = DefaultLoginRateLimiter::new(MongoLoginAttemptStore::new(db_client));
+    // the failed attempts of unknown usernames are keyed by the submitted value
+    let mut keys = vec![get_ip_key(target), get_user_key(target)];
+    match db_client.get_user_by_username_or_email(target).await {
+        Ok(user) => keys.push(get_user_key(&user._id.to_hex())),
+        Err(AuthError::UserNotFound) => {}
+        Err(e) => return Err(Error::other(e)),
+    }
+
+    let mut unlocked = 0;
+    for key in keys {
+        if limiter.unlock(&key).await.map_err(Error::other)? {
+            println!("Unlocked {}", key);
+            unlocked += 1;
+        }
+    }
+    Ok(unlocked)
+}
+
+/// Runs a maintenance command from the command line
+/// instead of starting the web server.
+pub async fn run(
+    args: &[String],
+    db_client: &DbClient,
+    s3_client: &s3::Client,
+) -> std::io::Result<()> {
+    let command = args.first().map(String::as_str).unwrap_or_default();
+    let argument = args.get(1);
+    match command {
+        "restore" => match argument {
+            Some(blog_id) => {
+                let blog_id = ObjectId::parse_str(blog_id)
+                    .map_err(|_| invalid_input("Invalid blog post ID"))?;
+                let version = args.get(2).map(String::as_str);
+                let missing_files = restore_blog(db_client, s3_client, &blog_id, version)
+                    .await
+                    .map_err(Error::other)?;
+                println!("Blog {} restored successfully", blog_id);
+                for file in missing_files {
+                    println!("Missing file: {}", file);
+                }
+                Ok(())
+            }
+            None => {
+                let report = restore_all_blogs(db_client, s3_client)
+                    .await
+                    .map_err(Error::other)?;
+                print_report(&report)
+            }
+        },
+        "backups" => {
+            let blog_id = argument.ok_or_else(|| invalid_input("Missing blog post ID"))?;
+            let blog_id =
+                ObjectId::parse_str(blog_id).map_err(|_| invalid_input("Invalid blog post ID"))?;
+            let manifest = get_blog_backups(s3_client, &blog_id)
+                .await
+                .map_err(Error::other)?;
+            print_report(&manifest)
+        }
+        "export" => {
+            let path = argument.ok_or_else(|| invalid_input("Missing output file"))?;
+            let archive = export_site(db_client, s3_client)
+                .await
+                .map_err(Error::other)?;
+            std::fs::write(path, archive)?;
+            println!("Site exported to {}", path);
+            Ok(())
+        }
+        "import" => {
+            let path = argument.ok_or_else(|| invalid_input("Missing archive file"))?;
+            let archive = std::fs::read(path)?;
+            let report = import_site(db_client, s3_client, archive, None)
+                .await
+                .map_err(Error::other)?;
+            print_report(&report)
+        }
+        "export-md" => {
+            let path = argument.ok_or_else(|| invalid_input("Missing output file"))?;
+            let blog_id = match args.get(2) {
+                Some(blog_id) => Some(
+                    ObjectId::parse_str(blog_id)
+                        .map_err(|_| invalid_input("Invalid blog post ID"))?,
+                ),
+                None => None,
+            };
+            let archive = export_markdown(db_client, s3_client, blog_id.as_ref())
+                .await
+                .map_err(Error::other)?;
+            std::fs::write(path, archive)?;
+            println!("Markdown exported to {}", path);
+            Ok(())
+        }
+        "import-md" => {
+            let (paths, dry_run) = get_import_paths(args);
+            if paths.is_empty() {
+                return Err(invalid_input("Missing markdown or archive file"));
+            }
+            let mut files = Vec::with_capacity(paths.len());
+            for path in paths {
+                files.push((path.clone(), std::fs::read(path)?));
+            }
+            let report = import_markdown(db_client, s3_client, files, None, dry_run)
+                .await
+                .map_err(Error::other)?;
+            print_report(&report)
+        }
+        "import-wxr" => {
+            let (paths, dry_run) = get_import_paths(args);
+            let path = paths
+                .first()
+                .ok_or_else(|| invalid_input("Missing WordPress export file"))?;
+            let xml = std::fs::read(path)?;
+            let report = import_wordpress(db_client, s3_client, xml, None, dry_run)
+                .await
+                .map_err(Error::other)?;
+            print_report(&report)
+        }
+        "import-dir" => {
+            let (paths, dry_run) = get_import_paths(args);
+            let path = paths
+                .first()
+                .ok_or_else(|| invalid_input("Missing site directory or archive"))?;
+            let root = std::path::Path::new(path);
+            let mut files = Vec::new();
+            if root.is_dir() {
+                read_dir_files(root, root, &mut files)?;
+            } else {
+                files.push((path.to_string(), std::fs::read(root)?));
+            }
+            let report = import_content_dir(db_client, s3_client, files, None, dry_run)
+                .await
+                .map_err(Error::other)?;
+            print_report(&report)
+        }
+        "export-static" => {
+            let path = argument.ok_or_else(|| invalid_input("Missing output directory"))?;
+            let report = export_static_site(db_client, std::path::Path::new(path)).await?;
+            print_report(&report)
+        }
+        "unlock" => {
+            let target = argument.ok_or_else(|| invalid_input("Missing username or IP address"))?;
+            if unlock_login(db_client, target).await? == 0 {
+                println!("No failed sign in attempts found for {}", target);
+            }
+            Ok(())
+        }
+        "reencrypt-totp" => {
+            let report = reencrypt_totp_secrets(db_client)
+                .await
+                .map_err(Error::other)?;
+            print_report(&report)
+        }
+        _ => Err(invalid_input("Unknown command")),
+    }
+}
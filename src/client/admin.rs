@@ -1,15 +1,33 @@
 use crate::database::db;
-use crate::middleware::auth::get_user_claim;
+use crate::errors::auth::AuthError;
+use crate::errors::blog::BlogError;
+use crate::middleware::auth::{get_user_claim, get_user_role};
+use crate::models::audit_event::AuditLogQuery;
 use crate::models::blog_identifier::BlogIdentifier;
-use crate::templates::admin::{EditBlog, NewBlog, Profile};
+use crate::models::link_check::LinkOutcome;
+use crate::models::role::Role;
+use crate::security::rate_limiter::DefaultLoginRateLimiter;
+use crate::templates::admin::{
+    AuditLog, AuditLogEntry, EditBlog, LinkReport, MediaLibrary, NewBlog, Profile,
+};
 use crate::templates::error::ErrorTemplate;
 use crate::utils::{
-    html::render_template, security::extract_for_template, validations::get_id_from_path,
+    api_tokens::get_api_tokens,
+    audit::get_audit_events,
+    auth::check_blog_author,
+    draft::get_blog_draft,
+    html::render_template,
+    link_checker::{get_link_report, is_link_check_running},
+    media::get_media_library,
+    security::extract_for_template,
+    users::{get_invites, get_users},
+    validations::get_id_from_path,
 };
 
 use actix_web::http::StatusCode;
-use actix_web::web::{Data, Path};
+use actix_web::web::{Data, Path, Query};
 use actix_web::{get, HttpRequest, HttpResponse};
+use aws_sdk_s3 as s3;
 use bson::doc;
 use mongodb::options::FindOneOptions;
 
@@ -32,7 +50,18 @@ async fn edit_blog(
         Ok(blog_id) => blog_id,
         Err(response) => return response,
     };
-    let blog = client.into_inner().get_blog_post(&blog_id, None).await;
+    if let Err(BlogError::PermissionDenied) = check_blog_author(&client, &req, &blog_id).await {
+        let template = ErrorTemplate {
+            common: extract_for_template(&req),
+            status: 403,
+            message: "You can only edit your own blog posts",
+        };
+        return render_template(template, StatusCode::FORBIDDEN);
+    }
+    let (blog, draft) = tokio::join!(
+        client.get_blog_post(&blog_id, None),
+        get_blog_draft(&client, &blog_id),
+    );
     let blog = match blog {
         Ok(blog) => blog,
         Err(_) => {
@@ -45,6 +74,12 @@ async fn edit_blog(
         }
     };
 
+    // the editor still works without the draft, it just starts from the published version
+    let draft = draft.unwrap_or_default();
+    let draft_files_json = draft
+        .as_ref()
+        .and_then(|draft| serde_json::to_string(&draft.new_files).ok())
+        .unwrap_or_else(|| "[]".to_string());
     let template = EditBlog {
         common: extract_for_template(&req),
         id: &blog_id.to_hex(),
@@ -53,17 +88,24 @@ async fn edit_blog(
         content: &blog.content,
         public: blog.is_public,
         tags: &blog.tags.join(", "),
-        post_blog_btn_txt: "Update Blog",
+        version: blog.version,
+        draft: draft.as_ref(),
+        draft_files_json: &draft_files_json,
+        post_blog_btn_txt: "Publish Changes",
     };
     // since the minification will not preserve the whitespace in the content
     render_template(template, StatusCode::OK)
 }
 
 #[get("/admin/profile")]
-async fn profile(client: Data<db::DbClient>, req: HttpRequest) -> HttpResponse {
+async fn profile(
+    client: Data<db::DbClient>,
+    limiter: Data<DefaultLoginRateLimiter>,
+    req: HttpRequest,
+) -> HttpResponse {
     let user_info = get_user_claim(&req);
     let options = FindOneOptions::builder()
-        .projection(doc! {"totp_secret": 1})
+        .projection(doc! {"totp_secret": 1, "passkeys": 1, "recovery_codes": 1})
         .build();
     let user = client
         .get_projected_user_by_id(&user_info.user_id, Some(options))
@@ -81,9 +123,144 @@ async fn profile(client: Data<db::DbClient>, req: HttpRequest) -> HttpResponse {
         return user.unwrap_err();
     }
 
+    let user = user.unwrap();
+    let sessions = client
+        .get_user_sessions(&user_info.user_id)
+        .await
+        .unwrap_or_default();
+    let api_tokens = get_api_tokens(&client, &user_info.user_id)
+        .await
+        .unwrap_or_default();
+    // the profile can still be shown if the owner's sections cannot be fetched
+    let role = get_user_role(&req);
+    let (login_attempts, users, invites) = if role == Role::Owner {
+        (
+            limiter.get_blocked().await.unwrap_or_default(),
+            get_users(&client).await.unwrap_or_default(),
+            get_invites(&client, &req).await.unwrap_or_default(),
+        )
+    } else {
+        (vec![], vec![], vec![])
+    };
     let template = Profile {
         common: extract_for_template(&req),
-        has_2fa: !user.unwrap().totp_secret.unwrap_or_default().is_empty(),
+        has_2fa: !user.totp_secret.unwrap_or_default().is_empty(),
+        recovery_codes: vec![],
+        recovery_codes_left: user.recovery_codes.unwrap_or_default().len(),
+        passkeys: user.passkeys.unwrap_or_default(),
+        sessions,
+        current_session_id: user_info.session_id.to_hex(),
+        login_attempts,
+        now: chrono::Utc::now(),
+        role,
+        users,
+        current_user_id: user_info.user_id.to_hex(),
+        invites,
+        api_tokens,
+        new_token: None,
+    };
+    render_template(template, StatusCode::OK)
+}
+
+#[get("/admin/media")]
+async fn media_library(
+    client: Data<db::DbClient>,
+    s3_client: Data<s3::Client>,
+    req: HttpRequest,
+) -> HttpResponse {
+    let media_list = match get_media_library(&client, &s3_client, "").await {
+        Ok(media_list) => media_list,
+        Err(_) => {
+            let template = ErrorTemplate {
+                common: extract_for_template(&req),
+                status: 500,
+                message: "Failed to get the media library",
+            };
+            return render_template(template, StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let template = MediaLibrary {
+        common: extract_for_template(&req),
+        media_list,
+    };
+    render_template(template, StatusCode::OK)
+}
+
+#[get("/admin/links")]
+async fn link_report(client: Data<db::DbClient>, req: HttpRequest) -> HttpResponse {
+    let links = match get_link_report(&client).await {
+        Ok(links) => links,
+        Err(_) => {
+            let template = ErrorTemplate {
+                common: extract_for_template(&req),
+                status: 500,
+                message: "Failed to get the link report",
+            };
+            return render_template(template, StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let count = |outcome| links.iter().filter(|link| link.outcome == outcome).count();
+    let template = LinkReport {
+        common: extract_for_template(&req),
+        broken: count(LinkOutcome::Broken),
+        skipped: count(LinkOutcome::Skipped),
+        is_running: is_link_check_running(),
+        links,
+    };
+    render_template(template, StatusCode::OK)
+}
+
+#[get("/admin/audit-log")]
+async fn audit_log(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    query: Query<AuditLogQuery>,
+) -> HttpResponse {
+    let (events, has_next_page) = match get_audit_events(&client, &query).await {
+        Ok(result) => result,
+        Err(e) => {
+            let (status, message) = match e {
+                AuthError::InvalidAuditLogFilter => (400, "Unknown audit log action"),
+                _ => (500, "Failed to get the audit log"),
+            };
+            let template = ErrorTemplate {
+                common: extract_for_template(&req),
+                status,
+                message,
+            };
+            return render_template(template, StatusCode::from_u16(status).unwrap());
+        }
+    };
+
+    let mut actor_ids: Vec<_> = events.iter().filter_map(|event| event.actor_id).collect();
+    actor_ids.sort();
+    actor_ids.dedup();
+    let usernames = client.get_usernames(actor_ids).await;
+    let entries = events
+        .into_iter()
+        .map(|event| {
+            let actor = match event.actor_id {
+                Some(actor_id) => usernames
+                    .get(&actor_id)
+                    .cloned()
+                    .unwrap_or_else(|| actor_id.to_hex()),
+                None => event.actor_name.clone().unwrap_or_default(),
+            };
+            AuditLogEntry { event, actor }
+        })
+        .collect();
+
+    let template = AuditLog {
+        common: extract_for_template(&req),
+        entries,
+        action: query.action.clone().unwrap_or_default(),
+        actor: query.actor.clone().unwrap_or_default(),
+        ip: query.ip.clone().unwrap_or_default(),
+        filter_query: query.get_filter_query_string(),
+        page: query.get_page(),
+        has_next_page,
     };
     render_template(template, StatusCode::OK)
 }
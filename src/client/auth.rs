@@ -1,9 +1,14 @@
 use crate::constants;
-use crate::templates::auth::Login;
+use crate::database::db;
+use crate::models::invite::InviteQuery;
+use crate::templates::auth::{Invite, Login};
+use crate::templates::error::ErrorTemplate;
+use crate::utils::users::get_valid_invite;
 use crate::utils::{html::render_template, security::extract_for_template};
 
 use actix_web::http::header::LOCATION;
 use actix_web::http::StatusCode;
+use actix_web::web::{Data, Query};
 use actix_web::{get, web, HttpRequest, HttpResponse};
 
 #[get("/login")]
@@ -46,3 +51,45 @@ pub async fn login_auth(req: HttpRequest) -> HttpResponse {
         }
     }
 }
+
+#[get("/invite")]
+async fn invite(
+    client: Data<db::DbClient>,
+    req: HttpRequest,
+    query: Query<InviteQuery>,
+) -> HttpResponse {
+    if req.cookie(constants::AUTH_COOKIE_NAME).is_some() {
+        return HttpResponse::TemporaryRedirect()
+            .append_header((LOCATION, "/"))
+            .finish();
+    }
+
+    let token = query.into_inner().token.unwrap_or_default();
+    match get_valid_invite(&client, &token).await {
+        Ok(invite) => {
+            let username = match invite.user_id {
+                Some(user_id) => client
+                    .get_user_by_id(&user_id)
+                    .await
+                    .ok()
+                    .map(|user| user.get_username().to_string()),
+                None => None,
+            };
+            let template = Invite {
+                common: extract_for_template(&req),
+                token: &token,
+                role: invite.role.get_label(),
+                username: username.as_deref(),
+            };
+            render_template(template, StatusCode::OK)
+        }
+        Err(_) => {
+            let template = ErrorTemplate {
+                common: extract_for_template(&req),
+                status: 404,
+                message: "The invite link is invalid or has expired",
+            };
+            render_template(template, StatusCode::NOT_FOUND)
+        }
+    }
+}
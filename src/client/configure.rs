@@ -1,5 +1,5 @@
-use crate::client::admin::{edit_blog, new_blog, profile};
-use crate::client::auth::{login_admin, login_auth, login_redirect};
+use crate::client::admin::{audit_log, edit_blog, link_report, media_library, new_blog, profile};
+use crate::client::auth::{invite, login_admin, login_auth, login_redirect};
 use crate::client::general::{
     awards, blog_id, blogs, certificates, experiences, index, projects, resume, skills,
     testimonials,
@@ -56,10 +56,16 @@ fn add_general_routes(cfg: &mut web::ServiceConfig) {
 fn add_auth_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(login_redirect)
         .route(&constants::get_login_uri_path(), web::get().to(login_auth))
-        .service(login_admin);
+        .service(login_admin)
+        .service(invite);
 }
 
 #[inline]
 fn add_admin_routes(cfg: &mut web::ServiceConfig) {
-    cfg.service(new_blog).service(edit_blog).service(profile);
+    cfg.service(new_blog)
+        .service(edit_blog)
+        .service(media_library)
+        .service(link_report)
+        .service(audit_log)
+        .service(profile);
 }
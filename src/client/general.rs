@@ -1,6 +1,7 @@
 use crate::database::db;
 use crate::models::blog;
 use crate::models::blog_identifier::BlogIdentifier;
+use crate::models::preview_link::PreviewQuery;
 use crate::templates::error::ErrorTemplate;
 use crate::templates::general::{
     Awards, BlogPost, BlogPostInfo, Blogs, Certificates, Experiences, Index, Projects, Resume,
@@ -8,8 +9,10 @@ use crate::templates::general::{
 };
 use crate::utils::awards::get_awards;
 use crate::utils::certificates::get_certificates;
+use crate::utils::draft::get_blog_draft;
 use crate::utils::experiences::get_experiences;
 use crate::utils::html::render_template;
+use crate::utils::preview::verify_preview_token;
 use crate::utils::projects::get_projects;
 use crate::utils::security::extract_for_template;
 use crate::utils::skills::{
@@ -20,7 +23,7 @@ use crate::utils::testimonials::get_testimonials;
 use crate::utils::validations::get_id_from_path;
 
 use actix_web::http::StatusCode;
-use actix_web::web::Data;
+use actix_web::web::{Data, Query};
 use actix_web::{get, web::Path, HttpRequest, HttpResponse};
 use futures_util::TryStreamExt;
 use mongodb::bson::doc;
@@ -182,15 +185,21 @@ async fn blog_id(
     client: Data<db::DbClient>,
     req: HttpRequest,
     blog_identifier: Path<BlogIdentifier>,
+    preview_query: Query<PreviewQuery>,
 ) -> HttpResponse {
     let blog_id = match get_id_from_path(&req, blog_identifier) {
         Ok(blog_id) => blog_id,
         Err(response) => return response,
     };
+    // an invalid, expired or revoked preview token is treated like a normal visit
+    let is_preview = match &preview_query.preview {
+        Some(token) => verify_preview_token(&client, &blog_id, token).await,
+        None => false,
+    };
     let query = doc! { "_id": blog_id };
     let common = extract_for_template(&req);
     let blog_collection = client.get_blog_collection();
-    let blog_post = if common.is_logged_in {
+    let blog_post = if common.is_logged_in || is_preview {
         blog_collection.find_one(query).await
     } else {
         let update = doc! {"$inc": {blog::VIEWS_KEY: 1}};
@@ -205,11 +214,25 @@ async fn blog_id(
     };
 
     match blog_post {
-        Ok(Some(blog_post)) => {
-            if !blog_post.is_public && !common.is_logged_in {
+        Ok(Some(mut blog_post)) => {
+            if !blog_post.is_public && !common.is_logged_in && !is_preview {
                 blog_not_found!(req);
             }
 
+            // previews show the unpublished changes if there are any
+            if is_preview {
+                if let Ok(Some(draft)) = get_blog_draft(&client, &blog_id).await {
+                    blog_post.title = draft.title;
+                    blog_post.seo_desc = draft.seo_desc;
+                    blog_post.tags = draft.tags;
+                    blog_post.content = draft.content;
+                    blog_post.is_public = draft.is_public;
+                }
+            }
+
+            let authors = client
+                .get_usernames(blog_post.author_id.into_iter().collect())
+                .await;
             let template = BlogPost {
                 common,
                 id: &blog_post.get_id_string(),
@@ -222,6 +245,11 @@ async fn blog_id(
                 content: &blog_post.get_html_content(),
                 public: blog_post.is_public,
                 tags: &blog_post.tags,
+                preview: is_preview,
+                author: blog_post
+                    .author_id
+                    .and_then(|author_id| authors.get(&author_id))
+                    .map(String::as_str),
             };
             render_template(template, StatusCode::OK)
         }
@@ -2,12 +2,33 @@ use once_cell::sync::Lazy;
 use std::time;
 
 pub const APP_NAME: &str = "kjhjasoncom"; // used for API SDKs like MongoDB
-pub const SESSION_TIMEOUT: i64 = 60 * 60 * 24 * 1; // 1 day
-pub const SESSION_TIMEOUT_REMEMBER: i64 = 60 * 60 * 24 * 30; // 1 month
+
+// the idle timeouts are renewed on activity up to the maximum lifetime of the session
+pub const SESSION_TIMEOUT: i64 = 60 * 60 * 2; // 2 hours
+pub const SESSION_TIMEOUT_REMEMBER: i64 = 60 * 60 * 24 * 7; // 1 week
+pub const SESSION_MAX_LIFETIME: i64 = 60 * 60 * 24; // 1 day
+pub const SESSION_MAX_LIFETIME_REMEMBER: i64 = 60 * 60 * 24 * 30; // 1 month
+pub const SESSION_LAST_SEEN_INTERVAL: time::Duration = time::Duration::from_secs(60);
+pub const SESSION_USER_AGENT_MAX_LENGTH: usize = 256;
+pub const USERNAME_MIN_LENGTH: usize = 3;
+pub const USERNAME_MAX_LENGTH: usize = 32;
+pub const EMAIL_MAX_LENGTH: usize = 254;
+pub const PASSWORD_MIN_LENGTH: usize = 8;
+pub const PASSWORD_MAX_LENGTH: usize = 64;
 pub const AUTH_COOKIE_NAME: &str = "_session";
 pub const DOMAIN: &str = "kjhjason.com";
 pub const CSRF_COOKIE_NAME: &str = "csrf-token";
 pub const CSRF_HEADER_NAME: &str = "X-CSRF-Token";
+pub const BLOG_VERSION_HEADER: &str = "X-Blog-Version";
+pub const WS_PREVIEW_PROTOCOL: &str = "blog-preview";
+pub const WS_PREVIEW_MAX_FRAME_SIZE: usize = 1024 * 1024 * 2;
+pub const WS_PREVIEW_HEARTBEAT_INTERVAL: time::Duration = time::Duration::from_secs(15);
+// the connection is closed if the browser stops answering the pings
+pub const WS_PREVIEW_CLIENT_TIMEOUT: time::Duration = time::Duration::from_secs(45);
+// the connection is closed after no edits for this long and reopened on the next edit
+pub const WS_PREVIEW_IDLE_TIMEOUT: time::Duration = time::Duration::from_secs(60 * 30);
+// the session is only checked when connecting, so it is checked again while connected
+pub const WS_PREVIEW_SESSION_CHECK_INTERVAL: time::Duration = time::Duration::from_secs(60);
 pub const CSRF_TOKEN_LENGTH: usize = 32;
 pub const CSRF_MAX_AGE: i64 = 60 * 60 * 24 * 1; // 1 day
 
@@ -16,12 +37,28 @@ pub const DATABASE: &str = "kjhjason";
 pub const BLOG_COLLECTION: &str = "blogs";
 pub const USER_COLLECTION: &str = "users";
 pub const SESSION_COLLECTION: &str = "sessions";
+pub const MEDIA_COLLECTION: &str = "media";
+pub const BLOG_OPERATION_COLLECTION: &str = "blog_operations";
+pub const BLOG_DRAFT_COLLECTION: &str = "blog_drafts";
+pub const PREVIEW_LINK_COLLECTION: &str = "preview_links";
+pub const LINK_CHECK_COLLECTION: &str = "link_checks";
+pub const PASSKEY_CHALLENGE_COLLECTION: &str = "passkey_challenges";
+pub const LOGIN_ATTEMPT_COLLECTION: &str = "login_attempts";
+pub const INVITE_COLLECTION: &str = "invites";
+pub const API_TOKEN_COLLECTION: &str = "api_tokens";
+pub const AUDIT_LOG_COLLECTION: &str = "audit_log";
 
 pub const TITLE_MAX_LENGTH: usize = 150;
 pub const MAX_TAGS: usize = 8;
+pub const LINT_LONG_PARAGRAPH_WORDS: usize = 150;
+pub const LINT_SEO_DESC_MIN_LENGTH: usize = 50;
+pub const LINT_SEO_DESC_MAX_LENGTH: usize = 150;
+pub const PREVIEW_LINK_DEFAULT_DAYS: i64 = 7;
+pub const PREVIEW_LINK_MAX_DAYS: i64 = 30;
+pub const INVITE_DEFAULT_DAYS: i64 = 3;
+pub const INVITE_MAX_DAYS: i64 = 14;
 
 pub const MAX_FILE_SIZE: usize = 1024 * 1024 * 100;
-pub const TEMP_DIR: &str = "uploads";
 
 pub const BUCKET: &str = "kjhjason";
 pub const BLOG_BACKUP_BUCKET: &str = "kjhjason-private";
@@ -29,7 +66,80 @@ pub const BUCKET_FOR_TEMP: &str = "kjhjason-private";
 pub const PUBLIC_S3_URL: &str = "https://storage.kjhjason.com";
 pub const SIGNED_URL_MAX_AGE: time::Duration = time::Duration::from_secs(60 * 60 * 24 * 7);
 pub const TEMP_OBJ_PREFIX: &str = "temp";
+// uploaded files are kept in the browser's localStorage for up to 7 days (see blog.js)
+// so temp objects must outlive both that and the signed url before being swept.
+pub const TEMP_OBJ_MAX_AGE: time::Duration = time::Duration::from_secs(60 * 60 * 24 * 8);
+pub const TEMP_SWEEP_INTERVAL: time::Duration = time::Duration::from_secs(60 * 60 * 6);
+// a blog post write still in the journal after this is assumed to have been interrupted
+pub const BLOG_OPERATION_LEASE: time::Duration = time::Duration::from_secs(60 * 15);
+pub const BLOG_OPERATION_RECOVERY_INTERVAL: time::Duration = time::Duration::from_secs(60 * 15);
+// blog files younger than this may have been copied by a blog post that is still being written
+pub const RECONCILE_GRACE_PERIOD: time::Duration = time::Duration::from_secs(60 * 60 * 24);
 pub const BLOG_BACKUP_OBJ_PREFIX: &str = "blog-backup";
+// every backup from the last day is kept, then the newest backup of each day
+// for a month and the newest backup of each week for a year.
+pub const BACKUP_KEEP_ALL_PERIOD: time::Duration = time::Duration::from_secs(60 * 60 * 24);
+pub const BACKUP_DAILY_RETENTION: time::Duration = time::Duration::from_secs(60 * 60 * 24 * 30);
+pub const BACKUP_WEEKLY_RETENTION: time::Duration = time::Duration::from_secs(60 * 60 * 24 * 365);
+pub const BACKUP_PRUNE_INTERVAL: time::Duration = time::Duration::from_secs(60 * 60 * 24);
+// the backup manifest is written conditionally and re-read when another backup changed it
+pub const BACKUP_MANIFEST_MAX_ATTEMPTS: u32 = 5;
+pub const MEDIA_OBJ_PREFIX: &str = "media";
+// version 2 added the roles and disabled flags of the users
+pub const SITE_EXPORT_VERSION: u32 = 2;
+pub const MIN_SITE_EXPORT_VERSION: u32 = 1;
+// the largest uncompressed size of a single file inside an import archive
+pub const MAX_ARCHIVE_ENTRY_SIZE: u64 = 256 * 1024 * 1024;
+// the largest uncompressed size of all the files of an import together
+pub const MAX_ARCHIVE_TOTAL_SIZE: u64 = 1024 * 1024 * 1024;
+pub const MAX_ARCHIVE_ENTRIES: usize = 10_000;
+pub const MARKDOWN_MEDIA_DIR: &str = "media";
+pub const IMPORT_DOWNLOAD_TIMEOUT: time::Duration = time::Duration::from_secs(30);
+pub const IMPORT_USER_AGENT: &str = "kjhjason-importer/1.0 (+https://kjhjason.com)";
+pub const LINK_CHECK_INTERVAL: time::Duration = time::Duration::from_secs(60 * 60 * 24);
+// the minimum time between two requests to the same host unless its robots.txt asks for longer
+pub const LINK_CHECK_HOST_DELAY: time::Duration = time::Duration::from_secs(1);
+pub const LINK_CHECK_MAX_HOST_DELAY: time::Duration = time::Duration::from_secs(30);
+pub const LINK_CHECK_TIMEOUT: time::Duration = time::Duration::from_secs(15);
+pub const LINK_CHECK_MAX_RETRIES: u32 = 2;
+pub const LINK_CHECK_MAX_REDIRECTS: usize = 10;
+// the same limit as Google's crawler, the rest of a larger robots.txt is ignored
+pub const LINK_CHECK_MAX_ROBOTS_SIZE: usize = 1024 * 500;
+pub const LINK_CHECK_HISTORY_LENGTH: i32 = 10;
+pub const LINK_CHECK_USER_AGENT: &str = "kjhjason-link-checker/1.0 (+https://kjhjason.com)";
+
+// failed logins are throttled with an exponential backoff after the free attempts
+// and the account is locked after the lockout threshold, both per IP address and per user.
+pub const LOGIN_FREE_ATTEMPTS: i64 = 3;
+pub const LOGIN_BACKOFF_BASE: time::Duration = time::Duration::from_secs(1);
+pub const LOGIN_BACKOFF_MAX: time::Duration = time::Duration::from_secs(60 * 15);
+pub const LOGIN_LOCKOUT_THRESHOLD: i64 = 10;
+pub const LOGIN_LOCKOUT_DURATION: time::Duration = time::Duration::from_secs(60 * 60);
+// the failed attempts are forgotten after this long without another failure
+pub const LOGIN_ATTEMPT_WINDOW: time::Duration = time::Duration::from_secs(60 * 60 * 24);
+
+pub const WEBAUTHN_RP_NAME: &str = "KJHJason";
+pub const PASSKEY_TIMEOUT_MS: i64 = 1000 * 60 * 5; // 5 minutes
+pub const MAX_PASSKEYS: usize = 10;
+pub const PASSKEY_NAME_MAX_LENGTH: usize = 64;
+
+// the prefix makes leaked tokens easy to recognise, e.g. by secret scanners
+pub const API_TOKEN_PREFIX: &str = "kjh_pat_";
+pub const API_TOKEN_BYTES: usize = 32;
+pub const API_TOKEN_NAME_MAX_LENGTH: usize = 64;
+pub const API_TOKEN_DEFAULT_DAYS: i64 = 90;
+pub const API_TOKEN_MAX_DAYS: i64 = 365;
+pub const MAX_API_TOKENS: usize = 20;
+// the number of requests a token can make in each rate limit window
+pub const API_TOKEN_RATE_LIMIT: i64 = 120;
+pub const API_TOKEN_RATE_WINDOW: time::Duration = time::Duration::from_secs(60);
+
+pub const AUDIT_LOG_PAGE_SIZE: u64 = 50;
+// the events are deleted by a TTL index once they are older than this
+pub const AUDIT_LOG_RETENTION: time::Duration = time::Duration::from_secs(60 * 60 * 24 * 365);
+// at most one honeypot event is recorded per IP address in this window
+// since the honeypot is hit before the captcha is checked
+pub const HONEYPOT_AUDIT_WINDOW: time::Duration = time::Duration::from_secs(60 * 10);
 
 pub const CF_TURNSTILE_SITE_KEY: &str = "0x4AAAAAAAcnZh9gukmZdThg";
 
@@ -49,6 +159,8 @@ const __SECRET_KEY_SALT: &str = "SECRET_KEY_SALT";
 const __CSRF_KEY_SALT: &str = "CSRF_KEY_SALT";
 const __DB_ENCRYPTION_KEY: &str = "DB_ENCRYPTION_KEY";
 const __DB_ENCRYPTION_KEY_AAD: &str = "DB_ENCRYPTION_KEY_AAD";
+const __SECRET_KEYRING: &str = "SECRET_KEYRING";
+const __DB_ENCRYPTION_KEYRING: &str = "DB_ENCRYPTION_KEYRING";
 
 #[inline(always)]
 fn get_env_var(var_name: &str) -> String {
@@ -74,6 +186,27 @@ pub fn get_minify_html() -> bool {
     }
 }
 
+// e.g. "long_paragraph=off,bare_url=error" to override the default lint rule levels
+#[inline(always)]
+pub fn get_lint_rules() -> String {
+    std::env::var("LINT_RULES").unwrap_or_default()
+}
+
+// e.g. "2:<hex key>,1:<hex key>:2025-01-31" to rotate the keys,
+// the single key above is used if unset (see security::keyring)
+macro_rules! generate_optional_env_getter {
+    ($fn_name:ident, $var_name:expr) => {
+        pub fn $fn_name() -> Option<String> {
+            std::env::var($var_name)
+                .ok()
+                .filter(|val| !val.trim().is_empty())
+        }
+    };
+}
+
+generate_optional_env_getter!(get_secret_keyring, __SECRET_KEYRING);
+generate_optional_env_getter!(get_db_encryption_keyring, __DB_ENCRYPTION_KEYRING);
+
 macro_rules! generate_env_getter {
     ($fn_name:ident, $var_name:expr) => {
         pub fn $fn_name() -> String {
@@ -114,4 +247,10 @@ macro_rules! generate_debug_dependent_val_getter {
 }
 
 generate_debug_dependent_val_getter!(get_domain, "localhost", DOMAIN);
+// the origin that WebAuthn clients report in their client data
+generate_debug_dependent_val_getter!(
+    get_webauthn_origin,
+    "http://localhost:8080",
+    "https://kjhjason.com"
+);
 generate_debug_dependent_val_getter!(get_blog_obj_prefix, "blog-dev", "blog");
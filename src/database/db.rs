@@ -2,13 +2,19 @@ use crate::constants;
 use crate::errors::{auth::AuthError, blog::BlogError, session::SessionError};
 use crate::models::projected_user::ProjectedUser;
 use crate::models::{
-    blog::Blog, projected_blog::ProjectedBlog, session::Session, user, user::User,
+    api_token::ApiToken, audit_event::AuditEvent, blog::Blog, blog_draft::BlogDraft,
+    blog_operation::BlogOperation, invite::Invite, link_check::LinkCheck,
+    login_attempt::LoginAttempt, media::Media, passkey, passkey::PasskeyCeremony,
+    passkey::PasskeyChallenge, preview_link::PreviewLink, projected_blog::ProjectedBlog, session,
+    session::Session, user, user::User,
 };
 
 use bson::oid::ObjectId;
+use futures_util::TryStreamExt;
 use mongodb::bson::doc;
-use mongodb::options::FindOneOptions;
+use mongodb::options::{FindOneOptions, FindOptions};
 use mongodb::{Client, Collection};
+use std::collections::HashMap;
 
 #[derive(Clone)]
 pub struct DbClient {
@@ -56,6 +62,104 @@ impl DbClient {
             .collection(constants::SESSION_COLLECTION)
     }
 
+    #[inline]
+    pub fn get_blog_draft_collection(&self) -> Collection<BlogDraft> {
+        self.get_database(None)
+            .collection(constants::BLOG_DRAFT_COLLECTION)
+    }
+
+    #[inline]
+    pub fn get_link_check_collection(&self) -> Collection<LinkCheck> {
+        self.get_database(None)
+            .collection(constants::LINK_CHECK_COLLECTION)
+    }
+
+    #[inline]
+    pub fn get_preview_link_collection(&self) -> Collection<PreviewLink> {
+        self.get_database(None)
+            .collection(constants::PREVIEW_LINK_COLLECTION)
+    }
+
+    #[inline]
+    pub fn get_blog_operation_collection(&self) -> Collection<BlogOperation> {
+        self.get_database(None)
+            .collection(constants::BLOG_OPERATION_COLLECTION)
+    }
+
+    #[inline]
+    pub fn get_media_collection(&self) -> Collection<Media> {
+        self.get_database(None)
+            .collection(constants::MEDIA_COLLECTION)
+    }
+
+    #[inline]
+    pub fn get_passkey_challenge_collection(&self) -> Collection<PasskeyChallenge> {
+        self.get_database(None)
+            .collection(constants::PASSKEY_CHALLENGE_COLLECTION)
+    }
+
+    #[inline]
+    pub fn get_invite_collection(&self) -> Collection<Invite> {
+        self.get_database(None)
+            .collection(constants::INVITE_COLLECTION)
+    }
+
+    #[inline]
+    pub fn get_api_token_collection(&self) -> Collection<ApiToken> {
+        self.get_database(None)
+            .collection(constants::API_TOKEN_COLLECTION)
+    }
+
+    #[inline]
+    pub fn get_audit_log_collection(&self) -> Collection<AuditEvent> {
+        self.get_database(None)
+            .collection(constants::AUDIT_LOG_COLLECTION)
+    }
+
+    #[inline]
+    pub fn get_login_attempt_collection(&self) -> Collection<LoginAttempt> {
+        self.get_database(None)
+            .collection(constants::LOGIN_ATTEMPT_COLLECTION)
+    }
+
+    /// Deletes and returns the challenge so that it can only be used once.
+    pub async fn take_passkey_challenge(
+        &self,
+        id: &str,
+        ceremony: PasskeyCeremony,
+    ) -> Result<PasskeyChallenge, AuthError> {
+        let id = ObjectId::parse_str(id).map_err(|_| AuthError::PasskeyChallengeExpired)?;
+        let result = self
+            .get_passkey_challenge_collection()
+            .find_one_and_delete(doc! {"_id": id})
+            .await;
+        match result {
+            Ok(Some(challenge))
+                if challenge.ceremony == ceremony && challenge.expiry > chrono::Utc::now() =>
+            {
+                Ok(challenge)
+            }
+            Ok(_) => Err(AuthError::PasskeyChallengeExpired),
+            Err(err) => {
+                log::error!("Failed to get passkey challenge from database: {:?}", err);
+                Err(AuthError::InternalServerError)
+            }
+        }
+    }
+
+    pub async fn get_user_by_id(&self, id: &ObjectId) -> Result<User, AuthError> {
+        let result = self.get_user_collection().find_one(doc! {"_id": id}).await;
+        Self::handle_user_result(result)
+    }
+
+    pub async fn get_user_by_passkey_id(&self, passkey_id: &str) -> Result<User, AuthError> {
+        let result = self
+            .get_user_collection()
+            .find_one(doc! {format!("{}.{}", user::PASSKEYS_KEY, passkey::ID_KEY): passkey_id})
+            .await;
+        Self::handle_user_result(result)
+    }
+
     pub async fn get_session_by_id(&self, id: &ObjectId) -> Result<Session, SessionError> {
         match self
             .get_session_collection()
@@ -71,6 +175,58 @@ impl DbClient {
         }
     }
 
+    /// Returns the active sessions of the user with the most recently used first.
+    pub async fn get_user_sessions(&self, user_id: &ObjectId) -> Result<Vec<Session>, AuthError> {
+        let sessions: Vec<Session> = self
+            .get_session_collection()
+            .find(doc! {session::USER_ID_KEY: user_id})
+            .await
+            .map_err(|e| {
+                log::error!("Failed to get user's sessions from database: {:?}", e);
+                AuthError::InternalServerError
+            })?
+            .try_collect()
+            .await
+            .map_err(|e| {
+                log::error!("Failed to get user's sessions from database: {:?}", e);
+                AuthError::InternalServerError
+            })?;
+        let mut sessions: Vec<Session> = sessions
+            .into_iter()
+            .filter(|session| !session.is_expired())
+            .collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.get_last_seen()));
+        Ok(sessions)
+    }
+
+    /// Signs the user out of every device except the current session
+    /// and returns the number of sessions revoked.
+    pub async fn revoke_other_sessions(
+        &self,
+        user_id: &ObjectId,
+        current_session_id: &ObjectId,
+    ) -> Result<u64, AuthError> {
+        let result = self
+            .get_session_collection()
+            .delete_many(doc! {
+                session::USER_ID_KEY: user_id,
+                "_id": {"$ne": current_session_id},
+            })
+            .await
+            .map_err(|e| {
+                log::error!("Failed to revoke user's other sessions: {:?}", e);
+                AuthError::InternalServerError
+            })?;
+        if result.deleted_count > 0 {
+            log::info!(
+                "Revoked {} other sessions of user {}",
+                result.deleted_count,
+                user_id
+            );
+        }
+        Ok(result.deleted_count)
+    }
+
     #[inline]
     fn handle_user_result<T>(
         result: Result<Option<T>, mongodb::error::Error>,
@@ -95,6 +251,42 @@ impl DbClient {
         Self::handle_user_result(result)
     }
 
+    /// Returns the usernames of the users for the author bylines.
+    ///
+    /// Users that could not be found are left out so that the bylines are just not shown.
+    pub async fn get_usernames(&self, ids: Vec<ObjectId>) -> HashMap<ObjectId, String> {
+        if ids.is_empty() {
+            return HashMap::new();
+        }
+        let col: Collection<bson::Document> =
+            self.get_custom_collection(constants::USER_COLLECTION);
+        let options = FindOptions::builder()
+            .projection(doc! {user::USERNAME_KEY: 1})
+            .build();
+        let result = col
+            .find(doc! {"_id": {"$in": ids}})
+            .with_options(options)
+            .await;
+        let users: Vec<bson::Document> = match result {
+            Ok(cursor) => cursor.try_collect().await.unwrap_or_else(|e| {
+                log::error!("Failed to get usernames from database: {:?}", e);
+                vec![]
+            }),
+            Err(e) => {
+                log::error!("Failed to get usernames from database: {:?}", e);
+                vec![]
+            }
+        };
+        users
+            .into_iter()
+            .filter_map(|user| {
+                let id = user.get_object_id("_id").ok()?;
+                let username = user.get_str(user::USERNAME_KEY).ok()?;
+                Some((id, username.to_string()))
+            })
+            .collect()
+    }
+
     pub async fn get_user_by_username_or_email(
         &self,
         username_or_email: &str,
@@ -1,8 +1,18 @@
 use crate::constants;
 use crate::database::db::DbClient;
+use crate::models::api_token::ApiToken;
+use crate::models::audit_event::AuditEvent;
 use crate::models::blog::Blog;
+use crate::models::invite::Invite;
+use crate::models::login_attempt::LoginAttempt;
+use crate::models::passkey::PasskeyChallenge;
+use crate::models::preview_link::PreviewLink;
+use crate::models::role::Role;
 use crate::models::session::Session;
-use crate::models::{blog, session, user, user::User};
+use crate::models::{
+    api_token, audit_event, blog, invite, login_attempt, passkey, preview_link, session, user,
+    user::User,
+};
 use crate::security::pw_hasher;
 
 use bson::doc;
@@ -65,7 +75,13 @@ async fn init_user_collection(client: &Client) {
         .await
         .expect("Should be able to hash admin password");
 
-    let user = User::new(admin_username, admin_email, hashed_admin_password, None);
+    let user = User::new(
+        admin_username,
+        admin_email,
+        hashed_admin_password,
+        None,
+        Role::Owner,
+    );
     match collection.insert_one(user).await {
         Ok(_) => log::info!("Admin account created"),
         Err(e) => panic!("Failed to create admin account: {}", e),
@@ -77,17 +93,28 @@ async fn init_session_collection(client: &Client) {
     let db = client.database(constants::DATABASE);
     let collection: Collection<Session> = db.collection(constants::SESSION_COLLECTION);
 
-    // check if the collection already exists
-    let result = collection.find_one(doc! {}).await;
-    match result {
-        Ok(Some(_)) => return,
-        _ => {}
+    // used for listing the sessions of the user, creating an existing index is a no-op
+    let user_id_idx = IndexModel::builder()
+        .keys(doc! {session::USER_ID_KEY: 1})
+        .build();
+    if let Err(e) = collection.create_index(user_id_idx).await {
+        log::error!(
+            "Failed to create user id index for session collection: {}",
+            e
+        );
     }
 
+    // the TTL index used to be on a field that sessions did not have so they were never removed
+    if let Err(e) = collection.drop_index("EXPIRY_1").await {
+        log::debug!(
+            "No legacy expiry index to drop for session collection: {}",
+            e
+        );
+    }
+
+    // the expiry is renewed on activity so the session is removed once it is idle for too long
     let opts = IndexOptions::builder()
-        .expire_after(std::time::Duration::from_secs(
-            constants::SESSION_TIMEOUT as u64,
-        ))
+        .expire_after(std::time::Duration::from_secs(0))
         .build();
     let index = IndexModel::builder()
         .keys(doc! {session::EXPIRY_KEY: 1})
@@ -101,10 +128,205 @@ async fn init_session_collection(client: &Client) {
     log::info!("Session collection initialised");
 }
 
+async fn init_preview_link_collection(client: &Client) {
+    let db = client.database(constants::DATABASE);
+    let collection: Collection<PreviewLink> = db.collection(constants::PREVIEW_LINK_COLLECTION);
+
+    // creating an existing index is a no-op so there is no need to check if the collection exists
+    let opts = IndexOptions::builder()
+        .expire_after(std::time::Duration::from_secs(0))
+        .build();
+    let expiry_idx = IndexModel::builder()
+        .keys(doc! {preview_link::EXPIRY_KEY: 1})
+        .options(opts)
+        .build();
+    if let Err(e) = collection.create_index(expiry_idx).await {
+        log::error!(
+            "Failed to create expiry index for preview link collection: {}",
+            e
+        );
+    }
+
+    let blog_id_idx = IndexModel::builder()
+        .keys(doc! {preview_link::BLOG_ID_KEY: 1})
+        .build();
+    if let Err(e) = collection.create_index(blog_id_idx).await {
+        log::error!(
+            "Failed to create blog id index for preview link collection: {}",
+            e
+        );
+    }
+}
+
+async fn init_passkey_challenge_collection(client: &Client) {
+    let db = client.database(constants::DATABASE);
+    let collection: Collection<PasskeyChallenge> =
+        db.collection(constants::PASSKEY_CHALLENGE_COLLECTION);
+
+    // expired challenges are rejected when taken, this only cleans up the unused ones
+    let opts = IndexOptions::builder()
+        .expire_after(std::time::Duration::from_secs(0))
+        .build();
+    let expiry_idx = IndexModel::builder()
+        .keys(doc! {passkey::EXPIRY_KEY: 1})
+        .options(opts)
+        .build();
+    if let Err(e) = collection.create_index(expiry_idx).await {
+        log::error!(
+            "Failed to create expiry index for passkey challenge collection: {}",
+            e
+        );
+    }
+}
+
+async fn init_login_attempt_collection(client: &Client) {
+    let db = client.database(constants::DATABASE);
+    let collection: Collection<LoginAttempt> = db.collection(constants::LOGIN_ATTEMPT_COLLECTION);
+
+    // the failed attempts are forgotten after the window, or after the lockout if it is longer
+    let opts = IndexOptions::builder()
+        .expire_after(std::time::Duration::from_secs(0))
+        .build();
+    let expiry_idx = IndexModel::builder()
+        .keys(doc! {login_attempt::EXPIRY_KEY: 1})
+        .options(opts)
+        .build();
+    if let Err(e) = collection.create_index(expiry_idx).await {
+        log::error!(
+            "Failed to create expiry index for login attempt collection: {}",
+            e
+        );
+    }
+}
+
+async fn init_invite_collection(client: &Client) {
+    let db = client.database(constants::DATABASE);
+    let collection: Collection<Invite> = db.collection(constants::INVITE_COLLECTION);
+
+    // expired invites are rejected when accepted, this only cleans up the unused ones
+    let opts = IndexOptions::builder()
+        .expire_after(std::time::Duration::from_secs(0))
+        .build();
+    let expiry_idx = IndexModel::builder()
+        .keys(doc! {invite::EXPIRY_KEY: 1})
+        .options(opts)
+        .build();
+    if let Err(e) = collection.create_index(expiry_idx).await {
+        log::error!("Failed to create expiry index for invite collection: {}", e);
+    }
+}
+
+async fn init_api_token_collection(client: &Client) {
+    let db = client.database(constants::DATABASE);
+    let collection: Collection<ApiToken> = db.collection(constants::API_TOKEN_COLLECTION);
+
+    // every authenticated API request looks up the token by its hash
+    let opts = IndexOptions::builder().unique(true).build();
+    let hash_idx = IndexModel::builder()
+        .keys(doc! {api_token::TOKEN_HASH_KEY: 1})
+        .options(opts)
+        .build();
+    if let Err(e) = collection.create_index(hash_idx).await {
+        log::error!(
+            "Failed to create token hash index for API token collection: {}",
+            e
+        );
+    }
+
+    let user_id_idx = IndexModel::builder()
+        .keys(doc! {api_token::USER_ID_KEY: 1})
+        .build();
+    if let Err(e) = collection.create_index(user_id_idx).await {
+        log::error!(
+            "Failed to create user id index for API token collection: {}",
+            e
+        );
+    }
+
+    let opts = IndexOptions::builder()
+        .expire_after(std::time::Duration::from_secs(0))
+        .build();
+    let expiry_idx = IndexModel::builder()
+        .keys(doc! {api_token::EXPIRY_KEY: 1})
+        .options(opts)
+        .build();
+    if let Err(e) = collection.create_index(expiry_idx).await {
+        log::error!(
+            "Failed to create expiry index for API token collection: {}",
+            e
+        );
+    }
+}
+
+async fn init_audit_log_collection(client: &Client) {
+    let db = client.database(constants::DATABASE);
+    let collection: Collection<AuditEvent> = db.collection(constants::AUDIT_LOG_COLLECTION);
+
+    // also used for sorting by the time in either direction
+    let opts = IndexOptions::builder()
+        .expire_after(constants::AUDIT_LOG_RETENTION)
+        .build();
+    let retention_idx = IndexModel::builder()
+        .keys(doc! {audit_event::TIMESTAMP_KEY: 1})
+        .options(opts)
+        .build();
+    if let Err(e) = collection.create_index(retention_idx).await {
+        log::error!(
+            "Failed to create retention index for audit log collection: {}",
+            e
+        );
+    }
+
+    let indexes = [
+        (
+            "action",
+            doc! {audit_event::ACTION_KEY: 1, audit_event::TIMESTAMP_KEY: -1},
+        ),
+        (
+            "actor id",
+            doc! {audit_event::ACTOR_ID_KEY: 1, audit_event::TIMESTAMP_KEY: -1},
+        ),
+        (
+            "IP address",
+            doc! {audit_event::IP_ADDR_KEY: 1, audit_event::TIMESTAMP_KEY: -1},
+        ),
+    ];
+    for (name, keys) in indexes {
+        let idx = IndexModel::builder().keys(keys).build();
+        if let Err(e) = collection.create_index(idx).await {
+            log::error!(
+                "Failed to create {} index for audit log collection: {}",
+                name,
+                e
+            );
+        }
+    }
+}
+
 async fn init_blog_collection(client: &Client) {
     let db = client.database(constants::DATABASE);
     let collection: Collection<Blog> = db.collection(constants::BLOG_COLLECTION);
 
+    // used for counting the references to a stored file. Since creating an
+    // existing index is a no-op, it is also created for existing collections.
+    let files_url_idx = IndexModel::builder()
+        .keys(doc! {format!("{}.url", blog::FILES_KEY): 1})
+        .build();
+    if let Err(e) = collection.create_index(files_url_idx).await {
+        log::error!("Failed to create files url index: {}", e);
+    }
+
+    // blog posts created before versioning are at version 0 so that they can be updated conditionally
+    if let Err(e) = collection
+        .update_many(
+            doc! {blog::VERSION_KEY: {"$exists": false}},
+            doc! {"$set": {blog::VERSION_KEY: 0_i64}},
+        )
+        .await
+    {
+        log::error!("Failed to set the version of existing blog posts: {}", e);
+    }
+
     // check if the collection already exists
     let result = collection.find_one(doc! {}).await;
     match result {
@@ -181,7 +403,23 @@ pub async fn init_db() -> Result<DbClient, mongodb::error::Error> {
     let init_user_future = init_user_collection(client_ref);
     let init_session_future = init_session_collection(client_ref);
     let init_blog_future = init_blog_collection(client_ref);
-    tokio::join!(init_user_future, init_session_future, init_blog_future);
+    let init_preview_link_future = init_preview_link_collection(client_ref);
+    let init_passkey_challenge_future = init_passkey_challenge_collection(client_ref);
+    let init_login_attempt_future = init_login_attempt_collection(client_ref);
+    let init_invite_future = init_invite_collection(client_ref);
+    let init_api_token_future = init_api_token_collection(client_ref);
+    let init_audit_log_future = init_audit_log_collection(client_ref);
+    tokio::join!(
+        init_user_future,
+        init_session_future,
+        init_blog_future,
+        init_preview_link_future,
+        init_passkey_challenge_future,
+        init_login_attempt_future,
+        init_invite_future,
+        init_api_token_future,
+        init_audit_log_future
+    );
 
     Ok(client)
 }
@@ -21,10 +21,82 @@ pub enum AuthError {
     MissingTotp,
     #[display("Invalid Time-based One-Time Password (TOTP)")]
     InvalidTotp,
+    #[display("Unsupported TOTP algorithm or number of digits")]
+    InvalidTotpConfig,
     #[display("Already enabled 2FA")]
     AlreadyEnabled2fa,
     #[display("Already disabled 2FA")]
     AlreadyDisabled2fa,
+    #[display("Invalid passkey")]
+    InvalidPasskey,
+    #[display("The passkey request has expired, please try again")]
+    PasskeyChallengeExpired,
+    #[display("Passkey not found")]
+    PasskeyNotFound,
+    #[display("This passkey has already been registered")]
+    PasskeyAlreadyRegistered,
+    #[display(
+        "You cannot register more than {} passkeys",
+        crate::constants::MAX_PASSKEYS
+    )]
+    TooManyPasskeys,
+    #[display(
+        "The passkey name must be between 1 and {} characters",
+        crate::constants::PASSKEY_NAME_MAX_LENGTH
+    )]
+    InvalidPasskeyName,
+    #[display("Too many failed login attempts, please try again later")]
+    TooManyAttempts, // also used for locked accounts to avoid enumeration attacks
+    #[display("No failed login attempts found for {}", _0)]
+    LoginAttemptNotFound(#[error(not(source))] String),
+    #[display("Session not found")]
+    SessionNotFound,
+    #[display("Use the logout button to sign out of this device")]
+    CannotRevokeCurrentSession,
+    #[display("This account has been disabled")]
+    AccountDisabled,
+    #[display("You do not have permission to do this")]
+    PermissionDenied,
+    #[display(
+        "The username must be between {} and {} letters, digits, dots, dashes or underscores",
+        crate::constants::USERNAME_MIN_LENGTH,
+        crate::constants::USERNAME_MAX_LENGTH
+    )]
+    InvalidUsername,
+    #[display("Invalid email address")]
+    InvalidEmail,
+    #[display(
+        "The password must be between {} and {} characters",
+        crate::constants::PASSWORD_MIN_LENGTH,
+        crate::constants::PASSWORD_MAX_LENGTH
+    )]
+    InvalidPassword,
+    #[display("The username or email is already in use")]
+    UserAlreadyExists,
+    #[display("The invite link is invalid or has expired")]
+    InviteNotFound,
+    #[display("You cannot change the role of or disable your own account")]
+    CannotModifyOwnAccount,
+    #[display("Invalid or expired API token")]
+    InvalidApiToken,
+    #[display("API token not found")]
+    ApiTokenNotFound,
+    #[display(
+        "The API token name must be between 1 and {} characters",
+        crate::constants::API_TOKEN_NAME_MAX_LENGTH
+    )]
+    InvalidApiTokenName,
+    #[display("Select at least one scope for the API token")]
+    MissingApiTokenScope,
+    #[display(
+        "You cannot create more than {} API tokens",
+        crate::constants::MAX_API_TOKENS
+    )]
+    TooManyApiTokens,
+    #[display("Too many requests with this API token, please try again later")]
+    ApiTokenRateLimited,
+    #[display("Unknown audit log action")]
+    InvalidAuditLogFilter,
     #[display("Captcha verification failed")]
     CaptchaFailed,
     #[display("Internal server error")]
@@ -63,12 +135,90 @@ impl ResponseError for AuthError {
                 .insert_header(("X-Login-Error", "InvalidTotp"))
                 .content_type(content_type)
                 .body(error_html),
+            AuthError::InvalidTotpConfig => HttpResponse::BadRequest()
+                .content_type(content_type)
+                .body(error_html),
             AuthError::AlreadyEnabled2fa => HttpResponse::BadRequest()
                 .content_type(content_type)
                 .body(error_html),
             AuthError::AlreadyDisabled2fa => HttpResponse::BadRequest()
                 .content_type(content_type)
                 .body(error_html),
+            AuthError::InvalidPasskey => HttpResponse::Unauthorized()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::PasskeyChallengeExpired => HttpResponse::BadRequest()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::PasskeyNotFound => HttpResponse::NotFound()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::PasskeyAlreadyRegistered => HttpResponse::Conflict()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::TooManyPasskeys => HttpResponse::BadRequest()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::InvalidPasskeyName => HttpResponse::BadRequest()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::TooManyAttempts => HttpResponse::TooManyRequests()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::LoginAttemptNotFound(_) => HttpResponse::NotFound()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::SessionNotFound => HttpResponse::NotFound()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::CannotRevokeCurrentSession => HttpResponse::BadRequest()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::AccountDisabled => HttpResponse::Forbidden()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::PermissionDenied => HttpResponse::Forbidden()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::InvalidUsername => HttpResponse::BadRequest()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::InvalidEmail => HttpResponse::BadRequest()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::InvalidPassword => HttpResponse::BadRequest()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::UserAlreadyExists => HttpResponse::Conflict()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::InviteNotFound => HttpResponse::NotFound()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::CannotModifyOwnAccount => HttpResponse::BadRequest()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::InvalidApiToken => HttpResponse::Unauthorized()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::ApiTokenNotFound => HttpResponse::NotFound()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::InvalidApiTokenName => HttpResponse::BadRequest()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::MissingApiTokenScope => HttpResponse::BadRequest()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::TooManyApiTokens => HttpResponse::BadRequest()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::ApiTokenRateLimited => HttpResponse::TooManyRequests()
+                .content_type(content_type)
+                .body(error_html),
+            AuthError::InvalidAuditLogFilter => HttpResponse::BadRequest()
+                .content_type(content_type)
+                .body(error_html),
             AuthError::CaptchaFailed => HttpResponse::BadRequest()
                 .content_type(content_type)
                 .body(error_html),
@@ -0,0 +1,38 @@
+use actix_web::{HttpResponse, ResponseError};
+use derive_more::{Display, Error as DeriveError};
+
+#[derive(Debug, Display, DeriveError)]
+pub enum BackupError {
+    #[display("Backup not found")]
+    BackupNotFound,
+    #[display("Blog post not found")]
+    BlogNotFound,
+    #[display("Backup is corrupted")]
+    InvalidBackup,
+    #[display("Invalid export archive")]
+    InvalidArchive,
+    #[display("Export archive failed the integrity check")]
+    ChecksumMismatch,
+    #[display("Unsupported export archive version")]
+    UnsupportedVersion,
+    #[display("Failed to export site")]
+    ExportError,
+    #[display("Internal server error")]
+    InternalServerError,
+}
+
+impl ResponseError for BackupError {
+    fn error_response(&self) -> HttpResponse {
+        let error = self.to_string();
+        match self {
+            BackupError::BackupNotFound => HttpResponse::NotFound().body(error),
+            BackupError::BlogNotFound => HttpResponse::NotFound().body(error),
+            BackupError::InvalidBackup => HttpResponse::InternalServerError().body(error),
+            BackupError::InvalidArchive => HttpResponse::BadRequest().body(error),
+            BackupError::ChecksumMismatch => HttpResponse::BadRequest().body(error),
+            BackupError::UnsupportedVersion => HttpResponse::BadRequest().body(error),
+            BackupError::ExportError => HttpResponse::InternalServerError().body(error),
+            BackupError::InternalServerError => HttpResponse::InternalServerError().body(error),
+        }
+    }
+}
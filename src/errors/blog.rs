@@ -1,4 +1,6 @@
 use crate::constants::{MAX_FILE_SIZE, MAX_TAGS, TITLE_MAX_LENGTH};
+use crate::models::blog_conflict::BlogConflict;
+use crate::models::lint::LintReport;
 
 use actix_web::{HttpResponse, ResponseError};
 use derive_more::{Display, Error as DeriveError};
@@ -27,6 +29,24 @@ pub enum BlogError {
     FileTooLarge,
     #[display("Failed to upload file")]
     FileUploadError,
+    #[display("Invalid file URL")]
+    InvalidFileUrl,
+    #[display("The file is being deleted, please upload it again")]
+    FileBeingDeleted,
+    #[display("Failed to delete file")]
+    FileDeleteError,
+    #[display("Failed to list stored files")]
+    FileListError,
+    #[display("Media not found")]
+    MediaNotFound,
+    #[display("Alt text cannot be longer than 250 characters")]
+    AltTextTooLong,
+    #[display("Blog post was updated by someone else")]
+    VersionConflict(#[error(not(source))] Box<BlogConflict>),
+    #[display("Blog post has content issues that must be fixed before publishing")]
+    LintFailed(#[error(not(source))] Box<LintReport>),
+    #[display("You can only change your own blog posts")]
+    PermissionDenied,
     #[display("Internal server error")]
     InternalServerError,
 }
@@ -46,6 +66,15 @@ impl ResponseError for BlogError {
             BlogError::FileIsEmpty => HttpResponse::BadRequest().body(error),
             BlogError::FileTooLarge => HttpResponse::BadRequest().body(error),
             BlogError::FileUploadError => HttpResponse::InternalServerError().body(error),
+            BlogError::InvalidFileUrl => HttpResponse::BadRequest().body(error),
+            BlogError::FileBeingDeleted => HttpResponse::Conflict().body(error),
+            BlogError::FileDeleteError => HttpResponse::InternalServerError().body(error),
+            BlogError::FileListError => HttpResponse::InternalServerError().body(error),
+            BlogError::MediaNotFound => HttpResponse::NotFound().body(error),
+            BlogError::AltTextTooLong => HttpResponse::BadRequest().body(error),
+            BlogError::VersionConflict(conflict) => HttpResponse::Conflict().json(conflict),
+            BlogError::LintFailed(report) => HttpResponse::UnprocessableEntity().json(report),
+            BlogError::PermissionDenied => HttpResponse::Forbidden().body(error),
             BlogError::InternalServerError => HttpResponse::InternalServerError().body(error),
         }
     }
@@ -1,4 +1,5 @@
 pub(crate) mod auth;
+pub(crate) mod backup;
 pub(crate) mod base;
 pub(crate) mod blog;
 pub(crate) mod crypto;
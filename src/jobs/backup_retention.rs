@@ -0,0 +1,18 @@
+use crate::constants;
+use crate::utils::backup::prune_all_blog_backups;
+
+use aws_sdk_s3 as s3;
+
+pub fn spawn_backup_pruner(s3_client: s3::Client) {
+    actix_web::rt::spawn(async move {
+        let mut interval = tokio::time::interval(constants::BACKUP_PRUNE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match prune_all_blog_backups(&s3_client).await {
+                Some(0) => {}
+                Some(deleted) => log::info!("Pruned {} expired blog backup(s)", deleted),
+                None => log::error!("Failed to prune blog backups"),
+            }
+        }
+    });
+}
@@ -0,0 +1,22 @@
+use crate::constants;
+use crate::database::db::DbClient;
+use crate::utils::blog::operation_utils::recover_blog_operations;
+
+use aws_sdk_s3 as s3;
+
+/// Periodically settles the blog operations that outlived their lease
+/// since the ones in progress during startup are left alone.
+pub fn spawn_blog_operation_recovery(db_client: DbClient, s3_client: s3::Client) {
+    actix_web::rt::spawn(async move {
+        // the operations were already recovered on startup
+        let period = constants::BLOG_OPERATION_RECOVERY_INTERVAL;
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        loop {
+            interval.tick().await;
+            match recover_blog_operations(&db_client, &s3_client).await {
+                0 => {}
+                recovered => log::info!("Recovered {} interrupted blog operation(s)", recovered),
+            }
+        }
+    });
+}
@@ -0,0 +1,92 @@
+use crate::database::db::DbClient;
+use crate::errors::auth::AuthError;
+use crate::models::key_rotation_report::KeyRotationReport;
+use crate::models::user;
+use crate::security::chacha_crypto;
+
+use bson::doc;
+use futures_util::TryStreamExt;
+
+#[inline]
+fn to_binary(bytes: Vec<u8>) -> bson::Binary {
+    bson::Binary {
+        subtype: bson::spec::BinarySubtype::Generic,
+        bytes,
+    }
+}
+
+/// Re-encrypts every TOTP secret that is not encrypted with the newest DB key yet.
+///
+/// The update only applies if the secret has not changed since it was read
+/// so that a user setting up 2FA at the same time is not overwritten.
+/// Once the report has no failures, the older DB keys can be removed from the keyring.
+pub async fn reencrypt_totp_secrets(db_client: &DbClient) -> Result<KeyRotationReport, AuthError> {
+    let current_key_id = chacha_crypto::get_current_db_key_id();
+    let mut report = KeyRotationReport {
+        key_id: current_key_id.to_string(),
+        ..Default::default()
+    };
+
+    let mut cursor = db_client
+        .get_user_collection()
+        .find(doc! {user::TOTP_SECRET_KEY: {"$type": "binData"}})
+        .await
+        .map_err(|e| {
+            log::error!("Failed to find the users with a TOTP secret: {:?}", e);
+            AuthError::InternalServerError
+        })?;
+    while let Some(user) = cursor.try_next().await.map_err(|e| {
+        log::error!("Failed to read the users with a TOTP secret: {:?}", e);
+        AuthError::InternalServerError
+    })? {
+        let encrypted_secret = match user.get_encrypted_totp_secret() {
+            Some(encrypted_secret) => encrypted_secret.clone(),
+            None => continue,
+        };
+        report.scanned += 1;
+
+        let secret = match chacha_crypto::decrypt_with_db_key_id(&encrypted_secret) {
+            Ok((_, key_id)) if key_id == current_key_id => {
+                report.up_to_date += 1;
+                continue;
+            }
+            Ok((secret, _)) => secret,
+            Err(e) => {
+                log::error!("Failed to decrypt the TOTP secret of {}: {:?}", user._id, e);
+                report.failed.push(user._id.to_hex());
+                continue;
+            }
+        };
+        let reencrypted_secret = match chacha_crypto::encrypt_with_db_key(&secret) {
+            Ok(reencrypted_secret) => reencrypted_secret,
+            Err(e) => {
+                log::error!("Failed to encrypt the TOTP secret of {}: {:?}", user._id, e);
+                report.failed.push(user._id.to_hex());
+                continue;
+            }
+        };
+
+        let result = db_client
+            .get_user_collection()
+            .update_one(
+                doc! {"_id": user._id, user::TOTP_SECRET_KEY: to_binary(encrypted_secret)},
+                doc! {"$set": {user::TOTP_SECRET_KEY: to_binary(reencrypted_secret)}},
+            )
+            .await;
+        match result {
+            Ok(result) if result.modified_count == 1 => report.reencrypted += 1,
+            Ok(_) => {
+                log::warn!(
+                    "The TOTP secret of {} changed while re-encrypting",
+                    user._id
+                );
+                report.failed.push(user._id.to_hex());
+            }
+            Err(e) => {
+                log::error!("Failed to update the TOTP secret of {}: {:?}", user._id, e);
+                report.failed.push(user._id.to_hex());
+            }
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,23 @@
+use crate::constants;
+use crate::database::db::DbClient;
+use crate::utils::link_checker::{check_all_links, LinkClient};
+
+pub fn spawn_link_checker<C: LinkClient + Clone + 'static>(db_client: DbClient, client: C) {
+    actix_web::rt::spawn(async move {
+        let mut interval = tokio::time::interval(constants::LINK_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            match check_all_links(&db_client, client.clone()).await {
+                Ok(Some(summary)) => log::info!(
+                    "Checked {} outbound link(s): {} ok, {} broken, {} skipped",
+                    summary.checked,
+                    summary.ok,
+                    summary.broken,
+                    summary.skipped
+                ),
+                Ok(None) => log::info!("Skipped the link check as one is already running"),
+                Err(_) => log::error!("Failed to check the outbound links"),
+            }
+        }
+    });
+}
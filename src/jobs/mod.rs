@@ -0,0 +1,5 @@
+pub(crate) mod backup_retention;
+pub(crate) mod blog_operations;
+pub(crate) mod key_rotation;
+pub(crate) mod link_checker;
+pub(crate) mod storage_gc;
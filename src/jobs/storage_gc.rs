@@ -0,0 +1,149 @@
+use crate::constants;
+use crate::database::db::DbClient;
+use crate::errors::blog::BlogError;
+use crate::models::blog_operation::{self, BlogOperationStatus};
+use crate::models::reconcile_report::{MissingFile, ReconcileReport};
+use crate::utils::blog::file_utils::get_public_url;
+use crate::utils::media::{delete_media_info, get_media_references};
+use crate::utils::storage;
+
+use aws_sdk_s3 as s3;
+use bson::doc;
+use futures_util::TryStreamExt;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Deletes objects under the temp prefix that are older than `max_age`.
+///
+/// Returns the number of deleted objects or `None` if the bucket could not be listed.
+pub async fn sweep_temp_uploads(s3_client: &s3::Client, max_age: Duration) -> Option<usize> {
+    let prefix = format!("{}/", constants::TEMP_OBJ_PREFIX);
+    let blobs = storage::list_blobs(s3_client, constants::BUCKET_FOR_TEMP, &prefix).await?;
+
+    let max_age =
+        chrono::Duration::from_std(max_age).expect("max age should fit in a chrono duration");
+    let cutoff = chrono::Utc::now() - max_age;
+    let mut deleted = 0;
+    for blob in blobs.iter() {
+        // objects without a timestamp are left alone as their age cannot be determined
+        let is_stale = blob.last_modified.map(|dt| dt < cutoff).unwrap_or(false);
+        if !is_stale {
+            continue;
+        }
+        if storage::delete_blob(s3_client, constants::BUCKET_FOR_TEMP, &blob.name).await {
+            deleted += 1;
+        } else {
+            log::error!("Failed to delete stale temp upload, {}", blob.name);
+        }
+    }
+    Some(deleted)
+}
+
+pub fn spawn_temp_sweeper(s3_client: s3::Client) {
+    actix_web::rt::spawn(async move {
+        let mut interval = tokio::time::interval(constants::TEMP_SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            match sweep_temp_uploads(&s3_client, constants::TEMP_OBJ_MAX_AGE).await {
+                Some(0) => {}
+                Some(deleted) => log::info!("Swept {} stale temp upload(s)", deleted),
+                None => log::error!("Failed to sweep temp uploads"),
+            }
+        }
+    });
+}
+
+/// Returns the urls of the files stored by the blog operations that have not been committed yet.
+async fn get_pending_stored_files(db_client: &DbClient) -> Result<HashSet<String>, BlogError> {
+    let status = bson::to_bson(&BlogOperationStatus::Pending)
+        .expect("Should be able to serialise blog operation status");
+    let operations = match db_client
+        .get_blog_operation_collection()
+        .find(doc! {blog_operation::STATUS_KEY: status})
+        .await
+    {
+        Ok(cursor) => cursor.try_collect::<Vec<_>>().await,
+        Err(e) => Err(e),
+    };
+    let operations = operations.map_err(|e| {
+        log::error!("Failed to get blog operations from journal: {:?}", e);
+        BlogError::InternalServerError
+    })?;
+    Ok(operations
+        .into_iter()
+        .flat_map(|operation| operation.stored_files)
+        .collect())
+}
+
+/// Compares the objects under the blog prefix in the public bucket against
+/// the files of every blog post.
+///
+/// Objects that no blog post references are reported as orphaned and are
+/// deleted unless `dry_run` is set. Files that are referenced but no longer
+/// exist in the bucket are only reported.
+///
+/// Unreferenced objects younger than [`constants::RECONCILE_GRACE_PERIOD`] or
+/// stored by a pending blog operation are skipped as the blog post that
+/// copied them may not have been written yet.
+pub async fn reconcile_blog_files(
+    db_client: &DbClient,
+    s3_client: &s3::Client,
+    dry_run: bool,
+) -> Result<ReconcileReport, BlogError> {
+    let prefix = format!("{}/", constants::get_blog_obj_prefix());
+    let blobs = storage::list_blobs(s3_client, constants::BUCKET, &prefix)
+        .await
+        .ok_or(BlogError::FileListError)?;
+    let referenced = get_media_references(db_client).await?;
+    let pending_files = get_pending_stored_files(db_client).await?;
+    let grace_period = chrono::Duration::from_std(constants::RECONCILE_GRACE_PERIOD)
+        .expect("grace period should fit in a chrono duration");
+    let cutoff = chrono::Utc::now() - grace_period;
+
+    let mut report = ReconcileReport {
+        dry_run,
+        scanned: blobs.len(),
+        referenced: referenced.len(),
+        ..Default::default()
+    };
+    let stored: HashSet<&str> = blobs.iter().map(|blob| blob.name.as_str()).collect();
+    for blob in blobs.iter() {
+        if referenced.contains_key(&blob.name) {
+            continue;
+        }
+        // objects without a timestamp are skipped as their age cannot be determined
+        let is_recent = blob.last_modified.map(|dt| dt >= cutoff).unwrap_or(true);
+        if is_recent || pending_files.contains(&get_public_url(&blob.name)) {
+            report.skipped.push(blob.name.clone());
+        } else {
+            report.orphaned.push(blob.name.clone());
+            report.orphaned_bytes += blob.size;
+        }
+    }
+    for (obj_name, posts) in referenced.iter() {
+        // only files under the blog prefix are expected to be in the listing
+        if !obj_name.starts_with(&prefix) || stored.contains(obj_name.as_str()) {
+            continue;
+        }
+        for post in posts.iter() {
+            report.missing.push(MissingFile {
+                blog_id: post.blog_id.clone(),
+                url: get_public_url(obj_name),
+            });
+        }
+    }
+
+    if dry_run {
+        return Ok(report);
+    }
+    for obj_name in report.orphaned.iter() {
+        if storage::delete_blob(s3_client, constants::BUCKET, obj_name).await {
+            delete_media_info(db_client, obj_name).await;
+            report.deleted.push(obj_name.clone());
+        } else {
+            log::error!("Failed to delete orphaned blob, {}", obj_name);
+            report.failed.push(obj_name.clone());
+        }
+    }
+    Ok(report)
+}
@@ -1,8 +1,10 @@
 mod api;
+mod cli;
 mod client;
 mod constants;
 mod database;
 mod errors;
+mod jobs;
 mod middleware;
 mod models;
 mod security;
@@ -21,8 +23,8 @@ use client::configure::add_client_routes;
 use database::init as db;
 use dotenv::dotenv;
 use middleware::configure::{
-    configure_auth_middleware, configure_cache_control_middleware, configure_csp_middleware,
-    configure_csrf_middleware, configure_hsts_middleware,
+    configure_auth_middleware, configure_authz_middleware, configure_cache_control_middleware,
+    configure_csp_middleware, configure_csrf_middleware, configure_hsts_middleware,
 };
 use middleware::errors::render_error;
 
@@ -64,6 +66,24 @@ async fn main() -> std::io::Result<()> {
     };
     let (db_client, s3_client) = tokio::join!(db_future, aws_future);
 
+    utils::blog::operation_utils::recover_blog_operations(&db_client, &s3_client).await;
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return cli::commands::run(&args, &db_client, &s3_client).await;
+    }
+    jobs::storage_gc::spawn_temp_sweeper(s3_client.clone());
+    jobs::backup_retention::spawn_backup_pruner(s3_client.clone());
+    jobs::blog_operations::spawn_blog_operation_recovery(db_client.clone(), s3_client.clone());
+    jobs::link_checker::spawn_link_checker(
+        db_client.clone(),
+        utils::link_checker::HttpLinkClient::new(),
+    );
+
+    let login_rate_limiter = web::Data::new(security::rate_limiter::DefaultLoginRateLimiter::new(
+        security::rate_limiter::MongoLoginAttemptStore::new(&db_client),
+    ));
+
     let address = if constants::get_debug_mode() {
         ("127.0.0.1", 8080)
     } else {
@@ -74,6 +94,7 @@ async fn main() -> std::io::Result<()> {
         App::new()
             .app_data(web::Data::new(db_client.clone()))
             .app_data(web::Data::new(s3_client.clone()))
+            .app_data(login_rate_limiter.clone())
             .wrap(Logger::default())
             .wrap(Compress::default())
             .wrap(middleware::host::HostMiddleware)
@@ -82,6 +103,8 @@ async fn main() -> std::io::Result<()> {
             .wrap(configure_csp_middleware())
             .wrap(configure_hsts_middleware())
             .wrap(configure_cache_control_middleware())
+            // runs after the auth middleware as it needs the user's role
+            .wrap(configure_authz_middleware())
             .wrap(configure_auth_middleware())
             .wrap(error_handler_many!(
                 render_error,
@@ -110,6 +133,8 @@ async fn main() -> std::io::Result<()> {
                     HTTP_VERSION_NOT_SUPPORTED
                 ]
             ))
+            // outermost so that every response, including the error pages, has a request id
+            .wrap(middleware::request_id::RequestIdMiddleware)
             .configure(add_client_routes)
             .configure(add_api_routes)
     })
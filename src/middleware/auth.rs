@@ -1,19 +1,24 @@
 use crate::constants;
+use crate::models::role::Role;
+use crate::models::session;
+use crate::security::keyring::KeyedSigner;
+use crate::security::rate_limiter::DefaultLoginRateLimiter;
 use crate::templates::error::ErrorTemplate;
-use crate::utils::security::{convert_vec_str_to_owned, get_default_key_info};
+use crate::utils::api_tokens::{authenticate_api_token, get_bearer_token};
+use crate::utils::security::{convert_vec_str_to_owned, get_default_signer};
 
 use actix_web::body::{BoxBody, EitherBody};
-use actix_web::cookie::Cookie;
+use actix_web::cookie::{time::OffsetDateTime, Cookie, SameSite};
 use actix_web::http::{header::ContentType, Method, StatusCode};
 use actix_web::web::Data;
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    Error, HttpMessage, HttpRequest, HttpResponse,
+    Error, HttpMessage, HttpRequest, HttpResponse, ResponseError,
 };
 use askama::Template;
+use bson::doc;
 use bson::oid::ObjectId;
 use futures_util::future::LocalBoxFuture;
-use hmac_serialiser::HmacSigner;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::future::{ready, Ready};
@@ -52,6 +57,13 @@ pub fn get_user_claim(req: &HttpRequest) -> UserClaim {
         .clone()
 }
 
+/// Returns the role of the signed in user, which is only known on the protected routes.
+pub fn get_user_role(req: &HttpRequest) -> Role {
+    *req.extensions()
+        .get::<Role>()
+        .expect("Role should be in the request extension")
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct UserClaim {
     #[serde(rename = "_id")]
@@ -59,26 +71,55 @@ pub struct UserClaim {
     pub session_id: ObjectId,
     #[serde(serialize_with = "bson::serde_helpers::serialize_object_id_as_hex_string")]
     pub user_id: ObjectId,
+    // the session's expiry when the cookie was signed, it is re-signed whenever the session is renewed
+    #[serde(default)]
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    pub exp: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 impl hmac_serialiser::Payload for UserClaim {
     fn get_exp(&self) -> Option<chrono::DateTime<chrono::Utc>> {
-        None
+        self.exp
     }
 }
 
-pub fn create_user_claim(user_id: ObjectId, id: ObjectId) -> UserClaim {
+pub fn create_user_claim(
+    user_id: ObjectId,
+    id: ObjectId,
+    exp: chrono::DateTime<chrono::Utc>,
+) -> UserClaim {
     UserClaim {
         user_id,
         session_id: id,
+        exp: Some(exp),
     }
 }
 
-pub fn get_default_auth_signer() -> HmacSigner {
-    HmacSigner::new(
-        get_default_key_info(constants::get_secret_key_salt(), vec![]),
+/// Builds the auth cookie with the signed user claim.
+///
+/// Remembered sessions are kept by the browser until they expire
+/// while the others are removed when the browser is closed.
+pub fn build_auth_cookie(user_claim: &UserClaim, remember: bool) -> Cookie<'static> {
+    let expires = match user_claim.exp {
+        // 10 seconds before the session's expiry for extra leeway
+        Some(exp) if remember => OffsetDateTime::from_unix_timestamp(exp.timestamp() - 10).ok(),
+        _ => None,
+    };
+    Cookie::build(constants::AUTH_COOKIE_NAME, sign_payload(user_claim))
+        .domain(constants::get_domain())
+        .path("/")
+        .same_site(SameSite::Lax)
+        .http_only(true)
+        .secure(!constants::get_debug_mode())
+        .expires(expires)
+        .finish()
+}
+
+pub fn get_default_auth_signer() -> KeyedSigner {
+    get_default_signer(
+        constants::get_secret_key_salt(),
+        vec![],
         hmac_serialiser::algorithm::Algorithm::SHA512,
-        hmac_serialiser::Encoder::UrlSafeNoPadding,
     )
 }
 
@@ -89,13 +130,13 @@ macro_rules! init_auth_signer {
 }
 
 pub fn sign_payload(user_claim: &UserClaim) -> String {
-    static AUTH_SIGNER: Lazy<HmacSigner> = init_auth_signer!();
+    static AUTH_SIGNER: Lazy<KeyedSigner> = init_auth_signer!();
     AUTH_SIGNER.sign(user_claim)
 }
 
 #[derive(Clone)]
 struct UserAuth {
-    csrf_signer: HmacSigner,
+    csrf_signer: KeyedSigner,
     cookie_name: String,
     whitelist: Vec<(Method, String)>,
     whitelist_regex: Vec<(Method, regex::Regex)>,
@@ -103,7 +144,7 @@ struct UserAuth {
 
 impl UserAuth {
     pub fn new(
-        csrf_signer: HmacSigner,
+        csrf_signer: KeyedSigner,
         cookie_name: String,
         whitelist: Vec<(Method, &str)>,
         whitelist_regex: Vec<(Method, regex::Regex)>,
@@ -127,7 +168,7 @@ pub struct AuthMiddleware {
 
 impl AuthMiddleware {
     pub fn new(
-        signer: Option<HmacSigner>,
+        signer: Option<KeyedSigner>,
         cookie_name: &str,
         whitelist: Vec<(Method, &str)>,
         whitelist_regex: Vec<(Method, regex::Regex)>,
@@ -179,6 +220,40 @@ where
     // https://github.com/actix/actix-extras/issues/63
     // https://github.com/actix/actix-web/discussions/2597
     fn call(&self, req: ServiceRequest) -> Self::Future {
+        // API tokens are used instead of the auth cookie so that scripts do not need a session
+        if let Some(token) = get_bearer_token(req.request()) {
+            let token = token.to_string();
+            let client = req
+                .app_data::<Data<crate::database::db::DbClient>>()
+                .unwrap()
+                .clone();
+            let limiter = req
+                .app_data::<Data<DefaultLoginRateLimiter>>()
+                .unwrap()
+                .clone();
+            let requires_auth = self.inner.requires_auth(&req);
+            let service = Rc::clone(&self.service);
+            return Box::pin(async move {
+                match authenticate_api_token(&client, &limiter, req.request(), &token).await {
+                    Ok((user_claim, role, scopes)) => {
+                        req.extensions_mut().insert(user_claim);
+                        // like the cookies, the role is only needed on the protected routes
+                        if requires_auth {
+                            req.extensions_mut().insert(role);
+                            req.extensions_mut().insert(scopes);
+                        }
+                        let res = service.call(req).await?;
+                        Ok(res.map_into_left_body())
+                    }
+                    Err(e) => {
+                        log::warn!("Rejected a request with an API token: {}", e);
+                        let res = e.error_response();
+                        Ok(req.into_response(res).map_into_right_body())
+                    }
+                }
+            });
+        }
+
         let auth_cookie = req.cookie(&self.inner.cookie_name);
         let user_claim = if auth_cookie.is_none() {
             None
@@ -190,6 +265,25 @@ where
                 .unsign::<UserClaim>(&auth_cookie.value())
             {
                 Ok(user_claim) => Some(user_claim),
+                Err(hmac_serialiser::errors::Error::TokenExpired)
+                    if req.method() == Method::OPTIONS || !self.inner.requires_auth(&req) =>
+                {
+                    // the public routes are still served but as a guest
+                    let fut = self.service.call(req);
+                    return Box::pin(async move {
+                        let mut res = fut.await?;
+                        let mut auth_cookie = Cookie::build(constants::AUTH_COOKIE_NAME, "")
+                            .path("/")
+                            .domain(constants::get_domain())
+                            .http_only(true)
+                            .finish();
+                        auth_cookie.make_removal();
+                        if let Err(e) = res.response_mut().add_cookie(&auth_cookie) {
+                            log::error!("Failed to remove the expired auth cookie: {:?}", e);
+                        }
+                        Ok(res.map_into_left_body())
+                    });
+                }
                 Err(e) => {
                     return match e {
                         hmac_serialiser::errors::Error::TokenExpired => Box::pin(async move {
@@ -248,9 +342,49 @@ where
                 auth_failed!(req, StatusCode::NOT_FOUND);
             }
 
+            // the idle timeout is renewed together with the last seen time
+            // so that the session is written at most once per interval
+            let mut renewed_cookie = None;
+            if session.should_update_last_seen() {
+                let ip_addr = crate::security::cf_turnstile::get_ip_addr(req.request());
+                let expiry = session.get_renewed_expiry();
+                let result = client
+                    .get_session_collection()
+                    .update_one(
+                        doc! {"_id": session._id},
+                        doc! {"$set": {
+                            session::LAST_SEEN_KEY: bson::DateTime::now(),
+                            session::IP_ADDR_KEY: ip_addr.unwrap_or(session.ip_addr),
+                            session::EXPIRY_KEY: bson::DateTime::from_chrono(expiry),
+                        }},
+                    )
+                    .await;
+                match result {
+                    Ok(_) => {
+                        let renewed_claim =
+                            create_user_claim(user_claim.user_id, user_claim.session_id, expiry);
+                        renewed_cookie = Some(build_auth_cookie(&renewed_claim, session.remember));
+                    }
+                    Err(e) => log::error!("Failed to renew the session: {:?}", e),
+                }
+            }
+
             req.extensions_mut().insert(user_claim);
-            let fut = service.call(req).await?;
-            Ok(fut.map_into_left_body())
+            req.extensions_mut().insert(session.role);
+            let mut res = service.call(req).await?;
+            if let Some(renewed_cookie) = renewed_cookie {
+                // the handler may have already replaced or removed the auth cookie
+                let has_auth_cookie = res
+                    .response()
+                    .cookies()
+                    .any(|cookie| cookie.name() == constants::AUTH_COOKIE_NAME);
+                if !has_auth_cookie {
+                    if let Err(e) = res.response_mut().add_cookie(&renewed_cookie) {
+                        log::error!("Failed to set the renewed auth cookie: {:?}", e);
+                    }
+                }
+            }
+            Ok(res.map_into_left_body())
         })
     }
 }
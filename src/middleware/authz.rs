@@ -0,0 +1,146 @@
+use crate::errors::auth::AuthError;
+use crate::models::api_token::{TokenScope, TokenScopes};
+use crate::models::role::Role;
+use crate::templates::error::ErrorTemplate;
+use crate::utils::html::render_template;
+use crate::utils::security::extract_for_template;
+
+use actix_web::body::{BoxBody, EitherBody};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error, HttpMessage, ResponseError,
+};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+
+#[inline]
+fn find_rule<T: Copy>(rules: &[(Method, regex::Regex, T)], req: &ServiceRequest) -> Option<T> {
+    let path = req.path();
+    rules
+        .iter()
+        .find(|(method, regex, _)| method == req.method() && regex.is_match(path))
+        .map(|(_, _, requirement)| *requirement)
+}
+
+#[derive(Clone)]
+struct RoleRules {
+    rules: Vec<(Method, regex::Regex, Role)>,
+    default_role: Role,
+    scope_rules: Vec<(Method, regex::Regex, TokenScope)>,
+}
+
+impl RoleRules {
+    /// Returns the role required by the first matching rule or the default role.
+    fn get_required_role(&self, req: &ServiceRequest) -> Role {
+        find_rule(&self.rules, req).unwrap_or(self.default_role)
+    }
+
+    /// Returns true if one of the scopes allows the route, the routes without a scope are denied.
+    fn is_in_scope(&self, req: &ServiceRequest, scopes: &TokenScopes) -> bool {
+        find_rule(&self.scope_rules, req).is_some_and(|scope| scopes.0.contains(&scope))
+    }
+}
+
+/// Rejects the requests of signed in users whose role is lower than the one the route requires.
+///
+/// The role is added to the request by the [`crate::middleware::auth::AuthMiddleware`]
+/// on the protected routes, so this has to be wrapped before it to run after it.
+/// Requests without a role, such as the ones to the public routes, are left alone.
+/// Requests authenticated with an API token also need a scope of the token that allows the route.
+#[derive(Clone)]
+pub struct AuthzMiddleware {
+    inner: RoleRules,
+}
+
+impl AuthzMiddleware {
+    pub fn new(
+        rules: Vec<(Method, regex::Regex, Role)>,
+        default_role: Role,
+        scope_rules: Vec<(Method, regex::Regex, TokenScope)>,
+    ) -> Self {
+        Self {
+            inner: RoleRules {
+                rules,
+                default_role,
+                scope_rules,
+            },
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for AuthzMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B, BoxBody>>;
+    type Error = Error;
+    type Transform = AuthzMiddlewareService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthzMiddlewareService {
+            service: Rc::new(service),
+            inner: Rc::new(self.inner.clone()),
+        }))
+    }
+}
+
+pub struct AuthzMiddlewareService<S> {
+    service: Rc<S>,
+    inner: Rc<RoleRules>,
+}
+
+impl<S, B> Service<ServiceRequest> for AuthzMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B, BoxBody>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let role = req.extensions().get::<Role>().copied();
+        let is_allowed = match role {
+            Some(role) => role.has_at_least(self.inner.get_required_role(&req)),
+            None => true,
+        } && match req.extensions().get::<TokenScopes>() {
+            Some(scopes) => self.inner.is_in_scope(&req, scopes),
+            None => true,
+        };
+        if is_allowed {
+            let fut = self.service.call(req);
+            return Box::pin(async move {
+                let res = fut.await?;
+                Ok(res.map_into_left_body())
+            });
+        }
+
+        log::warn!(
+            "Denied {} {} to a user with the {} role",
+            req.method(),
+            req.path(),
+            role.unwrap_or(Role::Viewer)
+        );
+        // the admin pages are shown as an error page while the API returns an alert
+        let res = if req.path().starts_with("/api/") {
+            AuthError::PermissionDenied.error_response()
+        } else {
+            let template = ErrorTemplate {
+                common: extract_for_template(req.request()),
+                status: StatusCode::FORBIDDEN.as_u16(),
+                message: "You do not have permission to view this page",
+            };
+            render_template(template, StatusCode::FORBIDDEN)
+        };
+        Box::pin(async move { Ok(req.into_response(res).map_into_right_body()) })
+    }
+}
@@ -1,3 +1,5 @@
+use crate::models::api_token::TokenScope;
+use crate::models::role::Role;
 use crate::{constants, middleware};
 
 use actix_web::http::Method;
@@ -19,6 +21,7 @@ macro_rules! get_client_routes {
             (Method::GET, "/blogs"),
             (Method::GET, "/admin"),
             (Method::GET, "/login"),
+            (Method::GET, "/invite"),
             (Method::GET, "/api"),
             (Method::GET, "/api/health"),
             (Method::GET, "/api/csrf-token"),
@@ -37,6 +40,10 @@ macro_rules! add_login_api_uri_path {
     ($whitelist:ident) => {
         let login_api_uri = format!("/api{}", constants::get_login_uri_path());
         $whitelist.push((Method::POST, &login_api_uri));
+        let passkey_options_api_uri = format!("{}/passkey/options", login_api_uri);
+        $whitelist.push((Method::POST, &passkey_options_api_uri));
+        let passkey_api_uri = format!("{}/passkey", login_api_uri);
+        $whitelist.push((Method::POST, &passkey_api_uri));
     };
 }
 
@@ -47,6 +54,7 @@ pub fn configure_auth_middleware() -> middleware::auth::AuthMiddleware {
         (Method::POST, "/api/login"),
         (Method::POST, "/api/auth/login"),
         (Method::POST, "/api/logout"),
+        (Method::POST, "/api/invite"),
     ]);
     add_login_uri_path!(auth_whitelist);
     add_login_api_uri_path!(auth_whitelist);
@@ -63,6 +71,130 @@ pub fn configure_auth_middleware() -> middleware::auth::AuthMiddleware {
     auth_middleware
 }
 
+macro_rules! route_rule {
+    ($method:expr, $regex:expr, $requirement:expr) => {
+        ($method, regex::Regex::new($regex).unwrap(), $requirement)
+    };
+}
+
+pub fn configure_authz_middleware() -> middleware::authz::AuthzMiddleware {
+    let rules = vec![
+        // every signed in user can view the admin pages and manage their own account
+        route_rule!(Method::GET, r"^/admin/(profile|media|links)$", Role::Viewer),
+        route_rule!(Method::GET, r"^/api/admin/media$", Role::Viewer),
+        route_rule!(Method::GET, r"^/api/admin/generate-2fa$", Role::Viewer),
+        route_rule!(
+            Method::POST,
+            r"^/api/admin/(setup-2fa|remove-2fa|recovery-codes)$",
+            Role::Viewer
+        ),
+        route_rule!(Method::PATCH, r"^/api/admin/change-password$", Role::Viewer),
+        route_rule!(
+            Method::POST,
+            r"^/api/admin/passkeys(/options)?$",
+            Role::Viewer
+        ),
+        route_rule!(Method::DELETE, r"^/api/admin/passkeys/[^/]+$", Role::Viewer),
+        route_rule!(Method::DELETE, r"^/api/admin/sessions/[^/]+$", Role::Viewer),
+        route_rule!(
+            Method::POST,
+            r"^/api/admin/sessions/revoke-others$",
+            Role::Viewer
+        ),
+        route_rule!(Method::POST, r"^/api/admin/tokens$", Role::Viewer),
+        route_rule!(Method::DELETE, r"^/api/admin/tokens/[^/]+$", Role::Viewer),
+        // authors can only change their own blog posts, which is checked by the handlers
+        route_rule!(Method::GET, r"^/admin/new/blog$", Role::Author),
+        route_rule!(Method::GET, r"^/admin/blogs/[^/]+/edit$", Role::Author),
+        route_rule!(Method::POST, r"^/api/new/blog$", Role::Author),
+        route_rule!(Method::PATCH, r"^/api/blog/update$", Role::Author),
+        route_rule!(Method::POST, r"^/api/blog/upload/files$", Role::Author),
+        route_rule!(Method::DELETE, r"^/api/blogs/[^/]+/delete$", Role::Author),
+        route_rule!(
+            Method::PATCH,
+            r"^/api/blogs/[^/]+/(publish|unpublish)$",
+            Role::Author
+        ),
+        route_rule!(Method::PUT, r"^/api/blogs/[^/]+/draft$", Role::Author),
+        route_rule!(Method::DELETE, r"^/api/blogs/[^/]+/draft$", Role::Author),
+        route_rule!(
+            Method::GET,
+            r"^/api/blogs/[^/]+/preview-links$",
+            Role::Author
+        ),
+        route_rule!(
+            Method::POST,
+            r"^/api/blogs/[^/]+/preview-links$",
+            Role::Author
+        ),
+        route_rule!(
+            Method::DELETE,
+            r"^/api/blogs/[^/]+/preview-links/[^/]+$",
+            Role::Author
+        ),
+        route_rule!(Method::GET, r"^/api/admin/ws/blog/preview$", Role::Author),
+        route_rule!(Method::POST, r"^/api/admin/ws/blog/preview$", Role::Author),
+        // editors manage the content of the whole site
+        route_rule!(Method::PATCH, r"^/api/admin/media/alt-text$", Role::Editor),
+        route_rule!(Method::POST, r"^/api/admin/links/check$", Role::Editor),
+        route_rule!(Method::GET, r"^/api/admin/backups/[^/]+$", Role::Editor),
+        route_rule!(
+            Method::POST,
+            r"^/api/admin/backups/[^/]+/restore$",
+            Role::Editor
+        ),
+        route_rule!(Method::GET, r"^/api/admin/export/markdown$", Role::Editor),
+        route_rule!(
+            Method::GET,
+            r"^/api/admin/blogs/[^/]+/export/markdown$",
+            Role::Editor
+        ),
+        route_rule!(
+            Method::POST,
+            r"^/api/admin/import/(markdown|wordpress|content-dir)$",
+            Role::Editor
+        ),
+    ];
+    // requests with an API token can only use the routes of the token's scopes
+    let scope_rules = vec![
+        route_rule!(Method::POST, r"^/api/new/blog$", TokenScope::PostsWrite),
+        route_rule!(Method::PATCH, r"^/api/blog/update$", TokenScope::PostsWrite),
+        route_rule!(
+            Method::DELETE,
+            r"^/api/blogs/[^/]+/delete$",
+            TokenScope::PostsWrite
+        ),
+        route_rule!(
+            Method::PATCH,
+            r"^/api/blogs/[^/]+/(publish|unpublish)$",
+            TokenScope::PostsWrite
+        ),
+        route_rule!(
+            Method::PUT,
+            r"^/api/blogs/[^/]+/draft$",
+            TokenScope::PostsWrite
+        ),
+        route_rule!(
+            Method::DELETE,
+            r"^/api/blogs/[^/]+/draft$",
+            TokenScope::PostsWrite
+        ),
+        route_rule!(
+            Method::POST,
+            r"^/api/blog/upload/files$",
+            TokenScope::MediaWrite
+        ),
+        route_rule!(Method::GET, r"^/api/admin/media$", TokenScope::MediaWrite),
+        route_rule!(
+            Method::PATCH,
+            r"^/api/admin/media/alt-text$",
+            TokenScope::MediaWrite
+        ),
+    ];
+    // the routes that are not listed, including the ones added later, are only for the owners
+    middleware::authz::AuthzMiddleware::new(rules, Role::Owner, scope_rules)
+}
+
 pub fn configure_csrf_middleware() -> middleware::csrf::CsrfMiddleware {
     let mut csrf_whitelist = get_client_routes!();
     add_login_uri_path!(csrf_whitelist);
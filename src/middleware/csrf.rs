@@ -1,5 +1,6 @@
 use crate::errors::csrf as csrf_errors;
 use crate::security::csrf::CsrfSigner;
+use crate::utils::api_tokens::get_bearer_token;
 use crate::utils::security::{convert_vec_str_to_owned, is_protected};
 
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
@@ -151,7 +152,10 @@ where
         });
 
         // Since rust do not natively support negative lookahead
+        // browsers cannot add the Authorization header to a cross-site request on their own
+        // and the auth middleware has already rejected the request if its API token is invalid
         if req.path().starts_with("/api/")
+            && get_bearer_token(req.request()).is_none()
             && is_protected(&self.inner.whitelist, &self.inner.whitelist_regex, &req)
         {
             if req_csrf_csrf_token.is_empty() {
@@ -1,4 +1,5 @@
 pub(crate) mod auth;
+pub(crate) mod authz;
 pub(crate) mod cache_control;
 pub(crate) mod configure;
 pub(crate) mod content_type;
@@ -7,3 +8,4 @@ pub(crate) mod csrf;
 pub(crate) mod errors;
 pub(crate) mod host;
 pub(crate) mod hsts;
+pub(crate) mod request_id;
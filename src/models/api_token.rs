@@ -0,0 +1,117 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+pub const USER_ID_KEY: &str = "user_id";
+pub const TOKEN_HASH_KEY: &str = "token_hash";
+pub const EXPIRY_KEY: &str = "expiry";
+pub const LAST_USED_KEY: &str = "last_used";
+pub const LAST_USED_IP_KEY: &str = "last_used_ip";
+pub const WINDOW_START_KEY: &str = "window_start";
+pub const WINDOW_REQUESTS_KEY: &str = "window_requests";
+
+/// What a personal access token is allowed to do on top of the role of its user.
+#[derive(Serialize, Deserialize, Display, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TokenScope {
+    #[serde(rename = "posts:write")]
+    #[display("posts:write")]
+    PostsWrite,
+    #[serde(rename = "media:write")]
+    #[display("media:write")]
+    MediaWrite,
+}
+
+impl TokenScope {
+    #[inline]
+    pub fn get_description(&self) -> &'static str {
+        match self {
+            TokenScope::PostsWrite => "Create, update, publish and delete blog posts",
+            TokenScope::MediaWrite => "Upload and edit media",
+        }
+    }
+}
+
+pub const TOKEN_SCOPES: [TokenScope; 2] = [TokenScope::PostsWrite, TokenScope::MediaWrite];
+
+/// A personal access token for scripting the admin API.
+///
+/// Only the SHA-256 hash of the token is stored since the token is random enough
+/// to not need a slow password hash, which would be too slow for every request.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiToken {
+    pub _id: ObjectId,
+    pub user_id: ObjectId,
+    pub name: String,
+    pub token_hash: String,
+    // the start of the token so that the user can tell the tokens apart
+    pub prefix: String,
+    pub scopes: Vec<TokenScope>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expiry: DateTime<Utc>,
+    #[serde(default)]
+    #[serde(with = "crate::utils::datetime::opt_chrono_datetime_as_bson_datetime")]
+    pub last_used: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_used_ip: Option<String>,
+    // the fixed window of the per-token rate limit
+    #[serde(default)]
+    #[serde(with = "crate::utils::datetime::opt_chrono_datetime_as_bson_datetime")]
+    pub window_start: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub window_requests: i64,
+}
+
+impl ApiToken {
+    #[inline]
+    pub fn get_created_date_string(&self) -> String {
+        self.created.format("%Y-%m-%d %H:%M").to_string()
+    }
+
+    #[inline]
+    pub fn get_expiry_date_string(&self) -> String {
+        self.expiry.format("%Y-%m-%d %H:%M").to_string()
+    }
+
+    #[inline]
+    pub fn get_last_used_string(&self) -> String {
+        match (&self.last_used, &self.last_used_ip) {
+            (Some(last_used), Some(ip)) if !ip.is_empty() => {
+                format!("{} UTC from {}", last_used.format("%Y-%m-%d %H:%M"), ip)
+            }
+            (Some(last_used), _) => format!("{} UTC", last_used.format("%Y-%m-%d %H:%M")),
+            (None, _) => "Never".to_string(),
+        }
+    }
+}
+
+/// The scopes of the token that authenticated the request, which is only
+/// added to the request extensions for the requests signed in with a token.
+#[derive(Clone, Debug)]
+pub struct TokenScopes(pub Vec<TokenScope>);
+
+#[derive(Deserialize)]
+pub struct NewApiToken {
+    pub name: String,
+    pub days: Option<i64>,
+    // checkboxes are only submitted when they are checked
+    #[serde(rename = "posts:write")]
+    pub posts_write: Option<String>,
+    #[serde(rename = "media:write")]
+    pub media_write: Option<String>,
+}
+
+impl NewApiToken {
+    pub fn get_scopes(&self) -> Vec<TokenScope> {
+        let mut scopes = vec![];
+        if self.posts_write.is_some() {
+            scopes.push(TokenScope::PostsWrite);
+        }
+        if self.media_write.is_some() {
+            scopes.push(TokenScope::MediaWrite);
+        }
+        scopes
+    }
+}
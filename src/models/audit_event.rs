@@ -0,0 +1,226 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+pub const ACTION_KEY: &str = "action";
+pub const ACTOR_ID_KEY: &str = "actor_id";
+pub const ACTOR_NAME_KEY: &str = "actor_name";
+pub const IP_ADDR_KEY: &str = "ip_addr";
+pub const TIMESTAMP_KEY: &str = "timestamp";
+
+#[derive(Serialize, Deserialize, Display, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    #[display("login_success")]
+    LoginSuccess,
+    #[display("login_failure")]
+    LoginFailure,
+    #[display("logout")]
+    Logout,
+    #[display("honeypot_hit")]
+    HoneypotHit,
+    #[display("2fa_enabled")]
+    #[serde(rename = "2fa_enabled")]
+    TwoFactorEnabled,
+    #[display("2fa_disabled")]
+    #[serde(rename = "2fa_disabled")]
+    TwoFactorDisabled,
+    #[display("recovery_codes_regenerated")]
+    RecoveryCodesRegenerated,
+    #[display("passkey_added")]
+    PasskeyAdded,
+    #[display("passkey_removed")]
+    PasskeyRemoved,
+    #[display("password_changed")]
+    PasswordChanged,
+    #[display("session_revoked")]
+    SessionRevoked,
+    #[display("other_sessions_revoked")]
+    OtherSessionsRevoked,
+    #[display("login_unlocked")]
+    LoginUnlocked,
+    #[display("api_token_created")]
+    ApiTokenCreated,
+    #[display("api_token_revoked")]
+    ApiTokenRevoked,
+    #[display("user_created")]
+    UserCreated,
+    #[display("user_role_changed")]
+    UserRoleChanged,
+    #[display("user_disabled")]
+    UserDisabled,
+    #[display("user_enabled")]
+    UserEnabled,
+    #[display("invite_created")]
+    InviteCreated,
+    #[display("invite_revoked")]
+    InviteRevoked,
+    #[display("invite_accepted")]
+    InviteAccepted,
+    #[display("post_created")]
+    PostCreated,
+    #[display("post_updated")]
+    PostUpdated,
+    #[display("post_deleted")]
+    PostDeleted,
+    #[display("post_published")]
+    PostPublished,
+    #[display("post_unpublished")]
+    PostUnpublished,
+    #[display("files_uploaded")]
+    FilesUploaded,
+}
+
+pub const AUDIT_ACTIONS: [AuditAction; 28] = [
+    AuditAction::LoginSuccess,
+    AuditAction::LoginFailure,
+    AuditAction::Logout,
+    AuditAction::HoneypotHit,
+    AuditAction::TwoFactorEnabled,
+    AuditAction::TwoFactorDisabled,
+    AuditAction::RecoveryCodesRegenerated,
+    AuditAction::PasskeyAdded,
+    AuditAction::PasskeyRemoved,
+    AuditAction::PasswordChanged,
+    AuditAction::SessionRevoked,
+    AuditAction::OtherSessionsRevoked,
+    AuditAction::LoginUnlocked,
+    AuditAction::ApiTokenCreated,
+    AuditAction::ApiTokenRevoked,
+    AuditAction::UserCreated,
+    AuditAction::UserRoleChanged,
+    AuditAction::UserDisabled,
+    AuditAction::UserEnabled,
+    AuditAction::InviteCreated,
+    AuditAction::InviteRevoked,
+    AuditAction::InviteAccepted,
+    AuditAction::PostCreated,
+    AuditAction::PostUpdated,
+    AuditAction::PostDeleted,
+    AuditAction::PostPublished,
+    AuditAction::PostUnpublished,
+    AuditAction::FilesUploaded,
+];
+
+impl AuditAction {
+    /// Returns true for the events that may be an attack, which are highlighted in the admin view.
+    #[inline]
+    pub fn is_warning(&self) -> bool {
+        matches!(self, AuditAction::LoginFailure | AuditAction::HoneypotHit)
+    }
+}
+
+/// A security relevant event, which is only ever inserted and never updated or deleted by the app.
+///
+/// The database deletes the events after [`crate::constants::AUDIT_LOG_RETENTION`].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditEvent {
+    pub _id: ObjectId,
+    pub action: AuditAction,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub timestamp: DateTime<Utc>,
+    // the signed in user or the user that signed in, which is unknown for failed logins
+    pub actor_id: Option<ObjectId>,
+    // the submitted username or email of the failed logins
+    pub actor_name: Option<String>,
+    pub ip_addr: String,
+    pub request_id: String,
+    // e.g. the id of the blog post or session that was changed
+    pub target: Option<String>,
+    pub details: Option<String>,
+}
+
+impl AuditEvent {
+    #[inline]
+    pub fn with_actor(mut self, actor_id: ObjectId) -> Self {
+        self.actor_id = Some(actor_id);
+        self
+    }
+
+    /// Sets the submitted username or email, which is truncated as it can be anything.
+    #[inline]
+    pub fn with_actor_name(mut self, actor_name: &str) -> Self {
+        self.actor_name = Some(
+            actor_name
+                .trim()
+                .chars()
+                .take(crate::constants::EMAIL_MAX_LENGTH)
+                .collect(),
+        );
+        self
+    }
+
+    #[inline]
+    pub fn with_target(mut self, target: impl ToString) -> Self {
+        self.target = Some(target.to_string());
+        self
+    }
+
+    #[inline]
+    pub fn with_details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+}
+
+/// An audit event in the exported JSON lines with plain strings instead of BSON types.
+#[derive(Serialize)]
+pub struct ExportedAuditEvent {
+    pub id: String,
+    pub action: AuditAction,
+    pub timestamp: String,
+    pub actor_id: Option<String>,
+    pub actor_name: Option<String>,
+    pub ip_addr: String,
+    pub request_id: String,
+    pub target: Option<String>,
+    pub details: Option<String>,
+}
+
+impl From<AuditEvent> for ExportedAuditEvent {
+    fn from(event: AuditEvent) -> Self {
+        ExportedAuditEvent {
+            id: event._id.to_hex(),
+            action: event.action,
+            timestamp: event.timestamp.to_rfc3339(),
+            actor_id: event.actor_id.map(|id| id.to_hex()),
+            actor_name: event.actor_name,
+            ip_addr: event.ip_addr,
+            request_id: event.request_id,
+            target: event.target,
+            details: event.details,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AuditLogQuery {
+    pub action: Option<String>,
+    pub actor: Option<String>,
+    pub ip: Option<String>,
+    pub page: Option<u64>,
+}
+
+impl AuditLogQuery {
+    #[inline]
+    pub fn get_page(&self) -> u64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    /// Returns the filters without the page as a query string for the pagination and export links.
+    pub fn get_filter_query_string(&self) -> String {
+        let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+        let filters = [
+            ("action", &self.action),
+            ("actor", &self.actor),
+            ("ip", &self.ip),
+        ];
+        for (key, value) in filters {
+            if let Some(value) = value.as_deref().filter(|value| !value.is_empty()) {
+                serializer.append_pair(key, value);
+            }
+        }
+        serializer.finish()
+    }
+}
@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BackupVersion {
+    // the timestamp part of the object name, also used to select a version to restore
+    pub version: String,
+    #[serde(with = "crate::utils::datetime::rfc3339")]
+    pub created: DateTime<Utc>,
+    pub size: usize,
+    // digest of the unencrypted backup
+    pub sha256: String,
+    #[serde(default)]
+    pub blog_version: i64,
+}
+
+/// Lists the backups of a blog post so that they can be
+/// listed without listing the objects in the bucket.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct BackupManifest {
+    pub blog_id: String,
+    pub title: String,
+    // sorted from the newest to the oldest backup
+    pub versions: Vec<BackupVersion>,
+}
+
+#[derive(Deserialize)]
+pub struct RestoreOptions {
+    pub version: Option<String>,
+}
@@ -13,6 +13,8 @@ pub const CONTENT_KEY: &str = "content";
 pub const IS_PUBLIC_KEY: &str = "is_public";
 pub const VIEWS_KEY: &str = "views";
 pub const LAST_MODIFIED_KEY: &str = "last_modified";
+pub const VERSION_KEY: &str = "version";
+pub const AUTHOR_ID_KEY: &str = "author_id";
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Blog {
@@ -29,6 +31,12 @@ pub struct Blog {
     pub timestamp: chrono::DateTime<Utc>,
     #[serde(with = "crate::utils::datetime::opt_chrono_datetime_as_bson_datetime")]
     pub last_modified: Option<chrono::DateTime<Utc>>,
+    // incremented on every update to detect concurrent edits
+    #[serde(default)]
+    pub version: i64,
+    // none for the blog posts written before there were multiple users or that were imported
+    #[serde(default)]
+    pub author_id: Option<ObjectId>,
 }
 
 // api struct setter
@@ -52,6 +60,8 @@ impl Blog {
             views: 0,
             timestamp: Utc::now(),
             last_modified: None,
+            version: 0,
+            author_id: None,
         }
     }
 
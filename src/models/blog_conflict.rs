@@ -0,0 +1,32 @@
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+pub struct FieldConflict<T> {
+    // none if the version that was edited is no longer backed up
+    pub base: Option<T>,
+    pub current: T,
+    // none if the field was not changed in the editor
+    pub yours: Option<T>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DiffLine {
+    pub tag: &'static str,
+    pub text: String,
+}
+
+/// Sent with a 409 response when the blog post was updated after the editor loaded it.
+#[derive(Serialize, Debug)]
+pub struct BlogConflict {
+    pub base_version: i64,
+    pub current_version: i64,
+    pub title: FieldConflict<String>,
+    pub seo_desc: FieldConflict<String>,
+    pub tags: FieldConflict<Vec<String>>,
+    pub is_public: FieldConflict<bool>,
+    pub content: FieldConflict<String>,
+    // line diffs of the content against the base version,
+    // or against the current version if the base is not available.
+    pub current_changes: Vec<DiffLine>,
+    pub your_changes: Vec<DiffLine>,
+}
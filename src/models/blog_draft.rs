@@ -0,0 +1,39 @@
+use crate::models::file_info::FileInfo;
+
+use bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// Unpublished changes to a blog post that are autosaved by the editor.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BlogDraft {
+    // a blog post has at most one draft
+    #[serde(rename = "_id")]
+    pub blog_id: ObjectId,
+    pub title: String,
+    pub seo_desc: String,
+    pub tags: Vec<String>,
+    pub content: String,
+    pub is_public: bool,
+    // uploaded files that are not stored in the public bucket yet
+    pub new_files: Vec<FileInfo>,
+    // the version of the blog post that the draft was started from
+    pub base_version: i64,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub saved: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct SaveDraft {
+    pub title: String,
+    pub seo_desc: String,
+    pub tags: Vec<String>,
+    pub content: String,
+    pub is_public: bool,
+    pub new_files: Vec<FileInfo>,
+    pub base_version: i64,
+}
+
+#[derive(Serialize)]
+pub struct SavedDraft {
+    pub saved: String,
+}
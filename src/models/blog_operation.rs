@@ -0,0 +1,48 @@
+use bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+pub const STATUS_KEY: &str = "status";
+pub const STORED_FILES_KEY: &str = "stored_files";
+pub const RELEASED_FILES_KEY: &str = "released_files";
+pub const DELETING_FILES_KEY: &str = "deleting_files";
+pub const STARTED_KEY: &str = "started";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlogOperationKind {
+    Create,
+    Update,
+    Delete,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlogOperationStatus {
+    Pending,
+    // the blog post was written to the database
+    Committed,
+}
+
+/// Journal entry of a blog post write that also changes files in the bucket.
+///
+/// Files are recorded before they are stored or released so that an interrupted
+/// operation can be rolled back or resumed by checking which files are still referenced.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BlogOperation {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub blog_id: ObjectId,
+    pub kind: BlogOperationKind,
+    pub status: BlogOperationStatus,
+    // urls of media copied into the public bucket or reused by this operation
+    pub stored_files: Vec<String>,
+    // urls of files that are no longer referenced by the blog post,
+    // these are only deleted after the operation is committed.
+    pub released_files: Vec<String>,
+    // urls of files that this operation is about to delete, which other
+    // operations must not reference until the deletion has finished.
+    #[serde(default)]
+    pub deleting_files: Vec<String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub started: chrono::DateTime<chrono::Utc>,
+}
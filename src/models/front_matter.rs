@@ -0,0 +1,44 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The YAML front matter at the top of an exported markdown file.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FrontMatter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub title: String,
+    #[serde(default)]
+    pub slug: String,
+    #[serde(default)]
+    pub seo_desc: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub date: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub public: bool,
+}
+
+#[derive(Deserialize)]
+pub struct ImportOptions {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize, Default)]
+pub struct MarkdownImportReport {
+    // nothing was written if this is a dry run
+    pub dry_run: bool,
+    // the source file followed by the ID of the blog post
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub unchanged: Vec<String>,
+    // the items of the source blog that are not blog posts, like trashed posts
+    pub skipped: Vec<String>,
+    // the file name followed by the reason it was not imported
+    pub failed: Vec<String>,
+    pub media: usize,
+    pub missing_media: Vec<String>,
+}
@@ -0,0 +1,67 @@
+use crate::models::role::Role;
+
+use bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+pub const EXPIRY_KEY: &str = "expiry";
+pub const USER_ID_KEY: &str = "user_id";
+
+/// A single use link that lets someone create an account with the given role.
+/// Revoking or accepting the invite deletes the document so that its token no longer verifies.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Invite {
+    pub _id: ObjectId,
+    pub role: Role,
+    // set for the accounts created by a site import, which are
+    // activated by choosing a password instead of creating a new account
+    #[serde(default)]
+    pub user_id: Option<ObjectId>,
+    pub created_by: ObjectId,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created: chrono::DateTime<chrono::Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expiry: chrono::DateTime<chrono::Utc>,
+}
+
+/// The signed payload of an invite token.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InviteClaim {
+    #[serde(rename = "_id")]
+    #[serde(serialize_with = "bson::serde_helpers::serialize_object_id_as_hex_string")]
+    pub invite_id: ObjectId,
+    // in seconds so that the same token is signed again from the stored invite when listing the invites
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub expiry: chrono::DateTime<chrono::Utc>,
+}
+
+impl hmac_serialiser::Payload for InviteClaim {
+    fn get_exp(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        Some(self.expiry)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewInvite {
+    pub role: Role,
+    pub days: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct InviteQuery {
+    pub token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AcceptInvite {
+    #[serde(rename = "cf-turnstile-response")]
+    pub cf_turnstile_res: String,
+    pub token: String,
+    // not sent when activating an imported account
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub email: String,
+    pub password: String,
+    #[serde(rename = "confirm-password")]
+    pub confirm_password: String,
+}
@@ -0,0 +1,12 @@
+use serde::Serialize;
+
+#[derive(Serialize, Default)]
+pub struct KeyRotationReport {
+    // the id of the newest DB key, empty if it is the legacy key
+    pub key_id: String,
+    pub scanned: usize,
+    pub reencrypted: usize,
+    pub up_to_date: usize,
+    // the ids of the users whose TOTP secret could not be re-encrypted
+    pub failed: Vec<String>,
+}
@@ -0,0 +1,51 @@
+use bson::oid::ObjectId;
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+pub const LAST_STATUS_KEY: &str = "last_status";
+pub const HISTORY_KEY: &str = "history";
+pub const BLOG_IDS_KEY: &str = "blog_ids";
+pub const HOST_KEY: &str = "host";
+
+#[derive(Serialize, Deserialize, Display, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkOutcome {
+    #[display("ok")]
+    Ok,
+    #[display("broken")]
+    Broken,
+    // not checked because of the site's robots.txt, because it could not be fetched
+    // or because the link points to a non-public address
+    #[display("skipped")]
+    Skipped,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LinkStatus {
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub checked: chrono::DateTime<chrono::Utc>,
+    pub outcome: LinkOutcome,
+    // none if the request failed before getting a response
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+/// An outbound link found in the blog posts with the results of its latest checks.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LinkCheck {
+    #[serde(rename = "_id")]
+    pub url: String,
+    pub host: String,
+    pub blog_ids: Vec<ObjectId>,
+    pub last_status: LinkStatus,
+    // oldest first
+    pub history: Vec<LinkStatus>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct LinkCheckSummary {
+    pub checked: usize,
+    pub ok: usize,
+    pub broken: usize,
+    pub skipped: usize,
+}
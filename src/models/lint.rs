@@ -0,0 +1,80 @@
+use derive_more::Display;
+use serde::Serialize;
+
+#[derive(Serialize, Display, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum LintRule {
+    #[display("alt_text")]
+    AltText,
+    #[display("heading_order")]
+    HeadingOrder,
+    #[display("duplicate_heading")]
+    DuplicateHeading,
+    #[display("long_paragraph")]
+    LongParagraph,
+    #[display("bare_url")]
+    BareUrl,
+    #[display("broken_link")]
+    BrokenLink,
+    #[display("dead_internal_link")]
+    DeadInternalLink,
+    #[display("seo_desc")]
+    SeoDesc,
+    #[display("seo_desc_length")]
+    SeoDescLength,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 9] = [
+        LintRule::AltText,
+        LintRule::HeadingOrder,
+        LintRule::DuplicateHeading,
+        LintRule::LongParagraph,
+        LintRule::BareUrl,
+        LintRule::BrokenLink,
+        LintRule::DeadInternalLink,
+        LintRule::SeoDesc,
+        LintRule::SeoDescLength,
+    ];
+
+    /// The level of the rule when it is not configured.
+    pub fn get_default_level(&self) -> LintLevel {
+        match self {
+            LintRule::AltText
+            | LintRule::BrokenLink
+            | LintRule::DeadInternalLink
+            | LintRule::SeoDesc => LintLevel::Error,
+            LintRule::HeadingOrder
+            | LintRule::DuplicateHeading
+            | LintRule::LongParagraph
+            | LintRule::BareUrl
+            | LintRule::SeoDescLength => LintLevel::Warning,
+        }
+    }
+}
+
+#[derive(Serialize, Display, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum LintLevel {
+    #[display("off")]
+    Off,
+    #[display("warning")]
+    Warning,
+    // blocks publishing unless the author overrides it
+    #[display("error")]
+    Error,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LintIssue {
+    pub rule: LintRule,
+    pub level: LintLevel,
+    // none for the issues that are not in the content like the SEO description
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LintReport {
+    pub issues: Vec<LintIssue>,
+}
@@ -0,0 +1,43 @@
+use crate::models::lint::LintIssue;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A message sent by the editor over the live preview WebSocket.
+///
+/// Offsets are in UTF-16 code units since they are computed from JavaScript strings.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PreviewEdit {
+    // sent when connecting and whenever the server asks for a resync
+    Full {
+        rev: u64,
+        content: String,
+    },
+    Edit {
+        rev: u64,
+        start: usize,
+        delete: usize,
+        text: String,
+    },
+}
+
+/// A message sent to the editor over the live preview WebSocket.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PreviewUpdate {
+    Render {
+        rev: u64,
+        // the keys of the rendered blocks in document order
+        blocks: Vec<String>,
+        // only the blocks that the editor has not received before
+        fragments: HashMap<String, String>,
+        warnings: Vec<LintIssue>,
+    },
+    Resync {
+        rev: u64,
+    },
+    Error {
+        message: String,
+    },
+}
@@ -0,0 +1,64 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const FAILURES_KEY: &str = "failures";
+pub const LAST_FAILURE_KEY: &str = "last_failure";
+pub const BLOCKED_UNTIL_KEY: &str = "blocked_until";
+pub const LOCKED_UNTIL_KEY: &str = "locked_until";
+pub const EXPIRY_KEY: &str = "expiry";
+
+/// The failed login attempts of an IP address or a user.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LoginAttempt {
+    // e.g. "ip:203.0.113.1" or "user:<user id>"
+    #[serde(rename = "_id")]
+    pub key: String,
+    pub failures: i64,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub last_failure: DateTime<Utc>,
+    // the exponential backoff before the next attempt is allowed
+    #[serde(default)]
+    #[serde(with = "crate::utils::datetime::opt_chrono_datetime_as_bson_datetime")]
+    pub blocked_until: Option<DateTime<Utc>>,
+    // the lockout after too many failed attempts, which can be lifted by an admin
+    #[serde(default)]
+    #[serde(with = "crate::utils::datetime::opt_chrono_datetime_as_bson_datetime")]
+    pub locked_until: Option<DateTime<Utc>>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expiry: DateTime<Utc>,
+}
+
+impl LoginAttempt {
+    #[inline]
+    pub fn get_blocked_until(&self) -> Option<DateTime<Utc>> {
+        match (self.blocked_until, self.locked_until) {
+            (Some(blocked_until), Some(locked_until)) => Some(blocked_until.max(locked_until)),
+            (blocked_until, locked_until) => blocked_until.or(locked_until),
+        }
+    }
+
+    #[inline]
+    pub fn get_blocked_until_date_string(&self) -> String {
+        match self.get_blocked_until() {
+            Some(blocked_until) => blocked_until.format("%Y-%m-%d %H:%M:%S").to_string(),
+            None => "-".to_string(),
+        }
+    }
+
+    #[inline]
+    pub fn is_blocked(&self, now: DateTime<Utc>) -> bool {
+        self.get_blocked_until()
+            .is_some_and(|blocked_until| blocked_until > now)
+    }
+
+    #[inline]
+    pub fn is_locked(&self, now: DateTime<Utc>) -> bool {
+        self.locked_until
+            .is_some_and(|locked_until| locked_until > now)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UnlockLogin {
+    pub key: String,
+}
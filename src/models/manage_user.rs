@@ -0,0 +1,16 @@
+use crate::models::role::Role;
+
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct NewUser {
+    pub username: String,
+    pub email: String,
+    pub password: String,
+    pub role: Role,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateUserRole {
+    pub role: Role,
+}
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+pub const ALT_TEXT_KEY: &str = "alt_text";
+pub const LAST_MODIFIED_KEY: &str = "last_modified";
+
+/// Metadata of a stored media file that cannot be
+/// derived from the object in the bucket itself.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Media {
+    // name of the object in the public bucket
+    #[serde(rename = "_id")]
+    pub name: String,
+    pub alt_text: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub last_modified: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct MediaReference {
+    pub blog_id: String,
+    pub title: String,
+}
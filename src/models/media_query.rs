@@ -0,0 +1,8 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct MediaQuery {
+    pub q: Option<String>,
+    // whether the media is being picked for the blog editor
+    pub picker: Option<bool>,
+}
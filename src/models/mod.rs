@@ -1,19 +1,45 @@
+pub(crate) mod api_token;
+pub(crate) mod audit_event;
+pub(crate) mod backup_manifest;
 pub(crate) mod blog;
+pub(crate) mod blog_conflict;
+pub(crate) mod blog_draft;
 pub(crate) mod blog_identifier;
+pub(crate) mod blog_operation;
 pub(crate) mod blog_preview;
 pub(crate) mod blog_response;
 pub(crate) mod change_password;
 pub(crate) mod checkbox;
 pub(crate) mod file_info;
+pub(crate) mod front_matter;
 pub(crate) mod generated_totp;
 pub(crate) mod index;
+pub(crate) mod invite;
+pub(crate) mod key_rotation_report;
+pub(crate) mod link_check;
+pub(crate) mod lint;
+pub(crate) mod live_preview;
+pub(crate) mod login_attempt;
 pub(crate) mod login_data;
+pub(crate) mod manage_user;
+pub(crate) mod media;
+pub(crate) mod media_query;
 pub(crate) mod new_blog;
+pub(crate) mod passkey;
+pub(crate) mod preview_link;
 pub(crate) mod projected_blog;
 pub(crate) mod projected_user;
+pub(crate) mod reconcile_report;
+pub(crate) mod recovery_codes;
 pub(crate) mod remove_2fa;
+pub(crate) mod restore_report;
+pub(crate) mod role;
 pub(crate) mod session;
 pub(crate) mod setup_2fa;
+pub(crate) mod site_export;
+pub(crate) mod static_export;
+pub(crate) mod totp_config;
 pub(crate) mod update_blog;
+pub(crate) mod update_media;
 pub(crate) mod uploaded_files;
 pub(crate) mod user;
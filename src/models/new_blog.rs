@@ -10,4 +10,7 @@ pub struct NewBlog {
     pub files: Vec<FileInfo>,
     pub content: String,
     pub is_public: bool,
+    // publish even if the lint pass reports errors
+    #[serde(default)]
+    pub ignore_lint: bool,
 }
@@ -0,0 +1,162 @@
+use crate::models::checkbox;
+
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const ID_KEY: &str = "id";
+pub const SIGN_COUNT_KEY: &str = "sign_count";
+pub const LAST_USED_KEY: &str = "last_used";
+pub const EXPIRY_KEY: &str = "expiry";
+
+/// A WebAuthn credential registered by a user.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Passkey {
+    // the base64url encoded credential id
+    pub id: String,
+    pub name: String,
+    // the uncompressed SEC1 encoded P-256 public key
+    #[serde(with = "serde_bytes")]
+    pub public_key: Vec<u8>,
+    pub sign_count: i64,
+    #[serde(default)]
+    pub transports: Vec<String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created: DateTime<Utc>,
+    #[serde(default)]
+    #[serde(with = "crate::utils::datetime::opt_chrono_datetime_as_bson_datetime")]
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+impl Passkey {
+    #[inline]
+    pub fn get_created_date_string(&self) -> String {
+        self.created.format("%Y-%m-%d %H:%M").to_string()
+    }
+
+    #[inline]
+    pub fn get_last_used_date_string(&self) -> String {
+        match self.last_used {
+            Some(last_used) => last_used.format("%Y-%m-%d %H:%M").to_string(),
+            None => "Never".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PasskeyCeremony {
+    Registration,
+    Authentication,
+}
+
+/// A single use challenge for a WebAuthn ceremony.
+///
+/// For authentication, the user id is only set when the passkey is used as
+/// a second factor after the password has been verified, otherwise any
+/// discoverable credential can be used to sign in without a password.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PasskeyChallenge {
+    pub _id: ObjectId,
+    pub ceremony: PasskeyCeremony,
+    #[serde(with = "serde_bytes")]
+    pub challenge: Vec<u8>,
+    pub user_id: Option<ObjectId>,
+    #[serde(default)]
+    pub remember: bool,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expiry: DateTime<Utc>,
+}
+
+impl PasskeyChallenge {
+    pub fn new(
+        ceremony: PasskeyCeremony,
+        challenge: Vec<u8>,
+        user_id: Option<ObjectId>,
+        remember: bool,
+        timeout_ms: i64,
+    ) -> PasskeyChallenge {
+        PasskeyChallenge {
+            _id: ObjectId::new(),
+            ceremony,
+            challenge,
+            user_id,
+            remember,
+            expiry: Utc::now() + chrono::Duration::milliseconds(timeout_ms),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct PasskeyCredentialDescriptor {
+    pub id: String,
+    pub transports: Vec<String>,
+}
+
+/// The values needed by the browser to call `navigator.credentials.create()`.
+#[derive(Serialize)]
+pub struct PasskeyCreationOptions {
+    pub challenge_id: String,
+    pub challenge: String,
+    pub rp_id: String,
+    pub rp_name: String,
+    pub user_id: String,
+    pub user_name: String,
+    pub alg: i64,
+    pub timeout: i64,
+    pub exclude_credentials: Vec<PasskeyCredentialDescriptor>,
+}
+
+/// The values needed by the browser to call `navigator.credentials.get()`.
+#[derive(Serialize)]
+pub struct PasskeyRequestOptions {
+    pub challenge_id: String,
+    pub challenge: String,
+    pub rp_id: String,
+    pub timeout: i64,
+    pub user_verification: &'static str,
+    pub allow_credentials: Vec<PasskeyCredentialDescriptor>,
+}
+
+/// The response of `navigator.credentials.create()` with the binary values base64url encoded.
+#[derive(Deserialize)]
+pub struct NewPasskey {
+    #[serde(rename = "cf-turnstile-response")]
+    pub cf_turnstile_res: String,
+    #[serde(rename = "current-password")]
+    pub current_password: String,
+    pub challenge_id: String,
+    pub name: String,
+    pub id: String,
+    pub client_data_json: String,
+    pub attestation_object: String,
+    #[serde(default)]
+    pub transports: Vec<String>,
+}
+
+/// The response of `navigator.credentials.get()` with the binary values base64url encoded.
+#[derive(Deserialize)]
+pub struct PasskeyAssertion {
+    pub challenge_id: String,
+    pub id: String,
+    pub client_data_json: String,
+    pub authenticator_data: String,
+    pub signature: String,
+    pub user_handle: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PasskeyLoginData {
+    #[serde(rename = "cf-turnstile-response")]
+    pub cf_turnstile_res: String,
+    pub remember: Option<checkbox::State>,
+}
+
+impl PasskeyLoginData {
+    pub fn remember_session(&self) -> bool {
+        match &self.remember {
+            Some(remember) => remember.get_state(),
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+pub const BLOG_ID_KEY: &str = "blog_id";
+pub const EXPIRY_KEY: &str = "expiry";
+
+/// A shareable link that lets anyone with the token view a private or draft blog post.
+/// Revoking the link deletes the document so that its token no longer verifies.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PreviewLink {
+    pub _id: ObjectId,
+    pub blog_id: ObjectId,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created: chrono::DateTime<chrono::Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expiry: chrono::DateTime<chrono::Utc>,
+}
+
+/// The signed payload of a preview token.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PreviewClaim {
+    #[serde(rename = "_id")]
+    #[serde(serialize_with = "bson::serde_helpers::serialize_object_id_as_hex_string")]
+    pub link_id: ObjectId,
+    #[serde(serialize_with = "bson::serde_helpers::serialize_object_id_as_hex_string")]
+    pub blog_id: ObjectId,
+    // in seconds so that the same token is signed again from the stored link when listing the links
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub expiry: chrono::DateTime<chrono::Utc>,
+}
+
+impl hmac_serialiser::Payload for PreviewClaim {
+    fn get_exp(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        Some(self.expiry)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewPreviewLink {
+    pub days: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct PreviewLinkIdentifier {
+    pub id: String,
+    pub link_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct PreviewQuery {
+    pub preview: Option<String>,
+}
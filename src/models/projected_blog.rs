@@ -14,4 +14,6 @@ pub struct ProjectedBlog {
     pub content: Option<String>,
     pub is_public: Option<bool>,
     pub views: Option<i64>,
+    #[serde(default)]
+    pub author_id: Option<ObjectId>,
 }
@@ -1,3 +1,5 @@
+use crate::models::passkey::Passkey;
+
 use bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 
@@ -13,4 +15,8 @@ pub struct ProjectedUser {
     // to avoid deserialisation errors
     #[serde(with = "serde_bytes")]
     pub totp_secret: Option<Vec<u8>>,
+    #[serde(default)]
+    pub passkeys: Option<Vec<Passkey>>,
+    #[serde(default)]
+    pub recovery_codes: Option<Vec<String>>,
 }
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct ReconcileOptions {
+    #[serde(default = "default_dry_run")]
+    pub dry_run: bool,
+}
+
+#[inline]
+fn default_dry_run() -> bool {
+    // never delete anything unless explicitly asked to
+    true
+}
+
+#[derive(Serialize)]
+pub struct MissingFile {
+    pub blog_id: String,
+    pub url: String,
+}
+
+#[derive(Serialize, Default)]
+pub struct ReconcileReport {
+    pub dry_run: bool,
+    pub scanned: usize,
+    pub referenced: usize,
+    pub orphaned: Vec<String>,
+    pub orphaned_bytes: i64,
+    // unreferenced objects that may still be in use by a blog post that is being written
+    pub skipped: Vec<String>,
+    pub missing: Vec<MissingFile>,
+    pub deleted: Vec<String>,
+    pub failed: Vec<String>,
+}
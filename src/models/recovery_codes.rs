@@ -0,0 +1,9 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct RegenerateRecoveryCodes {
+    #[serde(rename = "cf-turnstile-response")]
+    pub cf_turnstile_res: String,
+    #[serde(rename = "current-password")]
+    pub current_password: String,
+}
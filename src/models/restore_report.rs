@@ -0,0 +1,21 @@
+use serde::Serialize;
+
+#[derive(Serialize, Default)]
+pub struct RestoreReport {
+    pub restored: Vec<String>,
+    pub failed: Vec<String>,
+    // the files of the restored blog posts that were deleted after the backup
+    pub missing_files: Vec<String>,
+}
+
+#[derive(Serialize, Default)]
+pub struct ImportReport {
+    pub blogs: usize,
+    pub users_created: usize,
+    pub users_updated: usize,
+    pub skipped_users: Vec<String>,
+    // the new users who have to activate their account from an invite on the users page
+    pub pending_invites: Vec<String>,
+    pub media: usize,
+    pub missing_media: Vec<String>,
+}
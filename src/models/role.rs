@@ -0,0 +1,70 @@
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+/// The role of a user, each role can do everything the roles below it can.
+///
+/// - Owner: manages the users and the site's backups, storage and keys
+/// - Editor: edits every blog post and manages the media and links
+/// - Author: writes and publishes their own blog posts
+/// - Viewer: only views the admin pages and manages their own account
+#[derive(Serialize, Deserialize, Display, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    #[display("owner")]
+    Owner,
+    #[display("editor")]
+    Editor,
+    #[display("author")]
+    Author,
+    #[display("viewer")]
+    Viewer,
+}
+
+pub const ROLES: [Role; 4] = [Role::Owner, Role::Editor, Role::Author, Role::Viewer];
+
+impl Role {
+    #[inline]
+    fn get_level(&self) -> u8 {
+        match self {
+            Role::Owner => 3,
+            Role::Editor => 2,
+            Role::Author => 1,
+            Role::Viewer => 0,
+        }
+    }
+
+    /// Returns true if the role can do everything the given role can.
+    #[inline]
+    pub fn has_at_least(&self, role: Role) -> bool {
+        self.get_level() >= role.get_level()
+    }
+
+    /// Returns true if the role can edit the blog posts of other users.
+    #[inline]
+    pub fn can_edit_any_blog(&self) -> bool {
+        self.has_at_least(Role::Editor)
+    }
+
+    #[inline]
+    pub fn get_label(&self) -> &'static str {
+        match self {
+            Role::Owner => "Owner",
+            Role::Editor => "Editor",
+            Role::Author => "Author",
+            Role::Viewer => "Viewer",
+        }
+    }
+}
+
+/// The users and sessions created before roles were added belong to the only admin account.
+#[inline]
+pub fn default_role() -> Role {
+    Role::Owner
+}
+
+/// Exported users without a role are imported with the lowest role
+/// so that an old export cannot grant anyone more access.
+#[inline]
+pub fn default_imported_role() -> Role {
+    Role::Viewer
+}
@@ -1,7 +1,14 @@
+use crate::constants;
+use crate::models::role::{default_role, Role};
+
 use bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 
-pub const EXPIRY_KEY: &str = "EXPIRY";
+pub const EXPIRY_KEY: &str = "expiry";
+pub const USER_ID_KEY: &str = "user_id";
+pub const IP_ADDR_KEY: &str = "ip_addr";
+pub const LAST_SEEN_KEY: &str = "last_seen";
+pub const ROLE_KEY: &str = "role";
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Session {
@@ -9,17 +16,57 @@ pub struct Session {
     pub user_id: ObjectId,
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub created: chrono::DateTime<chrono::Utc>,
+    // the idle timeout which is renewed on activity
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub expiry: chrono::DateTime<chrono::Utc>,
+    // the session cannot be renewed past this, sessions created
+    // before it was recorded cannot be renewed past their original expiry
+    #[serde(default)]
+    #[serde(with = "crate::utils::datetime::opt_chrono_datetime_as_bson_datetime")]
+    pub max_expiry: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    pub remember: bool,
+    // copied from the user so that the role does not have to be fetched on every request
+    #[serde(default = "default_role")]
+    pub role: Role,
+    // the device that signed in, sessions created before these were recorded have them empty
+    #[serde(default)]
+    pub user_agent: String,
+    #[serde(default)]
+    pub ip_addr: String,
+    #[serde(default)]
+    #[serde(with = "crate::utils::datetime::opt_chrono_datetime_as_bson_datetime")]
+    pub last_seen: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 impl Session {
-    pub fn new(user_id: ObjectId, exp: i64) -> Session {
+    pub fn new(
+        user_id: ObjectId,
+        role: Role,
+        remember: bool,
+        user_agent: String,
+        ip_addr: String,
+    ) -> Session {
+        let now = chrono::Utc::now();
+        let (idle_timeout, max_lifetime) = if remember {
+            (
+                constants::SESSION_TIMEOUT_REMEMBER,
+                constants::SESSION_MAX_LIFETIME_REMEMBER,
+            )
+        } else {
+            (constants::SESSION_TIMEOUT, constants::SESSION_MAX_LIFETIME)
+        };
         Session {
             _id: ObjectId::new(),
             user_id,
-            created: chrono::Utc::now(),
-            expiry: chrono::Utc::now() + chrono::Duration::seconds(exp),
+            created: now,
+            expiry: now + chrono::Duration::seconds(idle_timeout),
+            max_expiry: Some(now + chrono::Duration::seconds(max_lifetime)),
+            remember,
+            role,
+            user_agent,
+            ip_addr,
+            last_seen: Some(now),
         }
     }
 
@@ -27,4 +74,58 @@ impl Session {
     pub fn is_expired(&self) -> bool {
         self.expiry < chrono::Utc::now()
     }
+
+    /// Returns the expiry after renewing the idle timeout from now,
+    /// which cannot be later than the maximum lifetime of the session.
+    pub fn get_renewed_expiry(&self) -> chrono::DateTime<chrono::Utc> {
+        let idle_timeout = if self.remember {
+            constants::SESSION_TIMEOUT_REMEMBER
+        } else {
+            constants::SESSION_TIMEOUT
+        };
+        let renewed_expiry = chrono::Utc::now() + chrono::Duration::seconds(idle_timeout);
+        renewed_expiry.min(self.max_expiry.unwrap_or(self.expiry))
+    }
+
+    #[inline]
+    pub fn get_last_seen(&self) -> chrono::DateTime<chrono::Utc> {
+        self.last_seen.unwrap_or(self.created)
+    }
+
+    /// Returns true if the last seen time is old enough to be updated.
+    ///
+    /// It is not updated on every request to avoid writing to the database each time.
+    #[inline]
+    pub fn should_update_last_seen(&self) -> bool {
+        let interval = chrono::Duration::from_std(constants::SESSION_LAST_SEEN_INTERVAL).unwrap();
+        self.get_last_seen() + interval < chrono::Utc::now()
+    }
+
+    #[inline]
+    pub fn get_created_date_string(&self) -> String {
+        self.created.format("%Y-%m-%d %H:%M").to_string()
+    }
+
+    #[inline]
+    pub fn get_last_seen_date_string(&self) -> String {
+        self.get_last_seen().format("%Y-%m-%d %H:%M").to_string()
+    }
+
+    #[inline]
+    pub fn get_user_agent(&self) -> &str {
+        if self.user_agent.is_empty() {
+            "Unknown device"
+        } else {
+            &self.user_agent
+        }
+    }
+
+    #[inline]
+    pub fn get_ip_addr(&self) -> &str {
+        if self.ip_addr.is_empty() {
+            "Unknown IP address"
+        } else {
+            &self.ip_addr
+        }
+    }
 }
@@ -1,3 +1,5 @@
+use crate::models::totp_config::{TotpAlgorithm, TotpConfig};
+
 use serde::Deserialize;
 
 #[derive(Deserialize)]
@@ -9,4 +11,16 @@ pub struct Setup2fa {
     pub totp_code: String,
     #[serde(rename = "current-password")]
     pub current_password: String,
+    pub algorithm: Option<TotpAlgorithm>,
+    pub digits: Option<u8>,
+}
+
+impl Setup2fa {
+    pub fn get_totp_config(&self) -> TotpConfig {
+        let default_config = TotpConfig::default();
+        TotpConfig {
+            algorithm: self.algorithm.unwrap_or(default_config.algorithm),
+            digits: self.digits.unwrap_or(default_config.digits),
+        }
+    }
 }
@@ -0,0 +1,46 @@
+use crate::models::role::{default_imported_role, Role};
+
+use bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+pub const BLOGS_FILE: &str = "blogs.json";
+pub const USERS_FILE: &str = "users.json";
+pub const MEDIA_FILE: &str = "media.json";
+
+#[derive(Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub sha256: String,
+    pub size: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SiteManifest {
+    pub version: u32,
+    pub created: String,
+    pub entries: Vec<ArchiveEntry>,
+}
+
+/// A user without any secrets like the password hash or the TOTP secret.
+#[derive(Serialize, Deserialize)]
+pub struct ExportedUser {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub username: String,
+    pub email: String,
+    #[serde(default = "default_imported_role")]
+    pub role: Role,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportedMedia {
+    pub name: String,
+    pub url: String,
+    pub size: i64,
+    pub last_modified: Option<String>,
+    pub alt_text: String,
+    pub blog_ids: Vec<String>,
+}
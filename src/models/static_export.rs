@@ -0,0 +1,9 @@
+use serde::Serialize;
+
+#[derive(Serialize, Default)]
+pub struct StaticExportReport {
+    pub pages: usize,
+    pub blogs: usize,
+    pub assets: usize,
+    pub output_dir: String,
+}
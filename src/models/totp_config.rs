@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+pub const ALLOWED_DIGITS: [u8; 2] = [6, 8];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TotpAlgorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+#[inline]
+fn default_digits() -> u8 {
+    ALLOWED_DIGITS[0]
+}
+
+/// The TOTP parameters of a user.
+///
+/// SHA1 with 6 digits is the default since
+/// some authenticator apps do not support anything else.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TotpConfig {
+    #[serde(default)]
+    pub algorithm: TotpAlgorithm,
+    #[serde(default = "default_digits")]
+    pub digits: u8,
+}
+
+impl Default for TotpConfig {
+    fn default() -> Self {
+        TotpConfig {
+            algorithm: TotpAlgorithm::default(),
+            digits: default_digits(),
+        }
+    }
+}
+
+impl TotpConfig {
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        ALLOWED_DIGITS.contains(&self.digits)
+    }
+}
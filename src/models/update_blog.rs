@@ -2,13 +2,18 @@ use crate::models::file_info::FileInfo;
 
 use serde::Deserialize;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct UpdateBlog {
     pub id: String,
+    // the version of the blog post that the changes were made to
+    pub version: i64,
     pub title: Option<String>,
     pub seo_desc: Option<String>,
     pub tags: Option<Vec<String>>,
     pub new_files: Option<Vec<FileInfo>>,
     pub content: Option<String>,
     pub is_public: Option<bool>,
+    // publish even if the lint pass reports errors
+    #[serde(default)]
+    pub ignore_lint: bool,
 }
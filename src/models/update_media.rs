@@ -0,0 +1,8 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct UpdateMedia {
+    pub name: String,
+    #[serde(rename = "alt-text")]
+    pub alt_text: String,
+}
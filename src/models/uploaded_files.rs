@@ -12,6 +12,11 @@ impl UploadedFiles {
         UploadedFiles { files }
     }
 
+    #[inline]
+    pub fn get_names(&self) -> Vec<&str> {
+        self.files.iter().map(|file| file.name.as_str()).collect()
+    }
+
     #[inline]
     pub fn append(&mut self, name: String, url: String, signed_url: String) {
         self.files.push(FileInfo {
@@ -1,3 +1,7 @@
+use crate::models::passkey::Passkey;
+use crate::models::role::{default_role, Role};
+use crate::models::totp_config::TotpConfig;
+
 use bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 
@@ -5,6 +9,12 @@ pub const USERNAME_KEY: &str = "username";
 pub const EMAIL_KEY: &str = "email";
 pub const PASSWORD_KEY: &str = "password";
 pub const TOTP_SECRET_KEY: &str = "totp_secret";
+pub const PASSKEYS_KEY: &str = "passkeys";
+pub const RECOVERY_CODES_KEY: &str = "recovery_codes";
+pub const TOTP_CONFIG_KEY: &str = "totp_config";
+pub const TOTP_LAST_STEP_KEY: &str = "totp_last_step";
+pub const ROLE_KEY: &str = "role";
+pub const DISABLED_KEY: &str = "disabled";
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct User {
@@ -14,6 +24,21 @@ pub struct User {
     password: String,
     #[serde(with = "serde_bytes")]
     totp_secret: Option<Vec<u8>>,
+    #[serde(default)]
+    totp_config: TotpConfig,
+    // the time step of the last accepted TOTP code to prevent it from being reused
+    #[serde(default)]
+    totp_last_step: Option<i64>,
+    #[serde(default)]
+    passkeys: Vec<Passkey>,
+    // the Argon2 hashes of the unused TOTP recovery codes
+    #[serde(default)]
+    recovery_codes: Vec<String>,
+    #[serde(default = "default_role")]
+    role: Role,
+    // disabled users cannot sign in but their blog posts are kept
+    #[serde(default)]
+    disabled: bool,
 }
 
 impl User {
@@ -22,6 +47,7 @@ impl User {
         email: String,
         password: String,
         totp_secret: Option<Vec<u8>>,
+        role: Role,
     ) -> User {
         User {
             _id: ObjectId::new(),
@@ -29,9 +55,35 @@ impl User {
             email,
             password,
             totp_secret,
+            totp_config: TotpConfig::default(),
+            totp_last_step: None,
+            passkeys: vec![],
+            recovery_codes: vec![],
+            role,
+            disabled: false,
         }
     }
 
+    #[inline]
+    pub fn get_username(&self) -> &str {
+        &self.username
+    }
+
+    #[inline]
+    pub fn get_email(&self) -> &str {
+        &self.email
+    }
+
+    #[inline]
+    pub fn get_role(&self) -> Role {
+        self.role
+    }
+
+    #[inline]
+    pub fn is_disabled(&self) -> bool {
+        self.disabled
+    }
+
     #[inline]
     pub fn get_password(&self) -> &str {
         &self.password
@@ -46,4 +98,29 @@ impl User {
     pub fn get_encrypted_totp_secret(&self) -> Option<&Vec<u8>> {
         self.totp_secret.as_ref()
     }
+
+    #[inline]
+    pub fn get_totp_config(&self) -> &TotpConfig {
+        &self.totp_config
+    }
+
+    #[inline]
+    pub fn get_totp_last_step(&self) -> Option<i64> {
+        self.totp_last_step
+    }
+
+    #[inline]
+    pub fn has_passkeys(&self) -> bool {
+        !self.passkeys.is_empty()
+    }
+
+    #[inline]
+    pub fn get_passkeys(&self) -> &[Passkey] {
+        &self.passkeys
+    }
+
+    #[inline]
+    pub fn get_recovery_codes(&self) -> &[String] {
+        &self.recovery_codes
+    }
 }
@@ -1,5 +1,6 @@
-use crate::constants::{get_db_encryption_key, get_db_encryption_key_aad};
+use crate::constants::get_db_encryption_key_aad;
 use crate::errors::crypto::CryptoError;
+use crate::security::keyring::{self, Key};
 
 use chacha20poly1305::aead::generic_array::GenericArray;
 use chacha20poly1305::{
@@ -9,6 +10,28 @@ use chacha20poly1305::{
 use once_cell::sync::Lazy;
 
 const XNONCE_LEN: usize = 24;
+const XCHACHA_KEY_LEN: usize = 32;
+
+// the ciphertexts encrypted by a key with an id are prefixed with
+// [KEY_ID_MARKER, key id length, key id...] while the legacy ones have no prefix
+const KEY_ID_MARKER: u8 = 0xFE;
+
+static DB_CIPHERS: Lazy<Vec<(Key, XChaCha20Poly1305)>> = Lazy::new(|| {
+    keyring::get_db_keyring()
+        .get_keys()
+        .iter()
+        .map(|key| {
+            if key.secret.len() != XCHACHA_KEY_LEN {
+                panic!(
+                    "DB encryption key {:?} must be {} bytes long",
+                    key.id, XCHACHA_KEY_LEN
+                );
+            }
+            let cipher = XChaCha20Poly1305::new(&GenericArray::clone_from_slice(&key.secret));
+            (key.clone(), cipher)
+        })
+        .collect()
+});
 
 pub fn encrypt(
     cipher: &XChaCha20Poly1305,
@@ -28,12 +51,20 @@ pub fn encrypt(
     Ok(encrypted_data_with_nonce)
 }
 
+/// Encrypts the data with the newest DB key and prefixes the key id if it has one.
 pub fn encrypt_with_db_key(data: &[u8]) -> Result<Vec<u8>, CryptoError> {
-    static CIPHER: Lazy<XChaCha20Poly1305> = Lazy::new(|| {
-        let key = get_db_encryption_key();
-        XChaCha20Poly1305::new(&GenericArray::clone_from_slice(&key))
-    });
-    encrypt(&CIPHER, data, &get_db_encryption_key_aad())
+    let (key, cipher) = &DB_CIPHERS[0];
+    let encrypted_data = encrypt(cipher, data, &get_db_encryption_key_aad())?;
+    if key.id.is_empty() {
+        return Ok(encrypted_data);
+    }
+
+    let mut prefixed_data = Vec::with_capacity(2 + key.id.len() + encrypted_data.len());
+    prefixed_data.push(KEY_ID_MARKER);
+    prefixed_data.push(key.id.len() as u8);
+    prefixed_data.extend(key.id.as_bytes());
+    prefixed_data.extend(encrypted_data);
+    Ok(prefixed_data)
 }
 
 pub fn decrypt(
@@ -59,10 +90,50 @@ pub fn decrypt(
     }
 }
 
+#[inline]
+fn get_db_cipher(key_id: &str) -> Option<&'static (Key, XChaCha20Poly1305)> {
+    let now = chrono::Utc::now();
+    DB_CIPHERS
+        .iter()
+        .find(|(key, _)| key.id == key_id && key.is_active(now))
+}
+
+/// Splits the key id prefix from the ciphertext if it has one.
+fn split_db_key_id(data: &[u8]) -> Option<(&str, &[u8])> {
+    if data.len() < 2 || data[0] != KEY_ID_MARKER {
+        return None;
+    }
+    let id_len = data[1] as usize;
+    if id_len == 0 || data.len() < 2 + id_len {
+        return None;
+    }
+    let key_id = std::str::from_utf8(&data[2..2 + id_len]).ok()?;
+    Some((key_id, &data[2 + id_len..]))
+}
+
+/// Decrypts the data with the DB key it was encrypted with and returns the key's id as well.
+pub fn decrypt_with_db_key_id(data: &[u8]) -> Result<(Vec<u8>, String), CryptoError> {
+    let aad = get_db_encryption_key_aad();
+    if let Some((key_id, encrypted_data)) = split_db_key_id(data) {
+        if let Some((key, cipher)) = get_db_cipher(key_id) {
+            if let Ok(decrypted_data) = decrypt(cipher, encrypted_data, &aad) {
+                return Ok((decrypted_data, key.id.clone()));
+            }
+        }
+    }
+
+    // a legacy ciphertext may start with the marker byte by chance
+    match get_db_cipher("") {
+        Some((key, cipher)) => Ok((decrypt(cipher, data, &aad)?, key.id.clone())),
+        None => Err(CryptoError::DecryptionFailed),
+    }
+}
+
 pub fn decrypt_with_db_key(data: &[u8]) -> Result<Vec<u8>, CryptoError> {
-    static CIPHER: Lazy<XChaCha20Poly1305> = Lazy::new(|| {
-        let key = get_db_encryption_key();
-        XChaCha20Poly1305::new(&GenericArray::clone_from_slice(&key))
-    });
-    decrypt(&CIPHER, data, &get_db_encryption_key_aad())
+    decrypt_with_db_key_id(data).map(|(decrypted_data, _)| decrypted_data)
+}
+
+#[inline]
+pub fn get_current_db_key_id() -> &'static str {
+    &DB_CIPHERS[0].0.id
 }
@@ -1,8 +1,10 @@
 use crate::constants;
 use crate::errors::csrf;
+use crate::security::keyring::{self, KeyRing, KeyedSigner};
 use crate::utils::security;
 
 use actix_web::cookie::{time as cookie_time, Cookie, SameSite};
+use actix_web::http::header;
 use base64::{engine::general_purpose, Engine as _};
 use serde::{Deserialize, Serialize};
 
@@ -31,7 +33,7 @@ pub struct CsrfSigner {
     cookie_name: String,
     header_name: String,
     token_len: usize,
-    signer: hmac_serialiser::HmacSigner,
+    signer: KeyedSigner,
 }
 
 impl Default for CsrfSigner {
@@ -40,7 +42,8 @@ impl Default for CsrfSigner {
             constants::CSRF_COOKIE_NAME,
             constants::CSRF_HEADER_NAME,
             constants::CSRF_TOKEN_LENGTH,
-            security::get_default_key_info(constants::get_csrf_key_salt(), vec![]),
+            keyring::get_secret_keyring(),
+            constants::get_csrf_key_salt(),
             hmac_serialiser::algorithm::Algorithm::SHA1,
             hmac_serialiser::Encoder::UrlSafeNoPadding,
         )
@@ -52,7 +55,8 @@ impl CsrfSigner {
         cookie_name: &str,
         header_name: &str,
         token_len: usize,
-        key_info: hmac_serialiser::KeyInfo,
+        keyring: &KeyRing,
+        salt: Vec<u8>,
         algo: hmac_serialiser::algorithm::Algorithm,
         encoder: hmac_serialiser::Encoder,
     ) -> CsrfSigner {
@@ -60,7 +64,7 @@ impl CsrfSigner {
             cookie_name: cookie_name.to_string(),
             header_name: header_name.to_string(),
             token_len,
-            signer: hmac_serialiser::HmacSigner::new(key_info, algo, encoder),
+            signer: KeyedSigner::new(keyring, salt, vec![], algo, encoder),
         }
     }
 
@@ -114,13 +118,41 @@ impl CsrfSigner {
     ) -> Result<String, csrf::CsrfError> {
         let csrf_header = match req.headers().get(&self.header_name) {
             Some(header) => header.to_str().unwrap().to_string(),
-            None => return Err(csrf::CsrfError::MissingToken),
+            None => return self.extract_csrf_ws_protocol(req),
         };
 
         let csrf_token = self.verify_token(&csrf_header)?;
         Ok(csrf_token.token)
     }
 
+    /// Browsers cannot set custom headers on a WebSocket handshake, so the
+    /// token is sent as one of the requested subprotocols instead.
+    fn extract_csrf_ws_protocol(
+        &self,
+        req: &actix_web::dev::ServiceRequest,
+    ) -> Result<String, csrf::CsrfError> {
+        let is_ws_upgrade = req
+            .headers()
+            .get(header::UPGRADE)
+            .and_then(|upgrade| upgrade.to_str().ok())
+            .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"));
+        if !is_ws_upgrade {
+            return Err(csrf::CsrfError::MissingToken);
+        }
+
+        let protocols = match req.headers().get(header::SEC_WEBSOCKET_PROTOCOL) {
+            Some(protocols) => protocols.to_str().unwrap_or_default(),
+            None => return Err(csrf::CsrfError::MissingToken),
+        };
+        protocols
+            .split(',')
+            .map(str::trim)
+            .filter(|protocol| *protocol != constants::WS_PREVIEW_PROTOCOL)
+            .find_map(|protocol| self.verify_token(protocol).ok())
+            .map(|csrf_token| csrf_token.token)
+            .ok_or(csrf::CsrfError::InvalidToken)
+    }
+
     #[inline]
     pub fn get_csrf_cookie_name(&self) -> &str {
         &self.cookie_name
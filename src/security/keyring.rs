@@ -0,0 +1,190 @@
+use crate::constants;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use hmac_serialiser::{algorithm::Algorithm, Encoder, HmacSigner, KeyInfo, Payload};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+// separates the key id from the token, it is not used by the base64 encoding or hmac_serialiser
+pub const KEY_ID_SEPARATOR: char = '~';
+const KEY_ID_MAX_LENGTH: usize = 32;
+
+/// A secret key that can be rotated.
+///
+/// The legacy key has an empty id so that the tokens and ciphertexts
+/// created before key rotation was supported can still be verified.
+#[derive(Clone)]
+pub struct Key {
+    pub id: String,
+    pub secret: Vec<u8>,
+    // the end of the grace period after which the old key is no longer accepted
+    pub not_after: Option<DateTime<Utc>>,
+}
+
+impl Key {
+    #[inline]
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.not_after.is_none_or(|not_after| now < not_after)
+    }
+}
+
+/// The keys with the newest first, which is used for signing and encrypting
+/// while the older ones are only used for verifying and decrypting.
+#[derive(Clone)]
+pub struct KeyRing {
+    keys: Vec<Key>,
+}
+
+impl KeyRing {
+    /// Parses the comma separated keys with the newest first, e.g. "2:<hex key>,1:<hex key>:2025-01-31".
+    ///
+    /// Each key is "<id>:<hex key>" with an optional "YYYY-MM-DD" date after which
+    /// the old key is no longer accepted. The id can be empty for the legacy key.
+    pub fn parse(value: &str) -> Result<KeyRing, String> {
+        let mut keys: Vec<Key> = Vec::new();
+        for entry in value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let mut parts = entry.splitn(3, ':');
+            let id = parts.next().unwrap_or_default().to_string();
+            let is_valid_id = id.len() <= KEY_ID_MAX_LENGTH
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !is_valid_id {
+                return Err(format!("Invalid key id {:?}", id));
+            }
+            if keys.iter().any(|key| key.id == id) {
+                return Err(format!("Duplicate key id {:?}", id));
+            }
+
+            let secret = hex::decode(parts.next().unwrap_or_default())
+                .map_err(|e| format!("Invalid hex key for key id {:?}: {}", id, e))?;
+            if secret.is_empty() {
+                return Err(format!("Empty key for key id {:?}", id));
+            }
+            let not_after = match parts.next() {
+                Some(date) => {
+                    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                        .map_err(|e| format!("Invalid date for key id {:?}: {}", id, e))?;
+                    Some(date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+                }
+                None => None,
+            };
+            keys.push(Key {
+                id,
+                secret,
+                not_after,
+            });
+        }
+
+        match keys.first() {
+            None => Err("The keyring is empty".to_string()),
+            Some(key) if key.not_after.is_some() => {
+                Err("The newest key cannot have a grace period".to_string())
+            }
+            Some(_) => Ok(KeyRing { keys }),
+        }
+    }
+
+    /// Uses the keyring if it is set, otherwise the legacy key becomes the only key.
+    fn from_env(keyring: Option<String>, get_legacy_key: fn() -> Vec<u8>) -> KeyRing {
+        match keyring {
+            Some(keyring) => KeyRing::parse(&keyring).unwrap_or_else(|e| panic!("{}", e)),
+            None => KeyRing {
+                keys: vec![Key {
+                    id: String::new(),
+                    secret: get_legacy_key(),
+                    not_after: None,
+                }],
+            },
+        }
+    }
+
+    #[inline]
+    pub fn get_keys(&self) -> &[Key] {
+        &self.keys
+    }
+}
+
+pub fn get_secret_keyring() -> &'static KeyRing {
+    static KEYRING: Lazy<KeyRing> =
+        Lazy::new(|| KeyRing::from_env(constants::get_secret_keyring(), constants::get_secret_key));
+    &KEYRING
+}
+
+pub fn get_db_keyring() -> &'static KeyRing {
+    static KEYRING: Lazy<KeyRing> = Lazy::new(|| {
+        KeyRing::from_env(
+            constants::get_db_encryption_keyring(),
+            constants::get_db_encryption_key,
+        )
+    });
+    &KEYRING
+}
+
+/// Splits the key id from the token, tokens without one were signed by the legacy key.
+#[inline]
+pub fn split_key_id(token: &str) -> (&str, &str) {
+    token.split_once(KEY_ID_SEPARATOR).unwrap_or(("", token))
+}
+
+/// A HMAC signer that embeds the key id in the tokens so that
+/// the tokens signed by the older keys can still be verified.
+#[derive(Clone)]
+pub struct KeyedSigner {
+    signers: Vec<(Key, HmacSigner)>,
+}
+
+impl KeyedSigner {
+    pub fn new(
+        keyring: &KeyRing,
+        salt: Vec<u8>,
+        info: Vec<u8>,
+        algo: Algorithm,
+        encoder: Encoder,
+    ) -> KeyedSigner {
+        let signers = keyring
+            .get_keys()
+            .iter()
+            .map(|key| {
+                let key_info = KeyInfo {
+                    key: key.secret.clone(),
+                    salt: salt.clone(),
+                    info: info.clone(),
+                };
+                let signer = HmacSigner::new(key_info, algo.clone(), encoder.clone());
+                (key.clone(), signer)
+            })
+            .collect();
+        KeyedSigner { signers }
+    }
+
+    pub fn sign<T: Serialize + Payload>(&self, payload: &T) -> String {
+        let (key, signer) = &self.signers[0];
+        let token = signer.sign(payload);
+        if key.id.is_empty() {
+            token
+        } else {
+            format!("{}{}{}", key.id, KEY_ID_SEPARATOR, token)
+        }
+    }
+
+    pub fn unsign<T: for<'de> Deserialize<'de> + Payload>(
+        &self,
+        token: &str,
+    ) -> Result<T, hmac_serialiser::errors::Error> {
+        let (key_id, token) = split_key_id(token);
+        let now = Utc::now();
+        match self
+            .signers
+            .iter()
+            .find(|(key, _)| key.id == key_id && key.is_active(now))
+        {
+            Some((_, signer)) => signer.unsign(token),
+            None => Err(hmac_serialiser::errors::Error::InvalidSignature),
+        }
+    }
+}
@@ -1,5 +1,8 @@
 pub(crate) mod cf_turnstile;
 pub(crate) mod chacha_crypto;
 pub(crate) mod csrf;
+pub(crate) mod keyring;
 pub(crate) mod pw_hasher;
+pub(crate) mod rate_limiter;
 pub(crate) mod totp;
+pub(crate) mod webauthn;
@@ -0,0 +1,496 @@
+use crate::constants;
+use crate::database::db::DbClient;
+use crate::errors::auth::AuthError;
+use crate::models::login_attempt::{self, LoginAttempt};
+
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::doc;
+use mongodb::options::{FindOneAndUpdateOptions, ReturnDocument};
+use mongodb::Collection;
+use std::future::Future;
+
+const IP_KEY_PREFIX: &str = "ip:";
+const USER_KEY_PREFIX: &str = "user:";
+
+#[inline]
+pub fn get_ip_key(ip: &str) -> String {
+    format!("{}{}", IP_KEY_PREFIX, ip)
+}
+
+/// Returns the key for the failed attempts against an account.
+///
+/// The user id is used for existing accounts so that signing in with the username
+/// and the email address share the same limit. Unknown usernames are limited by
+/// the submitted value instead so that a lockout does not reveal if an account exists.
+#[inline]
+pub fn get_user_key(user: &str) -> String {
+    format!("{}{}", USER_KEY_PREFIX, user.trim().to_lowercase())
+}
+
+/// The storage of the failed login attempts.
+///
+/// It can be replaced with the in-memory store to test the limiter without a database.
+pub trait LoginAttemptStore: Send + Sync {
+    fn get(
+        &self,
+        key: &str,
+    ) -> impl Future<Output = Result<Option<LoginAttempt>, AuthError>> + Send;
+
+    /// Atomically increments the number of failures and returns the updated attempt.
+    ///
+    /// The count restarts from 1 if the previous failures have expired.
+    fn increment(
+        &self,
+        key: &str,
+        now: DateTime<Utc>,
+        expiry: DateTime<Utc>,
+    ) -> impl Future<Output = Result<LoginAttempt, AuthError>> + Send;
+
+    fn set_blocked(
+        &self,
+        key: &str,
+        blocked_until: Option<DateTime<Utc>>,
+        locked_until: Option<DateTime<Utc>>,
+        expiry: DateTime<Utc>,
+    ) -> impl Future<Output = Result<(), AuthError>> + Send;
+
+    /// Returns true if there were any failed attempts to remove.
+    fn remove(&self, key: &str) -> impl Future<Output = Result<bool, AuthError>> + Send;
+
+    fn get_blocked(
+        &self,
+        now: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<LoginAttempt>, AuthError>> + Send;
+}
+
+#[derive(Clone)]
+pub struct MongoLoginAttemptStore {
+    collection: Collection<LoginAttempt>,
+}
+
+impl MongoLoginAttemptStore {
+    pub fn new(db_client: &DbClient) -> Self {
+        Self {
+            collection: db_client.get_login_attempt_collection(),
+        }
+    }
+}
+
+#[inline]
+fn map_db_error(e: mongodb::error::Error) -> AuthError {
+    log::error!("Failed to access the login attempts in db: {:?}", e);
+    AuthError::InternalServerError
+}
+
+impl LoginAttemptStore for MongoLoginAttemptStore {
+    async fn get(&self, key: &str) -> Result<Option<LoginAttempt>, AuthError> {
+        let attempt = self
+            .collection
+            .find_one(doc! {"_id": key})
+            .await
+            .map_err(map_db_error)?;
+        // the TTL monitor only deletes the expired documents periodically
+        Ok(attempt.filter(|attempt| attempt.expiry > Utc::now()))
+    }
+
+    async fn increment(
+        &self,
+        key: &str,
+        now: DateTime<Utc>,
+        expiry: DateTime<Utc>,
+    ) -> Result<LoginAttempt, AuthError> {
+        let now = bson::DateTime::from_chrono(now);
+        let is_active = doc! {"$gt": [format!("${}", login_attempt::EXPIRY_KEY), now]};
+        let update = vec![doc! {"$set": {
+            login_attempt::FAILURES_KEY: {"$cond": [
+                is_active.clone(),
+                {"$add": [{"$ifNull": [format!("${}", login_attempt::FAILURES_KEY), 0_i64]}, 1_i64]},
+                1_i64,
+            ]},
+            login_attempt::BLOCKED_UNTIL_KEY: {"$cond": [
+                is_active.clone(),
+                format!("${}", login_attempt::BLOCKED_UNTIL_KEY),
+                null,
+            ]},
+            login_attempt::LOCKED_UNTIL_KEY: {"$cond": [
+                is_active.clone(),
+                format!("${}", login_attempt::LOCKED_UNTIL_KEY),
+                null,
+            ]},
+            login_attempt::LAST_FAILURE_KEY: now,
+            login_attempt::EXPIRY_KEY: bson::DateTime::from_chrono(expiry),
+        }}];
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+        self.collection
+            .find_one_and_update(doc! {"_id": key}, update)
+            .with_options(options)
+            .await
+            .map_err(map_db_error)?
+            .ok_or_else(|| {
+                log::error!(
+                    "The login attempt {} was not returned after the upsert",
+                    key
+                );
+                AuthError::InternalServerError
+            })
+    }
+
+    async fn set_blocked(
+        &self,
+        key: &str,
+        blocked_until: Option<DateTime<Utc>>,
+        locked_until: Option<DateTime<Utc>>,
+        expiry: DateTime<Utc>,
+    ) -> Result<(), AuthError> {
+        self.collection
+            .update_one(
+                doc! {"_id": key},
+                doc! {"$set": {
+                    login_attempt::BLOCKED_UNTIL_KEY: blocked_until.map(bson::DateTime::from_chrono),
+                    login_attempt::LOCKED_UNTIL_KEY: locked_until.map(bson::DateTime::from_chrono),
+                    login_attempt::EXPIRY_KEY: bson::DateTime::from_chrono(expiry),
+                }},
+            )
+            .await
+            .map_err(map_db_error)?;
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<bool, AuthError> {
+        let result = self
+            .collection
+            .delete_one(doc! {"_id": key})
+            .await
+            .map_err(map_db_error)?;
+        Ok(result.deleted_count > 0)
+    }
+
+    async fn get_blocked(&self, now: DateTime<Utc>) -> Result<Vec<LoginAttempt>, AuthError> {
+        let now = bson::DateTime::from_chrono(now);
+        self.collection
+            .find(doc! {"$or": [
+                {login_attempt::BLOCKED_UNTIL_KEY: {"$gt": now}},
+                {login_attempt::LOCKED_UNTIL_KEY: {"$gt": now}},
+            ]})
+            .sort(doc! {login_attempt::LAST_FAILURE_KEY: -1})
+            .await
+            .map_err(map_db_error)?
+            .try_collect()
+            .await
+            .map_err(map_db_error)
+    }
+}
+
+/// Keeps the login attempts in memory so that the limiter can be tested without a database.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryLoginAttemptStore {
+    attempts: std::sync::Mutex<std::collections::HashMap<String, LoginAttempt>>,
+}
+
+#[cfg(test)]
+impl MemoryLoginAttemptStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(test)]
+impl LoginAttemptStore for MemoryLoginAttemptStore {
+    async fn get(&self, key: &str) -> Result<Option<LoginAttempt>, AuthError> {
+        let attempts = self.attempts.lock().unwrap();
+        Ok(attempts
+            .get(key)
+            .filter(|attempt| attempt.expiry > Utc::now())
+            .cloned())
+    }
+
+    async fn increment(
+        &self,
+        key: &str,
+        now: DateTime<Utc>,
+        expiry: DateTime<Utc>,
+    ) -> Result<LoginAttempt, AuthError> {
+        let mut attempts = self.attempts.lock().unwrap();
+        let attempt = attempts
+            .entry(key.to_string())
+            .and_modify(|attempt| {
+                if attempt.expiry > now {
+                    attempt.failures += 1;
+                } else {
+                    attempt.failures = 1;
+                    attempt.blocked_until = None;
+                    attempt.locked_until = None;
+                }
+                attempt.last_failure = now;
+                attempt.expiry = expiry;
+            })
+            .or_insert_with(|| LoginAttempt {
+                key: key.to_string(),
+                failures: 1,
+                last_failure: now,
+                blocked_until: None,
+                locked_until: None,
+                expiry,
+            });
+        Ok(attempt.clone())
+    }
+
+    async fn set_blocked(
+        &self,
+        key: &str,
+        blocked_until: Option<DateTime<Utc>>,
+        locked_until: Option<DateTime<Utc>>,
+        expiry: DateTime<Utc>,
+    ) -> Result<(), AuthError> {
+        let mut attempts = self.attempts.lock().unwrap();
+        if let Some(attempt) = attempts.get_mut(key) {
+            attempt.blocked_until = blocked_until;
+            attempt.locked_until = locked_until;
+            attempt.expiry = expiry;
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<bool, AuthError> {
+        let mut attempts = self.attempts.lock().unwrap();
+        Ok(attempts.remove(key).is_some())
+    }
+
+    async fn get_blocked(&self, now: DateTime<Utc>) -> Result<Vec<LoginAttempt>, AuthError> {
+        let attempts = self.attempts.lock().unwrap();
+        let mut blocked: Vec<LoginAttempt> = attempts
+            .values()
+            .filter(|attempt| attempt.is_blocked(now))
+            .cloned()
+            .collect();
+        blocked.sort_by_key(|attempt| std::cmp::Reverse(attempt.last_failure));
+        Ok(blocked)
+    }
+}
+
+/// Returns how long the next attempt has to wait after the given number of failures.
+///
+/// The first few failures are free so that typos are not punished,
+/// after which the delay doubles with every failure up to the maximum.
+pub fn get_backoff(failures: i64) -> chrono::Duration {
+    if failures <= constants::LOGIN_FREE_ATTEMPTS {
+        return chrono::Duration::zero();
+    }
+    let exponent = failures - constants::LOGIN_FREE_ATTEMPTS - 1;
+    let base = constants::LOGIN_BACKOFF_BASE.as_millis() as i64;
+    let max = constants::LOGIN_BACKOFF_MAX.as_millis() as i64;
+    let backoff = match 1_i64.checked_shl(exponent.min(62) as u32) {
+        Some(multiplier) => base.saturating_mul(multiplier).min(max),
+        None => max,
+    };
+    chrono::Duration::milliseconds(backoff)
+}
+
+/// Throttles the failed logins per IP address and per account.
+///
+/// Every failure after the free attempts blocks further attempts with an exponential
+/// backoff and the account is locked for a while after the lockout threshold.
+/// Blocked keys can be unlocked by the admin from the profile page or the command line.
+pub struct LoginRateLimiter<S: LoginAttemptStore> {
+    store: S,
+}
+
+pub type DefaultLoginRateLimiter = LoginRateLimiter<MongoLoginAttemptStore>;
+
+impl<S: LoginAttemptStore> LoginRateLimiter<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    /// Returns an error if the key is still blocked from another attempt.
+    pub async fn check(&self, key: &str) -> Result<(), AuthError> {
+        match self.store.get(key).await? {
+            Some(attempt) if attempt.is_blocked(Utc::now()) => {
+                log::warn!("Rejected a login attempt from the blocked key {}", key);
+                Err(AuthError::TooManyAttempts)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Records a failed login attempt and blocks the key for the backoff period.
+    ///
+    /// Only account keys are locked after the lockout threshold since IP addresses
+    /// can be shared and are already throttled by the backoff.
+    pub async fn record_failure(&self, key: &str) -> Result<LoginAttempt, AuthError> {
+        let now = Utc::now();
+        let window = chrono::Duration::from_std(constants::LOGIN_ATTEMPT_WINDOW).unwrap();
+        let mut attempt = self.store.increment(key, now, now + window).await?;
+
+        let backoff = get_backoff(attempt.failures);
+        let blocked_until = if backoff.is_zero() {
+            None
+        } else {
+            Some(now + backoff)
+        };
+        let is_new_lockout = key.starts_with(USER_KEY_PREFIX)
+            && attempt.failures >= constants::LOGIN_LOCKOUT_THRESHOLD
+            && !attempt.is_locked(now);
+        let locked_until = if is_new_lockout {
+            let lockout = chrono::Duration::from_std(constants::LOGIN_LOCKOUT_DURATION).unwrap();
+            Some(now + lockout)
+        } else {
+            attempt.locked_until
+        };
+        if blocked_until.is_none() && locked_until.is_none() {
+            return Ok(attempt);
+        }
+
+        let expiry = attempt.expiry.max(locked_until.unwrap_or(now));
+        self.store
+            .set_blocked(key, blocked_until, locked_until, expiry)
+            .await?;
+        if is_new_lockout {
+            log::warn!(
+                "Locked {} after {} failed login attempts until {}, it can be unlocked by the admin",
+                key,
+                attempt.failures,
+                locked_until.unwrap()
+            );
+        }
+        attempt.blocked_until = blocked_until;
+        attempt.locked_until = locked_until;
+        attempt.expiry = expiry;
+        Ok(attempt)
+    }
+
+    /// Forgets the failed attempts of the key after a successful login.
+    pub async fn clear(&self, key: &str) -> Result<(), AuthError> {
+        self.store.remove(key).await?;
+        Ok(())
+    }
+
+    pub async fn get_blocked(&self) -> Result<Vec<LoginAttempt>, AuthError> {
+        self.store.get_blocked(Utc::now()).await
+    }
+
+    /// Lifts the backoff and the lockout of the key and returns true if it had any failed attempts.
+    pub async fn unlock(&self, key: &str) -> Result<bool, AuthError> {
+        let removed = self.store.remove(key).await?;
+        if removed {
+            log::info!("Unlocked the login attempts of {}", key);
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_limiter() -> LoginRateLimiter<MemoryLoginAttemptStore> {
+        LoginRateLimiter::new(MemoryLoginAttemptStore::new())
+    }
+
+    async fn fail(limiter: &LoginRateLimiter<MemoryLoginAttemptStore>, key: &str, times: i64) {
+        for _ in 0..times {
+            limiter.record_failure(key).await.unwrap();
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_after_the_free_attempts_up_to_the_maximum() {
+        for failures in 0..=constants::LOGIN_FREE_ATTEMPTS {
+            assert!(get_backoff(failures).is_zero());
+        }
+        let base = chrono::Duration::from_std(constants::LOGIN_BACKOFF_BASE).unwrap();
+        let free = constants::LOGIN_FREE_ATTEMPTS;
+        assert_eq!(get_backoff(free + 1), base);
+        assert_eq!(get_backoff(free + 2), base * 2);
+        assert_eq!(get_backoff(free + 3), base * 4);
+
+        let max = chrono::Duration::from_std(constants::LOGIN_BACKOFF_MAX).unwrap();
+        assert_eq!(get_backoff(free + 30), max);
+        assert_eq!(get_backoff(i64::MAX), max);
+    }
+
+    #[tokio::test]
+    async fn failures_after_the_free_attempts_block_the_key() {
+        let limiter = new_limiter();
+        let key = get_ip_key("203.0.113.1");
+        fail(&limiter, &key, constants::LOGIN_FREE_ATTEMPTS).await;
+        assert!(limiter.check(&key).await.is_ok());
+
+        let attempt = limiter.record_failure(&key).await.unwrap();
+        assert!(attempt.blocked_until.is_some());
+        assert!(matches!(
+            limiter.check(&key).await,
+            Err(AuthError::TooManyAttempts)
+        ));
+    }
+
+    #[tokio::test]
+    async fn only_account_keys_are_locked_out() {
+        let limiter = new_limiter();
+        let ip_key = get_ip_key("203.0.113.1");
+        let user_key = get_user_key("Alice");
+        fail(&limiter, &ip_key, constants::LOGIN_LOCKOUT_THRESHOLD).await;
+        fail(&limiter, &user_key, constants::LOGIN_LOCKOUT_THRESHOLD).await;
+
+        let now = Utc::now();
+        let ip_attempt = limiter.store.get(&ip_key).await.unwrap().unwrap();
+        assert!(!ip_attempt.is_locked(now));
+        let user_attempt = limiter.store.get(&user_key).await.unwrap().unwrap();
+        assert!(user_attempt.is_locked(now));
+        assert!(user_attempt.expiry >= user_attempt.locked_until.unwrap());
+
+        let blocked = limiter.get_blocked().await.unwrap();
+        assert_eq!(blocked.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn failures_are_forgotten_after_the_window() {
+        let limiter = new_limiter();
+        let key = get_user_key("alice");
+        fail(&limiter, &key, constants::LOGIN_LOCKOUT_THRESHOLD).await;
+
+        // pretends that the window has passed since the last failure
+        let past = Utc::now() - chrono::Duration::seconds(1);
+        if let Some(attempt) = limiter.store.attempts.lock().unwrap().get_mut(&key) {
+            attempt.expiry = past;
+            attempt.blocked_until = Some(past);
+            attempt.locked_until = Some(past);
+        }
+        assert!(limiter.store.get(&key).await.unwrap().is_none());
+        assert!(limiter.check(&key).await.is_ok());
+
+        let attempt = limiter.record_failure(&key).await.unwrap();
+        assert_eq!(attempt.failures, 1);
+        assert!(attempt.blocked_until.is_none());
+        assert!(attempt.locked_until.is_none());
+    }
+
+    #[tokio::test]
+    async fn unlock_lifts_the_lockout() {
+        let limiter = new_limiter();
+        let key = get_user_key("alice");
+        fail(&limiter, &key, constants::LOGIN_LOCKOUT_THRESHOLD).await;
+        assert!(limiter.check(&key).await.is_err());
+
+        assert!(limiter.unlock(&key).await.unwrap());
+        assert!(limiter.check(&key).await.is_ok());
+        assert!(limiter.get_blocked().await.unwrap().is_empty());
+        assert!(!limiter.unlock(&key).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn clear_forgets_the_failures() {
+        let limiter = new_limiter();
+        let key = get_ip_key("203.0.113.1");
+        fail(&limiter, &key, constants::LOGIN_FREE_ATTEMPTS).await;
+        limiter.clear(&key).await.unwrap();
+
+        let attempt = limiter.record_failure(&key).await.unwrap();
+        assert_eq!(attempt.failures, 1);
+    }
+}
@@ -1,27 +1,52 @@
 use crate::constants;
+use crate::errors::auth::AuthError;
 use crate::models::generated_totp::GeneratedTotp;
+use crate::models::totp_config::{TotpAlgorithm, TotpConfig, ALLOWED_DIGITS};
+use crate::security::pw_hasher;
 use crate::utils::security::generate_random_bytes;
 
+use rand::Rng;
+use std::time::{SystemTime, UNIX_EPOCH};
+use subtle::ConstantTimeEq;
 use totp_rs::{Algorithm, Secret, TOTP};
 
 const TOTP_SECRET_LEN: usize = 34; // bytes (16 bytes minimum but >=20 bytes recommended by RFC-4226)
-const TOTP_DIGITS: usize = 6;
 const TOTP_SKEW: u8 = 1;
 const TOTP_STEP: u64 = 30; // recommended to be 30 seconds by RFC-6238
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_GROUP_LEN: usize = 5;
+// without the characters that are easily confused with each other like 0/o and 1/l/i
+const RECOVERY_CODE_CHARSET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+pub const RECOVERY_CODE_MAX_INPUT_LEN: usize = 32;
 
-pub fn generate_totp(username: &str) -> GeneratedTotp {
-    let secret_bytes = generate_random_bytes(TOTP_SECRET_LEN);
-    let encoded_secret = Secret::Raw(secret_bytes.clone()).to_encoded().to_string();
-    let totp = TOTP::new(
-        Algorithm::SHA1,
-        TOTP_DIGITS,
+#[inline]
+fn get_algorithm(config: &TotpConfig) -> Algorithm {
+    match config.algorithm {
+        TotpAlgorithm::Sha1 => Algorithm::SHA1,
+        TotpAlgorithm::Sha256 => Algorithm::SHA256,
+        TotpAlgorithm::Sha512 => Algorithm::SHA512,
+    }
+}
+
+#[inline]
+fn get_totp(secret_bytes: Vec<u8>, config: &TotpConfig, username: &str) -> Option<TOTP> {
+    TOTP::new(
+        get_algorithm(config),
+        config.digits as usize,
         TOTP_SKEW,
         TOTP_STEP,
         secret_bytes,
         Some(constants::DOMAIN.to_string()),
         username.to_string(),
     )
-    .expect("TOTP instance should be created without errors");
+    .ok()
+}
+
+pub fn generate_totp(username: &str, config: &TotpConfig) -> GeneratedTotp {
+    let secret_bytes = generate_random_bytes(TOTP_SECRET_LEN);
+    let encoded_secret = Secret::Raw(secret_bytes.clone()).to_encoded().to_string();
+    let totp = get_totp(secret_bytes, config, username)
+        .expect("TOTP instance should be created without errors");
 
     let qr_code_data = totp
         .get_qr_base64()
@@ -32,21 +57,98 @@ pub fn generate_totp(username: &str) -> GeneratedTotp {
     }
 }
 
-pub fn verify_totp(totp_input: &str, secret: &str) -> bool {
-    let secret = Secret::Encoded(secret.to_string());
-    let totp = TOTP::new(
-        Algorithm::SHA1,
-        TOTP_DIGITS,
-        TOTP_SKEW,
-        TOTP_STEP,
-        secret.to_bytes().unwrap(),
-        Some(constants::DOMAIN.to_string()),
-        "".to_string(),
-    )
-    .expect("TOTP instance should be created without errors");
+/// Verifies the code against every time step within the allowed skew
+/// and returns the time step that the code was generated for.
+///
+/// Time steps up to and including the last accepted one are skipped
+/// so that a code cannot be used again, even within its validity window.
+pub fn verify_totp(
+    totp_input: &str,
+    secret: &str,
+    config: &TotpConfig,
+    last_step: Option<i64>,
+) -> Option<i64> {
+    let secret_bytes = Secret::Encoded(secret.to_string()).to_bytes().ok()?;
+    let totp = get_totp(secret_bytes, config, "")?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System time should be after the unix epoch")
+        .as_secs();
 
-    let token = totp
-        .generate_current()
-        .expect("Should be able to generate TOTP token for verification");
-    totp_input == token
+    let current_step = (now / TOTP_STEP) as i64;
+    let skew = TOTP_SKEW as i64;
+    let mut accepted_step = None;
+    for step in (current_step - skew)..=(current_step + skew) {
+        if last_step.is_some_and(|last_step| step <= last_step) {
+            continue;
+        }
+        // every step is checked even after a match so that the timing does not reveal which one matched
+        let token = totp.generate(step as u64 * TOTP_STEP);
+        if bool::from(token.as_bytes().ct_eq(totp_input.as_bytes())) {
+            accepted_step = Some(step);
+        }
+    }
+    accepted_step
+}
+
+#[inline]
+pub fn is_totp_code(input: &str) -> bool {
+    ALLOWED_DIGITS.contains(&(input.len() as u8)) && input.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Generates single-use recovery codes in the format of "xxxxx-xxxxx"
+/// to be used in place of a TOTP code if the authenticator is lost.
+pub fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            // sampled uniformly as taking a random byte modulo the
+            // charset length would make some characters more likely
+            let chars: String = (0..RECOVERY_CODE_GROUP_LEN * 2)
+                .map(|_| {
+                    RECOVERY_CODE_CHARSET[rng.random_range(0..RECOVERY_CODE_CHARSET.len())] as char
+                })
+                .collect();
+            let (first, second) = chars.split_at(RECOVERY_CODE_GROUP_LEN);
+            format!("{}-{}", first, second)
+        })
+        .collect()
+}
+
+#[inline]
+fn normalise_recovery_code(input: &str) -> String {
+    input
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Note: This is a blocking operation as every code is hashed with Argon2.
+pub fn hash_recovery_codes(codes: &[String]) -> Result<Vec<String>, AuthError> {
+    codes
+        .iter()
+        .map(|code| {
+            pw_hasher::hash_password(&normalise_recovery_code(code))
+                .map_err(|_| AuthError::InternalServerError)
+        })
+        .collect()
+}
+
+/// Returns the hash of the recovery code that matches the input.
+///
+/// Note: This is a blocking operation as the input is verified against every hash with Argon2.
+pub fn find_recovery_code(input: &str, hashes: &[String]) -> Result<Option<String>, AuthError> {
+    let input = normalise_recovery_code(input);
+    if input.len() != RECOVERY_CODE_GROUP_LEN * 2 {
+        return Ok(None);
+    }
+    for hash in hashes {
+        let is_valid =
+            pw_hasher::verify_password(&input, hash).map_err(|_| AuthError::InternalServerError)?;
+        if is_valid {
+            return Ok(Some(hash.clone()));
+        }
+    }
+    Ok(None)
 }
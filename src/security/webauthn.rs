@@ -0,0 +1,287 @@
+use crate::constants;
+use crate::errors::auth::AuthError;
+use crate::models::passkey::Passkey;
+use crate::utils::security::generate_random_bytes;
+
+use base64::{engine::general_purpose, Engine as _};
+use ciborium::value::Value;
+use p256::ecdsa::{signature::Verifier, DerSignature, VerifyingKey};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+// https://www.w3.org/TR/webauthn-3/
+pub const COSE_ALG_ES256: i64 = -7;
+const CHALLENGE_LEN: usize = 32;
+const RP_ID_HASH_LEN: usize = 32;
+const AAGUID_LEN: usize = 16;
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+// COSE key labels and values for an EC2 P-256 public key (RFC 9053)
+const COSE_KEY_KTY: i128 = 1;
+const COSE_KEY_ALG: i128 = 3;
+const COSE_KEY_CRV: i128 = -1;
+const COSE_KEY_X: i128 = -2;
+const COSE_KEY_Y: i128 = -3;
+const COSE_KTY_EC2: i128 = 2;
+const COSE_CRV_P256: i128 = 1;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClientData {
+    #[serde(rename = "type")]
+    ceremony_type: String,
+    challenge: String,
+    origin: String,
+    #[serde(default)]
+    cross_origin: bool,
+}
+
+struct AuthenticatorData<'a> {
+    rp_id_hash: &'a [u8],
+    flags: u8,
+    sign_count: u32,
+    attested_credential_data: &'a [u8],
+}
+
+/// A credential that has passed the registration ceremony.
+pub struct VerifiedCredential {
+    pub id: String,
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+#[inline]
+pub fn generate_challenge() -> Vec<u8> {
+    generate_random_bytes(CHALLENGE_LEN)
+}
+
+#[inline]
+pub fn encode(bytes: &[u8]) -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+#[inline]
+pub fn decode(value: &str) -> Result<Vec<u8>, AuthError> {
+    general_purpose::URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| AuthError::InvalidPasskey)
+}
+
+macro_rules! reject {
+    ($($arg:tt)*) => {{
+        log::warn!($($arg)*);
+        return Err(AuthError::InvalidPasskey);
+    }};
+}
+
+fn verify_client_data(
+    client_data_json: &[u8],
+    ceremony_type: &str,
+    challenge: &[u8],
+) -> Result<(), AuthError> {
+    let client_data: ClientData = match serde_json::from_slice(client_data_json) {
+        Ok(client_data) => client_data,
+        Err(e) => reject!("Failed to parse the WebAuthn client data: {:?}", e),
+    };
+    if client_data.ceremony_type != ceremony_type {
+        reject!(
+            "Expected a {} ceremony but got {}",
+            ceremony_type,
+            client_data.ceremony_type
+        );
+    }
+    if decode(&client_data.challenge)? != challenge {
+        reject!("The WebAuthn challenge does not match");
+    }
+    if client_data.origin != constants::get_webauthn_origin() || client_data.cross_origin {
+        reject!(
+            "Unexpected WebAuthn origin: {} (cross origin: {})",
+            client_data.origin,
+            client_data.cross_origin
+        );
+    }
+    Ok(())
+}
+
+fn parse_authenticator_data(data: &[u8]) -> Result<AuthenticatorData<'_>, AuthError> {
+    if data.len() < RP_ID_HASH_LEN + 5 {
+        reject!("The authenticator data is too short");
+    }
+    let (rp_id_hash, rest) = data.split_at(RP_ID_HASH_LEN);
+    let sign_count = u32::from_be_bytes([rest[1], rest[2], rest[3], rest[4]]);
+    Ok(AuthenticatorData {
+        rp_id_hash,
+        flags: rest[0],
+        sign_count,
+        attested_credential_data: &rest[5..],
+    })
+}
+
+fn verify_authenticator_data(
+    auth_data: &AuthenticatorData,
+    require_user_verification: bool,
+) -> Result<(), AuthError> {
+    let rp_id_hash = Sha256::digest(constants::get_domain().as_bytes());
+    if auth_data.rp_id_hash != rp_id_hash.as_slice() {
+        reject!("The WebAuthn relying party id hash does not match");
+    }
+    if auth_data.flags & FLAG_USER_PRESENT == 0 {
+        reject!("The user was not present during the WebAuthn ceremony");
+    }
+    if require_user_verification && auth_data.flags & FLAG_USER_VERIFIED == 0 {
+        reject!("The user was not verified during the WebAuthn ceremony");
+    }
+    Ok(())
+}
+
+fn get_map_value(map: &[(Value, Value)], label: i128) -> Option<&Value> {
+    map.iter().find_map(|(key, value)| match key {
+        Value::Integer(key) if i128::from(*key) == label => Some(value),
+        _ => None,
+    })
+}
+
+/// Converts a COSE encoded ES256 public key into an uncompressed SEC1 public key.
+fn parse_cose_key(value: &Value) -> Result<Vec<u8>, AuthError> {
+    let map = match value {
+        Value::Map(map) => map,
+        _ => reject!("The credential public key is not a COSE key"),
+    };
+    let get_int = |label| match get_map_value(map, label) {
+        Some(Value::Integer(value)) => Some(i128::from(*value)),
+        _ => None,
+    };
+    if get_int(COSE_KEY_KTY) != Some(COSE_KTY_EC2)
+        || get_int(COSE_KEY_ALG) != Some(COSE_ALG_ES256 as i128)
+        || get_int(COSE_KEY_CRV) != Some(COSE_CRV_P256)
+    {
+        reject!("Only ES256 passkeys are supported");
+    }
+    let (x, y) = match (
+        get_map_value(map, COSE_KEY_X),
+        get_map_value(map, COSE_KEY_Y),
+    ) {
+        (Some(Value::Bytes(x)), Some(Value::Bytes(y))) if x.len() == 32 && y.len() == 32 => (x, y),
+        _ => reject!("The credential public key has invalid coordinates"),
+    };
+
+    let mut public_key = Vec::with_capacity(65);
+    public_key.push(0x04);
+    public_key.extend_from_slice(x);
+    public_key.extend_from_slice(y);
+    if VerifyingKey::from_sec1_bytes(&public_key).is_err() {
+        reject!("The credential public key is not a valid P-256 point");
+    }
+    Ok(public_key)
+}
+
+/// Verifies the response of a registration ceremony.
+///
+/// Since the attestation conveyance is "none", the attestation statement
+/// is not verified and only the authenticator data is used.
+pub fn verify_registration(
+    challenge: &[u8],
+    credential_id: &str,
+    client_data_json: &[u8],
+    attestation_object: &[u8],
+) -> Result<VerifiedCredential, AuthError> {
+    verify_client_data(client_data_json, "webauthn.create", challenge)?;
+
+    let attestation: Value = match ciborium::from_reader(attestation_object) {
+        Ok(attestation) => attestation,
+        Err(e) => reject!("Failed to parse the attestation object: {:?}", e),
+    };
+    let auth_data = match &attestation {
+        Value::Map(map) => map.iter().find_map(|(key, value)| match (key, value) {
+            (Value::Text(key), Value::Bytes(auth_data)) if key == "authData" => Some(auth_data),
+            _ => None,
+        }),
+        _ => None,
+    };
+    let auth_data = match auth_data {
+        Some(auth_data) => parse_authenticator_data(auth_data)?,
+        None => reject!("The attestation object has no authenticator data"),
+    };
+    verify_authenticator_data(&auth_data, false)?;
+    if auth_data.flags & FLAG_ATTESTED_CREDENTIAL_DATA == 0 {
+        reject!("The authenticator data has no attested credential data");
+    }
+
+    // aaguid (16 bytes) | credential id length (2 bytes) | credential id | COSE public key
+    let data = auth_data.attested_credential_data;
+    if data.len() < AAGUID_LEN + 2 {
+        reject!("The attested credential data is too short");
+    }
+    let id_len = u16::from_be_bytes([data[AAGUID_LEN], data[AAGUID_LEN + 1]]) as usize;
+    let id_start = AAGUID_LEN + 2;
+    if data.len() < id_start + id_len {
+        reject!("The attested credential data is too short");
+    }
+    let id = encode(&data[id_start..id_start + id_len]);
+    if id != credential_id.trim_end_matches('=') {
+        reject!("The credential id does not match the attested credential data");
+    }
+    let cose_key: Value = match ciborium::from_reader(&data[id_start + id_len..]) {
+        Ok(cose_key) => cose_key,
+        Err(e) => reject!("Failed to parse the credential public key: {:?}", e),
+    };
+
+    Ok(VerifiedCredential {
+        id,
+        public_key: parse_cose_key(&cose_key)?,
+        sign_count: auth_data.sign_count,
+    })
+}
+
+/// Verifies the response of an authentication ceremony and returns the new sign counter.
+///
+/// The sign counter must increase on every use unless the authenticator does not
+/// implement it, in which case it is always 0. A counter that goes backwards means
+/// that the credential may have been cloned so the assertion is rejected.
+pub fn verify_assertion(
+    passkey: &Passkey,
+    challenge: &[u8],
+    client_data_json: &[u8],
+    authenticator_data: &[u8],
+    signature: &[u8],
+    require_user_verification: bool,
+) -> Result<u32, AuthError> {
+    verify_client_data(client_data_json, "webauthn.get", challenge)?;
+    let auth_data = parse_authenticator_data(authenticator_data)?;
+    verify_authenticator_data(&auth_data, require_user_verification)?;
+
+    let verifying_key = match VerifyingKey::from_sec1_bytes(&passkey.public_key) {
+        Ok(verifying_key) => verifying_key,
+        Err(e) => {
+            log::error!(
+                "Stored passkey {} has an invalid public key: {:?}",
+                passkey.id,
+                e
+            );
+            return Err(AuthError::InternalServerError);
+        }
+    };
+    let signature = match DerSignature::try_from(signature) {
+        Ok(signature) => signature,
+        Err(_) => reject!("The passkey signature is not DER encoded"),
+    };
+    let mut signed_data = authenticator_data.to_vec();
+    signed_data.extend_from_slice(&Sha256::digest(client_data_json));
+    if verifying_key.verify(&signed_data, &signature).is_err() {
+        reject!("Invalid signature for passkey {}", passkey.id);
+    }
+
+    let stored_count = passkey.sign_count;
+    let new_count = auth_data.sign_count;
+    if (stored_count != 0 || new_count != 0) && i64::from(new_count) <= stored_count {
+        reject!(
+            "The sign counter of passkey {} went from {} to {}, it may have been cloned",
+            passkey.id,
+            stored_count,
+            new_count
+        );
+    }
+    Ok(new_count)
+}
@@ -1,6 +1,17 @@
+use crate::models::api_token::ApiToken;
+use crate::models::audit_event::AuditEvent;
+use crate::models::blog_draft::BlogDraft;
+use crate::models::link_check::LinkOutcome;
+use crate::models::login_attempt::LoginAttempt;
+use crate::models::media::MediaReference;
+use crate::models::passkey::Passkey;
+use crate::models::role::Role;
+use crate::models::session::Session;
+use crate::templates::admin_profile::{InviteInfo, UserInfo};
 use crate::utils::security::TemplateValues;
 
 use askama::Template;
+use chrono::{DateTime, Utc};
 
 #[derive(Template)]
 #[template(path = "admin/new_blog.html")]
@@ -19,6 +30,9 @@ pub struct EditBlog<'a> {
     pub content: &'a str,
     pub public: bool,
     pub tags: &'a str,
+    pub version: i64,
+    pub draft: Option<&'a BlogDraft>,
+    pub draft_files_json: &'a str,
     pub post_blog_btn_txt: &'a str,
 }
 
@@ -27,6 +41,19 @@ pub struct EditBlog<'a> {
 pub struct Profile {
     pub common: TemplateValues,
     pub has_2fa: bool,
+    pub recovery_codes: Vec<String>,
+    pub recovery_codes_left: usize,
+    pub passkeys: Vec<Passkey>,
+    pub sessions: Vec<Session>,
+    pub current_session_id: String,
+    pub login_attempts: Vec<LoginAttempt>,
+    pub now: DateTime<Utc>,
+    pub role: Role,
+    pub users: Vec<UserInfo>,
+    pub current_user_id: String,
+    pub invites: Vec<InviteInfo>,
+    pub api_tokens: Vec<ApiToken>,
+    pub new_token: Option<String>,
 }
 
 #[derive(Template)]
@@ -36,3 +63,91 @@ pub struct Unlocked;
 #[derive(Template)]
 #[template(path = "components/locked.html")]
 pub struct Locked;
+
+pub struct MediaInfo {
+    pub name: String,
+    pub file_name: String,
+    pub url: String,
+    pub media_type: &'static str,
+    pub is_video: bool,
+    pub size: String,
+    pub uploaded: String,
+    pub alt_text: String,
+    pub posts: Vec<MediaReference>,
+}
+
+#[derive(Template)]
+#[template(path = "admin/media.html")]
+pub struct MediaLibrary {
+    pub common: TemplateValues,
+    pub media_list: Vec<MediaInfo>,
+}
+
+#[derive(Template)]
+#[template(path = "components/media_grid.html")]
+pub struct MediaGrid {
+    pub csrf_header_json: String,
+    pub media_list: Vec<MediaInfo>,
+    pub picker: bool,
+}
+
+pub struct PreviewLinkInfo {
+    pub id: String,
+    pub url: String,
+    pub created: String,
+    pub expiry: String,
+}
+
+#[derive(Template)]
+#[template(path = "components/preview_links.html")]
+pub struct PreviewLinks {
+    pub csrf_header_json: String,
+    pub blog_id: String,
+    pub links: Vec<PreviewLinkInfo>,
+    pub max_days: i64,
+    pub default_days: i64,
+}
+
+pub struct LinkPost {
+    pub blog_id: String,
+    pub title: String,
+}
+
+pub struct LinkInfo {
+    pub url: String,
+    pub outcome: LinkOutcome,
+    pub status: String,
+    pub checked: String,
+    // oldest first
+    pub history: Vec<LinkOutcome>,
+    pub posts: Vec<LinkPost>,
+}
+
+#[derive(Template)]
+#[template(path = "admin/links.html")]
+pub struct LinkReport {
+    pub common: TemplateValues,
+    pub links: Vec<LinkInfo>,
+    pub broken: usize,
+    pub skipped: usize,
+    pub is_running: bool,
+}
+
+pub struct AuditLogEntry {
+    pub event: AuditEvent,
+    // the username of the actor, or their id if the user has been deleted
+    pub actor: String,
+}
+
+#[derive(Template)]
+#[template(path = "admin/audit_log.html")]
+pub struct AuditLog {
+    pub common: TemplateValues,
+    pub entries: Vec<AuditLogEntry>,
+    pub action: String,
+    pub actor: String,
+    pub ip: String,
+    pub filter_query: String,
+    pub page: u64,
+    pub has_next_page: bool,
+}
@@ -1,4 +1,11 @@
+use crate::models::api_token::ApiToken;
+use crate::models::login_attempt::LoginAttempt;
+use crate::models::passkey::Passkey;
+use crate::models::role::Role;
+use crate::models::session::Session;
+
 use askama::Template;
+use chrono::{DateTime, Utc};
 
 #[derive(Template)]
 #[template(path = "components/enable_2fa.html")]
@@ -10,4 +17,78 @@ pub struct Enable2FA {
 #[template(path = "components/disable_2fa.html")]
 pub struct Disable2FA {
     pub csrf_header_json: String,
+    pub recovery_codes: Vec<String>,
+    pub recovery_codes_left: usize,
+}
+
+#[derive(Template)]
+#[template(path = "components/recovery_codes.html")]
+pub struct RecoveryCodes {
+    // only shown once right after they are generated
+    pub recovery_codes: Vec<String>,
+    pub recovery_codes_left: usize,
+}
+
+#[derive(Template)]
+#[template(path = "components/passkey_list.html")]
+pub struct PasskeyList {
+    pub csrf_header_json: String,
+    pub passkeys: Vec<Passkey>,
+}
+
+#[derive(Template)]
+#[template(path = "components/session_list.html")]
+pub struct SessionList {
+    pub csrf_header_json: String,
+    pub sessions: Vec<Session>,
+    pub current_session_id: String,
+}
+
+#[derive(Template)]
+#[template(path = "components/login_attempt_list.html")]
+pub struct LoginAttemptList {
+    pub csrf_header_json: String,
+    pub login_attempts: Vec<LoginAttempt>,
+    pub now: DateTime<Utc>,
+}
+
+pub struct UserInfo {
+    pub id: String,
+    pub username: String,
+    pub email: String,
+    pub role: Role,
+    pub disabled: bool,
+}
+
+#[derive(Template)]
+#[template(path = "components/user_list.html")]
+pub struct UserList {
+    pub csrf_header_json: String,
+    pub users: Vec<UserInfo>,
+    pub current_user_id: String,
+}
+
+pub struct InviteInfo {
+    pub id: String,
+    pub url: String,
+    pub role: Role,
+    // the imported account that the invite activates
+    pub username: Option<String>,
+    pub expiry: String,
+}
+
+#[derive(Template)]
+#[template(path = "components/invite_list.html")]
+pub struct InviteList {
+    pub csrf_header_json: String,
+    pub invites: Vec<InviteInfo>,
+}
+
+#[derive(Template)]
+#[template(path = "components/api_token_list.html")]
+pub struct ApiTokenList {
+    pub csrf_header_json: String,
+    pub api_tokens: Vec<ApiToken>,
+    // only shown once right after it is created
+    pub new_token: Option<String>,
 }
@@ -10,3 +10,13 @@ pub struct Login<'a> {
     pub login_url: &'a str,
     pub client_login_url: &'a str,
 }
+
+#[derive(Template)]
+#[template(path = "auth/invite.html")]
+pub struct Invite<'a> {
+    pub common: TemplateValues,
+    pub token: &'a str,
+    pub role: &'a str,
+    // the imported account to activate instead of creating a new one
+    pub username: Option<&'a str>,
+}
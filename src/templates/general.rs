@@ -146,4 +146,6 @@ pub struct BlogPost<'a> {
     pub content: &'a str,
     pub public: bool,
     pub tags: &'a Vec<String>,
+    pub preview: bool,
+    pub author: Option<&'a str>,
 }
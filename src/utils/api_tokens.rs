@@ -0,0 +1,230 @@
+use crate::constants;
+use crate::database::db::DbClient;
+use crate::errors::auth::AuthError;
+use crate::middleware::auth::UserClaim;
+use crate::models::api_token::{self, ApiToken, NewApiToken, TokenScopes};
+use crate::models::role::Role;
+use crate::security::cf_turnstile::get_ip_addr;
+use crate::security::rate_limiter::{get_ip_key, DefaultLoginRateLimiter};
+use crate::utils::security::generate_random_bytes;
+
+use actix_web::HttpRequest;
+use base64::{engine::general_purpose, Engine as _};
+use bson::oid::ObjectId;
+use futures_util::TryStreamExt;
+use mongodb::bson::doc;
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument};
+use sha2::{Digest, Sha256};
+
+// the prefix and the first few characters of the random part
+const DISPLAYED_PREFIX_LENGTH: usize = constants::API_TOKEN_PREFIX.len() + 6;
+
+#[inline]
+fn hash_api_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Returns the token from the `Authorization: Bearer <token>` header if there is one.
+#[inline]
+pub fn get_bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(actix_web::http::header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+/// Creates a token for the user and returns it, which is the only time it can be seen.
+pub async fn create_api_token(
+    db_client: &DbClient,
+    user_id: ObjectId,
+    data: &NewApiToken,
+) -> Result<String, AuthError> {
+    let name = data.name.trim();
+    if name.is_empty() || name.chars().count() > constants::API_TOKEN_NAME_MAX_LENGTH {
+        return Err(AuthError::InvalidApiTokenName);
+    }
+    let scopes = data.get_scopes();
+    if scopes.is_empty() {
+        return Err(AuthError::MissingApiTokenScope);
+    }
+
+    let collection = db_client.get_api_token_collection();
+    let token_count = collection
+        .count_documents(doc! {api_token::USER_ID_KEY: user_id})
+        .await
+        .map_err(|e| {
+            log::error!("Failed to count user's API tokens: {:?}", e);
+            AuthError::InternalServerError
+        })?;
+    if token_count as usize >= constants::MAX_API_TOKENS {
+        return Err(AuthError::TooManyApiTokens);
+    }
+
+    let token = format!(
+        "{}{}",
+        constants::API_TOKEN_PREFIX,
+        general_purpose::URL_SAFE_NO_PAD.encode(generate_random_bytes(constants::API_TOKEN_BYTES))
+    );
+    let days = data
+        .days
+        .unwrap_or(constants::API_TOKEN_DEFAULT_DAYS)
+        .clamp(1, constants::API_TOKEN_MAX_DAYS);
+    let created = chrono::Utc::now();
+    let api_token = ApiToken {
+        _id: ObjectId::new(),
+        user_id,
+        name: name.to_string(),
+        token_hash: hash_api_token(&token),
+        prefix: token[..DISPLAYED_PREFIX_LENGTH].to_string(),
+        scopes,
+        created,
+        expiry: created + chrono::Duration::days(days),
+        last_used: None,
+        last_used_ip: None,
+        window_start: None,
+        window_requests: 0,
+    };
+    collection.insert_one(&api_token).await.map_err(|e| {
+        log::error!("Failed to insert API token: {:?}", e);
+        AuthError::InternalServerError
+    })?;
+    log::info!("Created API token {} for user {}", api_token._id, user_id);
+    Ok(token)
+}
+
+/// Returns the user's tokens that have not expired with the newest first.
+pub async fn get_api_tokens(
+    db_client: &DbClient,
+    user_id: &ObjectId,
+) -> Result<Vec<ApiToken>, AuthError> {
+    let now = bson::DateTime::now();
+    let options = FindOptions::builder().sort(doc! {"_id": -1}).build();
+    db_client
+        .get_api_token_collection()
+        .find(doc! {api_token::USER_ID_KEY: user_id, api_token::EXPIRY_KEY: {"$gt": now}})
+        .with_options(options)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user's API tokens from database: {:?}", e);
+            AuthError::InternalServerError
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get user's API tokens from database: {:?}", e);
+            AuthError::InternalServerError
+        })
+}
+
+pub async fn revoke_api_token(
+    db_client: &DbClient,
+    user_id: &ObjectId,
+    token_id: &ObjectId,
+) -> Result<(), AuthError> {
+    let result = db_client
+        .get_api_token_collection()
+        .delete_one(doc! {"_id": token_id, api_token::USER_ID_KEY: user_id})
+        .await
+        .map_err(|e| {
+            log::error!("Failed to revoke API token: {:?}", e);
+            AuthError::InternalServerError
+        })?;
+    if result.deleted_count == 0 {
+        return Err(AuthError::ApiTokenNotFound);
+    }
+    log::info!("Revoked API token {} of user {}", token_id, user_id);
+    Ok(())
+}
+
+/// Records the use of the token and counts the request against its rate limit window.
+///
+/// Returns None if the token does not exist or has expired.
+async fn use_api_token(
+    db_client: &DbClient,
+    token: &str,
+    ip_addr: &str,
+) -> Result<Option<ApiToken>, AuthError> {
+    let now = chrono::Utc::now();
+    let window = chrono::Duration::from_std(constants::API_TOKEN_RATE_WINDOW).unwrap();
+    let is_same_window = doc! {"$gt": [
+        format!("${}", api_token::WINDOW_START_KEY),
+        bson::DateTime::from_chrono(now - window),
+    ]};
+    let now = bson::DateTime::from_chrono(now);
+    let update = vec![doc! {"$set": {
+        api_token::WINDOW_REQUESTS_KEY: {"$cond": [
+            is_same_window.clone(),
+            {"$add": [{"$ifNull": [format!("${}", api_token::WINDOW_REQUESTS_KEY), 0_i64]}, 1_i64]},
+            1_i64,
+        ]},
+        api_token::WINDOW_START_KEY: {"$cond": [
+            is_same_window,
+            format!("${}", api_token::WINDOW_START_KEY),
+            now,
+        ]},
+        api_token::LAST_USED_KEY: now,
+        api_token::LAST_USED_IP_KEY: ip_addr,
+    }}];
+    let options = FindOneAndUpdateOptions::builder()
+        .return_document(ReturnDocument::After)
+        .build();
+    db_client
+        .get_api_token_collection()
+        .find_one_and_update(
+            doc! {api_token::TOKEN_HASH_KEY: hash_api_token(token), api_token::EXPIRY_KEY: {"$gt": now}},
+            update,
+        )
+        .with_options(options)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to update API token: {:?}", e);
+            AuthError::InternalServerError
+        })
+}
+
+/// Verifies the bearer token and returns the user claim, role and scopes for the request.
+///
+/// Invalid tokens count as failed logins of the IP address so that
+/// guessing tokens is throttled the same way as guessing passwords.
+pub async fn authenticate_api_token(
+    db_client: &DbClient,
+    limiter: &DefaultLoginRateLimiter,
+    req: &HttpRequest,
+    token: &str,
+) -> Result<(UserClaim, Role, TokenScopes), AuthError> {
+    let ip_addr = get_ip_addr(req);
+    let ip_key = get_ip_key(ip_addr.as_deref().unwrap_or("unknown"));
+    limiter.check(&ip_key).await?;
+
+    let api_token = match use_api_token(db_client, token, &ip_addr.unwrap_or_default()).await? {
+        Some(api_token) => api_token,
+        None => {
+            limiter.record_failure(&ip_key).await?;
+            return Err(AuthError::InvalidApiToken);
+        }
+    };
+    if api_token.window_requests > constants::API_TOKEN_RATE_LIMIT {
+        log::warn!("API token {} exceeded its rate limit", api_token._id);
+        return Err(AuthError::ApiTokenRateLimited);
+    }
+
+    // the role is read on every request so that role changes apply to the existing tokens
+    let user = match db_client.get_user_by_id(&api_token.user_id).await {
+        Ok(user) => user,
+        Err(AuthError::UserNotFound) => return Err(AuthError::InvalidApiToken),
+        Err(e) => return Err(e),
+    };
+    if user.is_disabled() {
+        return Err(AuthError::AccountDisabled);
+    }
+
+    // the token id takes the place of the session id as there is no session
+    let user_claim = UserClaim {
+        session_id: api_token._id,
+        user_id: api_token.user_id,
+        exp: Some(api_token.expiry),
+    };
+    Ok((user_claim, user.get_role(), TokenScopes(api_token.scopes)))
+}
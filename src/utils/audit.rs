@@ -0,0 +1,206 @@
+use crate::constants;
+use crate::database::db::DbClient;
+use crate::errors::auth::AuthError;
+use crate::middleware::auth::UserClaim;
+use crate::middleware::request_id::get_request_id;
+use crate::models::audit_event::{self, AuditAction, AuditEvent, AuditLogQuery, AUDIT_ACTIONS};
+use crate::security::cf_turnstile::get_ip_addr;
+
+use actix_web::{web, HttpMessage, HttpRequest};
+use bson::oid::ObjectId;
+use futures_util::{Stream, TryStreamExt};
+use mongodb::bson::doc;
+use mongodb::options::FindOptions;
+
+/// Creates an event of the request with the signed in user as the actor if there is one.
+pub fn new_audit_event(req: &HttpRequest, action: AuditAction) -> AuditEvent {
+    AuditEvent {
+        _id: ObjectId::new(),
+        action,
+        timestamp: chrono::Utc::now(),
+        actor_id: req
+            .extensions()
+            .get::<UserClaim>()
+            .map(|user_claim| user_claim.user_id),
+        actor_name: None,
+        ip_addr: get_ip_addr(req).unwrap_or_default(),
+        request_id: get_request_id(req),
+        target: None,
+        details: None,
+    }
+}
+
+/// Saves the event to the audit log.
+///
+/// A failure is only logged since the action being audited has already happened.
+pub async fn record_audit_event(db_client: &DbClient, event: AuditEvent) {
+    if let Err(e) = db_client
+        .get_audit_log_collection()
+        .insert_one(&event)
+        .await
+    {
+        log::error!(
+            "Failed to record {} audit event of request {}: {:?}",
+            event.action,
+            event.request_id,
+            e
+        );
+    }
+}
+
+/// Saves the event unless an event of the same action from the same IP address
+/// was saved within the window, so that unauthenticated requests cannot flood the audit log.
+pub async fn record_rate_limited_audit_event(
+    db_client: &DbClient,
+    event: AuditEvent,
+    window: std::time::Duration,
+) {
+    let window =
+        chrono::Duration::from_std(window).expect("window should fit in a chrono duration");
+    let cutoff = bson::DateTime::from_chrono(event.timestamp - window);
+    let filter = doc! {
+        audit_event::ACTION_KEY: event.action.to_string(),
+        audit_event::IP_ADDR_KEY: &event.ip_addr,
+        audit_event::TIMESTAMP_KEY: {"$gte": cutoff},
+    };
+    match db_client.get_audit_log_collection().find_one(filter).await {
+        Ok(Some(_)) => {}
+        Ok(None) => record_audit_event(db_client, event).await,
+        Err(e) => log::error!(
+            "Failed to get {} audit events of request {}: {:?}",
+            event.action,
+            event.request_id,
+            e
+        ),
+    }
+}
+
+#[inline]
+pub fn parse_audit_action(action: &str) -> Option<AuditAction> {
+    AUDIT_ACTIONS
+        .iter()
+        .copied()
+        .find(|audit_action| audit_action.to_string() == action)
+}
+
+/// Converts the filters of the admin view into a query, where the actor is a username, email or user id.
+async fn get_audit_log_filter(
+    db_client: &DbClient,
+    query: &AuditLogQuery,
+) -> Result<bson::Document, AuthError> {
+    let mut filter = doc! {};
+    if let Some(action) = query.action.as_deref().filter(|action| !action.is_empty()) {
+        let action = parse_audit_action(action).ok_or(AuthError::InvalidAuditLogFilter)?;
+        filter.insert(audit_event::ACTION_KEY, action.to_string());
+    }
+
+    if let Some(actor) = query
+        .actor
+        .as_deref()
+        .map(str::trim)
+        .filter(|actor| !actor.is_empty())
+    {
+        let actor_id = match ObjectId::parse_str(actor) {
+            Ok(actor_id) => Some(actor_id),
+            Err(_) => match db_client.get_user_by_username_or_email(actor).await {
+                Ok(user) => Some(user._id),
+                Err(AuthError::UserNotFound) => None,
+                Err(e) => return Err(e),
+            },
+        };
+        // failed logins only have the submitted name as the user is unknown
+        let mut actor_filters = vec![doc! {audit_event::ACTOR_NAME_KEY: actor}];
+        if let Some(actor_id) = actor_id {
+            actor_filters.push(doc! {audit_event::ACTOR_ID_KEY: actor_id});
+        }
+        filter.insert("$or", actor_filters);
+    }
+
+    if let Some(ip_addr) = query
+        .ip
+        .as_deref()
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty())
+    {
+        filter.insert(audit_event::IP_ADDR_KEY, ip_addr);
+    }
+    Ok(filter)
+}
+
+async fn find_audit_events(
+    db_client: &DbClient,
+    filter: bson::Document,
+    options: FindOptions,
+) -> Result<Vec<AuditEvent>, AuthError> {
+    db_client
+        .get_audit_log_collection()
+        .find(filter)
+        .with_options(options)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get audit log from database: {:?}", e);
+            AuthError::InternalServerError
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get audit log from database: {:?}", e);
+            AuthError::InternalServerError
+        })
+}
+
+/// Returns a page of the matching events with the newest first and whether there is a next page.
+pub async fn get_audit_events(
+    db_client: &DbClient,
+    query: &AuditLogQuery,
+) -> Result<(Vec<AuditEvent>, bool), AuthError> {
+    let filter = get_audit_log_filter(db_client, query).await?;
+    let page = query.get_page();
+    // one more event than the page size is fetched to know if there is a next page
+    let options = FindOptions::builder()
+        .sort(doc! {audit_event::TIMESTAMP_KEY: -1})
+        .skip((page - 1) * constants::AUDIT_LOG_PAGE_SIZE)
+        .limit(constants::AUDIT_LOG_PAGE_SIZE as i64 + 1)
+        .build();
+    let mut events = find_audit_events(db_client, filter, options).await?;
+    let has_next_page = events.len() as u64 > constants::AUDIT_LOG_PAGE_SIZE;
+    events.truncate(constants::AUDIT_LOG_PAGE_SIZE as usize);
+    Ok((events, has_next_page))
+}
+
+/// Returns all the matching events in chronological order as a stream of JSON lines
+/// so that the events do not have to be loaded into memory at once.
+pub async fn export_audit_events(
+    db_client: &DbClient,
+    query: &AuditLogQuery,
+) -> Result<impl Stream<Item = Result<web::Bytes, AuthError>>, AuthError> {
+    let filter = get_audit_log_filter(db_client, query).await?;
+    let options = FindOptions::builder()
+        .sort(doc! {audit_event::TIMESTAMP_KEY: 1})
+        .build();
+    let cursor = db_client
+        .get_audit_log_collection()
+        .find(filter)
+        .with_options(options)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get audit log from database: {:?}", e);
+            AuthError::InternalServerError
+        })?;
+
+    // an error after the response has started can only end the download early
+    Ok(cursor
+        .map_err(|e| {
+            log::error!("Failed to get audit log from database: {:?}", e);
+            AuthError::InternalServerError
+        })
+        .and_then(|event| async move {
+            let mut line = serde_json::to_vec(&audit_event::ExportedAuditEvent::from(event))
+                .map_err(|e| {
+                    log::error!("Failed to serialise audit event: {:?}", e);
+                    AuthError::InternalServerError
+                })?;
+            line.push(b'\n');
+            Ok(web::Bytes::from(line))
+        }))
+}
@@ -1,6 +1,12 @@
-use crate::middleware::auth::UserClaim;
+use crate::database::db::DbClient;
+use crate::errors::blog::BlogError;
+use crate::middleware::auth::{get_user_claim, get_user_role, UserClaim};
+use crate::models::blog;
 
 use actix_web::{HttpMessage, HttpRequest};
+use bson::doc;
+use bson::oid::ObjectId;
+use mongodb::options::FindOneOptions;
 
 pub mod cf_turnstile {
     /// Note: Remember to import the necessary modules for the macro to work
@@ -27,3 +33,31 @@ pub fn is_logged_in(req: &HttpRequest) -> bool {
         None => false,
     }
 }
+
+/// Checks that the signed in user can change the blog post.
+///
+/// Authors can only change the blog posts they wrote while the editors and owners can change any.
+pub async fn check_blog_author(
+    client: &DbClient,
+    req: &HttpRequest,
+    blog_id: &ObjectId,
+) -> Result<(), BlogError> {
+    if get_user_role(req).can_edit_any_blog() {
+        return Ok(());
+    }
+
+    let options = FindOneOptions::builder()
+        .projection(doc! {blog::AUTHOR_ID_KEY: 1})
+        .build();
+    let blog = client
+        .get_projected_blog_post(blog_id, Some(options))
+        .await?;
+    if blog.author_id != Some(get_user_claim(req).user_id) {
+        log::warn!(
+            "Denied a change to blog {} by a user who did not write it",
+            blog_id
+        );
+        return Err(BlogError::PermissionDenied);
+    }
+    Ok(())
+}
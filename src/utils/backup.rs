@@ -0,0 +1,884 @@
+use crate::constants;
+use crate::database::db::DbClient;
+use crate::errors::backup::BackupError;
+use crate::errors::blog::BlogError;
+use crate::models::backup_manifest::{BackupManifest, BackupVersion};
+use crate::models::blog::Blog;
+use crate::models::blog_operation::BlogOperationKind;
+use crate::models::media;
+use crate::models::restore_report::{ImportReport, RestoreReport};
+use crate::models::site_export::{
+    ArchiveEntry, ExportedMedia, ExportedUser, SiteManifest, BLOGS_FILE, MANIFEST_FILE, MEDIA_FILE,
+    USERS_FILE,
+};
+use crate::models::user;
+use crate::security::{chacha_crypto, pw_hasher};
+use crate::utils::blog::file_utils::{get_public_url, hash_file_data};
+use crate::utils::blog::operation_utils::BlogOperationJournal;
+use crate::utils::media::{get_alt_texts, get_media_references};
+use crate::utils::security::generate_random_bytes;
+use crate::utils::storage::{self, ConditionalUpload};
+use crate::utils::users::create_invite;
+
+use actix_web::web;
+use aws_sdk_s3 as s3;
+use bson::oid::ObjectId;
+use chrono::{DateTime, Datelike, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, Bson};
+use mongodb::options::{ReplaceOptions, UpdateOptions};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::{Cursor, Read, Write};
+use zip::write::SimpleFileOptions;
+
+/// Serialises the value as relaxed extended JSON so that
+/// ObjectIds and dates survive a round trip through the archive.
+fn to_ext_json<T: Serialize>(value: &T) -> Result<Vec<u8>, BackupError> {
+    let bson = bson::to_bson(value).map_err(|e| {
+        log::error!("Failed to serialise export data: {:?}", e);
+        BackupError::ExportError
+    })?;
+    serde_json::to_vec(&bson.into_relaxed_extjson()).map_err(|e| {
+        log::error!("Failed to serialise export data: {:?}", e);
+        BackupError::ExportError
+    })
+}
+
+fn from_ext_json<T: DeserializeOwned>(data: &[u8]) -> Option<T> {
+    let value: serde_json::Value = serde_json::from_slice(data)
+        .map_err(|e| log::error!("Failed to parse JSON: {:?}", e))
+        .ok()?;
+    let bson = Bson::try_from(value)
+        .map_err(|e| log::error!("Failed to parse extended JSON: {:?}", e))
+        .ok()?;
+    bson::from_bson(bson)
+        .map_err(|e| log::error!("Failed to deserialise extended JSON: {:?}", e))
+        .ok()
+}
+
+// sortable timestamp used as the version of a backup
+const BACKUP_VERSION_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+const BACKUP_OBJ_SUFFIX: &str = ".json.enc";
+const BACKUP_MANIFEST_NAME: &str = "manifest.json.enc";
+
+macro_rules! get_backup_obj_name {
+    ($blog_id:expr, $version:expr) => {
+        format!(
+            "{}/{}/{}{}",
+            constants::BLOG_BACKUP_OBJ_PREFIX,
+            $blog_id,
+            $version,
+            BACKUP_OBJ_SUFFIX
+        )
+    };
+}
+
+macro_rules! get_backup_manifest_obj_name {
+    ($blog_id:expr) => {
+        format!(
+            "{}/{}/{}",
+            constants::BLOG_BACKUP_OBJ_PREFIX,
+            $blog_id,
+            BACKUP_MANIFEST_NAME
+        )
+    };
+}
+
+// backups made before they were versioned were stored unencrypted
+macro_rules! get_legacy_backup_obj_name {
+    ($blog_id:expr) => {
+        format!("{}/{}.json", constants::BLOG_BACKUP_OBJ_PREFIX, $blog_id)
+    };
+}
+
+#[inline]
+fn to_chrono_duration(duration: std::time::Duration) -> chrono::Duration {
+    chrono::Duration::from_std(duration).expect("duration should fit in a chrono duration")
+}
+
+/// Splits the versions into the ones to keep and the ones to prune.
+///
+/// Every backup from [`constants::BACKUP_KEEP_ALL_PERIOD`] is kept, followed by the
+/// newest backup of each day and then of each week within their retention periods.
+/// The newest backup is always kept so that a deleted blog post can be restored.
+fn apply_retention(
+    mut versions: Vec<BackupVersion>,
+    now: DateTime<Utc>,
+) -> (Vec<BackupVersion>, Vec<BackupVersion>) {
+    let keep_all_cutoff = now - to_chrono_duration(constants::BACKUP_KEEP_ALL_PERIOD);
+    let daily_cutoff = now - to_chrono_duration(constants::BACKUP_DAILY_RETENTION);
+    let weekly_cutoff = now - to_chrono_duration(constants::BACKUP_WEEKLY_RETENTION);
+    versions.sort_by_key(|version| std::cmp::Reverse(version.created));
+
+    let mut kept_days = HashSet::new();
+    let mut kept_weeks = HashSet::new();
+    let (mut kept, mut pruned) = (Vec::new(), Vec::new());
+    for (idx, version) in versions.into_iter().enumerate() {
+        let created = version.created;
+        let day = created.date_naive();
+        let week = created.iso_week();
+        let keep = idx == 0
+            || created >= keep_all_cutoff
+            || (created >= daily_cutoff && !kept_days.contains(&day))
+            || (created >= weekly_cutoff && !kept_weeks.contains(&week));
+        if keep {
+            kept_days.insert(day);
+            kept_weeks.insert(week);
+            kept.push(version);
+        } else {
+            pruned.push(version);
+        }
+    }
+    (kept, pruned)
+}
+
+async fn download_backup_manifest(s3_client: &s3::Client, blog_id: &str) -> Option<BackupManifest> {
+    let obj_name = get_backup_manifest_obj_name!(blog_id);
+    let data = storage::download_blob(s3_client, constants::BLOG_BACKUP_BUCKET, &obj_name).await?;
+    let data = chacha_crypto::decrypt_with_db_key(&data)
+        .map_err(|e| log::error!("Failed to decrypt backup manifest: {:?}", e))
+        .ok()?;
+    serde_json::from_slice(&data)
+        .map_err(|e| log::error!("Failed to parse backup manifest: {:?}", e))
+        .ok()
+}
+
+/// Deletes the backups that are no longer retained and removes them from the manifest.
+///
+/// Returns the number of deleted backups.
+async fn prune_backup_versions(
+    s3_client: &s3::Client,
+    manifest: &mut BackupManifest,
+    now: DateTime<Utc>,
+) -> usize {
+    let versions = std::mem::take(&mut manifest.versions);
+    let (mut kept, pruned) = apply_retention(versions, now);
+    let mut deleted = 0;
+    for version in pruned {
+        let obj_name = get_backup_obj_name!(manifest.blog_id, version.version);
+        if storage::delete_blob(s3_client, constants::BLOG_BACKUP_BUCKET, &obj_name).await {
+            deleted += 1;
+        } else {
+            // keep it in the manifest so that it is pruned again next time
+            log::error!("Failed to delete expired blog backup, {}", obj_name);
+            kept.push(version);
+        }
+    }
+    kept.sort_by_key(|version| std::cmp::Reverse(version.created));
+    manifest.versions = kept;
+    deleted
+}
+
+/// Reads the backup manifest with its ETag, or a new manifest if the blog post has no backups yet.
+async fn download_backup_manifest_for_update(
+    s3_client: &s3::Client,
+    blog_id: &str,
+) -> Option<(BackupManifest, Option<String>)> {
+    let obj_name = get_backup_manifest_obj_name!(blog_id);
+    let Some((data, etag)) =
+        storage::download_blob_with_etag(s3_client, constants::BLOG_BACKUP_BUCKET, &obj_name)
+            .await
+            .ok()?
+    else {
+        let manifest = BackupManifest {
+            blog_id: blog_id.to_string(),
+            ..Default::default()
+        };
+        return Some((manifest, None));
+    };
+    // an unreadable manifest is never overwritten as that would lose track of the older backups
+    let data = chacha_crypto::decrypt_with_db_key(&data)
+        .map_err(|e| log::error!("Failed to decrypt backup manifest: {:?}", e))
+        .ok()?;
+    let manifest = serde_json::from_slice(&data)
+        .map_err(|e| log::error!("Failed to parse backup manifest: {:?}", e))
+        .ok()?;
+    Some((manifest, Some(etag)))
+}
+
+/// Applies the change to the backup manifest of the blog post and prunes its expired backups.
+///
+/// The manifest is written only if it was not changed since it was read, otherwise
+/// the change is applied again to the newer manifest so that concurrent backups
+/// do not drop each other's versions.
+///
+/// Returns the number of deleted backups or `None` if the manifest could not be updated.
+async fn update_backup_manifest<F>(
+    s3_client: &s3::Client,
+    blog_id: &str,
+    now: DateTime<Utc>,
+    change: F,
+) -> Option<usize>
+where
+    F: Fn(&mut BackupManifest) -> bool,
+{
+    let obj_name = get_backup_manifest_obj_name!(blog_id);
+    for _ in 0..constants::BACKUP_MANIFEST_MAX_ATTEMPTS {
+        let (mut manifest, etag) = download_backup_manifest_for_update(s3_client, blog_id).await?;
+        let is_changed = change(&mut manifest);
+        let pruned = prune_backup_versions(s3_client, &mut manifest, now).await;
+        if !is_changed && pruned == 0 {
+            return Some(0);
+        }
+
+        let data =
+            serde_json::to_vec(&manifest).expect("Should be able to serialise backup manifest");
+        let Ok(data) = chacha_crypto::encrypt_with_db_key(&data) else {
+            log::error!("Failed to encrypt backup manifest");
+            return None;
+        };
+        match storage::upload_blob_if_match(
+            s3_client,
+            constants::BLOG_BACKUP_BUCKET,
+            &obj_name,
+            data,
+            etag.as_deref(),
+        )
+        .await
+        {
+            ConditionalUpload::Uploaded => return Some(pruned),
+            ConditionalUpload::PreconditionFailed => {
+                log::warn!("Backup manifest of blog {} changed, retrying", blog_id);
+            }
+            ConditionalUpload::Failed => return None,
+        }
+    }
+    log::error!("Gave up updating the backup manifest of blog {}", blog_id);
+    None
+}
+
+/// Stores a new encrypted backup of the blog post and prunes
+/// its older backups according to the retention policy.
+pub async fn back_up_blog(s3_client: &s3::Client, blog: &Blog) {
+    let blog_id = blog.get_id_string();
+    let data = serde_json::to_vec(blog).expect("Should be able to serialise blog");
+    let now = Utc::now();
+    let version = BackupVersion {
+        version: now.format(BACKUP_VERSION_FORMAT).to_string(),
+        created: now,
+        size: data.len(),
+        sha256: hash_file_data(&data),
+        blog_version: blog.version,
+    };
+    let Ok(data) = chacha_crypto::encrypt_with_db_key(&data) else {
+        log::error!("Failed to encrypt blog backup");
+        return;
+    };
+    let obj_name = get_backup_obj_name!(blog_id, version.version);
+    if !storage::upload_blob(s3_client, constants::BLOG_BACKUP_BUCKET, &obj_name, data).await {
+        log::error!("Failed to back up blog");
+        return;
+    }
+
+    let change = |manifest: &mut BackupManifest| {
+        manifest.title = blog.title.clone();
+        if !manifest
+            .versions
+            .iter()
+            .any(|v| v.version == version.version)
+        {
+            manifest.versions.insert(0, version.clone());
+        }
+        true
+    };
+    if update_backup_manifest(s3_client, &blog_id, now, change)
+        .await
+        .is_none()
+    {
+        log::error!("Failed to update backup manifest of blog {}", blog_id);
+    }
+}
+
+/// Returns the manifest listing the backups of the blog post.
+pub async fn get_blog_backups(
+    s3_client: &s3::Client,
+    blog_id: &ObjectId,
+) -> Result<BackupManifest, BackupError> {
+    download_backup_manifest(s3_client, &blog_id.to_hex())
+        .await
+        .ok_or(BackupError::BackupNotFound)
+}
+
+/// Returns the newest backup of the blog post at the given blog version, if it is still retained.
+pub async fn get_blog_backup_at_version(
+    s3_client: &s3::Client,
+    blog_id: &ObjectId,
+    blog_version: i64,
+) -> Option<Blog> {
+    let blog_id = blog_id.to_hex();
+    let manifest = download_backup_manifest(s3_client, &blog_id).await?;
+    let backup = manifest
+        .versions
+        .iter()
+        .find(|version| version.blog_version == blog_version)?;
+    let data = download_blog_backup(s3_client, &blog_id, Some(&backup.version))
+        .await
+        .ok()?;
+    from_ext_json(&data)
+}
+
+/// Downloads and decrypts the given backup version or the newest backup if no version is given.
+async fn download_blog_backup(
+    s3_client: &s3::Client,
+    blog_id: &str,
+    version: Option<&str>,
+) -> Result<Vec<u8>, BackupError> {
+    let Some(manifest) = download_backup_manifest(s3_client, blog_id).await else {
+        if version.is_some() {
+            return Err(BackupError::BackupNotFound);
+        }
+        let obj_name = get_legacy_backup_obj_name!(blog_id);
+        return storage::download_blob(s3_client, constants::BLOG_BACKUP_BUCKET, &obj_name)
+            .await
+            .ok_or(BackupError::BackupNotFound);
+    };
+
+    let backup = match version {
+        Some(version) => manifest.versions.iter().find(|v| v.version == version),
+        None => manifest.versions.first(),
+    }
+    .ok_or(BackupError::BackupNotFound)?;
+    let obj_name = get_backup_obj_name!(blog_id, backup.version);
+    let data = storage::download_blob(s3_client, constants::BLOG_BACKUP_BUCKET, &obj_name)
+        .await
+        .ok_or(BackupError::BackupNotFound)?;
+    let data = chacha_crypto::decrypt_with_db_key(&data).map_err(|e| {
+        log::error!("Failed to decrypt blog backup: {:?}", e);
+        BackupError::InvalidBackup
+    })?;
+    if hash_file_data(&data) != backup.sha256 {
+        log::error!("Checksum mismatch for blog backup, {}", obj_name);
+        return Err(BackupError::InvalidBackup);
+    }
+    Ok(data)
+}
+
+/// Restores a blog post from a backup in the private bucket,
+/// recreating it if it was deleted from the database.
+///
+/// The newest backup is restored if no version is given.
+///
+/// The media of a deleted blog post is deleted once no other blog post uses it,
+/// so the files of the backup that no longer exist are left out of the restored
+/// blog post and their URLs are returned instead.
+pub async fn restore_blog(
+    db_client: &DbClient,
+    s3_client: &s3::Client,
+    blog_id: &ObjectId,
+    version: Option<&str>,
+) -> Result<Vec<String>, BackupError> {
+    let data = download_blog_backup(s3_client, &blog_id.to_hex(), version).await?;
+    let mut blog: Blog = from_ext_json(&data).ok_or(BackupError::InvalidBackup)?;
+    if blog.id != *blog_id {
+        log::error!("Backup of blog {} contains blog {}", blog_id, blog.id);
+        return Err(BackupError::InvalidBackup);
+    }
+
+    // bump the version so that editors opened before the restore cannot overwrite it
+    let current_files = match db_client.get_blog_post(blog_id, None).await {
+        Ok(current) => {
+            blog.version = current.version.max(blog.version) + 1;
+            current.files
+        }
+        Err(BlogError::BlogNotFound) => vec![],
+        Err(_) => return Err(BackupError::InternalServerError),
+    };
+
+    let mut journal =
+        BlogOperationJournal::begin(db_client, s3_client, *blog_id, BlogOperationKind::Update)
+            .await
+            .map_err(|_| BackupError::InternalServerError)?;
+    let mut missing_files = Vec::new();
+    for file in blog.files.iter() {
+        let (bucket, obj_name) = storage::extract_bucket_and_blob_from_url(&file.url);
+        let exists = if bucket == constants::BUCKET {
+            journal.reference_media(&obj_name).await
+        } else {
+            Ok(storage::blob_exists(s3_client, &bucket, &obj_name).await)
+        };
+        match exists {
+            Ok(true) => {}
+            Ok(false) => missing_files.push(file.url.clone()),
+            Err(e) => {
+                log::error!("Failed to reference restored file {}: {}", file.url, e);
+                journal.rollback().await;
+                return Err(BackupError::InternalServerError);
+            }
+        }
+    }
+    blog.files.retain(|file| !missing_files.contains(&file.url));
+
+    // the files that only the current blog post uses are deleted like on an update
+    for file in current_files.iter() {
+        if blog.files.iter().any(|restored| restored.url == file.url) {
+            continue;
+        }
+        if journal.release_file(&file.url).await.is_err() {
+            journal.rollback().await;
+            return Err(BackupError::InternalServerError);
+        }
+    }
+
+    let options = ReplaceOptions::builder().upsert(true).build();
+    if let Err(e) = db_client
+        .get_blog_collection()
+        .replace_one(doc! {"_id": blog_id}, &blog)
+        .with_options(options)
+        .await
+    {
+        log::error!("Failed to restore blog: {:?}", e);
+        journal.rollback().await;
+        return Err(BackupError::InternalServerError);
+    }
+    journal.commit().await;
+    if !missing_files.is_empty() {
+        log::warn!(
+            "Restored blog {} without {} missing file(s)",
+            blog_id,
+            missing_files.len()
+        );
+    }
+    Ok(missing_files)
+}
+
+/// Lists the IDs of every blog post with at least one backup.
+async fn get_backed_up_blog_ids(s3_client: &s3::Client) -> Option<BTreeSet<ObjectId>> {
+    let prefix = format!("{}/", constants::BLOG_BACKUP_OBJ_PREFIX);
+    let manifest_suffix = format!("/{}", BACKUP_MANIFEST_NAME);
+    let blobs = storage::list_blobs(s3_client, constants::BLOG_BACKUP_BUCKET, &prefix).await?;
+    let blog_ids = blobs
+        .iter()
+        .filter_map(|blob| {
+            let name = blob.name.strip_prefix(&prefix)?;
+            let blog_id = name
+                .strip_suffix(&manifest_suffix)
+                .or_else(|| name.strip_suffix(".json"))?;
+            ObjectId::parse_str(blog_id).ok()
+        })
+        .collect();
+    Some(blog_ids)
+}
+
+/// Restores every blog post from its newest backup in the private bucket.
+pub async fn restore_all_blogs(
+    db_client: &DbClient,
+    s3_client: &s3::Client,
+) -> Result<RestoreReport, BackupError> {
+    let blog_ids = get_backed_up_blog_ids(s3_client)
+        .await
+        .ok_or(BackupError::InternalServerError)?;
+
+    let mut report = RestoreReport::default();
+    for blog_id in blog_ids {
+        match restore_blog(db_client, s3_client, &blog_id, None).await {
+            Ok(missing_files) => {
+                report.restored.push(blog_id.to_hex());
+                report.missing_files.extend(missing_files);
+            }
+            Err(e) => {
+                log::error!("Failed to restore blog {}: {}", blog_id, e);
+                report.failed.push(blog_id.to_hex());
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Applies the retention policy to the backups of every blog post,
+/// including deleted blog posts that will no longer be backed up.
+///
+/// Returns the number of deleted backups or `None` if the bucket could not be listed.
+pub async fn prune_all_blog_backups(s3_client: &s3::Client) -> Option<usize> {
+    let blog_ids = get_backed_up_blog_ids(s3_client).await?;
+    let now = Utc::now();
+    let mut deleted = 0;
+    for blog_id in blog_ids {
+        match update_backup_manifest(s3_client, &blog_id.to_hex(), now, |_| false).await {
+            Some(pruned) => deleted += pruned,
+            None => log::error!("Failed to update backup manifest of blog {}", blog_id),
+        }
+    }
+    Some(deleted)
+}
+
+async fn get_export_entries(
+    db_client: &DbClient,
+    s3_client: &s3::Client,
+) -> Result<Vec<(&'static str, Vec<u8>)>, BackupError> {
+    let blogs = db_client
+        .get_blog_collection()
+        .find(doc! {})
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blogs from database: {:?}", e);
+            BackupError::ExportError
+        })?
+        .try_collect::<Vec<_>>()
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blogs from database: {:?}", e);
+            BackupError::ExportError
+        })?;
+
+    let user_col = db_client.get_custom_collection::<ExportedUser>(constants::USER_COLLECTION);
+    let options = mongodb::options::FindOptions::builder()
+        .projection(doc! {user::USERNAME_KEY: 1, user::EMAIL_KEY: 1, user::ROLE_KEY: 1, user::DISABLED_KEY: 1})
+        .build();
+    let users = user_col
+        .find(doc! {})
+        .with_options(options)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get users from database: {:?}", e);
+            BackupError::ExportError
+        })?
+        .try_collect::<Vec<_>>()
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get users from database: {:?}", e);
+            BackupError::ExportError
+        })?;
+
+    let prefix = format!("{}/", constants::get_blog_obj_prefix());
+    let (blobs, references, alt_texts) = tokio::join!(
+        storage::list_blobs(s3_client, constants::BUCKET, &prefix),
+        get_media_references(db_client),
+        get_alt_texts(db_client),
+    );
+    let blobs = blobs.ok_or(BackupError::ExportError)?;
+    let mut references = references.map_err(|_| BackupError::ExportError)?;
+    let mut alt_texts = alt_texts.map_err(|_| BackupError::ExportError)?;
+    let media = blobs
+        .into_iter()
+        .map(|blob| ExportedMedia {
+            url: get_public_url(&blob.name),
+            size: blob.size,
+            last_modified: blob.last_modified.map(|dt| dt.to_rfc3339()),
+            alt_text: alt_texts.remove(&blob.name).unwrap_or_default(),
+            blog_ids: references
+                .remove(&blob.name)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|reference| reference.blog_id)
+                .collect(),
+            name: blob.name,
+        })
+        .collect::<Vec<_>>();
+
+    Ok(vec![
+        (BLOGS_FILE, to_ext_json(&blogs)?),
+        (USERS_FILE, to_ext_json(&users)?),
+        (MEDIA_FILE, to_ext_json(&media)?),
+    ])
+}
+
+/// Exports the blog posts, users (without secrets) and the media
+/// metadata into a zip archive with a checksummed manifest.
+///
+/// Sessions are not exported and media files are referenced by
+/// their object name since they are already stored in the bucket.
+pub async fn export_site(
+    db_client: &DbClient,
+    s3_client: &s3::Client,
+) -> Result<Vec<u8>, BackupError> {
+    let entries = get_export_entries(db_client, s3_client).await?;
+    let manifest = SiteManifest {
+        version: constants::SITE_EXPORT_VERSION,
+        created: Utc::now().to_rfc3339(),
+        entries: entries
+            .iter()
+            .map(|(name, data)| ArchiveEntry {
+                name: name.to_string(),
+                sha256: hash_file_data(data),
+                size: data.len(),
+            })
+            .collect(),
+    };
+    let manifest = serde_json::to_vec_pretty(&manifest).map_err(|e| {
+        log::error!("Failed to serialise manifest: {:?}", e);
+        BackupError::ExportError
+    })?;
+
+    let write_archive = || -> zip::result::ZipResult<Vec<u8>> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        zip.start_file(MANIFEST_FILE, options)?;
+        zip.write_all(&manifest)?;
+        for (name, data) in entries.iter() {
+            zip.start_file(*name, options)?;
+            zip.write_all(data)?;
+        }
+        Ok(zip.finish()?.into_inner())
+    };
+    write_archive().map_err(|e| {
+        log::error!("Failed to write export archive: {:?}", e);
+        BackupError::ExportError
+    })
+}
+
+/// Reads a file of an archive without decompressing more than
+/// the size limit of a file or the remaining `budget` of the whole archive.
+///
+/// Archives over either limit are rejected instead of being read partially.
+pub fn read_archive_entry(
+    file: impl Read,
+    name: &str,
+    budget: &mut u64,
+) -> Result<Vec<u8>, BackupError> {
+    let limit = constants::MAX_ARCHIVE_ENTRY_SIZE.min(*budget);
+    let mut data = Vec::new();
+    // read one more byte than allowed to know whether the file is over the limit
+    file.take(limit + 1).read_to_end(&mut data).map_err(|e| {
+        log::error!("Failed to read {} from archive: {:?}", name, e);
+        BackupError::InvalidArchive
+    })?;
+    let size = data.len() as u64;
+    if size > limit {
+        log::warn!("{} in archive is over the size limit", name);
+        return Err(BackupError::InvalidArchive);
+    }
+    *budget -= size;
+    Ok(data)
+}
+
+fn read_archive_file(
+    archive: &mut zip::ZipArchive<Cursor<Vec<u8>>>,
+    name: &str,
+    budget: &mut u64,
+) -> Result<Vec<u8>, BackupError> {
+    let file = archive.by_name(name).map_err(|e| {
+        log::error!("Failed to read {} from archive: {:?}", name, e);
+        BackupError::InvalidArchive
+    })?;
+    read_archive_entry(file, name, budget)
+}
+
+/// Reads the archive and verifies every file against the manifest.
+fn read_archive(archive: Vec<u8>) -> Result<HashMap<String, Vec<u8>>, BackupError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(archive)).map_err(|e| {
+        log::error!("Failed to open import archive: {:?}", e);
+        BackupError::InvalidArchive
+    })?;
+    if archive.len() > constants::MAX_ARCHIVE_ENTRIES {
+        log::warn!("Import archive has too many files");
+        return Err(BackupError::InvalidArchive);
+    }
+    let mut budget = constants::MAX_ARCHIVE_TOTAL_SIZE;
+    let manifest = read_archive_file(&mut archive, MANIFEST_FILE, &mut budget)?;
+    let manifest: SiteManifest =
+        serde_json::from_slice(&manifest).map_err(|_| BackupError::InvalidArchive)?;
+    if !(constants::MIN_SITE_EXPORT_VERSION..=constants::SITE_EXPORT_VERSION)
+        .contains(&manifest.version)
+    {
+        return Err(BackupError::UnsupportedVersion);
+    }
+
+    if manifest.entries.len() > constants::MAX_ARCHIVE_ENTRIES {
+        log::warn!("Import archive manifest has too many entries");
+        return Err(BackupError::InvalidArchive);
+    }
+
+    let mut files = HashMap::with_capacity(manifest.entries.len());
+    for entry in manifest.entries {
+        let data = read_archive_file(&mut archive, &entry.name, &mut budget)?;
+        if data.len() != entry.size || hash_file_data(&data) != entry.sha256 {
+            log::warn!("Checksum mismatch for {} in import archive", entry.name);
+            return Err(BackupError::ChecksumMismatch);
+        }
+        files.insert(entry.name, data);
+    }
+    Ok(files)
+}
+
+async fn import_users(
+    db_client: &DbClient,
+    users: Vec<ExportedUser>,
+    imported_by: Option<ObjectId>,
+    report: &mut ImportReport,
+) -> Result<(), BackupError> {
+    let user_col = db_client.get_user_collection();
+    for exported_user in users {
+        let exists = user_col
+            .count_documents(doc! {"_id": exported_user.id})
+            .await
+            .map_err(|e| {
+                log::error!("Failed to get user from database: {:?}", e);
+                BackupError::InternalServerError
+            })?
+            > 0;
+
+        let mut update = doc! {
+            "$set": {
+                user::USERNAME_KEY: &exported_user.username,
+                user::EMAIL_KEY: &exported_user.email,
+            },
+        };
+        if !exists {
+            // passwords are never exported, so new users get an
+            // unusable random password until it is reset by an admin
+            let password = hex::encode(generate_random_bytes(32));
+            let hashed_password = web::block(move || pw_hasher::hash_password(&password).ok())
+                .await
+                .map_err(|_| BackupError::InternalServerError)?
+                .ok_or(BackupError::InternalServerError)?;
+            update.insert(
+                "$setOnInsert",
+                // the role and disabled flag of existing users are never changed
+                // by an import so that an archive cannot grant more access.
+                // New users stay disabled until they choose a password from their invite.
+                doc! {
+                    user::PASSWORD_KEY: hashed_password,
+                    user::TOTP_SECRET_KEY: Bson::Null,
+                    user::ROLE_KEY: exported_user.role.to_string(),
+                    user::DISABLED_KEY: true,
+                },
+            );
+        }
+
+        let options = UpdateOptions::builder().upsert(true).build();
+        match user_col
+            .update_one(doc! {"_id": exported_user.id}, update)
+            .with_options(options)
+            .await
+        {
+            Ok(_) if exists => report.users_updated += 1,
+            Ok(_) => {
+                report.users_created += 1;
+                // the exported users that were disabled do not get an invite to activate them
+                if exported_user.disabled {
+                    continue;
+                }
+                // the command line has no signed in user to record as the creator
+                let created_by = imported_by.unwrap_or(exported_user.id);
+                match create_invite(
+                    db_client,
+                    created_by,
+                    exported_user.role,
+                    constants::INVITE_MAX_DAYS,
+                    Some(exported_user.id),
+                )
+                .await
+                {
+                    Ok(_) => report.pending_invites.push(exported_user.username),
+                    Err(e) => log::error!(
+                        "Failed to create invite for imported user {}: {}",
+                        exported_user.username,
+                        e
+                    ),
+                }
+            }
+            Err(e) => {
+                // most likely a username or email that belongs to another user
+                log::warn!("Failed to import user {}: {:?}", exported_user.username, e);
+                report.skipped_users.push(exported_user.username);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Imports an archive created by [`export_site`].
+///
+/// Existing blog posts and users with the same IDs are overwritten, but the
+/// passwords, 2FA secrets, roles and disabled flags of existing users are kept.
+///
+/// Since passwords are never exported, new users are created disabled with an invite
+/// that activates their account once they choose a password. The invites are listed
+/// on the users page and the users that need one are in the report.
+pub async fn import_site(
+    db_client: &DbClient,
+    s3_client: &s3::Client,
+    archive: Vec<u8>,
+    imported_by: Option<ObjectId>,
+) -> Result<ImportReport, BackupError> {
+    let mut files = web::block(move || read_archive(archive))
+        .await
+        .map_err(|_| BackupError::InternalServerError)??;
+    let mut take_file = |name: &str| files.remove(name).ok_or(BackupError::InvalidArchive);
+    let blogs: Vec<Blog> =
+        from_ext_json(&take_file(BLOGS_FILE)?).ok_or(BackupError::InvalidArchive)?;
+    let users: Vec<ExportedUser> =
+        from_ext_json(&take_file(USERS_FILE)?).ok_or(BackupError::InvalidArchive)?;
+    let media_list: Vec<ExportedMedia> =
+        from_ext_json(&take_file(MEDIA_FILE)?).ok_or(BackupError::InvalidArchive)?;
+
+    let mut report = ImportReport::default();
+    let blog_col = db_client.get_blog_collection();
+    for mut blog in blogs {
+        // bump the version so that editors opened before the import cannot overwrite it
+        match db_client.get_blog_post(&blog.id, None).await {
+            Ok(current) => blog.version = current.version.max(blog.version) + 1,
+            Err(BlogError::BlogNotFound) => {}
+            Err(_) => return Err(BackupError::InternalServerError),
+        }
+        let options = ReplaceOptions::builder().upsert(true).build();
+        blog_col
+            .replace_one(doc! {"_id": blog.id}, &blog)
+            .with_options(options)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to import blog: {:?}", e);
+                BackupError::InternalServerError
+            })?;
+        report.blogs += 1;
+    }
+
+    import_users(db_client, users, imported_by, &mut report).await?;
+
+    let media_col = db_client.get_media_collection();
+    for exported_media in media_list {
+        if !storage::blob_exists(s3_client, constants::BUCKET, &exported_media.name).await {
+            report.missing_media.push(exported_media.name.clone());
+        }
+        if !exported_media.alt_text.is_empty() {
+            let options = UpdateOptions::builder().upsert(true).build();
+            media_col
+                .update_one(
+                    doc! {"_id": &exported_media.name},
+                    doc! {
+                        "$set": {
+                            media::ALT_TEXT_KEY: &exported_media.alt_text,
+                            media::LAST_MODIFIED_KEY: bson::DateTime::now(),
+                        },
+                    },
+                )
+                .with_options(options)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to import media metadata: {:?}", e);
+                    BackupError::InternalServerError
+                })?;
+        }
+        report.media += 1;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archive_entries_within_the_budget_are_read() {
+        let mut budget = 10;
+        let data = read_archive_entry(&b"hello"[..], "a.txt", &mut budget).unwrap();
+        assert_eq!(data, b"hello");
+        assert_eq!(budget, 5);
+        let data = read_archive_entry(&b"world"[..], "b.txt", &mut budget).unwrap();
+        assert_eq!(data, b"world");
+        assert_eq!(budget, 0);
+    }
+
+    #[test]
+    fn archive_entries_over_the_budget_are_rejected() {
+        let mut budget = 4;
+        assert!(matches!(
+            read_archive_entry(&b"hello"[..], "a.txt", &mut budget),
+            Err(BackupError::InvalidArchive)
+        ));
+        assert_eq!(budget, 4);
+    }
+}
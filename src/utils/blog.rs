@@ -2,30 +2,43 @@ pub mod file_utils {
     use crate::constants;
     use crate::errors::blog::BlogError;
     use crate::models::file_info::FileInfo;
+    use crate::utils::blog::operation_utils::BlogOperationJournal;
     use crate::utils::storage;
-    use aws_sdk_s3 as s3;
-    use bson::oid::ObjectId;
+    use sha2::{Digest, Sha256};
 
     #[inline]
-    fn change_obj_prefix(obj: &str, blog_id: &str, old_prefix: &str, new_prefix: &str) -> String {
-        if !obj.starts_with(old_prefix) {
-            log::error!("Object doesn't start with the old prefix");
-            return obj.to_string();
-        }
-        let obj_without_prefix = &obj[old_prefix.len()..];
-        format!("{}/{}{}", new_prefix, blog_id, obj_without_prefix)
+    pub fn hash_file_data(data: &[u8]) -> String {
+        hex::encode(Sha256::digest(data))
     }
 
-    macro_rules! delete_blob {
-        ($client:expr, $file_url:expr) => {
-            let (bucket, obj_name) = storage::extract_bucket_and_blob_from_url($file_url);
-            if bucket.is_empty() || obj_name.is_empty() {
-                return Err(BlogError::InternalServerError);
-            }
-            if !storage::delete_blob($client, &bucket, &obj_name).await {
-                return Err(BlogError::InternalServerError);
-            }
+    /// Media is stored once in the public bucket under its SHA-256 digest
+    /// so that the same file embedded in several blog posts is only stored once.
+    #[inline]
+    pub fn get_media_obj_name(file_name: &str) -> String {
+        format!(
+            "{}/{}/{}",
+            constants::get_blog_obj_prefix(),
+            constants::MEDIA_OBJ_PREFIX,
+            file_name
+        )
+    }
+
+    /// Returns whether the file name is a SHA-256 digest followed by the file extension
+    /// like the names given to the media by [`get_media_obj_name`].
+    pub fn is_media_file_name(file_name: &str) -> bool {
+        let Some((digest, ext)) = file_name.split_once('.') else {
+            return false;
         };
+        digest.len() == 64
+            && digest
+                .chars()
+                .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+            && ext.chars().all(|c| c.is_ascii_alphanumeric())
+    }
+
+    #[inline]
+    pub fn get_public_url(obj_name: &str) -> String {
+        format!("{}/{}", constants::PUBLIC_S3_URL, obj_name)
     }
 
     macro_rules! move_blob {
@@ -52,41 +65,11 @@ pub mod file_utils {
         };
     }
 
-    macro_rules! get_blog_backup_obj_name {
-        ($blog_id:expr) => {
-            format!("{}/{}.json", constants::BLOG_BACKUP_OBJ_PREFIX, $blog_id)
-        };
-    }
-
-    #[inline]
-    pub async fn back_up_blog(s3_client: &s3::Client, blog: &crate::models::blog::Blog) {
-        let blog_id = blog.get_id_string();
-        let blog_bucket = constants::BLOG_BACKUP_BUCKET;
-        let obj_name = get_blog_backup_obj_name!(blog_id);
-        let data = serde_json::to_string(blog)
-            .expect("Should be able to serialise blog")
-            .as_bytes()
-            .to_vec();
-        if !storage::upload_blob(s3_client, &blog_bucket, &obj_name, data).await {
-            log::error!("Failed to back up blog");
-        }
-    }
-
-    #[inline]
-    pub async fn delete_blog_backup(s3_client: &s3::Client, blog_id: &ObjectId) {
-        let blog_id = blog_id.to_hex();
-        let blog_bucket = constants::BLOG_BACKUP_BUCKET;
-        let obj_name = get_blog_backup_obj_name!(blog_id);
-        if !storage::delete_blob(s3_client, &blog_bucket, &obj_name).await {
-            log::error!("Failed to delete blog backup");
-        }
-    }
-
     pub async fn process_file_logic(
         blog_id: &str,
         file: &mut FileInfo,
         content: &mut String,
-        s3_client: &s3::Client,
+        journal: &mut BlogOperationJournal<'_>,
     ) -> Result<(), BlogError> {
         if file.url.is_empty() {
             return Err(BlogError::FileIsEmpty);
@@ -110,19 +93,31 @@ pub mod file_utils {
         if bucket.is_empty() || obj_name.is_empty() {
             return Err(BlogError::InternalServerError);
         }
+        // the url is sent by the client so only media stored by the upload endpoint is accepted
+        let file_name = match obj_name.rsplit_once('/') {
+            Some((_, file_name)) if is_media_file_name(file_name) => file_name,
+            _ => return Err(BlogError::InvalidFileUrl),
+        };
+        if bucket == constants::BUCKET {
+            // the uploaded file was already stored by another blog post
+            // so the signed url is the public url of the media itself.
+            if obj_name != get_media_obj_name(file_name)
+                || !journal.reference_media(&obj_name).await?
+            {
+                return Err(BlogError::InvalidFileUrl);
+            }
+            file.signed_url = None;
+            return Ok(());
+        }
+        if bucket != constants::BUCKET_FOR_TEMP
+            || obj_name != format!("{}/{}", constants::TEMP_OBJ_PREFIX, file_name)
+        {
+            return Err(BlogError::InvalidFileUrl);
+        }
 
         // replace the signed url with the actual url
-        let obj_name_with_changed_prefix = &change_obj_prefix(
-            &obj_name,
-            blog_id,
-            constants::TEMP_OBJ_PREFIX,
-            &constants::get_blog_obj_prefix(),
-        );
-        let new_url = format!(
-            "{}/{}",
-            constants::PUBLIC_S3_URL,
-            obj_name_with_changed_prefix,
-        );
+        let media_obj_name = &get_media_obj_name(file_name);
+        let new_url = get_public_url(media_obj_name);
         let signed_url_idx = match content.find(signed_url) {
             Some(idx) => idx,
             None => {
@@ -134,13 +129,12 @@ pub mod file_utils {
         file.signed_url = None;
         file.url = new_url;
 
-        move_blob!(
-            &s3_client,
-            &bucket,
-            &obj_name,
-            constants::BUCKET,
-            obj_name_with_changed_prefix
-        );
+        if journal
+            .store_media(&bucket, &obj_name, media_obj_name)
+            .await?
+        {
+            log::info!("Blog post {} stored new media, {}", blog_id, media_obj_name);
+        }
         return Ok(());
     }
 
@@ -148,20 +142,21 @@ pub mod file_utils {
     ///
     /// If the file is not used in the content, it will not be processed.
     ///
-    /// If the file is not found in the temp bucket, an error will be returned.
+    /// If the file is not found in the temp bucket, the journal is rolled back and an error will be returned.
     ///
     /// Sample usage:
     /// ```rust
     /// use crate::utils::blog::file_utils;
     /// use crate::utils::blog::file_utils::process_file_logic;
     ///
-    /// process_file!(blog_id, file, content, s3_client);
+    /// process_file!(blog_id, file, content, journal);
     /// ```
     macro_rules! process_file {
-        ($blog_id:expr, $file:expr, $content:expr, $s3_client:expr) => {
-            match process_file_logic($blog_id, $file, $content, $s3_client).await {
+        ($blog_id:expr, $file:expr, $content:expr, $journal:ident) => {
+            match process_file_logic($blog_id, $file, $content, &mut $journal).await {
                 Ok(_) => {}
                 Err(err) => {
+                    $journal.rollback().await;
                     return Err(err);
                 }
             }
@@ -169,7 +164,420 @@ pub mod file_utils {
     }
 
     // thanks to https://stackoverflow.com/questions/26731243/how-do-i-use-a-macro-across-module-files
-    pub(crate) use {delete_blob, move_blob, process_file, upload_blob};
+    pub(crate) use {move_blob, process_file, upload_blob};
+}
+
+pub mod operation_utils {
+    use crate::constants;
+    use crate::database::db::DbClient;
+    use crate::errors::blog::BlogError;
+    use crate::models::blog;
+    use crate::models::blog_operation::{
+        self, BlogOperation, BlogOperationKind, BlogOperationStatus,
+    };
+    use crate::utils::backup::back_up_blog;
+    use crate::utils::blog::file_utils::{get_public_url, move_blob};
+    use crate::utils::media::delete_media_info;
+    use crate::utils::storage;
+    use aws_sdk_s3 as s3;
+    use bson::doc;
+    use bson::oid::ObjectId;
+    use futures_util::TryStreamExt;
+
+    fn get_pending_status() -> bson::Bson {
+        bson::to_bson(&BlogOperationStatus::Pending)
+            .expect("Should be able to serialise blog operation status")
+    }
+
+    async fn update_operation_files(
+        db_client: &DbClient,
+        operation_id: &ObjectId,
+        update: bson::Document,
+    ) -> Result<(), BlogError> {
+        db_client
+            .get_blog_operation_collection()
+            .update_one(doc! {"_id": operation_id}, update)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to record file in blog operation journal: {:?}", e);
+                BlogError::InternalServerError
+            })?;
+        Ok(())
+    }
+
+    /// Counts the blog posts and the other pending blog operations that reference the file.
+    async fn count_file_references(
+        db_client: &DbClient,
+        operation_id: &ObjectId,
+        file_url: &str,
+    ) -> Result<u64, BlogError> {
+        let blog_query = doc! {
+            format!("{}.url", blog::FILES_KEY): file_url,
+        };
+        let operation_query = doc! {
+            "_id": {"$ne": operation_id},
+            blog_operation::STATUS_KEY: get_pending_status(),
+            blog_operation::STORED_FILES_KEY: file_url,
+        };
+        let blog_count = db_client
+            .get_blog_collection()
+            .count_documents(blog_query)
+            .await;
+        let operation_count = match blog_count {
+            Ok(0) => {
+                db_client
+                    .get_blog_operation_collection()
+                    .count_documents(operation_query)
+                    .await
+            }
+            _ => Ok(0),
+        };
+        match (blog_count, operation_count) {
+            (Ok(blog_count), Ok(operation_count)) => Ok(blog_count + operation_count),
+            (Err(e), _) | (_, Err(e)) => {
+                log::error!("Failed to count file references: {:?}", e);
+                Err(BlogError::InternalServerError)
+            }
+        }
+    }
+
+    /// Deletes the file unless a blog post or another pending blog operation still references it.
+    ///
+    /// The file is marked as being deleted by the operation before the references are counted
+    /// and [`BlogOperationJournal::reference_media`] records the reference before checking
+    /// for the mark, so a blog post that starts using the file while it is deleted either
+    /// stops the deletion or is rejected.
+    ///
+    /// Returns whether the file was deleted.
+    pub async fn delete_unreferenced_file(
+        db_client: &DbClient,
+        s3_client: &s3::Client,
+        operation_id: &ObjectId,
+        file_url: &str,
+    ) -> Result<bool, BlogError> {
+        update_operation_files(
+            db_client,
+            operation_id,
+            doc! {"$addToSet": {blog_operation::DELETING_FILES_KEY: file_url}},
+        )
+        .await?;
+        let result =
+            delete_file_if_unreferenced(db_client, s3_client, operation_id, file_url).await;
+        update_operation_files(
+            db_client,
+            operation_id,
+            doc! {"$pull": {blog_operation::DELETING_FILES_KEY: file_url}},
+        )
+        .await?;
+        result
+    }
+
+    async fn delete_file_if_unreferenced(
+        db_client: &DbClient,
+        s3_client: &s3::Client,
+        operation_id: &ObjectId,
+        file_url: &str,
+    ) -> Result<bool, BlogError> {
+        let ref_count = count_file_references(db_client, operation_id, file_url).await?;
+        if ref_count > 0 {
+            log::info!(
+                "File is still referenced by {} blog post(s) or operation(s), {}",
+                ref_count,
+                file_url
+            );
+            return Ok(false);
+        }
+
+        // the error is returned instead of only logged so that the operation
+        // stays in the journal and the deletion is retried when it is recovered.
+        let (bucket, obj_name) = storage::extract_bucket_and_blob_from_url(file_url);
+        if bucket.is_empty() || obj_name.is_empty() {
+            return Err(BlogError::InternalServerError);
+        }
+        if !storage::delete_blob(s3_client, &bucket, &obj_name).await {
+            return Err(BlogError::FileDeleteError);
+        }
+        delete_media_info(db_client, &obj_name).await;
+        Ok(true)
+    }
+
+    /// Deletes the files that no blog post references.
+    ///
+    /// Returns false if any file could not be checked or deleted.
+    async fn delete_unreferenced_files(
+        db_client: &DbClient,
+        s3_client: &s3::Client,
+        operation_id: &ObjectId,
+        file_urls: &[String],
+    ) -> bool {
+        let mut is_settled = true;
+        for file_url in file_urls.iter() {
+            if let Err(err) =
+                delete_unreferenced_file(db_client, s3_client, operation_id, file_url).await
+            {
+                log::error!("Failed to delete file {}: {}", file_url, err);
+                is_settled = false;
+            }
+        }
+        is_settled
+    }
+
+    async fn remove_operation(db_client: &DbClient, operation_id: &ObjectId) {
+        if let Err(e) = db_client
+            .get_blog_operation_collection()
+            .delete_one(doc! {"_id": operation_id})
+            .await
+        {
+            log::error!("Failed to remove blog operation from journal: {:?}", e);
+        }
+    }
+
+    /// Tracks the files stored and released while a blog post is written so that
+    /// the bucket can be brought back in line with the database if a step fails.
+    ///
+    /// Old files are only deleted after the blog post is written by calling [`Self::commit`].
+    /// If the server stops before the operation finishes, it is settled on startup
+    /// by [`recover_blog_operations`].
+    pub struct BlogOperationJournal<'a> {
+        db_client: &'a DbClient,
+        s3_client: &'a s3::Client,
+        operation: BlogOperation,
+    }
+
+    impl<'a> BlogOperationJournal<'a> {
+        pub async fn begin(
+            db_client: &'a DbClient,
+            s3_client: &'a s3::Client,
+            blog_id: ObjectId,
+            kind: BlogOperationKind,
+        ) -> Result<Self, BlogError> {
+            let operation = BlogOperation {
+                id: ObjectId::new(),
+                blog_id,
+                kind,
+                status: BlogOperationStatus::Pending,
+                stored_files: vec![],
+                released_files: vec![],
+                deleting_files: vec![],
+                started: chrono::Utc::now(),
+            };
+            db_client
+                .get_blog_operation_collection()
+                .insert_one(&operation)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to add blog operation to journal: {:?}", e);
+                    BlogError::InternalServerError
+                })?;
+            Ok(BlogOperationJournal {
+                db_client,
+                s3_client,
+                operation,
+            })
+        }
+
+        // records the file before the bucket is changed
+        async fn record_file(&mut self, key: &str, file_url: String) -> Result<(), BlogError> {
+            update_operation_files(
+                self.db_client,
+                &self.operation.id,
+                doc! {"$push": {key: &file_url}},
+            )
+            .await?;
+            if key == blog_operation::STORED_FILES_KEY {
+                self.operation.stored_files.push(file_url);
+            } else {
+                self.operation.released_files.push(file_url);
+            }
+            Ok(())
+        }
+
+        /// Records that the blog post references the media in the public bucket.
+        ///
+        /// The reference is recorded before checking whether the media exists so that
+        /// [`delete_unreferenced_file`] cannot delete it before the blog post is written.
+        ///
+        /// Returns whether the media exists.
+        pub async fn reference_media(&mut self, media_obj_name: &str) -> Result<bool, BlogError> {
+            let file_url = get_public_url(media_obj_name);
+            if !self.operation.stored_files.contains(&file_url) {
+                self.record_file(blog_operation::STORED_FILES_KEY, file_url.clone())
+                    .await?;
+            }
+
+            let deleting_query = doc! {
+                "_id": {"$ne": self.operation.id},
+                blog_operation::DELETING_FILES_KEY: &file_url,
+            };
+            let is_deleting = self
+                .db_client
+                .get_blog_operation_collection()
+                .count_documents(deleting_query)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to check for files being deleted: {:?}", e);
+                    BlogError::InternalServerError
+                })?
+                > 0;
+            if is_deleting {
+                log::warn!(
+                    "Media is being deleted by another operation, {}",
+                    media_obj_name
+                );
+                return Err(BlogError::FileBeingDeleted);
+            }
+            Ok(storage::blob_exists(self.s3_client, constants::BUCKET, media_obj_name).await)
+        }
+
+        /// Copies an uploaded file into the public bucket unless the media is already stored.
+        ///
+        /// Returns whether the media was copied.
+        pub async fn store_media(
+            &mut self,
+            source_bucket: &str,
+            source_name: &str,
+            media_obj_name: &str,
+        ) -> Result<bool, BlogError> {
+            if self.reference_media(media_obj_name).await? {
+                log::info!("Media already stored, skipping copy, {}", media_obj_name);
+                return Ok(false);
+            }
+            move_blob!(
+                self.s3_client,
+                source_bucket,
+                source_name,
+                constants::BUCKET,
+                media_obj_name
+            );
+            Ok(true)
+        }
+
+        /// Defers deleting a file that the blog post no longer references until the commit.
+        pub async fn release_file(&mut self, file_url: &str) -> Result<(), BlogError> {
+            if self
+                .operation
+                .released_files
+                .iter()
+                .any(|url| url == file_url)
+            {
+                return Ok(());
+            }
+            self.record_file(blog_operation::RELEASED_FILES_KEY, file_url.to_string())
+                .await
+        }
+
+        /// Marks the operation as committed after the blog post was written
+        /// and deletes the released files that no other blog post references.
+        pub async fn commit(self) {
+            let operation_col = self.db_client.get_blog_operation_collection();
+            let status = bson::to_bson(&BlogOperationStatus::Committed)
+                .expect("Should be able to serialise blog operation status");
+            if let Err(e) = operation_col
+                .update_one(
+                    doc! {"_id": self.operation.id},
+                    doc! {"$set": {blog_operation::STATUS_KEY: status}},
+                )
+                .await
+            {
+                log::error!("Failed to commit blog operation: {:?}", e);
+            }
+
+            let is_settled = delete_unreferenced_files(
+                self.db_client,
+                self.s3_client,
+                &self.operation.id,
+                &self.operation.released_files,
+            )
+            .await;
+            if is_settled {
+                remove_operation(self.db_client, &self.operation.id).await;
+            }
+        }
+
+        /// Undoes the operation after a failed step by deleting
+        /// the media it stored that no blog post references.
+        pub async fn rollback(self) {
+            let is_settled = delete_unreferenced_files(
+                self.db_client,
+                self.s3_client,
+                &self.operation.id,
+                &self.operation.stored_files,
+            )
+            .await;
+            if is_settled {
+                remove_operation(self.db_client, &self.operation.id).await;
+            }
+        }
+    }
+
+    /// Settles the blog operations that were interrupted, e.g. by a restart.
+    ///
+    /// Since files are only deleted when no blog post references them, deleting the
+    /// unreferenced stored and released files either rolls back the operation if the
+    /// blog post was not written or completes it if it was.
+    ///
+    /// Only operations started more than [`constants::BLOG_OPERATION_LEASE`] ago are
+    /// settled as younger ones may still be in progress on another instance.
+    ///
+    /// Returns the number of settled operations.
+    pub async fn recover_blog_operations(db_client: &DbClient, s3_client: &s3::Client) -> usize {
+        let lease = chrono::Duration::from_std(constants::BLOG_OPERATION_LEASE)
+            .expect("lease should fit in a chrono duration");
+        let cutoff = bson::DateTime::from_chrono(chrono::Utc::now() - lease);
+        let operations = match db_client
+            .get_blog_operation_collection()
+            .find(doc! {blog_operation::STARTED_KEY: {"$lt": cutoff}})
+            .await
+        {
+            Ok(cursor) => cursor.try_collect::<Vec<_>>().await,
+            Err(e) => Err(e),
+        };
+        let operations = match operations {
+            Ok(operations) => operations,
+            Err(e) => {
+                log::error!("Failed to get blog operations from journal: {:?}", e);
+                return 0;
+            }
+        };
+
+        let mut recovered = 0;
+        for operation in operations.iter() {
+            let mut is_settled = delete_unreferenced_files(
+                db_client,
+                s3_client,
+                &operation.id,
+                &operation.stored_files,
+            )
+            .await;
+            is_settled &= delete_unreferenced_files(
+                db_client,
+                s3_client,
+                &operation.id,
+                &operation.released_files,
+            )
+            .await;
+
+            if operation.kind != BlogOperationKind::Delete {
+                match db_client.get_blog_post(&operation.blog_id, None).await {
+                    // the backup may not have been made before the interruption
+                    Ok(blog) => back_up_blog(s3_client, &blog).await,
+                    Err(BlogError::BlogNotFound) => {}
+                    Err(_) => is_settled = false,
+                }
+            }
+
+            if is_settled {
+                remove_operation(db_client, &operation.id).await;
+                recovered += 1;
+                log::info!(
+                    "Recovered interrupted {:?} operation of blog {}",
+                    operation.kind,
+                    operation.blog_id
+                );
+            }
+        }
+        recovered
+    }
 }
 
 pub mod publish_utils {
@@ -192,7 +600,10 @@ pub mod publish_utils {
     ) -> Result<HttpResponse, BlogError> {
         let blog_id = validate_id(blog_id)?;
         let query = doc! { "_id": blog_id };
-        let update = doc! {"$set": {blog::IS_PUBLIC_KEY: is_public}};
+        let update = doc! {
+            "$set": {blog::IS_PUBLIC_KEY: is_public},
+            "$inc": {blog::VERSION_KEY: 1},
+        };
         let blog_col = client.into_inner().get_blog_collection();
         match blog_col.update_one(query, update).await {
             Ok(_) => {
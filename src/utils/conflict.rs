@@ -0,0 +1,60 @@
+use crate::models::blog::Blog;
+use crate::models::blog_conflict::{BlogConflict, DiffLine, FieldConflict};
+use crate::models::update_blog::UpdateBlog;
+use crate::utils::backup::get_blog_backup_at_version;
+
+use aws_sdk_s3 as s3;
+use similar::{ChangeTag, TextDiff};
+
+pub fn get_line_diff(old: &str, new: &str) -> Vec<DiffLine> {
+    TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .map(|change| DiffLine {
+            tag: match change.tag() {
+                ChangeTag::Equal => "equal",
+                ChangeTag::Insert => "insert",
+                ChangeTag::Delete => "delete",
+            },
+            text: change.value().to_string(),
+        })
+        .collect()
+}
+
+/// Compares the submitted changes and the current blog post against
+/// the version that was edited, using the backup of that version as the base.
+pub async fn get_blog_conflict(
+    s3_client: &s3::Client,
+    yours: &UpdateBlog,
+    current: &Blog,
+) -> BlogConflict {
+    let base = get_blog_backup_at_version(s3_client, &current.id, yours.version).await;
+    let your_content = yours.content.as_deref().unwrap_or(&current.content);
+    let (current_changes, your_changes) = match &base {
+        Some(base) => (
+            get_line_diff(&base.content, &current.content),
+            get_line_diff(&base.content, your_content),
+        ),
+        None => (vec![], get_line_diff(&current.content, your_content)),
+    };
+
+    macro_rules! field_conflict {
+        ($field:ident, $yours:expr) => {
+            FieldConflict {
+                base: base.as_ref().map(|base| base.$field.clone()),
+                current: current.$field.clone(),
+                yours: $yours,
+            }
+        };
+    }
+    BlogConflict {
+        base_version: yours.version,
+        current_version: current.version,
+        title: field_conflict!(title, yours.title.clone()),
+        seo_desc: field_conflict!(seo_desc, yours.seo_desc.clone()),
+        tags: field_conflict!(tags, yours.tags.clone()),
+        is_public: field_conflict!(is_public, yours.is_public),
+        content: field_conflict!(content, yours.content.clone()),
+        current_changes,
+        your_changes,
+    }
+}
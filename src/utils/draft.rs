@@ -0,0 +1,30 @@
+use crate::database::db::DbClient;
+use crate::errors::blog::BlogError;
+use crate::models::blog_draft::BlogDraft;
+
+use bson::oid::ObjectId;
+use mongodb::bson::doc;
+
+pub async fn get_blog_draft(
+    db_client: &DbClient,
+    blog_id: &ObjectId,
+) -> Result<Option<BlogDraft>, BlogError> {
+    db_client
+        .get_blog_draft_collection()
+        .find_one(doc! {"_id": blog_id})
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blog draft from database: {:?}", e);
+            BlogError::InternalServerError
+        })
+}
+
+pub async fn delete_blog_draft(db_client: &DbClient, blog_id: &ObjectId) {
+    if let Err(e) = db_client
+        .get_blog_draft_collection()
+        .delete_one(doc! {"_id": blog_id})
+        .await
+    {
+        log::error!("Failed to delete blog draft: {:?}", e);
+    }
+}
@@ -24,6 +24,12 @@ pub fn minify_html(html: &str) -> Vec<u8> {
     minify(&html_bytes, &minify_cfg)
 }
 
+/// Renders the template into HTML without minifying it.
+#[inline]
+pub fn render_template_string<T: Template>(template: T) -> String {
+    render_askama_template!(template)
+}
+
 #[inline]
 pub fn render_template<T: Template>(template: T, status_code: StatusCode) -> HttpResponse {
     let html = render_askama_template!(template);
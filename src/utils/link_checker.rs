@@ -0,0 +1,912 @@
+use crate::constants;
+use crate::database::db::DbClient;
+use crate::errors::blog::BlogError;
+use crate::models::link_check::{self, LinkCheck, LinkCheckSummary, LinkOutcome, LinkStatus};
+use crate::models::{blog, projected_blog::ProjectedBlog};
+use crate::templates::admin::{LinkInfo, LinkPost};
+use crate::utils::md::get_default_options;
+
+use bson::oid::ObjectId;
+use futures_util::TryStreamExt;
+use mongodb::bson::doc;
+use mongodb::options::{FindOptions, UpdateOptions};
+use mongodb::Collection;
+use pulldown_cmark::{Event, Parser, Tag};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[derive(Clone, Copy, Debug)]
+pub enum LinkMethod {
+    Head,
+    Get,
+}
+
+/// The HTTP client used by the link checker.
+///
+/// It can be replaced to check the links against a local mock server instead of the real sites.
+pub trait LinkClient: Send + Sync {
+    /// Returns the status code of the response after following any redirects.
+    fn get_status(
+        &self,
+        method: LinkMethod,
+        url: &str,
+    ) -> impl Future<Output = Result<u16, String>> + Send;
+
+    /// Returns the status code and the body of the response,
+    /// which is cut off after `max_len` bytes.
+    fn get_text(
+        &self,
+        url: &str,
+        max_len: usize,
+    ) -> impl Future<Output = Result<(u16, String), String>> + Send;
+}
+
+/// Returns false for the addresses of the server itself and of private networks,
+/// which the links in the blog posts must not be able to reach.
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            // 0.0.0.0/8 is "this network" and 100.64.0.0/10 is the carrier-grade NAT range
+            let is_reserved = first == 0 || (first == 100 && second & 0b1100_0000 == 64);
+            !(is_reserved
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation())
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ip(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+/// Returns false if the host of the URL is a non-public IP address.
+///
+/// The host names are checked once they are resolved by the [`PublicResolver`].
+fn is_public_url(url: &url::Url) -> bool {
+    match url.host() {
+        Some(url::Host::Domain(_)) => true,
+        Some(url::Host::Ipv4(ip)) => is_public_ip(IpAddr::V4(ip)),
+        Some(url::Host::Ipv6(ip)) => is_public_ip(IpAddr::V6(ip)),
+        None => false,
+    }
+}
+
+/// Resolves the host names of the links but leaves out the non-public addresses
+/// so that a link cannot make the server request its internal services.
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} does not resolve to a public address", host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+#[inline]
+fn check_public_url(url: &str) -> Result<(), String> {
+    match url::Url::parse(url) {
+        Ok(url) if is_public_url(&url) => Ok(()),
+        Ok(_) => Err("Not a public address".to_string()),
+        Err(_) => Err("Invalid URL".to_string()),
+    }
+}
+
+#[derive(Clone)]
+pub struct HttpLinkClient {
+    client: reqwest::Client,
+}
+
+impl HttpLinkClient {
+    pub fn new() -> Self {
+        // every redirect is checked as a public site could redirect to an internal one
+        let redirect_policy = reqwest::redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= constants::LINK_CHECK_MAX_REDIRECTS {
+                attempt.error("Too many redirects")
+            } else if !is_public_url(attempt.url()) {
+                attempt.error("Redirected to a non-public address")
+            } else {
+                attempt.follow()
+            }
+        });
+        let client = reqwest::Client::builder()
+            .user_agent(constants::LINK_CHECK_USER_AGENT)
+            .timeout(constants::LINK_CHECK_TIMEOUT)
+            .redirect(redirect_policy)
+            .dns_resolver(Arc::new(PublicResolver))
+            // a proxy would resolve the host names itself
+            .no_proxy()
+            .build()
+            .expect("Should be able to build the link checker HTTP client");
+        Self { client }
+    }
+}
+
+impl Default for HttpLinkClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LinkClient for HttpLinkClient {
+    async fn get_status(&self, method: LinkMethod, url: &str) -> Result<u16, String> {
+        check_public_url(url)?;
+        let request = match method {
+            LinkMethod::Head => self.client.head(url),
+            LinkMethod::Get => self.client.get(url),
+        };
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        Ok(response.status().as_u16())
+    }
+
+    async fn get_text(&self, url: &str, max_len: usize) -> Result<(u16, String), String> {
+        check_public_url(url)?;
+        let mut response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status().as_u16();
+        // the body is read in chunks so that a huge response is not loaded into memory
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            body.extend_from_slice(&chunk[..chunk.len().min(max_len - body.len())]);
+            if body.len() >= max_len {
+                break;
+            }
+        }
+        Ok((status, String::from_utf8_lossy(&body).to_string()))
+    }
+}
+
+/// The rules in a robots.txt file that apply to the link checker.
+#[derive(Default)]
+struct RobotsRules {
+    // whether the path is allowed, the path pattern and the length of the pattern
+    rules: Vec<(bool, regex::Regex, usize)>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    fn parse(robots_txt: &str) -> Self {
+        let user_agent = constants::LINK_CHECK_USER_AGENT
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        // the groups for the link checker take precedence over the groups for every crawler
+        let mut specific = RobotsRules::default();
+        let mut wildcard = RobotsRules::default();
+        let mut has_specific = false;
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_agent_lines = false;
+        for line in robots_txt.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+            if key == "user-agent" {
+                if !in_agent_lines {
+                    agents.clear();
+                }
+                agents.push(value.to_lowercase());
+                in_agent_lines = true;
+                continue;
+            }
+            in_agent_lines = false;
+
+            let is_specific = agents
+                .iter()
+                .any(|agent| agent != "*" && user_agent.contains(agent.as_str()));
+            let group = if is_specific {
+                has_specific = true;
+                &mut specific
+            } else if agents.iter().any(|agent| agent == "*") {
+                &mut wildcard
+            } else {
+                continue;
+            };
+            match key.as_str() {
+                "allow" | "disallow" if !value.is_empty() => {
+                    if let Some(pattern) = get_robots_pattern(value) {
+                        group.rules.push((key == "allow", pattern, value.len()));
+                    }
+                }
+                "crawl-delay" => {
+                    // negative, NaN, infinite or huge delays are ignored instead of panicking
+                    group.crawl_delay = value
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                        .map(|delay| delay.min(constants::LINK_CHECK_MAX_HOST_DELAY));
+                }
+                _ => {}
+            }
+        }
+        if has_specific {
+            specific
+        } else {
+            wildcard
+        }
+    }
+
+    fn is_allowed(&self, path: &str) -> bool {
+        // the longest matching rule wins and allow wins ties
+        self.rules
+            .iter()
+            .filter(|(_, pattern, _)| pattern.is_match(path))
+            .max_by_key(|(allow, _, len)| (*len, *allow))
+            .map(|(allow, _, _)| *allow)
+            .unwrap_or(true)
+    }
+}
+
+fn get_robots_pattern(path: &str) -> Option<regex::Regex> {
+    let (path, anchored) = match path.strip_suffix('$') {
+        Some(path) => (path, true),
+        None => (path, false),
+    };
+    let pattern = path
+        .split('*')
+        .map(regex::escape)
+        .collect::<Vec<_>>()
+        .join(".*");
+    let pattern = format!("^{}{}", pattern, if anchored { "$" } else { "" });
+    regex::Regex::new(&pattern).ok()
+}
+
+/// Checks the links with retries while respecting each site's robots.txt and rate limit.
+pub struct LinkChecker<C: LinkClient> {
+    client: C,
+    // none if the robots.txt of the site could not be fetched
+    robots: HashMap<String, Option<RobotsRules>>,
+    last_requests: HashMap<String, Instant>,
+}
+
+impl<C: LinkClient> LinkChecker<C> {
+    pub fn new(client: C) -> Self {
+        Self {
+            client,
+            robots: HashMap::new(),
+            last_requests: HashMap::new(),
+        }
+    }
+
+    async fn wait_for_origin(&mut self, origin: &str) {
+        let delay = self
+            .robots
+            .get(origin)
+            .and_then(|robots| robots.as_ref())
+            .and_then(|robots| robots.crawl_delay)
+            .unwrap_or_default()
+            .clamp(
+                constants::LINK_CHECK_HOST_DELAY,
+                constants::LINK_CHECK_MAX_HOST_DELAY,
+            );
+        if let Some(last_request) = self.last_requests.get(origin) {
+            let elapsed = last_request.elapsed();
+            if elapsed < delay {
+                tokio::time::sleep(delay - elapsed).await;
+            }
+        }
+        self.last_requests
+            .insert(origin.to_string(), Instant::now());
+    }
+
+    async fn fetch_robots(&mut self, origin: &str) {
+        if self.robots.contains_key(origin) {
+            return;
+        }
+        self.wait_for_origin(origin).await;
+        let robots = match self
+            .client
+            .get_text(
+                &format!("{}/robots.txt", origin),
+                constants::LINK_CHECK_MAX_ROBOTS_SIZE,
+            )
+            .await
+        {
+            Ok((200..=299, robots_txt)) => Some(RobotsRules::parse(&robots_txt)),
+            // sites without a robots.txt allow everything
+            Ok((400..=499, _)) => Some(RobotsRules::default()),
+            Ok(_) | Err(_) => None,
+        };
+        self.robots.insert(origin.to_string(), robots);
+    }
+
+    async fn request(&mut self, origin: &str, url: &str) -> Result<u16, String> {
+        self.wait_for_origin(origin).await;
+        let status = self.client.get_status(LinkMethod::Head, url).await?;
+        // some sites do not support or block HEAD requests
+        if matches!(status, 403 | 405 | 501) {
+            self.wait_for_origin(origin).await;
+            return self.client.get_status(LinkMethod::Get, url).await;
+        }
+        Ok(status)
+    }
+
+    pub async fn check(&mut self, url: &str) -> LinkStatus {
+        let new_status = |outcome, status, error: Option<&str>| LinkStatus {
+            checked: chrono::Utc::now(),
+            outcome,
+            status,
+            error: error.map(|e| e.to_string()),
+        };
+
+        let parsed_url = match url::Url::parse(url) {
+            Ok(parsed_url) => parsed_url,
+            Err(_) => return new_status(LinkOutcome::Broken, None, Some("Invalid URL")),
+        };
+        if !is_public_url(&parsed_url) {
+            return new_status(LinkOutcome::Skipped, None, Some("Not a public address"));
+        }
+        let origin = parsed_url.origin().ascii_serialization();
+        self.fetch_robots(&origin).await;
+        match self.robots.get(&origin) {
+            Some(Some(robots)) => {
+                let path = &parsed_url[url::Position::BeforePath..];
+                if !robots.is_allowed(path) {
+                    return new_status(
+                        LinkOutcome::Skipped,
+                        None,
+                        Some("Disallowed by robots.txt"),
+                    );
+                }
+            }
+            _ => {
+                return new_status(
+                    LinkOutcome::Skipped,
+                    None,
+                    Some("Failed to fetch robots.txt"),
+                )
+            }
+        }
+
+        let mut attempt = 0;
+        loop {
+            let result = self.request(&origin, url).await;
+            let should_retry = match &result {
+                Ok(status) => *status == 429 || *status >= 500,
+                Err(_) => true,
+            };
+            if should_retry && attempt < constants::LINK_CHECK_MAX_RETRIES {
+                attempt += 1;
+                tokio::time::sleep(Duration::from_secs(2_u64.pow(attempt))).await;
+                continue;
+            }
+            return match result {
+                Ok(status) if status < 400 => new_status(LinkOutcome::Ok, Some(status), None),
+                Ok(status) => new_status(LinkOutcome::Broken, Some(status), None),
+                Err(e) => new_status(LinkOutcome::Broken, None, Some(&e)),
+            };
+        }
+    }
+}
+
+/// Returns the links in the content that point to other sites.
+pub fn get_outbound_links(content: &str) -> Vec<String> {
+    let own_hosts = [
+        constants::DOMAIN.to_string(),
+        format!("www.{}", constants::DOMAIN),
+    ];
+    let mut links = Vec::new();
+    for event in Parser::new_ext(content, get_default_options()) {
+        let dest_url = match event {
+            Event::Start(Tag::Link { dest_url, .. })
+            | Event::Start(Tag::Image { dest_url, .. }) => dest_url,
+            _ => continue,
+        };
+        let Ok(url) = url::Url::parse(&dest_url) else {
+            continue;
+        };
+        let is_outbound = matches!(url.scheme(), "http" | "https")
+            && url
+                .host_str()
+                .is_some_and(|host| !own_hosts.iter().any(|own_host| own_host == host));
+        if is_outbound && !links.contains(&url.to_string()) {
+            links.push(url.to_string());
+        }
+    }
+    links
+}
+
+async fn get_blog_links(
+    db_client: &DbClient,
+) -> Result<BTreeMap<String, Vec<ObjectId>>, BlogError> {
+    let blog_col: Collection<ProjectedBlog> =
+        db_client.get_custom_collection(constants::BLOG_COLLECTION);
+    let options = FindOptions::builder()
+        .projection(doc! {blog::CONTENT_KEY: 1})
+        .build();
+    let blogs: Vec<ProjectedBlog> = blog_col
+        .find(doc! {})
+        .with_options(options)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blog posts from database: {:?}", e);
+            BlogError::InternalServerError
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blog posts from database: {:?}", e);
+            BlogError::InternalServerError
+        })?;
+
+    let mut links: BTreeMap<String, Vec<ObjectId>> = BTreeMap::new();
+    for blog_post in blogs {
+        let Some(blog_id) = blog_post.id else {
+            continue;
+        };
+        for link in get_outbound_links(&blog_post.content.unwrap_or_default()) {
+            links.entry(link).or_default().push(blog_id);
+        }
+    }
+    Ok(links)
+}
+
+static LINK_CHECK_RUNNING: AtomicBool = AtomicBool::new(false);
+
+struct RunningGuard;
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        LINK_CHECK_RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+#[inline]
+pub fn is_link_check_running() -> bool {
+    LINK_CHECK_RUNNING.load(Ordering::SeqCst)
+}
+
+/// Checks every outbound link in the blog posts and stores the results.
+///
+/// Returns none if a check is already running.
+pub async fn check_all_links<C: LinkClient>(
+    db_client: &DbClient,
+    client: C,
+) -> Result<Option<LinkCheckSummary>, BlogError> {
+    if LINK_CHECK_RUNNING
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return Ok(None);
+    }
+    let _guard = RunningGuard;
+
+    let links = get_blog_links(db_client).await?;
+    let link_col = db_client.get_link_check_collection();
+    let urls: Vec<&String> = links.keys().collect();
+    if let Err(e) = link_col.delete_many(doc! {"_id": {"$nin": &urls}}).await {
+        log::error!(
+            "Failed to delete link checks that are no longer used: {:?}",
+            e
+        );
+    }
+
+    // take turns between the sites so that the rate limit of one site does not hold up the rest
+    let mut by_host: BTreeMap<String, Vec<(&String, &Vec<ObjectId>)>> = BTreeMap::new();
+    for (url, blog_ids) in links.iter() {
+        let host = url::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| host.to_string()))
+            .unwrap_or_default();
+        by_host.entry(host).or_default().push((url, blog_ids));
+    }
+    let mut queue = Vec::with_capacity(links.len());
+    let max_per_host = by_host.values().map(|urls| urls.len()).max().unwrap_or(0);
+    for idx in 0..max_per_host {
+        for (host, urls) in by_host.iter() {
+            if let Some((url, blog_ids)) = urls.get(idx) {
+                queue.push((host, *url, *blog_ids));
+            }
+        }
+    }
+
+    let mut checker = LinkChecker::new(client);
+    let mut summary = LinkCheckSummary::default();
+    let options = UpdateOptions::builder().upsert(true).build();
+    for (host, url, blog_ids) in queue {
+        let status = checker.check(url).await;
+        summary.checked += 1;
+        match status.outcome {
+            LinkOutcome::Ok => summary.ok += 1,
+            LinkOutcome::Broken => summary.broken += 1,
+            LinkOutcome::Skipped => summary.skipped += 1,
+        }
+
+        let status_doc = match bson::to_bson(&status) {
+            Ok(status_doc) => status_doc,
+            Err(e) => {
+                log::error!("Failed to serialise link status: {:?}", e);
+                continue;
+            }
+        };
+        if let Err(e) = link_col
+            .update_one(
+                doc! {"_id": url},
+                doc! {
+                    "$set": {
+                        link_check::HOST_KEY: host,
+                        link_check::BLOG_IDS_KEY: blog_ids,
+                        link_check::LAST_STATUS_KEY: &status_doc,
+                    },
+                    "$push": {
+                        link_check::HISTORY_KEY: {
+                            "$each": [&status_doc],
+                            "$slice": -constants::LINK_CHECK_HISTORY_LENGTH,
+                        },
+                    },
+                },
+            )
+            .with_options(options.clone())
+            .await
+        {
+            log::error!("Failed to save link check for {}: {:?}", url, e);
+        }
+    }
+    Ok(Some(summary))
+}
+
+#[inline]
+fn get_status_text(status: &LinkStatus) -> String {
+    match (status.status, &status.error) {
+        (Some(code), _) => code.to_string(),
+        (None, Some(error)) => error.clone(),
+        (None, None) => String::new(),
+    }
+}
+
+/// Returns the checked links with the broken links first.
+pub async fn get_link_report(db_client: &DbClient) -> Result<Vec<LinkInfo>, BlogError> {
+    let link_checks: Vec<LinkCheck> = db_client
+        .get_link_check_collection()
+        .find(doc! {})
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get link checks from database: {:?}", e);
+            BlogError::InternalServerError
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get link checks from database: {:?}", e);
+            BlogError::InternalServerError
+        })?;
+
+    let blog_col: Collection<ProjectedBlog> =
+        db_client.get_custom_collection(constants::BLOG_COLLECTION);
+    let options = FindOptions::builder()
+        .projection(doc! {blog::TITLE_KEY: 1})
+        .build();
+    let titles: HashMap<ObjectId, String> = blog_col
+        .find(doc! {})
+        .with_options(options)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blog titles from database: {:?}", e);
+            BlogError::InternalServerError
+        })?
+        .try_collect::<Vec<ProjectedBlog>>()
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blog titles from database: {:?}", e);
+            BlogError::InternalServerError
+        })?
+        .into_iter()
+        .filter_map(|blog_post| Some((blog_post.id?, blog_post.title.unwrap_or_default())))
+        .collect();
+
+    let mut links: Vec<LinkInfo> = link_checks
+        .into_iter()
+        .map(|link_check| LinkInfo {
+            status: get_status_text(&link_check.last_status),
+            checked: link_check.last_status.checked.to_rfc3339(),
+            outcome: link_check.last_status.outcome,
+            history: link_check
+                .history
+                .iter()
+                .map(|status| status.outcome)
+                .collect(),
+            posts: link_check
+                .blog_ids
+                .iter()
+                .filter_map(|blog_id| {
+                    titles.get(blog_id).map(|title| LinkPost {
+                        blog_id: blog_id.to_hex(),
+                        title: title.clone(),
+                    })
+                })
+                .collect(),
+            url: link_check.url,
+        })
+        .collect();
+    links.sort_by_key(|link| {
+        let rank = match link.outcome {
+            LinkOutcome::Broken => 0,
+            LinkOutcome::Skipped => 1,
+            LinkOutcome::Ok => 2,
+        };
+        (rank, link.url.clone())
+    });
+    Ok(links)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// Answers with the given responses and records when each request was made.
+    #[derive(Default)]
+    struct FakeLinkClient {
+        robots: HashMap<String, (u16, String)>,
+        // the last status of a url is repeated once the others were returned
+        statuses: Mutex<HashMap<String, VecDeque<u16>>>,
+        requests: Mutex<Vec<(String, Instant)>>,
+    }
+
+    impl FakeLinkClient {
+        fn with_robots(mut self, origin: &str, robots_txt: &str) -> Self {
+            self.robots
+                .insert(origin.to_string(), (200, robots_txt.to_string()));
+            self
+        }
+
+        fn with_statuses(self, url: &str, statuses: &[u16]) -> Self {
+            self.statuses
+                .lock()
+                .unwrap()
+                .insert(url.to_string(), statuses.iter().copied().collect());
+            self
+        }
+
+        fn get_request_times(&self, url: &str) -> Vec<Instant> {
+            self.requests
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(requested_url, _)| requested_url == url)
+                .map(|(_, time)| *time)
+                .collect()
+        }
+    }
+
+    impl LinkClient for FakeLinkClient {
+        async fn get_status(&self, _method: LinkMethod, url: &str) -> Result<u16, String> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((url.to_string(), Instant::now()));
+            let mut statuses = self.statuses.lock().unwrap();
+            let statuses = statuses.entry(url.to_string()).or_default();
+            match statuses.len() {
+                0 => Ok(200),
+                1 => Ok(statuses[0]),
+                _ => Ok(statuses.pop_front().unwrap()),
+            }
+        }
+
+        async fn get_text(&self, url: &str, max_len: usize) -> Result<(u16, String), String> {
+            assert_eq!(max_len, constants::LINK_CHECK_MAX_ROBOTS_SIZE);
+            self.requests
+                .lock()
+                .unwrap()
+                .push((url.to_string(), Instant::now()));
+            let origin = url.trim_end_matches("/robots.txt");
+            Ok(self
+                .robots
+                .get(origin)
+                .cloned()
+                .unwrap_or((404, String::new())))
+        }
+    }
+
+    const ROBOTS_TXT: &str = "User-agent: *
+Disallow: /
+
+User-agent: kjhjason-link-checker
+Disallow: /private
+Allow: /private/public
+";
+
+    #[test]
+    fn the_link_checker_group_takes_precedence_over_the_wildcard_group() {
+        let robots = RobotsRules::parse(ROBOTS_TXT);
+        assert!(robots.is_allowed("/posts"));
+        assert!(!robots.is_allowed("/private/posts"));
+        // the longest matching rule wins
+        assert!(robots.is_allowed("/private/public/posts"));
+
+        let robots =
+            RobotsRules::parse("User-agent: *\nDisallow: /\n\nUser-agent: other-bot\nAllow: /\n");
+        assert!(!robots.is_allowed("/posts"));
+    }
+
+    #[test]
+    fn invalid_crawl_delays_are_ignored_and_long_ones_are_capped() {
+        let get_crawl_delay = |value: &str| {
+            RobotsRules::parse(&format!("User-agent: *\nCrawl-delay: {}\n", value)).crawl_delay
+        };
+        for value in ["-1", "NaN", "inf", "1e30", "soon"] {
+            assert_eq!(get_crawl_delay(value), None, "{}", value);
+        }
+        assert_eq!(get_crawl_delay("2.5"), Some(Duration::from_millis(2500)));
+        assert_eq!(
+            get_crawl_delay("3600"),
+            Some(constants::LINK_CHECK_MAX_HOST_DELAY)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn links_disallowed_by_robots_txt_are_skipped() {
+        let client = FakeLinkClient::default().with_robots("https://a.example", ROBOTS_TXT);
+        let mut checker = LinkChecker::new(client);
+
+        let status = checker.check("https://a.example/private/posts").await;
+        assert_eq!(status.outcome, LinkOutcome::Skipped);
+        let status = checker
+            .check("https://a.example/private/public/posts")
+            .await;
+        assert_eq!(status.outcome, LinkOutcome::Ok);
+        assert!(checker
+            .client
+            .get_request_times("https://a.example/private/posts")
+            .is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn server_errors_are_retried_with_exponential_backoff() {
+        let url = "https://a.example/posts";
+        let client = FakeLinkClient::default().with_statuses(url, &[503, 429, 200]);
+        let mut checker = LinkChecker::new(client);
+
+        let status = checker.check(url).await;
+        assert_eq!(status.outcome, LinkOutcome::Ok);
+        assert_eq!(status.status, Some(200));
+        let times = checker.client.get_request_times(url);
+        assert_eq!(times.len(), 3);
+        assert!(times[1] - times[0] >= Duration::from_secs(2));
+        assert!(times[2] - times[1] >= Duration::from_secs(4));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn links_are_broken_after_the_retries_or_a_client_error() {
+        let failing_url = "https://a.example/failing";
+        let missing_url = "https://a.example/missing";
+        let client = FakeLinkClient::default()
+            .with_statuses(failing_url, &[500])
+            .with_statuses(missing_url, &[404]);
+        let mut checker = LinkChecker::new(client);
+
+        let status = checker.check(failing_url).await;
+        assert_eq!(status.outcome, LinkOutcome::Broken);
+        assert_eq!(
+            checker.client.get_request_times(failing_url).len(),
+            constants::LINK_CHECK_MAX_RETRIES as usize + 1
+        );
+
+        let status = checker.check(missing_url).await;
+        assert_eq!(status.outcome, LinkOutcome::Broken);
+        assert_eq!(status.status, Some(404));
+        assert_eq!(checker.client.get_request_times(missing_url).len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn requests_to_the_same_origin_wait_for_the_crawl_delay() {
+        let client = FakeLinkClient::default()
+            .with_robots("https://a.example", "User-agent: *\nCrawl-delay: 5\n");
+        let mut checker = LinkChecker::new(client);
+
+        let start = Instant::now();
+        checker.check("https://a.example/first").await;
+        checker.check("https://a.example/second").await;
+        checker.check("https://b.example/first").await;
+
+        let robots_time = checker
+            .client
+            .get_request_times("https://a.example/robots.txt")[0];
+        let first = checker.client.get_request_times("https://a.example/first")[0];
+        let second = checker.client.get_request_times("https://a.example/second")[0];
+        assert!(first - robots_time >= Duration::from_secs(5));
+        assert!(second - first >= Duration::from_secs(5));
+
+        // another origin only waits for its own robots.txt request
+        let other_robots_time = checker
+            .client
+            .get_request_times("https://b.example/robots.txt")[0];
+        let other = checker.client.get_request_times("https://b.example/first")[0];
+        assert_eq!(other_robots_time, second);
+        assert!(other - other_robots_time >= constants::LINK_CHECK_HOST_DELAY);
+        assert!(other - start < Duration::from_secs(12));
+    }
+
+    #[test]
+    fn private_and_loopback_addresses_are_not_public() {
+        let non_public = [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ];
+        for ip in non_public {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["93.184.215.14", "100.128.0.1", "2606:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn http_client_refuses_private_and_loopback_targets() {
+        let client = HttpLinkClient::new();
+        for url in [
+            "http://127.0.0.1:8080/",
+            "http://[::1]/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://10.0.0.1/",
+            // the numeric form of 127.0.0.1
+            "http://2130706433/",
+        ] {
+            assert_eq!(
+                client.get_status(LinkMethod::Head, url).await,
+                Err("Not a public address".to_string()),
+                "{}",
+                url
+            );
+        }
+
+        let name: reqwest::dns::Name = "localhost".parse().unwrap();
+        assert!(reqwest::dns::Resolve::resolve(&PublicResolver, name)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn links_to_non_public_addresses_are_skipped() {
+        let mut checker = LinkChecker::new(FakeLinkClient::default());
+        let status = checker.check("http://192.168.0.1/admin").await;
+        assert_eq!(status.outcome, LinkOutcome::Skipped);
+        // not even the robots.txt is requested
+        assert!(checker.client.requests.lock().unwrap().is_empty());
+    }
+}
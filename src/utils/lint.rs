@@ -0,0 +1,339 @@
+use crate::constants;
+use crate::database::db::DbClient;
+use crate::errors::blog::BlogError;
+use crate::models::lint::{LintIssue, LintLevel, LintReport, LintRule};
+use crate::utils::md::get_default_options;
+
+use bson::oid::ObjectId;
+use mongodb::bson::doc;
+use once_cell::sync::Lazy;
+use pulldown_cmark::{BrokenLink, Event, LinkType, Parser, Tag, TagEnd};
+use std::collections::{HashMap, HashSet};
+
+static BARE_URL_REGEX: Lazy<regex::Regex> =
+    Lazy::new(|| regex::Regex::new(r"https?://[^\s<>]+").unwrap());
+static INTERNAL_BLOG_LINK_REGEX: Lazy<regex::Regex> = Lazy::new(|| {
+    regex::Regex::new(
+        r"^(?:https?://(?:www\.)?kjhjason\.com)?/blogs/([0-9a-fA-F]{24})/?(?:[?#].*)?$",
+    )
+    .unwrap()
+});
+
+/// Parses the rule levels configured with the `LINT_RULES` env variable,
+/// e.g. `long_paragraph=off,bare_url=error`.
+fn get_rule_levels() -> &'static HashMap<LintRule, LintLevel> {
+    static RULE_LEVELS: Lazy<HashMap<LintRule, LintLevel>> = Lazy::new(|| {
+        let mut levels: HashMap<LintRule, LintLevel> = LintRule::ALL
+            .iter()
+            .map(|rule| (*rule, rule.get_default_level()))
+            .collect();
+        for config in constants::get_lint_rules().split(',') {
+            let config = config.trim();
+            if config.is_empty() {
+                continue;
+            }
+            let Some((rule_name, level_name)) = config.split_once('=') else {
+                log::warn!("Ignoring invalid lint rule config: {}", config);
+                continue;
+            };
+            let rule = LintRule::ALL
+                .iter()
+                .find(|rule| rule.to_string() == rule_name.trim());
+            let level = [LintLevel::Off, LintLevel::Warning, LintLevel::Error]
+                .into_iter()
+                .find(|level| level.to_string() == level_name.trim());
+            match (rule, level) {
+                (Some(rule), Some(level)) => {
+                    levels.insert(*rule, level);
+                }
+                _ => log::warn!("Ignoring invalid lint rule config: {}", config),
+            }
+        }
+        levels
+    });
+    &RULE_LEVELS
+}
+
+struct Linter {
+    line_starts: Vec<usize>,
+    issues: Vec<LintIssue>,
+}
+
+impl Linter {
+    fn new(content: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Self {
+            line_starts,
+            issues: Vec::new(),
+        }
+    }
+
+    fn get_line(&self, offset: usize) -> usize {
+        match self.line_starts.binary_search(&offset) {
+            Ok(idx) => idx + 1,
+            Err(idx) => idx,
+        }
+    }
+
+    fn add(&mut self, rule: LintRule, offset: Option<usize>, message: String) {
+        let level = get_rule_levels()
+            .get(&rule)
+            .copied()
+            .unwrap_or(rule.get_default_level());
+        if level == LintLevel::Off {
+            return;
+        }
+        self.issues.push(LintIssue {
+            rule,
+            level,
+            line: offset.map(|offset| self.get_line(offset)),
+            message,
+        });
+    }
+
+    fn into_issues(mut self) -> Vec<LintIssue> {
+        self.issues.sort_by_key(|issue| issue.line);
+        self.issues
+    }
+}
+
+/// Checks the markdown content for issues that can be found without the database.
+pub fn lint_content(content: &str) -> Vec<LintIssue> {
+    let mut linter = Linter::new(content);
+
+    let mut broken_refs = Vec::new();
+    let broken_link_callback = |link: BrokenLink<'_>| {
+        // shortcut references like [this] are usually just text in brackets
+        if matches!(link.link_type, LinkType::Reference | LinkType::Collapsed) {
+            broken_refs.push((link.span.start, link.reference.to_string()));
+        }
+        None
+    };
+    let parser = Parser::new_with_broken_link_callback(
+        content,
+        get_default_options(),
+        Some(broken_link_callback),
+    );
+
+    // the content title is a h1 so the headings in the content should start from h2
+    let mut prev_heading_level = 1;
+    let mut headings: HashSet<String> = HashSet::new();
+    // the offset and text of the heading, image or paragraph that is being parsed
+    let mut heading: Option<(usize, String)> = None;
+    let mut image: Option<(usize, String)> = None;
+    let mut paragraph: Option<(usize, usize)> = None;
+    let mut link_depth = 0;
+    let mut in_code_block = false;
+    for (event, range) in parser.into_offset_iter() {
+        let is_text = matches!(event, Event::Text(_));
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                let level = level as usize;
+                if level > prev_heading_level + 1 {
+                    linter.add(
+                        LintRule::HeadingOrder,
+                        Some(range.start),
+                        format!(
+                            "Heading level skipped from h{} to h{}",
+                            prev_heading_level, level
+                        ),
+                    );
+                }
+                prev_heading_level = level;
+                heading = Some((range.start, String::new()));
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((offset, text)) = heading.take() {
+                    let text = text.trim().to_lowercase();
+                    if !text.is_empty() && !headings.insert(text.clone()) {
+                        linter.add(
+                            LintRule::DuplicateHeading,
+                            Some(offset),
+                            format!("Duplicate heading: {}", text),
+                        );
+                    }
+                }
+            }
+            Event::Start(Tag::Paragraph) => paragraph = Some((range.start, 0)),
+            Event::End(TagEnd::Paragraph) => {
+                if let Some((offset, words)) = paragraph.take() {
+                    if words > constants::LINT_LONG_PARAGRAPH_WORDS {
+                        linter.add(
+                            LintRule::LongParagraph,
+                            Some(offset),
+                            format!(
+                                "Paragraph has {} words, consider splitting it (max {})",
+                                words,
+                                constants::LINT_LONG_PARAGRAPH_WORDS
+                            ),
+                        );
+                    }
+                }
+            }
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                link_depth += 1;
+                if dest_url.trim().is_empty() {
+                    linter.add(
+                        LintRule::BrokenLink,
+                        Some(range.start),
+                        "Link has no destination".to_string(),
+                    );
+                } else if dest_url.contains("://") && url::Url::parse(&dest_url).is_err() {
+                    linter.add(
+                        LintRule::BrokenLink,
+                        Some(range.start),
+                        format!("Link to an invalid URL: {}", dest_url),
+                    );
+                }
+            }
+            Event::End(TagEnd::Link) => link_depth -= 1,
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                if dest_url.trim().is_empty() {
+                    linter.add(
+                        LintRule::BrokenLink,
+                        Some(range.start),
+                        "Image has no source".to_string(),
+                    );
+                }
+                image = Some((range.start, String::new()));
+            }
+            Event::End(TagEnd::Image) => {
+                if let Some((offset, alt_text)) = image.take() {
+                    if alt_text.trim().is_empty() {
+                        linter.add(
+                            LintRule::AltText,
+                            Some(offset),
+                            "Image has no alt text".to_string(),
+                        );
+                    }
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, alt_text)) = image.as_mut() {
+                    alt_text.push_str(&text);
+                }
+                if let Some((_, heading_text)) = heading.as_mut() {
+                    heading_text.push_str(&text);
+                }
+                if let Some((_, words)) = paragraph.as_mut() {
+                    *words += text.split_whitespace().count();
+                }
+                if is_text && link_depth == 0 && !in_code_block {
+                    for url in BARE_URL_REGEX.find_iter(&text) {
+                        linter.add(
+                            LintRule::BareUrl,
+                            Some(range.start),
+                            format!("Bare URL should be a link: {}", url.as_str()),
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (offset, reference) in broken_refs {
+        linter.add(
+            LintRule::BrokenLink,
+            Some(offset),
+            format!("Link reference is not defined: {}", reference),
+        );
+    }
+    linter.into_issues()
+}
+
+pub fn lint_seo_desc(seo_desc: &str) -> Vec<LintIssue> {
+    let mut linter = Linter::new("");
+    let len = seo_desc.trim().chars().count();
+    if len == 0 {
+        linter.add(
+            LintRule::SeoDesc,
+            None,
+            "SEO description is missing".to_string(),
+        );
+    } else if !(constants::LINT_SEO_DESC_MIN_LENGTH..=constants::LINT_SEO_DESC_MAX_LENGTH)
+        .contains(&len)
+    {
+        linter.add(
+            LintRule::SeoDescLength,
+            None,
+            format!(
+                "SEO description has {} characters, it should have {} to {} characters",
+                len,
+                constants::LINT_SEO_DESC_MIN_LENGTH,
+                constants::LINT_SEO_DESC_MAX_LENGTH
+            ),
+        );
+    }
+    linter.into_issues()
+}
+
+/// Checks that the links to other blog posts on this site still point to existing blog posts.
+pub async fn lint_internal_links(
+    db_client: &DbClient,
+    content: &str,
+) -> Result<Vec<LintIssue>, BlogError> {
+    let mut links: Vec<(usize, ObjectId)> = Vec::new();
+    for (event, range) in Parser::new_ext(content, get_default_options()).into_offset_iter() {
+        if let Event::Start(Tag::Link { dest_url, .. }) = event {
+            let blog_id = INTERNAL_BLOG_LINK_REGEX
+                .captures(&dest_url)
+                .and_then(|captures| ObjectId::parse_str(&captures[1]).ok());
+            if let Some(blog_id) = blog_id {
+                links.push((range.start, blog_id));
+            }
+        }
+    }
+    if links.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let blog_ids: Vec<ObjectId> = links.iter().map(|(_, blog_id)| *blog_id).collect();
+    let existing_ids: HashSet<ObjectId> = get_existing_blog_ids(db_client, &blog_ids).await?;
+    let mut linter = Linter::new(content);
+    for (offset, blog_id) in links {
+        if !existing_ids.contains(&blog_id) {
+            linter.add(
+                LintRule::DeadInternalLink,
+                Some(offset),
+                format!("Link to a blog post that does not exist: {}", blog_id),
+            );
+        }
+    }
+    Ok(linter.into_issues())
+}
+
+async fn get_existing_blog_ids(
+    db_client: &DbClient,
+    blog_ids: &[ObjectId],
+) -> Result<HashSet<ObjectId>, BlogError> {
+    let ids = db_client
+        .get_blog_collection()
+        .distinct("_id", doc! {"_id": {"$in": blog_ids}})
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get linked blog posts from database: {:?}", e);
+            BlogError::InternalServerError
+        })?;
+    Ok(ids.into_iter().filter_map(|id| id.as_object_id()).collect())
+}
+
+/// Lints the blog post before it is published and fails
+/// if there are any issues with the error level.
+pub async fn check_blog_lint(
+    db_client: &DbClient,
+    content: &str,
+    seo_desc: &str,
+) -> Result<(), BlogError> {
+    let mut issues = lint_seo_desc(seo_desc);
+    issues.extend(lint_content(content));
+    issues.extend(lint_internal_links(db_client, content).await?);
+    if issues.iter().any(|issue| issue.level == LintLevel::Error) {
+        return Err(BlogError::LintFailed(Box::new(LintReport { issues })));
+    }
+    Ok(())
+}
@@ -0,0 +1,368 @@
+use crate::models::live_preview::{PreviewEdit, PreviewUpdate};
+use crate::utils::html::minify_html;
+use crate::utils::lint::lint_content;
+use crate::utils::md::get_default_options;
+
+use pulldown_cmark::{html, CowStr, Event, Parser, Tag};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+/// A rendered top-level block of the content.
+pub struct RenderedBlock {
+    // the byte range of the block's markdown in the content
+    pub range: Range<usize>,
+    pub key: String,
+    pub html: String,
+}
+
+/// The server side copy of the content being edited in the live preview.
+#[derive(Default)]
+pub struct PreviewDocument {
+    content: String,
+    rev: u64,
+    // the blocks of the content in document order
+    blocks: Vec<RenderedBlock>,
+    // reference links and footnotes make blocks depend on each other,
+    // so the whole content is rendered again after every edit.
+    has_definitions: bool,
+    // the keys of the blocks that the editor already has
+    sent_blocks: HashSet<String>,
+}
+
+impl PreviewDocument {
+    /// Applies the edit and returns the update to send back to the editor.
+    pub fn apply(&mut self, edit: PreviewEdit) -> PreviewUpdate {
+        match edit {
+            PreviewEdit::Full { rev, content } => {
+                // the editor starts from an empty preview after a resync
+                self.sent_blocks.clear();
+                self.content = content;
+                self.rev = rev;
+                self.render_all()
+            }
+            PreviewEdit::Edit {
+                rev,
+                start,
+                delete,
+                text,
+            } => {
+                // the edits have to be applied in order or the content will diverge
+                if rev != self.rev + 1 {
+                    return PreviewUpdate::Resync { rev: self.rev };
+                }
+                let range = get_byte_offset(&self.content, start).and_then(|start_idx| {
+                    get_byte_offset(&self.content[start_idx..], delete)
+                        .map(|len| start_idx..start_idx + len)
+                });
+                let Some(range) = range else {
+                    return PreviewUpdate::Resync { rev: self.rev };
+                };
+                self.content.replace_range(range.clone(), &text);
+                self.rev = rev;
+                self.render_edit(range, text.len())
+            }
+        }
+    }
+
+    fn render_all(&mut self) -> PreviewUpdate {
+        let (blocks, has_definitions) = render_blocks(&self.content, 0);
+        self.has_definitions = has_definitions;
+        let block_count = self.blocks.len();
+        self.update_blocks(0..block_count, blocks)
+    }
+
+    /// Renders only the blocks around the edited range of the old content.
+    ///
+    /// The touched blocks are rendered again together with the block before and after them
+    /// since an edit can merge or split blocks. If the block after them no longer renders
+    /// the same, e.g. because a code fence was opened, the rest of the content may have
+    /// changed as well so the whole content is rendered instead.
+    fn render_edit(&mut self, edited: Range<usize>, inserted_len: usize) -> PreviewUpdate {
+        if self.has_definitions || self.blocks.is_empty() {
+            return self.render_all();
+        }
+
+        let first = self
+            .blocks
+            .iter()
+            .position(|block| block.range.end >= edited.start)
+            .unwrap_or(self.blocks.len())
+            .saturating_sub(1);
+        // the block after the edit, which has to render the same for the rest to be unchanged
+        let next = self
+            .blocks
+            .iter()
+            .position(|block| block.range.start > edited.end);
+        let window_start = self.blocks[first].range.start.min(edited.start);
+        let old_window_end = match next {
+            Some(next) => self.blocks[next].range.end,
+            None => self.content.len() + edited.len() - inserted_len,
+        };
+        let window_end = old_window_end + inserted_len - edited.len();
+
+        let (blocks, has_definitions) =
+            render_blocks(&self.content[window_start..window_end], window_start);
+        if has_definitions {
+            return self.render_all();
+        }
+        let splice_end = match next {
+            Some(next) => {
+                let old_block = &self.blocks[next];
+                let is_unchanged = blocks.last().is_some_and(|block| {
+                    block.key == old_block.key
+                        && block.range.end == window_end
+                        && block.range.len() == old_block.range.len()
+                });
+                if !is_unchanged {
+                    return self.render_all();
+                }
+                next + 1
+            }
+            None => self.blocks.len(),
+        };
+
+        for block in self.blocks[splice_end..].iter_mut() {
+            block.range = block.range.start + window_end - old_window_end
+                ..block.range.end + window_end - old_window_end;
+        }
+        self.update_blocks(first..splice_end, blocks)
+    }
+
+    /// Replaces the blocks in the range and returns the update with the blocks
+    /// that the editor has not received yet.
+    fn update_blocks(
+        &mut self,
+        replaced: Range<usize>,
+        blocks: Vec<RenderedBlock>,
+    ) -> PreviewUpdate {
+        let mut fragments = HashMap::new();
+        for block in blocks.iter() {
+            if self.sent_blocks.insert(block.key.clone()) {
+                let minified = String::from_utf8_lossy(&minify_html(&block.html)).to_string();
+                fragments.insert(block.key.clone(), minified);
+            }
+        }
+        self.blocks.splice(replaced, blocks);
+
+        // forget the blocks that were removed so that the editor can drop them too
+        let keys: Vec<String> = self.blocks.iter().map(|block| block.key.clone()).collect();
+        let current_blocks: HashSet<&String> = keys.iter().collect();
+        self.sent_blocks.retain(|key| current_blocks.contains(key));
+
+        // the lint rules look at the whole content, e.g. the order of the headings
+        PreviewUpdate::Render {
+            rev: self.rev,
+            blocks: keys,
+            fragments,
+            warnings: lint_content(&self.content),
+        }
+    }
+}
+
+/// Converts an offset in UTF-16 code units into a byte offset of the string.
+fn get_byte_offset(content: &str, utf16_offset: usize) -> Option<usize> {
+    let mut utf16_idx = 0;
+    for (byte_idx, c) in content.char_indices() {
+        if utf16_idx == utf16_offset {
+            return Some(byte_idx);
+        }
+        if utf16_idx > utf16_offset {
+            // the offset is in the middle of a surrogate pair
+            return None;
+        }
+        utf16_idx += c.len_utf16();
+    }
+    if utf16_idx == utf16_offset {
+        Some(content.len())
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn get_block_key(block: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    block.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Renders the content but returns the HTML of each top-level block separately
+/// with its range in the content shifted by `offset`.
+///
+/// Also returns whether the content defines reference links or has footnotes.
+pub fn render_blocks(content: &str, offset: usize) -> (Vec<RenderedBlock>, bool) {
+    if content.is_empty() {
+        return (vec![], false);
+    }
+
+    // a random separator so that it cannot appear in the content itself
+    let separator = format!("<!--block-{:016x}-->", rand::random::<u64>());
+    let mut parser = Parser::new_ext(content, get_default_options()).into_offset_iter();
+    let mut depth = 0_usize;
+    let mut has_definitions = false;
+    let mut block_start = 0;
+    let mut ranges = Vec::new();
+    let mut events = Vec::new();
+    for (event, range) in parser.by_ref() {
+        if depth == 0 {
+            block_start = range.start;
+        }
+        match &event {
+            Event::Start(Tag::FootnoteDefinition(_)) => {
+                has_definitions = true;
+                depth += 1;
+            }
+            Event::FootnoteReference(_) => has_definitions = true,
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+        events.push(event);
+        if depth == 0 {
+            ranges.push(offset + block_start..offset + range.end);
+            events.push(Event::Html(CowStr::from(separator.clone())));
+        }
+    }
+    has_definitions |= parser.reference_definitions().iter().next().is_some();
+
+    let mut html_output = String::new();
+    html::push_html(&mut html_output, events.into_iter());
+    let blocks = html_output
+        .split(&separator)
+        .zip(ranges)
+        // the writer puts a newline before every block but the first,
+        // which would give the same block a different key depending on its position
+        .map(|(block, range)| (block.trim(), range))
+        .filter(|(block, _)| !block.is_empty())
+        .map(|(block, range)| RenderedBlock {
+            range,
+            key: get_block_key(block),
+            html: block.to_string(),
+        })
+        .collect();
+    (blocks, has_definitions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_block_keys(document: &PreviewDocument) -> Vec<String> {
+        document
+            .blocks
+            .iter()
+            .map(|block| block.key.clone())
+            .collect()
+    }
+
+    fn get_rendered_keys(content: &str) -> Vec<String> {
+        render_blocks(content, 0)
+            .0
+            .into_iter()
+            .map(|block| block.key)
+            .collect()
+    }
+
+    /// Replaces `old` with `new` in the document and checks that the blocks
+    /// are the same as when rendering the whole content.
+    fn edit(document: &mut PreviewDocument, old: &str, new: &str) {
+        let start = document
+            .content
+            .find(old)
+            .expect("old text should be in the content");
+        let rev = document.rev + 1;
+        let edit = PreviewEdit::Edit {
+            rev,
+            start: document.content[..start].encode_utf16().count(),
+            delete: old.encode_utf16().count(),
+            text: new.to_string(),
+        };
+        assert!(matches!(document.apply(edit), PreviewUpdate::Render { .. }));
+        assert_eq!(
+            get_block_keys(document),
+            get_rendered_keys(&document.content)
+        );
+        // blocks with footnotes do not render the same on their own
+        let blocks = if document.has_definitions {
+            &[][..]
+        } else {
+            &document.blocks[..]
+        };
+        for block in blocks.iter() {
+            let (rendered, _) = render_blocks(&document.content[block.range.clone()], 0);
+            assert_eq!(
+                rendered.len(),
+                1,
+                "{:?}",
+                &document.content[block.range.clone()]
+            );
+            assert_eq!(rendered[0].key, block.key);
+        }
+    }
+
+    fn new_document(content: &str) -> PreviewDocument {
+        let mut document = PreviewDocument::default();
+        document.apply(PreviewEdit::Full {
+            rev: 1,
+            content: content.to_string(),
+        });
+        document
+    }
+
+    #[test]
+    fn edits_render_the_same_blocks_as_the_whole_content() {
+        let mut document =
+            new_document("# Title\n\nFirst paragraph.\n\nSecond paragraph.\n\n- a\n- b\n");
+        edit(&mut document, "First", "The first");
+        edit(&mut document, "paragraph.\n\nSecond", "paragraph.\nSecond");
+        edit(
+            &mut document,
+            "paragraph.\nSecond",
+            "paragraph.\n\n## Second",
+        );
+        edit(&mut document, "- b\n", "- b\n- c\n\nLast paragraph.\n");
+        edit(&mut document, "# Title\n\n", "");
+        edit(&mut document, "Last", "The last");
+    }
+
+    #[test]
+    fn opening_a_code_fence_changes_the_following_blocks() {
+        let mut document = new_document("Intro\n\nMiddle\n\nEnd\n");
+        edit(&mut document, "Intro\n\n", "Intro\n\n```\n");
+        assert_eq!(document.blocks.len(), 2);
+        edit(&mut document, "```\n", "");
+        assert_eq!(document.blocks.len(), 3);
+    }
+
+    #[test]
+    fn edits_with_footnotes_render_the_whole_content() {
+        let mut document = new_document("Text\n\nMore text\n");
+        edit(
+            &mut document,
+            "More text\n",
+            "More text\n\n[^1]: A footnote\n",
+        );
+        assert!(document.has_definitions);
+        edit(&mut document, "Text", "Text[^1]");
+        edit(&mut document, "\n\n[^1]: A footnote\n", "\n");
+        assert!(!document.has_definitions);
+    }
+
+    #[test]
+    fn out_of_order_edits_ask_for_a_resync() {
+        let mut document = new_document("Text\n");
+        let edit = PreviewEdit::Edit {
+            rev: 3,
+            start: 0,
+            delete: 0,
+            text: "More ".to_string(),
+        };
+        assert!(matches!(
+            document.apply(edit),
+            PreviewUpdate::Resync { rev: 1 }
+        ));
+        assert_eq!(document.content, "Text\n");
+    }
+}
@@ -0,0 +1,709 @@
+use crate::constants;
+use crate::database::db::DbClient;
+use crate::errors::backup::BackupError;
+use crate::models::blog::{self, Blog};
+use crate::models::blog_operation::BlogOperationKind;
+use crate::models::file_info::FileInfo;
+use crate::models::front_matter::{FrontMatter, MarkdownImportReport};
+use crate::models::projected_blog::ProjectedBlog;
+use crate::utils::backup::{back_up_blog, read_archive_entry};
+use crate::utils::blog::file_utils::{get_media_obj_name, get_public_url, hash_file_data};
+use crate::utils::blog::operation_utils::BlogOperationJournal;
+use crate::utils::draft::delete_blog_draft;
+use crate::utils::md::get_default_options;
+use crate::utils::media::get_media_type;
+use crate::utils::storage;
+
+use actix_web::web;
+use aws_sdk_s3 as s3;
+use bson::oid::ObjectId;
+use futures_util::TryStreamExt;
+use mongodb::bson::doc;
+use mongodb::options::FindOptions;
+use mongodb::Collection;
+use pulldown_cmark::{Event, Parser, Tag};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{Cursor, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+
+const FRONT_MATTER_DELIMITER: &str = "---";
+
+/// Returns a URL friendly version of the title, e.g. "Hello, World!" becomes "hello-world".
+pub fn get_slug(title: &str) -> String {
+    let mut slug = String::with_capacity(title.len());
+    for c in title.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Writes the blog post as a markdown file with YAML front matter.
+pub fn to_markdown_file(blog: &Blog, content: &str) -> Result<String, BackupError> {
+    let front_matter = FrontMatter {
+        id: Some(blog.get_id_string()),
+        title: blog.title.clone(),
+        slug: get_slug(&blog.title),
+        seo_desc: blog.seo_desc.clone(),
+        tags: blog.tags.clone(),
+        date: Some(blog.timestamp),
+        updated: blog.last_modified,
+        public: blog.is_public,
+    };
+    let yaml = serde_norway::to_string(&front_matter).map_err(|e| {
+        log::error!("Failed to serialise front matter: {:?}", e);
+        BackupError::ExportError
+    })?;
+    Ok(format!(
+        "{}\n{}{}\n\n{}\n",
+        FRONT_MATTER_DELIMITER,
+        yaml,
+        FRONT_MATTER_DELIMITER,
+        content.trim_end()
+    ))
+}
+
+/// Splits a markdown file into its front matter and its content.
+///
+/// Returns none if the file does not start with valid YAML front matter.
+pub fn parse_markdown_file(text: &str) -> Option<(FrontMatter, String)> {
+    let text = text.trim_start_matches('\u{feff}');
+    let mut lines = text.split_inclusive('\n');
+    if lines.next()?.trim_end() != FRONT_MATTER_DELIMITER {
+        return None;
+    }
+
+    let mut yaml = String::new();
+    let mut is_closed = false;
+    for line in lines.by_ref() {
+        if matches!(line.trim_end(), FRONT_MATTER_DELIMITER | "...") {
+            is_closed = true;
+            break;
+        }
+        yaml.push_str(line);
+    }
+    if !is_closed {
+        return None;
+    }
+    let front_matter: FrontMatter = serde_norway::from_str(&yaml)
+        .map_err(|e| log::warn!("Failed to parse front matter: {:?}", e))
+        .ok()?;
+    let content = lines.collect::<String>().trim().to_string();
+    Some((front_matter, content))
+}
+
+/// Returns the link and image destinations in the content that point to local files.
+fn get_local_paths(content: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    for event in Parser::new_ext(content, get_default_options()) {
+        let dest_url = match event {
+            Event::Start(Tag::Link { dest_url, .. })
+            | Event::Start(Tag::Image { dest_url, .. }) => dest_url,
+            _ => continue,
+        };
+        let is_local = url::Url::parse(&dest_url).is_err()
+            && !dest_url.is_empty()
+            && !dest_url.starts_with(['#', '?']);
+        if is_local && !paths.iter().any(|path| path == dest_url.as_ref()) {
+            paths.push(dest_url.to_string());
+        }
+    }
+    paths
+}
+
+/// Resolves the path relative to the directory of the markdown file.
+///
+/// Returns none if the path escapes the root of the import.
+fn resolve_path(md_file_name: &str, path: &str) -> Option<String> {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let mut components: Vec<&str> = md_file_name.split('/').collect();
+    components.pop();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop()?;
+            }
+            _ => components.push(component),
+        }
+    }
+    Some(components.join("/"))
+}
+
+#[inline]
+pub fn normalise_file_name(name: &str) -> String {
+    name.replace('\\', "/").trim_start_matches("./").to_string()
+}
+
+#[inline]
+pub fn is_markdown_file(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.ends_with(".md") || name.ends_with(".markdown")
+}
+
+/// Extracts the files inside the zip archives so that
+/// every uploaded file can be looked up by its path.
+///
+/// The size and file count limits apply to all the uploaded archives together.
+pub fn expand_uploaded_files(
+    files: Vec<(String, Vec<u8>)>,
+) -> Result<BTreeMap<String, Vec<u8>>, BackupError> {
+    let mut expanded = BTreeMap::new();
+    let mut budget = constants::MAX_ARCHIVE_TOTAL_SIZE;
+    let mut entry_count = 0;
+    for (name, data) in files {
+        if !name.to_lowercase().ends_with(".zip") {
+            expanded.insert(normalise_file_name(&name), data);
+            continue;
+        }
+
+        let mut archive = zip::ZipArchive::new(Cursor::new(data)).map_err(|e| {
+            log::error!("Failed to open markdown archive {}: {:?}", name, e);
+            BackupError::InvalidArchive
+        })?;
+        entry_count += archive.len();
+        if entry_count > constants::MAX_ARCHIVE_ENTRIES {
+            log::warn!("Markdown archives have too many files");
+            return Err(BackupError::InvalidArchive);
+        }
+        for idx in 0..archive.len() {
+            let file = archive.by_index(idx).map_err(|e| {
+                log::error!("Failed to read markdown archive {}: {:?}", name, e);
+                BackupError::InvalidArchive
+            })?;
+            // skip directories and paths that try to escape the archive
+            let Some(path) = file.enclosed_name().filter(|_| file.is_file()) else {
+                continue;
+            };
+            let path = normalise_file_name(&path.to_string_lossy());
+            let data = read_archive_entry(file, &path, &mut budget)?;
+            expanded.insert(path, data);
+        }
+    }
+    Ok(expanded)
+}
+
+async fn get_blogs(
+    db_client: &DbClient,
+    blog_id: Option<&ObjectId>,
+) -> Result<Vec<Blog>, BackupError> {
+    let filter = match blog_id {
+        Some(blog_id) => doc! {"_id": blog_id},
+        None => doc! {},
+    };
+    let blogs: Vec<Blog> = db_client
+        .get_blog_collection()
+        .find(filter)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blogs from database: {:?}", e);
+            BackupError::ExportError
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blogs from database: {:?}", e);
+            BackupError::ExportError
+        })?;
+    if blog_id.is_some() && blogs.is_empty() {
+        return Err(BackupError::BlogNotFound);
+    }
+    Ok(blogs)
+}
+
+/// Exports one or every blog post as markdown files with YAML front matter
+/// into a zip archive together with the media that the posts embed.
+///
+/// The media is placed in a shared directory and the URLs in the content
+/// are rewritten to relative paths so that the archive can be imported again.
+pub async fn export_markdown(
+    db_client: &DbClient,
+    s3_client: &s3::Client,
+    blog_id: Option<&ObjectId>,
+) -> Result<Vec<u8>, BackupError> {
+    let blogs = get_blogs(db_client, blog_id).await?;
+
+    let mut entries: Vec<(String, Vec<u8>)> = Vec::with_capacity(blogs.len());
+    let mut exported_media: HashSet<String> = HashSet::new();
+    let mut used_names: HashSet<String> = HashSet::with_capacity(blogs.len());
+    for blog in blogs.iter() {
+        let mut content = blog.content.clone();
+        for file in blog.files.iter() {
+            if !content.contains(&file.url) {
+                continue;
+            }
+            let (bucket, obj_name) = storage::extract_bucket_and_blob_from_url(&file.url);
+            let media_path = format!("{}/{}", constants::MARKDOWN_MEDIA_DIR, file.name);
+            if !exported_media.contains(&media_path) {
+                let Some(data) = storage::download_blob(s3_client, &bucket, &obj_name).await else {
+                    // keep the public url so that the media is not lost from the content
+                    log::warn!("Failed to download {} for markdown export", file.url);
+                    continue;
+                };
+                entries.push((media_path.clone(), data));
+                exported_media.insert(media_path.clone());
+            }
+            content = content.replace(&file.url, &media_path);
+        }
+
+        let mut name = format!("{}.md", get_slug(&blog.title));
+        if name == ".md" || !used_names.insert(name.clone()) {
+            name = format!("{}-{}.md", get_slug(&blog.title), blog.get_id_string());
+            used_names.insert(name.clone());
+        }
+        entries.push((name, to_markdown_file(blog, &content)?.into_bytes()));
+    }
+
+    let write_archive = || -> zip::result::ZipResult<Vec<u8>> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for (name, data) in entries.iter() {
+            zip.start_file(name.as_str(), options)?;
+            zip.write_all(data)?;
+        }
+        Ok(zip.finish()?.into_inner())
+    };
+    write_archive().map_err(|e| {
+        log::error!("Failed to write markdown archive: {:?}", e);
+        BackupError::ExportError
+    })
+}
+
+/// Maps the slug of every blog post's title to the ID of the blog post.
+async fn get_blog_slugs(db_client: &DbClient) -> Result<HashMap<String, ObjectId>, BackupError> {
+    let blog_col: Collection<ProjectedBlog> =
+        db_client.get_custom_collection(constants::BLOG_COLLECTION);
+    let options = FindOptions::builder()
+        .projection(doc! {blog::TITLE_KEY: 1})
+        .build();
+    let blogs: Vec<ProjectedBlog> = blog_col
+        .find(doc! {})
+        .with_options(options)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blog titles from database: {:?}", e);
+            BackupError::InternalServerError
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blog titles from database: {:?}", e);
+            BackupError::InternalServerError
+        })?;
+    Ok(blogs
+        .into_iter()
+        .filter_map(|blog_post| Some((get_slug(&blog_post.title?), blog_post.id?)))
+        .collect())
+}
+
+/// A blog post read from an export or another blogging platform that is ready to be imported.
+pub struct ImportedPost {
+    // the path of the source file which relative media paths are resolved against
+    pub source: String,
+    pub front_matter: FrontMatter,
+    pub content: String,
+}
+
+/// Returns the paths that the local path could refer to inside the import.
+fn get_asset_candidates(md_file_name: &str, local_path: &str) -> Vec<String> {
+    match local_path.strip_prefix('/') {
+        // site relative paths are served from the root or from Hugo's static directory
+        Some(root_path) => {
+            let root_path = resolve_path("", root_path).unwrap_or_default();
+            vec![format!("static/{}", root_path), root_path]
+        }
+        None => resolve_path(md_file_name, local_path).into_iter().collect(),
+    }
+}
+
+/// A local file from the import that the content embeds.
+struct LocalMedia<'a> {
+    file: FileInfo,
+    obj_name: String,
+    data: &'a [u8],
+}
+
+/// Finds the local media that the content embeds and rewrites the
+/// relative paths in the content to the public URLs that the media will have.
+fn resolve_local_media<'a>(
+    md_file_name: &str,
+    content: &mut String,
+    assets: &'a BTreeMap<String, Vec<u8>>,
+    report: &mut MarkdownImportReport,
+) -> Vec<LocalMedia<'a>> {
+    let mut media: Vec<LocalMedia> = Vec::new();
+    for local_path in get_local_paths(content) {
+        let path = local_path.split(['?', '#']).next().unwrap_or_default();
+        if get_media_type(path) == "application/octet-stream" {
+            // only media can be embedded, other local links are left as they are
+            continue;
+        }
+        let asset = get_asset_candidates(md_file_name, &local_path)
+            .into_iter()
+            .find_map(|path| assets.get(&path).map(|data| (path, data)));
+        let Some((path, data)) = asset else {
+            report
+                .missing_media
+                .push(format!("{}: {}", md_file_name, local_path));
+            continue;
+        };
+
+        let file_ext = Path::new(&path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        let file_name = format!("{}.{}", hash_file_data(data), file_ext);
+        let obj_name = get_media_obj_name(&file_name);
+        let url = get_public_url(&obj_name);
+        *content = content.replace(&format!("]({}", local_path), &format!("]({}", url));
+        let file = FileInfo {
+            name: file_name,
+            url,
+            signed_url: None,
+        };
+        if !media.iter().any(|local_media| local_media.file == file) {
+            media.push(LocalMedia {
+                file,
+                obj_name,
+                data,
+            });
+            report.media += 1;
+        }
+    }
+    media
+}
+
+/// Uploads the media that is not stored yet and releases the files
+/// that the blog post no longer uses within the journal's operation.
+async fn store_local_media(
+    journal: &mut BlogOperationJournal<'_>,
+    s3_client: &s3::Client,
+    media: &[LocalMedia<'_>],
+    released: &[FileInfo],
+) -> Result<(), BackupError> {
+    for local_media in media.iter() {
+        let is_stored = journal
+            .reference_media(&local_media.obj_name)
+            .await
+            .map_err(|e| {
+                log::error!(
+                    "Failed to reference imported media {}: {}",
+                    local_media.obj_name,
+                    e
+                );
+                BackupError::InternalServerError
+            })?;
+        if is_stored {
+            continue;
+        }
+        if !storage::upload_blob(
+            s3_client,
+            constants::BUCKET,
+            &local_media.obj_name,
+            local_media.data.to_vec(),
+        )
+        .await
+        {
+            return Err(BackupError::InternalServerError);
+        }
+        log::info!("Uploaded imported media, {}", local_media.obj_name);
+    }
+    for file in released.iter() {
+        journal
+            .release_file(&file.url)
+            .await
+            .map_err(|_| BackupError::InternalServerError)?;
+    }
+    Ok(())
+}
+
+enum ImportOutcome {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+/// Writes the imported blog post to the database, storing its media through
+/// the blog operation journal like the editor does so that a failed import
+/// does not leave media behind in the bucket.
+///
+/// Nothing is written on a dry run but the outcome is still returned.
+async fn import_blog(
+    db_client: &DbClient,
+    s3_client: &s3::Client,
+    blog_id: ObjectId,
+    post: ImportedPost,
+    media: Vec<LocalMedia<'_>>,
+    imported_by: Option<ObjectId>,
+    dry_run: bool,
+) -> Result<ImportOutcome, BackupError> {
+    let ImportedPost {
+        source,
+        front_matter,
+        content,
+    } = post;
+    let blog_col = db_client.get_blog_collection();
+    let blog_in_db = blog_col
+        .find_one(doc! {"_id": blog_id})
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blog from database: {:?}", e);
+            BackupError::InternalServerError
+        })?;
+    let mut files: Vec<FileInfo> = media
+        .iter()
+        .map(|local_media| local_media.file.clone())
+        .collect();
+
+    let Some(blog_in_db) = blog_in_db else {
+        if dry_run {
+            return Ok(ImportOutcome::Created);
+        }
+        let mut blog = Blog::new(
+            front_matter.title,
+            front_matter.seo_desc,
+            content,
+            &front_matter.tags,
+            &files,
+            front_matter.public,
+        );
+        blog.id = blog_id;
+        blog.timestamp = front_matter.date.unwrap_or(blog.timestamp);
+        blog.last_modified = front_matter.updated;
+        blog.author_id = imported_by;
+
+        let mut journal =
+            BlogOperationJournal::begin(db_client, s3_client, blog_id, BlogOperationKind::Create)
+                .await
+                .map_err(|_| BackupError::InternalServerError)?;
+        if let Err(e) = store_local_media(&mut journal, s3_client, &media, &[]).await {
+            journal.rollback().await;
+            return Err(e);
+        }
+        if let Err(e) = blog_col.insert_one(&blog).await {
+            log::error!("Failed to insert imported blog {}: {:?}", source, e);
+            journal.rollback().await;
+            return Err(BackupError::InternalServerError);
+        }
+        journal.commit().await;
+        back_up_blog(s3_client, &blog).await;
+        return Ok(ImportOutcome::Created);
+    };
+
+    let mut blog = blog_in_db.clone();
+    blog.title = front_matter.title;
+    blog.seo_desc = front_matter.seo_desc;
+    blog.tags = front_matter.tags;
+    blog.content = content;
+    blog.is_public = front_matter.public;
+    // keep the stored files that the content still uses, e.g. the
+    // public URLs of media that could not be exported as files
+    for file in blog_in_db.files.iter() {
+        if !files.contains(file) && blog.content.contains(&file.url) {
+            files.push(file.clone());
+        }
+    }
+    let released: Vec<FileInfo> = blog_in_db
+        .files
+        .iter()
+        .filter(|file| !files.contains(file))
+        .cloned()
+        .collect();
+    blog.files = files;
+    let is_unchanged = blog.title == blog_in_db.title
+        && blog.seo_desc == blog_in_db.seo_desc
+        && blog.tags == blog_in_db.tags
+        && blog.content == blog_in_db.content
+        && blog.is_public == blog_in_db.is_public
+        && released.is_empty()
+        && blog.files.len() == blog_in_db.files.len();
+    if is_unchanged {
+        return Ok(ImportOutcome::Unchanged);
+    } else if dry_run {
+        return Ok(ImportOutcome::Updated);
+    }
+
+    let mut journal =
+        BlogOperationJournal::begin(db_client, s3_client, blog_id, BlogOperationKind::Update)
+            .await
+            .map_err(|_| BackupError::InternalServerError)?;
+    if let Err(e) = store_local_media(&mut journal, s3_client, &media, &released).await {
+        journal.rollback().await;
+        return Err(e);
+    }
+
+    blog.last_modified = Some(chrono::Utc::now());
+    blog.version += 1;
+    // only replace the blog post if it was not edited in the meantime
+    let result = blog_col
+        .replace_one(
+            doc! {"_id": blog_id, blog::VERSION_KEY: blog_in_db.version},
+            &blog,
+        )
+        .await;
+    match result {
+        Ok(result) if result.matched_count == 0 => {
+            log::warn!("Blog {} was edited during the import", blog_id);
+            journal.rollback().await;
+            return Err(BackupError::InternalServerError);
+        }
+        Ok(_) => journal.commit().await,
+        Err(e) => {
+            log::error!("Failed to replace imported blog {}: {:?}", source, e);
+            journal.rollback().await;
+            return Err(BackupError::InternalServerError);
+        }
+    }
+    // the imported file replaces any unpublished edits
+    delete_blog_draft(db_client, &blog_id).await;
+    back_up_blog(s3_client, &blog).await;
+    Ok(ImportOutcome::Updated)
+}
+
+/// Imports the blog posts and the local media that they embed from the assets.
+///
+/// Existing blog posts are only updated if the front matter has their ID or
+/// a slug that matches their title, e.g. from a markdown export, so that a new
+/// post cannot replace another one just because their titles are alike.
+/// The created blog posts are written by the importing user.
+///
+/// On a dry run, the report describes what would be imported without
+/// writing anything to the database or the bucket.
+pub async fn import_posts(
+    db_client: &DbClient,
+    s3_client: &s3::Client,
+    posts: Vec<ImportedPost>,
+    assets: &BTreeMap<String, Vec<u8>>,
+    imported_by: Option<ObjectId>,
+    dry_run: bool,
+    report: &mut MarkdownImportReport,
+) -> Result<(), BackupError> {
+    report.dry_run = dry_run;
+    let mut slugs = get_blog_slugs(db_client).await?;
+    for post in posts {
+        let ImportedPost {
+            source,
+            mut front_matter,
+            mut content,
+        } = post;
+        front_matter.title = front_matter.title.trim().to_string();
+        if front_matter.title.is_empty() {
+            report.failed.push(format!("{}: Empty title", source));
+            continue;
+        } else if front_matter.title.len() > constants::TITLE_MAX_LENGTH {
+            report.failed.push(format!("{}: Title too long", source));
+            continue;
+        } else if front_matter.tags.len() > constants::MAX_TAGS {
+            report.failed.push(format!("{}: Too many tags", source));
+            continue;
+        } else if content.trim().is_empty() {
+            report.failed.push(format!("{}: Empty content", source));
+            continue;
+        }
+
+        let title_slug = get_slug(&front_matter.title);
+        let slug = front_matter.slug.trim().to_string();
+        let blog_id = front_matter
+            .id
+            .as_deref()
+            .and_then(|id| ObjectId::parse_str(id).ok())
+            .or_else(|| match slug.as_str() {
+                "" => None,
+                slug => slugs.get(slug).copied(),
+            })
+            .unwrap_or_else(ObjectId::new);
+
+        let media = resolve_local_media(&source, &mut content, assets, report);
+        let post = ImportedPost {
+            source: source.clone(),
+            front_matter,
+            content,
+        };
+        let outcome = import_blog(
+            db_client,
+            s3_client,
+            blog_id,
+            post,
+            media,
+            imported_by,
+            dry_run,
+        )
+        .await;
+        let entry = format!("{}: {}", source, blog_id.to_hex());
+        match outcome {
+            Ok(ImportOutcome::Created) => report.created.push(entry),
+            Ok(ImportOutcome::Updated) => report.updated.push(entry),
+            Ok(ImportOutcome::Unchanged) => report.unchanged.push(entry),
+            Err(e) => {
+                report.failed.push(format!("{}: {}", source, e));
+                continue;
+            }
+        }
+        // later posts with the same explicit slug update this blog post
+        slugs.insert(title_slug, blog_id);
+        if !slug.is_empty() {
+            slugs.insert(slug, blog_id);
+        }
+    }
+    Ok(())
+}
+
+/// Imports markdown files with YAML front matter, either uploaded
+/// directly or inside zip archives, as blog posts.
+pub async fn import_markdown(
+    db_client: &DbClient,
+    s3_client: &s3::Client,
+    files: Vec<(String, Vec<u8>)>,
+    imported_by: Option<ObjectId>,
+    dry_run: bool,
+) -> Result<MarkdownImportReport, BackupError> {
+    let mut assets = web::block(move || expand_uploaded_files(files))
+        .await
+        .map_err(|_| BackupError::InternalServerError)??;
+    let md_file_names: Vec<String> = assets
+        .keys()
+        .filter(|name| is_markdown_file(name))
+        .cloned()
+        .collect();
+    if md_file_names.is_empty() {
+        return Err(BackupError::InvalidArchive);
+    }
+
+    let mut report = MarkdownImportReport::default();
+    let mut posts = Vec::with_capacity(md_file_names.len());
+    for md_file_name in md_file_names {
+        let Some(data) = assets.remove(&md_file_name) else {
+            continue;
+        };
+        match std::str::from_utf8(&data)
+            .ok()
+            .and_then(parse_markdown_file)
+        {
+            Some((front_matter, content)) => posts.push(ImportedPost {
+                source: md_file_name,
+                front_matter,
+                content,
+            }),
+            None => report
+                .failed
+                .push(format!("{}: Invalid front matter", md_file_name)),
+        }
+    }
+    import_posts(
+        db_client,
+        s3_client,
+        posts,
+        &assets,
+        imported_by,
+        dry_run,
+        &mut report,
+    )
+    .await?;
+    Ok(report)
+}
@@ -0,0 +1,183 @@
+use crate::constants;
+use crate::database::db::DbClient;
+use crate::errors::blog::BlogError;
+use crate::models::media::MediaReference;
+use crate::models::{blog, projected_blog::ProjectedBlog};
+use crate::templates::admin::MediaInfo;
+use crate::utils::blog::file_utils::get_public_url;
+use crate::utils::storage;
+
+use futures_util::TryStreamExt;
+use mongodb::bson::doc;
+use mongodb::options::FindOptions;
+use mongodb::Collection;
+use std::collections::HashMap;
+use std::path::Path;
+
+pub fn format_file_size(size: i64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = size as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", size, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+pub fn get_media_type(file_name: &str) -> &'static str {
+    let ext = Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "mp4" => "video/mp4",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Maps the name of every object in the public bucket that is referenced
+/// by a blog post's files to the blog posts that reference it.
+pub async fn get_media_references(
+    db_client: &DbClient,
+) -> Result<HashMap<String, Vec<MediaReference>>, BlogError> {
+    let blog_col: Collection<ProjectedBlog> =
+        db_client.get_custom_collection(constants::BLOG_COLLECTION);
+    let options = FindOptions::builder()
+        .projection(doc! {blog::TITLE_KEY: 1, blog::FILES_KEY: 1})
+        .build();
+    let mut cursor = blog_col
+        .find(doc! {})
+        .with_options(options)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blog files from database: {:?}", e);
+            BlogError::InternalServerError
+        })?;
+
+    let mut references: HashMap<String, Vec<MediaReference>> = HashMap::new();
+    loop {
+        match cursor.try_next().await {
+            Ok(Some(blog_post)) => {
+                let reference = MediaReference {
+                    blog_id: blog_post.id.map(|id| id.to_hex()).unwrap_or_default(),
+                    title: blog_post.title.unwrap_or_default(),
+                };
+                for file in blog_post.files.unwrap_or_default() {
+                    let (bucket, obj_name) = storage::extract_bucket_and_blob_from_url(&file.url);
+                    if bucket != constants::BUCKET || obj_name.is_empty() {
+                        continue;
+                    }
+                    references
+                        .entry(obj_name)
+                        .or_default()
+                        .push(reference.clone());
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                log::error!("Failed to get blog files from database: {:?}", e);
+                return Err(BlogError::InternalServerError);
+            }
+        }
+    }
+    Ok(references)
+}
+
+/// Removes the stored metadata of a media file after its object has been deleted.
+pub async fn delete_media_info(db_client: &DbClient, obj_name: &str) {
+    if let Err(e) = db_client
+        .get_media_collection()
+        .delete_one(doc! {"_id": obj_name})
+        .await
+    {
+        log::error!("Failed to delete media info from database: {:?}", e);
+    }
+}
+
+pub async fn get_alt_texts(db_client: &DbClient) -> Result<HashMap<String, String>, BlogError> {
+    let cursor = db_client
+        .get_media_collection()
+        .find(doc! {})
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get media from database: {:?}", e);
+            BlogError::InternalServerError
+        })?;
+    let media = cursor.try_collect::<Vec<_>>().await.map_err(|e| {
+        log::error!("Failed to get media from database: {:?}", e);
+        BlogError::InternalServerError
+    })?;
+    Ok(media
+        .into_iter()
+        .map(|media| (media.name, media.alt_text))
+        .collect())
+}
+
+/// Lists every stored media file in the public bucket, newest first.
+///
+/// If `query` is not empty, only media whose file name, alt text
+/// or referencing blog post titles contain the query are returned.
+pub async fn get_media_library(
+    db_client: &DbClient,
+    s3_client: &aws_sdk_s3::Client,
+    query: &str,
+) -> Result<Vec<MediaInfo>, BlogError> {
+    let prefix = format!("{}/", constants::get_blog_obj_prefix());
+    let (blobs, references, alt_texts) = tokio::join!(
+        storage::list_blobs(s3_client, constants::BUCKET, &prefix),
+        get_media_references(db_client),
+        get_alt_texts(db_client),
+    );
+    let mut blobs = blobs.ok_or(BlogError::FileListError)?;
+    let mut references = references?;
+    let mut alt_texts = alt_texts?;
+    blobs.sort_by_key(|blob| std::cmp::Reverse(blob.last_modified));
+
+    let query = query.trim().to_lowercase();
+    let mut media_list = Vec::with_capacity(blobs.len());
+    for blob in blobs.into_iter() {
+        let file_name = Path::new(&blob.name)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_string();
+        let alt_text = alt_texts.remove(&blob.name).unwrap_or_default();
+        let posts = references.remove(&blob.name).unwrap_or_default();
+        let is_match = query.is_empty()
+            || file_name.to_lowercase().contains(&query)
+            || alt_text.to_lowercase().contains(&query)
+            || posts
+                .iter()
+                .any(|post| post.title.to_lowercase().contains(&query));
+        if !is_match {
+            continue;
+        }
+
+        let media_type = get_media_type(&file_name);
+        media_list.push(MediaInfo {
+            url: get_public_url(&blob.name),
+            is_video: media_type.starts_with("video/"),
+            media_type,
+            size: format_file_size(blob.size),
+            uploaded: blob
+                .last_modified
+                .map(|dt| dt.to_rfc3339())
+                .unwrap_or_default(),
+            name: blob.name,
+            file_name,
+            alt_text,
+            posts,
+        });
+    }
+    Ok(media_list)
+}
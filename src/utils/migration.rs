@@ -0,0 +1,616 @@
+use crate::constants;
+use crate::database::db::DbClient;
+use crate::errors::backup::BackupError;
+use crate::models::front_matter::{FrontMatter, MarkdownImportReport};
+use crate::utils::markdown_files::{expand_uploaded_files, import_posts};
+
+use actix_web::web;
+use aws_sdk_s3 as s3;
+use bson::oid::ObjectId;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+
+/// Parses the date formats used by WordPress, Hugo and Jekyll.
+///
+/// Dates without a timezone are assumed to be in UTC.
+pub fn parse_date(date: &str) -> Option<DateTime<Utc>> {
+    let date = date.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Some(date.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S %z", "%Y-%m-%d %H:%M %z"] {
+        if let Ok(date) = DateTime::parse_from_str(date, format) {
+            return Some(date.with_timezone(&Utc));
+        }
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(date, format) {
+            return Some(date.and_utc());
+        }
+    }
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| date.and_utc())
+}
+
+/// Merges the categories and tags of the source blog into
+/// the tags of the blog post while keeping their order.
+pub fn merge_tags<'a>(tags: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut merged: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim();
+        if tag.is_empty() || tag.eq_ignore_ascii_case("uncategorized") {
+            continue;
+        }
+        if !merged.iter().any(|merged| merged.eq_ignore_ascii_case(tag)) {
+            merged.push(tag.to_string());
+        }
+    }
+    // the extra tags are dropped instead of failing the whole post
+    merged.truncate(constants::MAX_TAGS);
+    merged
+}
+
+/// Converts a HTML body to markdown.
+///
+/// Bodies without any paragraphs use blank lines to separate them
+/// like the WordPress classic editor, so they are wrapped first.
+pub fn html_to_markdown(html: &str) -> String {
+    let html = if html.contains("<p") {
+        html.to_string()
+    } else {
+        html.split("\n\n")
+            .map(str::trim)
+            .filter(|paragraph| !paragraph.is_empty())
+            .map(|paragraph| format!("<p>{}</p>", paragraph.replace('\n', "<br>")))
+            .collect::<String>()
+    };
+    html2md::parse_html(&html).trim().to_string()
+}
+
+/// Strips the tags from the HTML, e.g. for an excerpt used as the SEO description.
+pub fn html_to_text(html: &str) -> String {
+    static TAG_REGEX: Lazy<regex::Regex> = Lazy::new(|| regex::Regex::new(r"<[^>]*>").unwrap());
+    TAG_REGEX
+        .replace_all(html, " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[inline]
+fn new_front_matter(title: String) -> FrontMatter {
+    FrontMatter {
+        id: None,
+        title,
+        slug: String::new(),
+        seo_desc: String::new(),
+        tags: Vec::new(),
+        date: None,
+        updated: None,
+        public: true,
+    }
+}
+
+async fn download_attachment(client: &reqwest::Client, url: &str) -> Option<Vec<u8>> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| log::warn!("Failed to download attachment {}: {:?}", url, e))
+        .ok()?;
+    if response
+        .content_length()
+        .is_some_and(|size| size > constants::MAX_FILE_SIZE as u64)
+    {
+        log::warn!("Attachment is too large, {}", url);
+        return None;
+    }
+    let data = response
+        .bytes()
+        .await
+        .map_err(|e| log::warn!("Failed to download attachment {}: {:?}", url, e))
+        .ok()?;
+    Some(data.to_vec())
+}
+
+/// Imports the posts of a WordPress eXtended RSS export and
+/// downloads the media that they embed from the WordPress site.
+///
+/// The attachments are downloaded on a dry run too so that
+/// the report lists the media that could not be downloaded.
+pub async fn import_wordpress(
+    db_client: &DbClient,
+    s3_client: &s3::Client,
+    xml: Vec<u8>,
+    imported_by: Option<ObjectId>,
+    dry_run: bool,
+) -> Result<MarkdownImportReport, BackupError> {
+    let export = web::block(move || {
+        let xml = String::from_utf8(xml).map_err(|_| BackupError::InvalidArchive)?;
+        wxr::parse_wxr(&xml)
+    })
+    .await
+    .map_err(|_| BackupError::InternalServerError)??;
+
+    let client = reqwest::Client::builder()
+        .user_agent(constants::IMPORT_USER_AGENT)
+        .timeout(constants::IMPORT_DOWNLOAD_TIMEOUT)
+        .build()
+        .map_err(|e| {
+            log::error!("Failed to build the attachment HTTP client: {:?}", e);
+            BackupError::InternalServerError
+        })?;
+    let mut report = MarkdownImportReport {
+        skipped: export.skipped,
+        ..Default::default()
+    };
+    let mut assets: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    let mut posts = Vec::with_capacity(export.posts.len());
+    for wxr::WxrPost {
+        mut post,
+        attachments,
+    } in export.posts
+    {
+        for (path, url) in attachments {
+            if assets.contains_key(&path) {
+                continue;
+            }
+            match download_attachment(&client, &url).await {
+                Some(data) => {
+                    assets.insert(path, data);
+                }
+                None => {
+                    // keep linking to the original site instead of a missing file
+                    post.content = post
+                        .content
+                        .replace(&format!("]({}", path), &format!("]({}", url));
+                    report
+                        .missing_media
+                        .push(format!("{}: {}", post.source, url));
+                }
+            }
+        }
+        posts.push(post);
+    }
+    import_posts(
+        db_client,
+        s3_client,
+        posts,
+        &assets,
+        imported_by,
+        dry_run,
+        &mut report,
+    )
+    .await?;
+    Ok(report)
+}
+
+/// Imports the posts of a Hugo or Jekyll site, either uploaded
+/// as separate files or inside zip archives, with their local media.
+pub async fn import_content_dir(
+    db_client: &DbClient,
+    s3_client: &s3::Client,
+    files: Vec<(String, Vec<u8>)>,
+    imported_by: Option<ObjectId>,
+    dry_run: bool,
+) -> Result<MarkdownImportReport, BackupError> {
+    let (assets, posts, failed) = web::block(move || {
+        let assets = expand_uploaded_files(files)?;
+        let (posts, failed) = content_dir::parse_content_dir(&assets);
+        Ok::<_, BackupError>((assets, posts, failed))
+    })
+    .await
+    .map_err(|_| BackupError::InternalServerError)??;
+    if posts.is_empty() && failed.is_empty() {
+        return Err(BackupError::InvalidArchive);
+    }
+
+    let mut report = MarkdownImportReport {
+        failed,
+        ..Default::default()
+    };
+    import_posts(
+        db_client,
+        s3_client,
+        posts,
+        &assets,
+        imported_by,
+        dry_run,
+        &mut report,
+    )
+    .await?;
+    Ok(report)
+}
+
+pub mod wxr {
+    use super::{html_to_markdown, html_to_text, merge_tags, new_front_matter, parse_date};
+    use crate::errors::backup::BackupError;
+    use crate::utils::link_checker::get_outbound_links;
+    use crate::utils::markdown_files::{get_slug, ImportedPost};
+    use crate::utils::media::get_media_type;
+
+    use roxmltree::{Document, Node};
+    use std::collections::BTreeMap;
+
+    const CONTENT_NS: &str = "http://purl.org/rss/1.0/modules/content/";
+    // the version at the end of the namespace differs between WordPress releases
+    const WP_NS_PREFIX: &str = "http://wordpress.org/export/";
+    const EXCERPT_NS_SUFFIX: &str = "/excerpt/";
+    pub const ATTACHMENT_DIR: &str = "attachments";
+
+    /// A WordPress post with the URLs of the attachments
+    /// that must be downloaded before it can be imported.
+    pub struct WxrPost {
+        pub post: ImportedPost,
+        pub attachments: BTreeMap<String, String>,
+    }
+
+    #[derive(Default)]
+    pub struct WxrExport {
+        pub posts: Vec<WxrPost>,
+        // the title of the item followed by the reason it was skipped
+        pub skipped: Vec<String>,
+    }
+
+    fn get_child_text<'a>(item: Node<'a, '_>, is_ns: impl Fn(&str) -> bool, name: &str) -> &'a str {
+        item.children()
+            .find(|child| {
+                child.tag_name().name() == name && child.tag_name().namespace().is_some_and(&is_ns)
+            })
+            .and_then(|child| child.text())
+            .unwrap_or_default()
+    }
+
+    #[inline]
+    fn is_wp_ns(ns: &str) -> bool {
+        ns.starts_with(WP_NS_PREFIX) && !ns.ends_with(EXCERPT_NS_SUFFIX)
+    }
+
+    #[inline]
+    fn is_excerpt_ns(ns: &str) -> bool {
+        ns.ends_with(EXCERPT_NS_SUFFIX)
+    }
+
+    /// Returns the path under the attachment directory for the WordPress media URL.
+    fn get_attachment_path(url: &str) -> Option<String> {
+        let url = url::Url::parse(url).ok()?;
+        let path = url.path().trim_matches('/');
+        if path.is_empty() || get_media_type(path) == "application/octet-stream" {
+            return None;
+        }
+        Some(format!(
+            "{}/{}/{}",
+            ATTACHMENT_DIR,
+            url.host_str().unwrap_or_default(),
+            path
+        ))
+    }
+
+    /// Replaces the URLs of the site's uploaded media with local paths so that
+    /// the media is stored in the bucket once the attachments are downloaded.
+    fn localise_attachments(
+        content: &mut String,
+        site_url: &str,
+        attachment_urls: &[&str],
+    ) -> BTreeMap<String, String> {
+        let site_host = url::Url::parse(site_url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| host.to_string()));
+        let mut attachments = BTreeMap::new();
+        for url in get_outbound_links(content) {
+            let is_upload = attachment_urls.contains(&url.as_str())
+                || url::Url::parse(&url).ok().is_some_and(|parsed_url| {
+                    parsed_url.host_str().map(|host| host.to_string()) == site_host
+                        && parsed_url.path().contains("/wp-content/uploads/")
+                });
+            if !is_upload {
+                continue;
+            }
+            if let Some(path) = get_attachment_path(&url) {
+                *content = content.replace(&format!("]({}", url), &format!("]({}", path));
+                attachments.insert(path, url);
+            }
+        }
+        attachments
+    }
+
+    /// Parses a WordPress eXtended RSS export into blog posts.
+    ///
+    /// Only posts are imported, pages and trashed posts are skipped, and
+    /// drafts, pending and private posts are imported as private blog posts.
+    pub fn parse_wxr(xml: &str) -> Result<WxrExport, BackupError> {
+        let doc = Document::parse(xml).map_err(|e| {
+            log::warn!("Failed to parse WordPress export: {:?}", e);
+            BackupError::InvalidArchive
+        })?;
+        let channel = doc
+            .root_element()
+            .children()
+            .find(|node| node.has_tag_name("channel"))
+            .ok_or(BackupError::InvalidArchive)?;
+        let site_url = get_child_text(channel, is_wp_ns, "base_blog_url");
+        let items: Vec<Node> = channel
+            .children()
+            .filter(|node| node.has_tag_name("item"))
+            .collect();
+        let attachment_urls: Vec<&str> = items
+            .iter()
+            .filter(|item| get_child_text(**item, is_wp_ns, "post_type") == "attachment")
+            .map(|item| get_child_text(*item, is_wp_ns, "attachment_url"))
+            .filter(|url| !url.is_empty())
+            .collect();
+
+        let mut export = WxrExport::default();
+        for item in items {
+            if get_child_text(item, is_wp_ns, "post_type") != "post" {
+                continue;
+            }
+            let title = item
+                .children()
+                .find(|node| node.has_tag_name("title"))
+                .and_then(|node| node.text())
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            let status = get_child_text(item, is_wp_ns, "status");
+            let public = match status {
+                "publish" => true,
+                "draft" | "pending" | "private" | "future" => false,
+                _ => {
+                    export
+                        .skipped
+                        .push(format!("{}: Unsupported status {}", title, status));
+                    continue;
+                }
+            };
+
+            let mut front_matter = new_front_matter(title);
+            front_matter.public = public;
+            front_matter.slug = get_child_text(item, is_wp_ns, "post_name").to_string();
+            // unpublished posts have no GMT date
+            front_matter.date = [
+                get_child_text(item, is_wp_ns, "post_date_gmt"),
+                get_child_text(item, is_wp_ns, "post_date"),
+            ]
+            .into_iter()
+            .find_map(parse_date);
+            front_matter.updated = parse_date(get_child_text(item, is_wp_ns, "post_modified_gmt"));
+            front_matter.seo_desc = html_to_text(get_child_text(item, is_excerpt_ns, "encoded"));
+            front_matter.tags = merge_tags(
+                item.children()
+                    .filter(|node| {
+                        node.has_tag_name("category")
+                            && matches!(node.attribute("domain"), Some("category" | "post_tag"))
+                    })
+                    .filter_map(|node| node.text()),
+            );
+            let mut content =
+                html_to_markdown(get_child_text(item, |ns| ns == CONTENT_NS, "encoded"));
+            let attachments = localise_attachments(&mut content, site_url, &attachment_urls);
+            let slug = match front_matter.slug.as_str() {
+                "" => get_slug(&front_matter.title),
+                slug => slug.to_string(),
+            };
+            export.posts.push(WxrPost {
+                post: ImportedPost {
+                    // at the root so that the attachment paths resolve
+                    source: format!("{}.md", slug),
+                    front_matter,
+                    content,
+                },
+                attachments,
+            });
+        }
+        Ok(export)
+    }
+}
+
+pub mod content_dir {
+    use super::{html_to_markdown, merge_tags, new_front_matter, parse_date};
+    use crate::models::front_matter::FrontMatter;
+    use crate::utils::markdown_files::{get_slug, is_markdown_file, ImportedPost};
+
+    use serde_json::{Map, Value};
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    fn toml_to_json(value: toml::Value) -> Value {
+        match value {
+            toml::Value::String(s) => Value::String(s),
+            toml::Value::Integer(i) => Value::from(i),
+            toml::Value::Float(f) => Value::from(f),
+            toml::Value::Boolean(b) => Value::Bool(b),
+            toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+            toml::Value::Array(array) => {
+                Value::Array(array.into_iter().map(toml_to_json).collect())
+            }
+            toml::Value::Table(table) => Value::Object(
+                table
+                    .into_iter()
+                    .map(|(key, value)| (key, toml_to_json(value)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Splits the file into its YAML (---) or TOML (+++) front matter and its body.
+    fn split_front_matter(text: &str) -> Option<(Map<String, Value>, String)> {
+        let text = text.trim_start_matches('\u{feff}');
+        let mut lines = text.split_inclusive('\n');
+        let delimiter = match lines.next()?.trim_end() {
+            delimiter @ ("---" | "+++") => delimiter,
+            _ => return None,
+        };
+
+        let mut raw = String::new();
+        let mut is_closed = false;
+        for line in lines.by_ref() {
+            if line.trim_end() == delimiter {
+                is_closed = true;
+                break;
+            }
+            raw.push_str(line);
+        }
+        if !is_closed {
+            return None;
+        }
+        let front_matter = if delimiter == "+++" {
+            toml::from_str::<toml::Value>(&raw)
+                .map(toml_to_json)
+                .map_err(|e| log::warn!("Failed to parse TOML front matter: {:?}", e))
+                .ok()?
+        } else {
+            serde_norway::from_str::<Value>(&raw)
+                .map_err(|e| log::warn!("Failed to parse YAML front matter: {:?}", e))
+                .ok()?
+        };
+        // an empty front matter is parsed as null
+        let front_matter = match front_matter {
+            Value::Object(map) => map,
+            Value::Null => Map::new(),
+            _ => return None,
+        };
+        Some((front_matter, lines.collect::<String>().trim().to_string()))
+    }
+
+    fn get_str<'a>(front_matter: &'a Map<String, Value>, keys: &[&str]) -> Option<&'a str> {
+        keys.iter()
+            .find_map(|key| front_matter.get(*key).and_then(Value::as_str))
+            .filter(|value| !value.trim().is_empty())
+    }
+
+    /// Returns the values of a list that Jekyll also allows to be a space separated string.
+    fn get_list<'a>(front_matter: &'a Map<String, Value>, keys: &[&str]) -> Vec<&'a str> {
+        let mut list = Vec::new();
+        for key in keys {
+            match front_matter.get(*key) {
+                Some(Value::Array(values)) => list.extend(values.iter().filter_map(Value::as_str)),
+                Some(Value::String(values)) => list.extend(values.split([',', ' '])),
+                _ => {}
+            }
+        }
+        list
+    }
+
+    /// Returns the date and slug that Jekyll encodes in
+    /// the file name of a post, e.g. "2020-01-31-hello-world.md".
+    fn parse_file_name(path: &str) -> (Option<&str>, String) {
+        let path = Path::new(path);
+        let stem = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default();
+        // the directory of a Hugo page bundle is the slug of the post
+        let stem = match stem {
+            "index" => path
+                .parent()
+                .and_then(|parent| parent.file_name())
+                .and_then(|name| name.to_str())
+                .unwrap_or_default(),
+            stem => stem,
+        };
+        let is_dated =
+            stem.len() > 11 && stem.as_bytes()[10] == b'-' && parse_date(&stem[..10]).is_some();
+        if is_dated {
+            (Some(&stem[..10]), stem[11..].to_string())
+        } else {
+            (None, stem.to_string())
+        }
+    }
+
+    fn to_front_matter(path: &str, front_matter: &Map<String, Value>) -> FrontMatter {
+        let (file_date, file_slug) = parse_file_name(path);
+        let title = get_str(front_matter, &["title"])
+            .map(|title| title.trim().to_string())
+            .unwrap_or_else(|| file_slug.replace('-', " "));
+        let mut imported = new_front_matter(title);
+        imported.slug = get_str(front_matter, &["slug"])
+            .map(get_slug)
+            .unwrap_or(file_slug);
+        imported.seo_desc = get_str(front_matter, &["description", "summary", "excerpt"])
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        imported.tags = merge_tags(get_list(
+            front_matter,
+            &["categories", "category", "tags", "tag"],
+        ));
+        imported.date = get_str(front_matter, &["date", "publishDate"])
+            .or(file_date)
+            .and_then(parse_date);
+        imported.updated =
+            get_str(front_matter, &["lastmod", "last_modified_at"]).and_then(parse_date);
+        let is_draft = front_matter.get("draft").and_then(Value::as_bool) == Some(true)
+            || front_matter.get("published").and_then(Value::as_bool) == Some(false)
+            || path.contains("_drafts/");
+        imported.public = !is_draft;
+        imported
+    }
+
+    /// Returns whether the file is the content of a post in a Hugo or Jekyll site.
+    fn is_post_file(path: &str, is_jekyll: bool) -> bool {
+        let file_name = Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        // Hugo's list pages and repository files are not posts
+        if file_name.starts_with('_') || file_name == "readme.md" {
+            return false;
+        }
+        if is_jekyll {
+            let in_posts = path.contains("_posts/") || path.contains("_drafts/");
+            in_posts && (is_markdown_file(path) || file_name.ends_with(".html"))
+        } else {
+            is_markdown_file(path)
+        }
+    }
+
+    /// Reads the posts from a Hugo or Jekyll site or just its content directory.
+    ///
+    /// Returns the posts and the files that could not be parsed.
+    pub fn parse_content_dir(
+        files: &BTreeMap<String, Vec<u8>>,
+    ) -> (Vec<ImportedPost>, Vec<String>) {
+        let is_jekyll = files
+            .keys()
+            .any(|path| path.contains("_posts/") || path.contains("_drafts/"));
+        let has_hugo_content = files
+            .keys()
+            .any(|path| path.starts_with("content/") || path.contains("/content/"));
+
+        let mut posts = Vec::new();
+        let mut failed = Vec::new();
+        for (path, data) in files.iter() {
+            if !is_post_file(path, is_jekyll) {
+                continue;
+            }
+            if !is_jekyll && has_hugo_content && !path.split('/').any(|dir| dir == "content") {
+                continue;
+            }
+            let parsed = std::str::from_utf8(data)
+                .ok()
+                .and_then(split_front_matter)
+                .map(|(front_matter, body)| (to_front_matter(path, &front_matter), body));
+            let Some((front_matter, body)) = parsed else {
+                failed.push(format!("{}: Invalid front matter", path));
+                continue;
+            };
+            let content = if path.to_lowercase().ends_with(".html") {
+                html_to_markdown(&body)
+            } else {
+                body
+            };
+            posts.push(ImportedPost {
+                source: path.clone(),
+                front_matter,
+                content,
+            });
+        }
+        (posts, failed)
+    }
+}
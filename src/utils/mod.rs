@@ -1,16 +1,30 @@
+pub(crate) mod api_tokens;
+pub(crate) mod audit;
 pub(crate) mod auth;
 pub(crate) mod awards;
+pub(crate) mod backup;
 pub(crate) mod blog;
 pub(crate) mod certificates;
+pub(crate) mod conflict;
 pub(crate) mod datetime;
+pub(crate) mod draft;
 pub(crate) mod experiences;
 pub(crate) mod html;
-pub(crate) mod io;
+pub(crate) mod link_checker;
+pub(crate) mod lint;
+pub(crate) mod live_preview;
+pub(crate) mod markdown_files;
 pub(crate) mod md;
+pub(crate) mod media;
+pub(crate) mod migration;
+pub(crate) mod passkey;
+pub(crate) mod preview;
 pub(crate) mod projects;
 pub(crate) mod redirect;
 pub(crate) mod security;
 pub(crate) mod skills;
+pub(crate) mod static_site;
 pub(crate) mod storage;
 pub(crate) mod testimonials;
+pub(crate) mod users;
 pub(crate) mod validations;
@@ -0,0 +1,104 @@
+use crate::constants;
+use crate::database::db::DbClient;
+use crate::errors::auth::AuthError;
+use crate::models::passkey::{
+    Passkey, PasskeyCeremony, PasskeyChallenge, PasskeyCreationOptions,
+    PasskeyCredentialDescriptor, PasskeyRequestOptions,
+};
+use crate::models::user::User;
+use crate::security::webauthn;
+
+use bson::oid::ObjectId;
+
+#[inline]
+fn get_credential_descriptors(passkeys: &[Passkey]) -> Vec<PasskeyCredentialDescriptor> {
+    passkeys
+        .iter()
+        .map(|passkey| PasskeyCredentialDescriptor {
+            id: passkey.id.clone(),
+            transports: passkey.transports.clone(),
+        })
+        .collect()
+}
+
+async fn insert_challenge(
+    client: &DbClient,
+    ceremony: PasskeyCeremony,
+    user_id: Option<ObjectId>,
+    remember: bool,
+) -> Result<PasskeyChallenge, AuthError> {
+    let challenge = PasskeyChallenge::new(
+        ceremony,
+        webauthn::generate_challenge(),
+        user_id,
+        remember,
+        constants::PASSKEY_TIMEOUT_MS,
+    );
+    client
+        .get_passkey_challenge_collection()
+        .insert_one(&challenge)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to insert passkey challenge into db: {:?}", e);
+            AuthError::InternalServerError
+        })?;
+    Ok(challenge)
+}
+
+/// Creates the challenge for registering a new passkey for the user.
+pub async fn get_creation_options(
+    client: &DbClient,
+    user_id: ObjectId,
+    username: &str,
+    passkeys: &[Passkey],
+) -> Result<PasskeyCreationOptions, AuthError> {
+    if passkeys.len() >= constants::MAX_PASSKEYS {
+        return Err(AuthError::TooManyPasskeys);
+    }
+
+    let challenge =
+        insert_challenge(client, PasskeyCeremony::Registration, Some(user_id), false).await?;
+    Ok(PasskeyCreationOptions {
+        challenge_id: challenge._id.to_hex(),
+        challenge: webauthn::encode(&challenge.challenge),
+        rp_id: constants::get_domain(),
+        rp_name: constants::WEBAUTHN_RP_NAME.to_string(),
+        user_id: webauthn::encode(&user_id.bytes()),
+        user_name: username.to_string(),
+        alg: webauthn::COSE_ALG_ES256,
+        timeout: constants::PASSKEY_TIMEOUT_MS,
+        exclude_credentials: get_credential_descriptors(passkeys),
+    })
+}
+
+/// Creates the challenge for signing in with a passkey.
+///
+/// When the user is given, the passkey is used as a second factor after
+/// their password has been verified so only their passkeys are allowed.
+/// Otherwise, the user is identified by a discoverable passkey which
+/// must also verify the user since there is no password.
+pub async fn get_request_options(
+    client: &DbClient,
+    user: Option<&User>,
+    remember: bool,
+) -> Result<PasskeyRequestOptions, AuthError> {
+    let challenge = insert_challenge(
+        client,
+        PasskeyCeremony::Authentication,
+        user.map(|user| user._id),
+        remember,
+    )
+    .await?;
+    let (user_verification, allow_credentials) = match user {
+        Some(user) => ("preferred", get_credential_descriptors(user.get_passkeys())),
+        None => ("required", vec![]),
+    };
+    Ok(PasskeyRequestOptions {
+        challenge_id: challenge._id.to_hex(),
+        challenge: webauthn::encode(&challenge.challenge),
+        rp_id: constants::get_domain(),
+        timeout: constants::PASSKEY_TIMEOUT_MS,
+        user_verification,
+        allow_credentials,
+    })
+}
@@ -0,0 +1,166 @@
+use crate::constants;
+use crate::database::db::DbClient;
+use crate::errors::blog::BlogError;
+use crate::models::preview_link::{self, PreviewClaim, PreviewLink};
+use crate::security::keyring::KeyedSigner;
+use crate::templates::admin::PreviewLinkInfo;
+use crate::utils::security::get_default_signer;
+
+use actix_web::HttpRequest;
+use bson::oid::ObjectId;
+use futures_util::TryStreamExt;
+use mongodb::bson::doc;
+use mongodb::options::FindOptions;
+use once_cell::sync::Lazy;
+
+static PREVIEW_SIGNER: Lazy<KeyedSigner> = Lazy::new(|| {
+    get_default_signer(
+        constants::get_secret_key_salt(),
+        b"blog-preview".to_vec(),
+        hmac_serialiser::algorithm::Algorithm::SHA512,
+    )
+});
+
+#[inline]
+fn sign_preview_link(link: &PreviewLink) -> String {
+    let claim = PreviewClaim {
+        link_id: link._id,
+        blog_id: link.blog_id,
+        expiry: link.expiry,
+    };
+    PREVIEW_SIGNER.sign(&claim)
+}
+
+fn get_preview_url(req: &HttpRequest, link: &PreviewLink) -> String {
+    let conn_info = req.connection_info();
+    format!(
+        "{}://{}/blogs/{}?preview={}",
+        conn_info.scheme(),
+        conn_info.host(),
+        link.blog_id.to_hex(),
+        sign_preview_link(link),
+    )
+}
+
+pub async fn create_preview_link(
+    db_client: &DbClient,
+    blog_id: ObjectId,
+    days: i64,
+) -> Result<PreviewLink, BlogError> {
+    let blog_count = db_client
+        .get_blog_collection()
+        .count_documents(doc! {"_id": blog_id})
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blog from database: {:?}", e);
+            BlogError::InternalServerError
+        })?;
+    if blog_count == 0 {
+        return Err(BlogError::BlogNotFound);
+    }
+
+    let created = chrono::Utc::now();
+    let link = PreviewLink {
+        _id: ObjectId::new(),
+        blog_id,
+        created,
+        expiry: created + chrono::Duration::days(days),
+    };
+    db_client
+        .get_preview_link_collection()
+        .insert_one(&link)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to insert preview link: {:?}", e);
+            BlogError::InternalServerError
+        })?;
+    Ok(link)
+}
+
+pub async fn get_preview_links(
+    db_client: &DbClient,
+    req: &HttpRequest,
+    blog_id: &ObjectId,
+) -> Result<Vec<PreviewLinkInfo>, BlogError> {
+    let options = FindOptions::builder().sort(doc! {"_id": -1}).build();
+    let links: Vec<PreviewLink> = db_client
+        .get_preview_link_collection()
+        .find(doc! {preview_link::BLOG_ID_KEY: blog_id})
+        .with_options(options)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get preview links from database: {:?}", e);
+            BlogError::InternalServerError
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get preview links from database: {:?}", e);
+            BlogError::InternalServerError
+        })?;
+
+    // the TTL monitor only runs periodically so expired links may not have been deleted yet
+    let now = chrono::Utc::now();
+    let links = links
+        .into_iter()
+        .filter(|link| link.expiry > now)
+        .map(|link| PreviewLinkInfo {
+            id: link._id.to_hex(),
+            url: get_preview_url(req, &link),
+            created: link.created.to_rfc3339(),
+            expiry: link.expiry.to_rfc3339(),
+        })
+        .collect();
+    Ok(links)
+}
+
+pub async fn revoke_preview_link(
+    db_client: &DbClient,
+    blog_id: &ObjectId,
+    link_id: &ObjectId,
+) -> Result<(), BlogError> {
+    db_client
+        .get_preview_link_collection()
+        .delete_one(doc! {"_id": link_id, preview_link::BLOG_ID_KEY: blog_id})
+        .await
+        .map_err(|e| {
+            log::error!("Failed to delete preview link: {:?}", e);
+            BlogError::InternalServerError
+        })?;
+    Ok(())
+}
+
+/// Returns true if the token is a valid preview token for the blog post
+/// and its link has not been revoked or expired.
+pub async fn verify_preview_token(db_client: &DbClient, blog_id: &ObjectId, token: &str) -> bool {
+    let claim = match PREVIEW_SIGNER.unsign::<PreviewClaim>(token) {
+        Ok(claim) => claim,
+        Err(_) => return false,
+    };
+    if &claim.blog_id != blog_id {
+        return false;
+    }
+
+    match db_client
+        .get_preview_link_collection()
+        .find_one(doc! {"_id": claim.link_id, preview_link::BLOG_ID_KEY: blog_id})
+        .await
+    {
+        Ok(Some(link)) => link.expiry > chrono::Utc::now(),
+        Ok(None) => false,
+        Err(e) => {
+            log::error!("Failed to get preview link from database: {:?}", e);
+            false
+        }
+    }
+}
+
+pub async fn revoke_blog_preview_links(db_client: &DbClient, blog_id: &ObjectId) {
+    if let Err(e) = db_client
+        .get_preview_link_collection()
+        .delete_many(doc! {preview_link::BLOG_ID_KEY: blog_id})
+        .await
+    {
+        log::error!("Failed to delete preview links: {:?}", e);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::constants;
 use crate::middleware::csrf;
+use crate::security::keyring::{self, KeyedSigner};
 use crate::utils::auth::is_logged_in;
 
 use actix_web::dev::ServiceRequest;
@@ -8,12 +9,18 @@ use actix_web::{HttpMessage, HttpRequest};
 use rand::Rng as _;
 
 #[inline]
-pub fn get_default_key_info(salt: Vec<u8>, info: Vec<u8>) -> hmac_serialiser::KeyInfo {
-    hmac_serialiser::KeyInfo {
-        key: constants::get_secret_key(),
+pub fn get_default_signer(
+    salt: Vec<u8>,
+    info: Vec<u8>,
+    algo: hmac_serialiser::algorithm::Algorithm,
+) -> KeyedSigner {
+    KeyedSigner::new(
+        keyring::get_secret_keyring(),
         salt,
         info,
-    }
+        algo,
+        hmac_serialiser::Encoder::UrlSafeNoPadding,
+    )
 }
 
 // https://rust-random.github.io/book/guide-rngs.html
@@ -89,6 +96,18 @@ pub struct TemplateValues {
     pub is_logged_in: bool,
 }
 
+/// Returns the values for a page rendered outside of a request, e.g. for the
+/// static site export, which is always rendered for a guest without a nonce.
+pub fn get_guest_template_values() -> TemplateValues {
+    TemplateValues {
+        nonce: String::new(),
+        csrf_header: constants::CSRF_HEADER_NAME.to_string(),
+        csrf_value: String::new(),
+        csrf_header_json: String::new(),
+        is_logged_in: false,
+    }
+}
+
 pub fn extract_for_template(req: &HttpRequest) -> TemplateValues {
     let nonce = {
         let extensions = req.extensions();
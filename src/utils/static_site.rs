@@ -0,0 +1,367 @@
+use crate::constants;
+use crate::database::db::DbClient;
+use crate::models::blog::{self, Blog};
+use crate::models::static_export::StaticExportReport;
+use crate::templates::error::ErrorTemplate;
+use crate::templates::general::{
+    Awards, BlogPost, BlogPostInfo, Blogs, Certificates, Experiences, Index, Projects, Resume,
+    Skills, Testimonials,
+};
+use crate::utils::awards::get_awards;
+use crate::utils::certificates::get_certificates;
+use crate::utils::experiences::get_experiences;
+use crate::utils::html::{minify_html, render_template_string};
+use crate::utils::projects::get_projects;
+use crate::utils::security::get_guest_template_values;
+use crate::utils::skills::{
+    get_backend, get_database, get_deployment, get_desktop_apps, get_frontend, get_general,
+    get_languages,
+};
+use crate::utils::testimonials::get_testimonials;
+
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::doc;
+use mongodb::bson::oid::ObjectId;
+use mongodb::options::FindOptions;
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, HashSet};
+use std::io::{Error, Write};
+use std::path::Path;
+
+// the files that are served from outside of the static directory, see client::static_files
+const EXTRA_ASSETS: [(&str, &str); 6] = [
+    ("favicon.ico", "./static/images/favicon.ico"),
+    ("robots.txt", "./seo/robots.txt"),
+    (
+        "static/js/sweetalert2.min.js",
+        "./node_modules/sweetalert2/dist/sweetalert2.min.js",
+    ),
+    (
+        "static/css/sweetalert2.min.css",
+        "./node_modules/sweetalert2/dist/sweetalert2.min.css",
+    ),
+    (
+        "static/js/htmx.org.min.js",
+        "./node_modules/htmx.org/dist/htmx.min.js",
+    ),
+    (
+        "static/js/htmx-ext-response-targets.js",
+        "./node_modules/htmx-ext-response-targets/response-targets.js",
+    ),
+];
+
+struct StaticPage {
+    route: String,
+    html: String,
+    last_modified: DateTime<Utc>,
+    priority: &'static str,
+}
+
+/// Rewrites the links in the page so that it works on a static host.
+///
+/// Exported routes get a trailing slash so that their index.html is served,
+/// links to the site's own domain become relative, and routes that need the
+/// server, like the admin pages and the API, point to the live site instead.
+fn rewrite_links(html: &str, routes: &HashSet<String>) -> String {
+    static LINK_REGEX: Lazy<regex::Regex> = Lazy::new(|| {
+        regex::Regex::new(&format!(
+            r#"(?P<attr>\s(?:href|src|action)=")(?:https://(?:www\.)?{})?(?P<path>/[^"]*)""#,
+            regex::escape(constants::DOMAIN)
+        ))
+        .unwrap()
+    });
+    let live_url = format!("https://{}", constants::DOMAIN);
+    LINK_REGEX
+        .replace_all(html, |caps: &regex::Captures| {
+            let attr = &caps["attr"];
+            let path = &caps["path"];
+            let matched = caps.get(0).unwrap();
+            // the canonical URL must keep pointing to the live site
+            let tag_start = html[..matched.start()].rfind('<').unwrap_or_default();
+            if html[tag_start..matched.start()].contains(r#"rel="canonical""#) {
+                return matched.as_str().to_string();
+            }
+
+            let route_end = path.find(['?', '#']).unwrap_or(path.len());
+            let (route, suffix) = path.split_at(route_end);
+            let route = match route {
+                "/" => route,
+                route => route.trim_end_matches('/'),
+            };
+            let is_file = route.starts_with("/static/")
+                || Path::new(route).extension().is_some()
+                || route.starts_with("//");
+            if is_file {
+                format!(r#"{}{}""#, attr, path)
+            } else if route == "/" {
+                format!(r#"{}/{}""#, attr, suffix)
+            } else if routes.contains(route) {
+                format!(r#"{}{}/{}""#, attr, route, suffix)
+            } else {
+                format!(r#"{}{}{}""#, attr, live_url, path)
+            }
+        })
+        .into_owned()
+}
+
+fn get_portfolio_pages() -> Vec<StaticPage> {
+    let now = Utc::now();
+    let page = |route: &str, html: String, priority| StaticPage {
+        route: route.to_string(),
+        html,
+        last_modified: now,
+        priority,
+    };
+    vec![
+        page(
+            "/",
+            render_template_string(Index {
+                common: get_guest_template_values(),
+            }),
+            "1.00",
+        ),
+        page(
+            "/resume",
+            render_template_string(Resume {
+                common: get_guest_template_values(),
+            }),
+            "0.80",
+        ),
+        page(
+            "/experiences",
+            render_template_string(Experiences {
+                common: get_guest_template_values(),
+                experiences: get_experiences(),
+            }),
+            "0.80",
+        ),
+        page(
+            "/testimonials",
+            render_template_string(Testimonials {
+                common: get_guest_template_values(),
+                testimonials: get_testimonials(),
+            }),
+            "0.80",
+        ),
+        page(
+            "/projects",
+            render_template_string(Projects {
+                common: get_guest_template_values(),
+                projects: get_projects(),
+            }),
+            "0.80",
+        ),
+        page(
+            "/skills",
+            render_template_string(Skills {
+                common: get_guest_template_values(),
+                languages: get_languages(),
+                backend: get_backend(),
+                frontend: get_frontend(),
+                desktop_apps: get_desktop_apps(),
+                database: get_database(),
+                deployment: get_deployment(),
+                general: get_general(),
+            }),
+            "0.80",
+        ),
+        page(
+            "/certificates",
+            render_template_string(Certificates {
+                common: get_guest_template_values(),
+                certificates: get_certificates(),
+            }),
+            "0.80",
+        ),
+        page(
+            "/awards",
+            render_template_string(Awards {
+                common: get_guest_template_values(),
+                awards: get_awards(),
+            }),
+            "0.80",
+        ),
+    ]
+}
+
+async fn get_public_blogs(db_client: &DbClient) -> std::io::Result<Vec<Blog>> {
+    let find_options = FindOptions::builder().sort(doc! { "_id": -1 }).build();
+    db_client
+        .get_blog_collection()
+        .find(doc! {blog::IS_PUBLIC_KEY: true})
+        .with_options(find_options)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blog posts from database: {:?}", e);
+            Error::other(e)
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get blog posts from database: {:?}", e);
+            Error::other(e)
+        })
+}
+
+fn get_blog_pages(blogs: &[Blog], authors: &HashMap<ObjectId, String>) -> Vec<StaticPage> {
+    let mut pages = Vec::with_capacity(blogs.len() + 1);
+    let blog_infos = blogs
+        .iter()
+        .map(|blog_post| BlogPostInfo {
+            id: blog_post.get_id_string(),
+            title: blog_post.title.clone(),
+            date: blog_post.get_date_string(),
+            views: blog_post.views,
+            tags: blog_post.tags.clone(),
+        })
+        .collect();
+    pages.push(StaticPage {
+        route: "/blogs".to_string(),
+        html: render_template_string(Blogs {
+            common: get_guest_template_values(),
+            blogs: blog_infos,
+        }),
+        last_modified: Utc::now(),
+        priority: "0.80",
+    });
+
+    for blog_post in blogs {
+        let html = render_template_string(BlogPost {
+            common: get_guest_template_values(),
+            id: &blog_post.get_id_string(),
+            title: &blog_post.title,
+            seo_desc: &blog_post.seo_desc,
+            date: &blog_post.get_date_string(),
+            readable_date: &blog_post.get_readable_date_diff(),
+            last_modified: &blog_post.get_last_modified_date_string(),
+            views: blog_post.views,
+            content: &blog_post.get_html_content(),
+            public: blog_post.is_public,
+            tags: &blog_post.tags,
+            preview: false,
+            author: blog_post
+                .author_id
+                .and_then(|author_id| authors.get(&author_id))
+                .map(String::as_str),
+        });
+        pages.push(StaticPage {
+            route: format!("/blogs/{}", blog_post.get_id_string()),
+            html,
+            last_modified: blog_post.last_modified.unwrap_or(blog_post.timestamp),
+            priority: "0.64",
+        });
+    }
+    pages
+}
+
+fn get_sitemap(pages: &[StaticPage]) -> String {
+    let mut sitemap = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for page in pages {
+        sitemap.push_str(&format!(
+            "<url>\n  <loc>https://{}{}</loc>\n  <lastmod>{}</lastmod>\n  <priority>{}</priority>\n</url>\n",
+            constants::DOMAIN,
+            page.route,
+            page.last_modified.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            page.priority
+        ));
+    }
+    sitemap.push_str("</urlset>\n");
+    sitemap
+}
+
+fn write_file(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::File::create(path)?.write_all(data)
+}
+
+/// Copies the files in the directory recursively and returns the number of files copied.
+fn copy_dir(source: &Path, destination: &Path) -> std::io::Result<usize> {
+    let mut copied = 0;
+    std::fs::create_dir_all(destination)?;
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let path = entry.path();
+        let destination = destination.join(entry.file_name());
+        if path.is_dir() {
+            copied += copy_dir(&path, &destination)?;
+        } else {
+            std::fs::copy(&path, &destination)?;
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+#[inline]
+fn get_page_html(html: &str) -> Vec<u8> {
+    if constants::get_minify_html() {
+        minify_html(html)
+    } else {
+        html.as_bytes().to_vec()
+    }
+}
+
+/// Renders every public page into a directory of static HTML files
+/// with the static assets and a sitemap so that it can be served
+/// from any static host as a fallback when the server is down.
+///
+/// Every route is written to its own index.html, e.g. /blogs/{id}
+/// is written to blogs/{id}/index.html.
+pub async fn export_static_site(
+    db_client: &DbClient,
+    output_dir: &Path,
+) -> std::io::Result<StaticExportReport> {
+    let blogs = get_public_blogs(db_client).await?;
+    let author_ids: HashSet<ObjectId> = blogs.iter().filter_map(|blog| blog.author_id).collect();
+    let authors = db_client
+        .get_usernames(author_ids.into_iter().collect())
+        .await;
+    let mut pages = get_portfolio_pages();
+    pages.extend(get_blog_pages(&blogs, &authors));
+    let routes: HashSet<String> = pages.iter().map(|page| page.route.clone()).collect();
+
+    let mut report = StaticExportReport {
+        blogs: blogs.len(),
+        output_dir: output_dir.to_string_lossy().to_string(),
+        ..Default::default()
+    };
+    for page in pages.iter() {
+        let html = rewrite_links(&page.html, &routes);
+        let path = output_dir
+            .join(page.route.trim_start_matches('/'))
+            .join("index.html");
+        write_file(&path, &get_page_html(&html))?;
+        report.pages += 1;
+    }
+
+    // most static hosts serve 404.html for missing pages
+    let not_found = render_template_string(ErrorTemplate {
+        common: get_guest_template_values(),
+        status: 404,
+        message: "Page not found",
+    });
+    let not_found = rewrite_links(&not_found, &routes);
+    write_file(&output_dir.join("404.html"), &get_page_html(&not_found))?;
+    write_file(
+        &output_dir.join("sitemap.xml"),
+        get_sitemap(&pages).as_bytes(),
+    )?;
+
+    report.assets += copy_dir(Path::new("./static"), &output_dir.join("static"))?;
+    for (destination, source) in EXTRA_ASSETS {
+        match std::fs::read(source) {
+            Ok(data) => {
+                write_file(&output_dir.join(destination), &data)?;
+                report.assets += 1;
+            }
+            Err(e) => log::warn!("Failed to copy {} for the static export: {:?}", source, e),
+        }
+    }
+    Ok(report)
+}
@@ -20,6 +20,100 @@ pub async fn upload_blob(client: &Client, bucket: &str, obj_name: &str, data: Ve
         })
 }
 
+pub async fn download_blob(client: &Client, bucket: &str, obj_name: &str) -> Option<Vec<u8>> {
+    let output = match client
+        .get_object()
+        .bucket(bucket)
+        .key(obj_name)
+        .send()
+        .await
+    {
+        Ok(output) => output,
+        Err(e) => {
+            log::error!("Failed to download blob: {:?}", e);
+            return None;
+        }
+    };
+    match output.body.collect().await {
+        Ok(data) => Some(data.into_bytes().to_vec()),
+        Err(e) => {
+            log::error!("Failed to read blob: {:?}", e);
+            None
+        }
+    }
+}
+
+/// Downloads the blob along with its ETag for a conditional upload with [`upload_blob_if_match`].
+///
+/// Returns `Ok(None)` if the blob does not exist.
+pub async fn download_blob_with_etag(
+    client: &Client,
+    bucket: &str,
+    obj_name: &str,
+) -> Result<Option<(Vec<u8>, String)>, ()> {
+    let output = match client
+        .get_object()
+        .bucket(bucket)
+        .key(obj_name)
+        .send()
+        .await
+    {
+        Ok(output) => output,
+        Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => return Ok(None),
+        Err(e) => {
+            log::error!("Failed to download blob: {:?}", e);
+            return Err(());
+        }
+    };
+    let etag = output.e_tag.unwrap_or_default();
+    match output.body.collect().await {
+        Ok(data) => Ok(Some((data.into_bytes().to_vec(), etag))),
+        Err(e) => {
+            log::error!("Failed to read blob: {:?}", e);
+            Err(())
+        }
+    }
+}
+
+pub enum ConditionalUpload {
+    Uploaded,
+    // the blob was changed or created since it was read
+    PreconditionFailed,
+    Failed,
+}
+
+/// Uploads the blob only if it still has the given ETag, or only if
+/// it does not exist yet when no ETag is given.
+pub async fn upload_blob_if_match(
+    client: &Client,
+    bucket: &str,
+    obj_name: &str,
+    data: Vec<u8>,
+    etag: Option<&str>,
+) -> ConditionalUpload {
+    let request = client
+        .put_object()
+        .bucket(bucket)
+        .key(obj_name)
+        .body(ByteStream::from(data));
+    let request = match etag {
+        Some(etag) => request.if_match(etag),
+        None => request.if_none_match("*"),
+    };
+    match request.send().await {
+        Ok(_) => ConditionalUpload::Uploaded,
+        Err(e) => {
+            let status = e.raw_response().map(|response| response.status().as_u16());
+            // 409 is returned if another conditional upload of the blob is in progress
+            if matches!(status, Some(412) | Some(409)) {
+                return ConditionalUpload::PreconditionFailed;
+            }
+            log::error!("Failed to upload blob: {:?}", e);
+            ConditionalUpload::Failed
+        }
+    }
+}
+
 pub async fn copy_blob(
     client: &Client,
     src_bucket: &str,
@@ -53,6 +147,60 @@ pub async fn delete_blob(client: &Client, bucket: &str, name: &str) -> bool {
         .is_ok();
 }
 
+pub async fn blob_exists(client: &Client, bucket: &str, name: &str) -> bool {
+    client
+        .head_object()
+        .bucket(bucket)
+        .key(name)
+        .send()
+        .await
+        .is_ok()
+}
+
+pub struct BlobInfo {
+    pub name: String,
+    pub size: i64,
+    pub last_modified: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Lists every object in the bucket whose name starts with the given prefix.
+///
+/// Returns `None` if any page of the listing fails so that callers
+/// never act on a partial view of the bucket.
+pub async fn list_blobs(client: &Client, bucket: &str, prefix: &str) -> Option<Vec<BlobInfo>> {
+    let mut blobs = Vec::new();
+    let mut pages = client
+        .list_objects_v2()
+        .bucket(bucket)
+        .prefix(prefix)
+        .into_paginator()
+        .send();
+    while let Some(page) = pages.next().await {
+        let page = match page {
+            Ok(page) => page,
+            Err(e) => {
+                log::error!("Failed to list blobs: {:?}", e);
+                return None;
+            }
+        };
+        for obj in page.contents() {
+            let name = match obj.key() {
+                Some(key) => key.to_string(),
+                None => continue,
+            };
+            let last_modified = obj
+                .last_modified()
+                .and_then(|dt| chrono::DateTime::from_timestamp(dt.secs(), dt.subsec_nanos()));
+            blobs.push(BlobInfo {
+                name,
+                size: obj.size().unwrap_or_default(),
+                last_modified,
+            });
+        }
+    }
+    Some(blobs)
+}
+
 pub async fn get_signed_url(client: &Client, bucket: &str, obj_name: &str) -> String {
     let presigning_config = PresigningConfig::expires_in(constants::SIGNED_URL_MAX_AGE).unwrap();
     client
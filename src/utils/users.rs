@@ -0,0 +1,418 @@
+use crate::constants;
+use crate::database::db::DbClient;
+use crate::errors::auth::AuthError;
+use crate::models::invite::{self, AcceptInvite, Invite, InviteClaim};
+use crate::models::role::Role;
+use crate::models::{session, user, user::User};
+use crate::security::keyring::KeyedSigner;
+use crate::security::pw_hasher;
+use crate::templates::admin_profile::{InviteInfo, UserInfo};
+use crate::utils::security::get_default_signer;
+
+use actix_web::{web, HttpRequest};
+use bson::oid::ObjectId;
+use futures_util::TryStreamExt;
+use mongodb::bson::doc;
+use mongodb::error::{ErrorKind, WriteFailure};
+use mongodb::options::{FindOptions, ReturnDocument};
+use once_cell::sync::Lazy;
+
+static INVITE_SIGNER: Lazy<KeyedSigner> = Lazy::new(|| {
+    get_default_signer(
+        constants::get_secret_key_salt(),
+        b"user-invite".to_vec(),
+        hmac_serialiser::algorithm::Algorithm::SHA512,
+    )
+});
+
+fn validate_new_user(username: &str, email: &str, password: &str) -> Result<(), AuthError> {
+    let username_len = username.chars().count();
+    let is_valid_username = (constants::USERNAME_MIN_LENGTH..=constants::USERNAME_MAX_LENGTH)
+        .contains(&username_len)
+        && username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+    if !is_valid_username {
+        return Err(AuthError::InvalidUsername);
+    }
+
+    if email.len() > constants::EMAIL_MAX_LENGTH
+        || email.chars().any(char::is_whitespace)
+        || !email
+            .split_once('@')
+            .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'))
+    {
+        return Err(AuthError::InvalidEmail);
+    }
+
+    validate_password(password)
+}
+
+#[inline]
+fn validate_password(password: &str) -> Result<(), AuthError> {
+    let password_len = password.chars().count();
+    if !(constants::PASSWORD_MIN_LENGTH..=constants::PASSWORD_MAX_LENGTH).contains(&password_len) {
+        return Err(AuthError::InvalidPassword);
+    }
+    Ok(())
+}
+
+async fn hash_new_password(password: &str) -> Result<String, AuthError> {
+    let password = password.to_string();
+    web::block(move || pw_hasher::hash_password(&password).ok())
+        .await
+        .map_err(|e| {
+            log::error!(
+                "Blocking Error when trying to hash user's password: {:?}",
+                e
+            );
+            AuthError::InternalServerError
+        })?
+        .ok_or(AuthError::InternalServerError)
+}
+
+#[inline]
+fn is_duplicate_key_error(e: &mongodb::error::Error) -> bool {
+    matches!(
+        e.kind.as_ref(),
+        ErrorKind::Write(WriteFailure::WriteError(write_error)) if write_error.code == 11000
+    )
+}
+
+pub async fn create_user(
+    db_client: &DbClient,
+    username: &str,
+    email: &str,
+    password: &str,
+    role: Role,
+) -> Result<User, AuthError> {
+    let username = username.trim();
+    let email = email.trim().to_lowercase();
+    validate_new_user(username, &email, password)?;
+
+    let hashed_password = hash_new_password(password).await?;
+
+    let user = User::new(username.to_string(), email, hashed_password, None, role);
+    // the unique indexes on the username and email reject the duplicates
+    match db_client.get_user_collection().insert_one(&user).await {
+        Ok(_) => {
+            log::info!("Created user {} with the {} role", user._id, role);
+            Ok(user)
+        }
+        Err(e) if is_duplicate_key_error(&e) => Err(AuthError::UserAlreadyExists),
+        Err(e) => {
+            log::error!("Failed to insert user: {:?}", e);
+            Err(AuthError::InternalServerError)
+        }
+    }
+}
+
+pub async fn get_users(db_client: &DbClient) -> Result<Vec<UserInfo>, AuthError> {
+    let options = FindOptions::builder().sort(doc! {"_id": 1}).build();
+    let users: Vec<User> = db_client
+        .get_user_collection()
+        .find(doc! {})
+        .with_options(options)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get users from database: {:?}", e);
+            AuthError::InternalServerError
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get users from database: {:?}", e);
+            AuthError::InternalServerError
+        })?;
+
+    let users = users
+        .into_iter()
+        .map(|user| UserInfo {
+            id: user._id.to_hex(),
+            username: user.get_username().to_string(),
+            email: user.get_email().to_string(),
+            role: user.get_role(),
+            disabled: user.is_disabled(),
+        })
+        .collect();
+    Ok(users)
+}
+
+async fn update_user(
+    db_client: &DbClient,
+    actor_id: &ObjectId,
+    user_id: &ObjectId,
+    update: bson::Document,
+) -> Result<(), AuthError> {
+    // prevents the owner from locking themselves out when they are the only owner
+    if actor_id == user_id {
+        return Err(AuthError::CannotModifyOwnAccount);
+    }
+    let result = db_client
+        .get_user_collection()
+        .update_one(doc! {"_id": user_id}, doc! {"$set": update})
+        .await
+        .map_err(|e| {
+            log::error!("Failed to update user: {:?}", e);
+            AuthError::InternalServerError
+        })?;
+    if result.matched_count == 0 {
+        return Err(AuthError::UserNotFound);
+    }
+    Ok(())
+}
+
+pub async fn set_user_role(
+    db_client: &DbClient,
+    actor_id: &ObjectId,
+    user_id: &ObjectId,
+    role: Role,
+) -> Result<(), AuthError> {
+    update_user(
+        db_client,
+        actor_id,
+        user_id,
+        doc! {user::ROLE_KEY: role.to_string()},
+    )
+    .await?;
+
+    // the role is read from the session, so the signed in devices get the new role right away
+    db_client
+        .get_session_collection()
+        .update_many(
+            doc! {session::USER_ID_KEY: user_id},
+            doc! {"$set": {session::ROLE_KEY: role.to_string()}},
+        )
+        .await
+        .map_err(|e| {
+            log::error!("Failed to update the role of user's sessions: {:?}", e);
+            AuthError::InternalServerError
+        })?;
+    log::info!("Changed the role of user {} to {}", user_id, role);
+    Ok(())
+}
+
+pub async fn set_user_disabled(
+    db_client: &DbClient,
+    actor_id: &ObjectId,
+    user_id: &ObjectId,
+    disabled: bool,
+) -> Result<(), AuthError> {
+    update_user(
+        db_client,
+        actor_id,
+        user_id,
+        doc! {user::DISABLED_KEY: disabled},
+    )
+    .await?;
+
+    if disabled {
+        db_client
+            .get_session_collection()
+            .delete_many(doc! {session::USER_ID_KEY: user_id})
+            .await
+            .map_err(|e| {
+                log::error!("Failed to revoke disabled user's sessions: {:?}", e);
+                AuthError::InternalServerError
+            })?;
+        // an imported account would be enabled again by accepting its invite
+        db_client
+            .get_invite_collection()
+            .delete_many(doc! {invite::USER_ID_KEY: user_id})
+            .await
+            .map_err(|e| {
+                log::error!("Failed to revoke disabled user's invites: {:?}", e);
+                AuthError::InternalServerError
+            })?;
+        log::info!("Disabled user {}", user_id);
+    } else {
+        log::info!("Enabled user {}", user_id);
+    }
+    Ok(())
+}
+
+fn get_invite_url(req: &HttpRequest, invite: &Invite) -> String {
+    let claim = InviteClaim {
+        invite_id: invite._id,
+        expiry: invite.expiry,
+    };
+    let conn_info = req.connection_info();
+    format!(
+        "{}://{}/invite?token={}",
+        conn_info.scheme(),
+        conn_info.host(),
+        INVITE_SIGNER.sign(&claim),
+    )
+}
+
+/// Creates an invite for a new account or, if a user is given,
+/// for activating the imported account of that user.
+pub async fn create_invite(
+    db_client: &DbClient,
+    created_by: ObjectId,
+    role: Role,
+    days: i64,
+    user_id: Option<ObjectId>,
+) -> Result<Invite, AuthError> {
+    let created = chrono::Utc::now();
+    let invite = Invite {
+        _id: ObjectId::new(),
+        role,
+        user_id,
+        created_by,
+        created,
+        expiry: created + chrono::Duration::days(days),
+    };
+    db_client
+        .get_invite_collection()
+        .insert_one(&invite)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to insert invite: {:?}", e);
+            AuthError::InternalServerError
+        })?;
+    Ok(invite)
+}
+
+pub async fn get_invites(
+    db_client: &DbClient,
+    req: &HttpRequest,
+) -> Result<Vec<InviteInfo>, AuthError> {
+    let now = bson::DateTime::now();
+    let options = FindOptions::builder().sort(doc! {"_id": -1}).build();
+    let invites: Vec<Invite> = db_client
+        .get_invite_collection()
+        .find(doc! {invite::EXPIRY_KEY: {"$gt": now}})
+        .with_options(options)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get invites from database: {:?}", e);
+            AuthError::InternalServerError
+        })?
+        .try_collect()
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get invites from database: {:?}", e);
+            AuthError::InternalServerError
+        })?;
+
+    let user_ids = invites.iter().filter_map(|invite| invite.user_id).collect();
+    let usernames = db_client.get_usernames(user_ids).await;
+    let invites = invites
+        .into_iter()
+        .map(|invite| InviteInfo {
+            id: invite._id.to_hex(),
+            url: get_invite_url(req, &invite),
+            role: invite.role,
+            username: invite
+                .user_id
+                .and_then(|user_id| usernames.get(&user_id).cloned()),
+            expiry: invite.expiry.to_rfc3339(),
+        })
+        .collect();
+    Ok(invites)
+}
+
+pub async fn revoke_invite(db_client: &DbClient, invite_id: &ObjectId) -> Result<(), AuthError> {
+    db_client
+        .get_invite_collection()
+        .delete_one(doc! {"_id": invite_id})
+        .await
+        .map_err(|e| {
+            log::error!("Failed to delete invite: {:?}", e);
+            AuthError::InternalServerError
+        })?;
+    Ok(())
+}
+
+/// Returns the invite of the token if it has not been accepted, revoked or expired.
+pub async fn get_valid_invite(db_client: &DbClient, token: &str) -> Result<Invite, AuthError> {
+    let claim = INVITE_SIGNER
+        .unsign::<InviteClaim>(token)
+        .map_err(|_| AuthError::InviteNotFound)?;
+    let now = bson::DateTime::now();
+    match db_client
+        .get_invite_collection()
+        .find_one(doc! {"_id": claim.invite_id, invite::EXPIRY_KEY: {"$gt": now}})
+        .await
+    {
+        Ok(Some(invite)) => Ok(invite),
+        Ok(None) => Err(AuthError::InviteNotFound),
+        Err(e) => {
+            log::error!("Failed to get invite from database: {:?}", e);
+            Err(AuthError::InternalServerError)
+        }
+    }
+}
+
+/// Sets the password of an imported account and enables it.
+async fn activate_user(
+    db_client: &DbClient,
+    user_id: &ObjectId,
+    password: &str,
+) -> Result<User, AuthError> {
+    validate_password(password)?;
+    let hashed_password = hash_new_password(password).await?;
+    let user = db_client
+        .get_user_collection()
+        .find_one_and_update(
+            doc! {"_id": user_id},
+            doc! {"$set": {
+                user::PASSWORD_KEY: hashed_password,
+                user::DISABLED_KEY: false,
+            }},
+        )
+        .return_document(ReturnDocument::After)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to activate user: {:?}", e);
+            AuthError::InternalServerError
+        })?
+        .ok_or(AuthError::UserNotFound)?;
+    log::info!("Activated imported user {}", user_id);
+    Ok(user)
+}
+
+/// Creates the account of the invite or activates the imported account that it is for.
+///
+/// The invite is deleted first so that it can only be used once.
+pub async fn accept_invite(db_client: &DbClient, data: &AcceptInvite) -> Result<User, AuthError> {
+    if data.password != data.confirm_password {
+        return Err(AuthError::PasswordMismatch);
+    }
+
+    let claim = INVITE_SIGNER
+        .unsign::<InviteClaim>(&data.token)
+        .map_err(|_| AuthError::InviteNotFound)?;
+    let now = bson::DateTime::now();
+    let invite = db_client
+        .get_invite_collection()
+        .find_one_and_delete(doc! {"_id": claim.invite_id, invite::EXPIRY_KEY: {"$gt": now}})
+        .await
+        .map_err(|e| {
+            log::error!("Failed to delete invite: {:?}", e);
+            AuthError::InternalServerError
+        })?
+        .ok_or(AuthError::InviteNotFound)?;
+
+    let result = match invite.user_id {
+        Some(user_id) => activate_user(db_client, &user_id, &data.password).await,
+        None => {
+            create_user(
+                db_client,
+                &data.username,
+                &data.email,
+                &data.password,
+                invite.role,
+            )
+            .await
+        }
+    };
+    if result.is_err() {
+        // lets the invitee try again, e.g. with another username or email
+        if let Err(insert_err) = db_client.get_invite_collection().insert_one(&invite).await {
+            log::error!("Failed to restore invite: {:?}", insert_err);
+        }
+    }
+    result
+}
@@ -121,6 +121,40 @@ const uploadImage = (file) => {
         });
 }
 
+const mediaPicker = document.getElementById("media-picker");
+
+/**
+ * Inserts a file from the media library into the content
+ * without uploading it again.
+ * 
+ * @param {HTMLButtonElement} btn
+ * @returns {void}
+ */
+const insertMedia = (btn) => {
+    if (fileUploadResponseHandler === null) {
+        throw new Error("fileUploadResponseHandler is null");
+    }
+
+    // stored media is already public so the url can be used as is
+    const file = {
+        name: btn.dataset.name,
+        url: btn.dataset.url,
+        signed_url: btn.dataset.url,
+    };
+    fileUploadResponseHandler(file);
+    const altText = btn.dataset.alt;
+    if (altText && !file.url.endsWith(".mp4")) {
+        content.value += `![${altText}](${file.url})\n`;
+    } else {
+        content.value += parseUrlToMd(file);
+    }
+    content.dispatchEvent(new Event("input", {
+        bubbles: true,
+        cancelable: true,
+    }));
+    mediaPicker.close();
+};
+
 /* Fn mainly for the new blog route */
 
 /**
@@ -170,6 +204,54 @@ const parseFileSliceForUpload = (fileSlice) => {
 
 /* End of file fn for the new blog route */
 
+/**
+ * @typedef {object} LintIssue
+ * @property {string} rule
+ * @property {"warning" | "error"} level
+ * @property {number | null} line
+ * @property {string} message
+ */
+/**
+ * Formats the lint issue for displaying to the user.
+ *
+ * @param {LintIssue} issue
+ * @returns {string}
+ */
+const formatLintIssue = (issue) => {
+    const location = issue.line === null ? "" : `Line ${issue.line}: `;
+    return `${location}${issue.message} (${issue.rule})`;
+};
+
+/**
+ * Shows the lint issues that blocked publishing the blog.
+ *
+ * @param {Response} response
+ * @returns {Promise<boolean>} true if the user wants to publish anyway
+ */
+const confirmLintOverride = async (response) => {
+    /** @type {{issues: LintIssue[]}} */
+    const report = await response.json();
+    const list = document.createElement("ul");
+    list.className = "text-left text-sm";
+    report.issues.forEach((issue) => {
+        const li = document.createElement("li");
+        li.className = issue.level === "error" ? "text-error" : "text-warning";
+        li.innerText = formatLintIssue(issue);
+        list.appendChild(li);
+    });
+
+    const result = await Swal.fire({
+        icon: "warning",
+        title: "Fix these issues before publishing",
+        html: list,
+        width: "48rem",
+        showCancelButton: true,
+        confirmButtonText: "Publish Anyway",
+        cancelButtonText: "Keep Editing",
+    });
+    return result.isConfirmed;
+};
+
 const previewDiv = document.getElementById("preview");
 const editBtnEvt = () => {
     editDiv.classList.remove("hidden");
@@ -178,11 +260,11 @@ const editBtnEvt = () => {
 const previewBtnEvt = () => {
     editDiv.classList.add("hidden");
     previewDiv.classList.remove("hidden");
+    refreshPreview();
 };
 
 let useLocalStorage = true;
 const content = document.getElementById("content");
-const contentPreview = document.getElementById("content-preview");
 
 /**
  * Updates the content of the blog
@@ -192,14 +274,16 @@ const contentPreview = document.getElementById("content-preview");
  */
 const updateContent = (value) => {
     content.value = value;
-    contentPreview.value = value;
+    // the live preview script is loaded after this script
+    if (typeof sendPreviewEdit === "function") {
+        sendPreviewEdit();
+    }
 }
 content.addEventListener("input", () => {
     const value = content.value;
     if (useLocalStorage) {
         localStorage.setItem(contentKey, value);
     }
-    contentPreview.value = value;
 });
 
 const title = document.getElementById("title");
@@ -0,0 +1,229 @@
+// Requires blog.js and the csrfHeaderName and csrfValue to be set before this script.
+
+const previewProtocol = "blog-preview";
+const previewReconnectDelay = 5000; // 5 seconds
+const previewSendDelay = 150; // 150ms
+// the server closes the connection after no edits for a while
+const previewIdleCloseCode = 4000;
+// or once the session has been logged out or revoked
+const previewPolicyCloseCode = 1008;
+
+const blogContent = document.getElementById("blog-content");
+const previewWarnings = document.getElementById("preview-warnings");
+
+/** @type {WebSocket | null} */
+let previewSocket = null;
+let previewRev = 0;
+// the content that the server has after applying all the sent edits
+let previewSynced = "";
+/** @type {Map<string, string>} */
+let previewFragments = new Map();
+let previewSendTimeout = null;
+let previewIsIdle = false;
+
+/**
+ * @param {object} msg
+ * @returns {void}
+ */
+const sendPreviewMsg = (msg) => {
+    if (previewSocket === null || previewSocket.readyState !== WebSocket.OPEN) {
+        return;
+    }
+    previewSocket.send(JSON.stringify(msg));
+};
+
+const sendFullPreview = () => {
+    previewRev++;
+    previewSynced = content.value;
+    previewFragments.clear();
+    sendPreviewMsg({
+        type: "full",
+        rev: previewRev,
+        content: previewSynced,
+    });
+};
+
+const isHighSurrogate = (code) => code >= 0xD800 && code <= 0xDBFF;
+const isLowSurrogate = (code) => code >= 0xDC00 && code <= 0xDFFF;
+
+/**
+ * Sends only the changed range of the content to the server.
+ *
+ * @returns {void}
+ */
+const sendPreviewEdit = () => {
+    if (previewSocket === null) {
+        return;
+    }
+    const oldValue = previewSynced;
+    const newValue = content.value;
+    if (oldValue === newValue) {
+        return;
+    }
+
+    let start = 0;
+    while (start < oldValue.length && start < newValue.length && oldValue[start] === newValue[start]) {
+        start++;
+    }
+    let oldEnd = oldValue.length;
+    let newEnd = newValue.length;
+    while (oldEnd > start && newEnd > start && oldValue[oldEnd - 1] === newValue[newEnd - 1]) {
+        oldEnd--;
+        newEnd--;
+    }
+    // do not split a surrogate pair as the server works with whole characters
+    if (start > 0 && isHighSurrogate(oldValue.charCodeAt(start - 1))) {
+        start--;
+    }
+    if (oldEnd < oldValue.length && isLowSurrogate(oldValue.charCodeAt(oldEnd))) {
+        oldEnd++;
+        newEnd++;
+    }
+
+    previewRev++;
+    previewSynced = newValue;
+    sendPreviewMsg({
+        type: "edit",
+        rev: previewRev,
+        start: start,
+        delete: oldEnd - start,
+        text: newValue.slice(start, newEnd),
+    });
+};
+
+/**
+ * @param {LintIssue[]} issues
+ * @returns {void}
+ */
+const renderPreviewWarnings = (issues) => {
+    previewWarnings.replaceChildren();
+    previewWarnings.classList.toggle("hidden", issues.length === 0);
+    issues.forEach((issue) => {
+        const li = document.createElement("li");
+        li.className = issue.level === "error" ? "text-error" : "text-warning";
+        li.innerText = formatLintIssue(issue);
+        previewWarnings.appendChild(li);
+    });
+};
+
+/**
+ * Replaces the blocks that changed and keeps the rest of the rendered content as it is.
+ *
+ * @param {string[]} blocks
+ * @param {Object<string, string>} fragments
+ * @returns {void}
+ */
+const renderPreviewBlocks = (blocks, fragments) => {
+    Object.entries(fragments).forEach(([key, html]) => previewFragments.set(key, html));
+
+    /** @type {Map<string, HTMLElement[]>} */
+    const existing = new Map();
+    blogContent.querySelectorAll(":scope > [data-block]").forEach((el) => {
+        const els = existing.get(el.dataset.block) ?? [];
+        els.push(el);
+        existing.set(el.dataset.block, els);
+    });
+
+    const children = blocks.map((key) => {
+        const reused = existing.get(key)?.shift();
+        if (reused) {
+            return reused;
+        }
+        const el = document.createElement("div");
+        el.dataset.block = key;
+        // keep the blocks as direct children of the content for the styling
+        el.style.display = "contents";
+        el.innerHTML = previewFragments.get(key) ?? "";
+        return el;
+    });
+    blogContent.replaceChildren(...children);
+
+    const keys = new Set(blocks);
+    [...previewFragments.keys()].forEach((key) => {
+        if (!keys.has(key)) {
+            previewFragments.delete(key);
+        }
+    });
+};
+
+/**
+ * @param {MessageEvent} event
+ * @returns {void}
+ */
+const handlePreviewUpdate = (event) => {
+    const update = JSON.parse(event.data);
+    switch (update.type) {
+        case "render":
+            renderPreviewBlocks(update.blocks, update.fragments);
+            renderPreviewWarnings(update.warnings);
+            break;
+        case "resync":
+            sendFullPreview();
+            break;
+        case "error":
+            console.error(update.message);
+            break;
+    }
+};
+
+const connectLivePreview = () => {
+    const scheme = window.location.protocol === "https:" ? "wss" : "ws";
+    // the CSRF token is sent as a subprotocol since WebSockets cannot have custom headers
+    const socket = new WebSocket(
+        `${scheme}://${window.location.host}/api/admin/ws/blog/preview`,
+        [previewProtocol, csrfValue],
+    );
+    socket.addEventListener("open", () => {
+        previewSocket = socket;
+        sendFullPreview();
+    });
+    socket.addEventListener("message", handlePreviewUpdate);
+    socket.addEventListener("close", (event) => {
+        previewSocket = null;
+        if (event.code === previewIdleCloseCode) {
+            // reconnected on the next edit
+            previewIsIdle = true;
+            return;
+        }
+        if (event.code === previewPolicyCloseCode) {
+            // falls back to the normal requests, which will ask the user to sign in again
+            return;
+        }
+        setTimeout(connectLivePreview, previewReconnectDelay);
+    });
+};
+
+/**
+ * Renders the whole content with a normal request when the live preview is not connected.
+ *
+ * @returns {Promise<void>}
+ */
+const refreshPreview = async () => {
+    if (previewSocket !== null) {
+        sendPreviewEdit();
+        return;
+    }
+
+    const response = await fetch("/api/admin/ws/blog/preview", {
+        method: "POST",
+        headers: {
+            [csrfHeaderName]: csrfValue,
+        },
+        body: new URLSearchParams({ content: content.value }),
+    });
+    if (!response.ok) {
+        console.error("Failed to render the preview!");
+        return;
+    }
+    blogContent.innerHTML = await response.text();
+};
+
+content.addEventListener("input", () => {
+    if (previewIsIdle) {
+        previewIsIdle = false;
+        connectLivePreview();
+    }
+    clearTimeout(previewSendTimeout);
+    previewSendTimeout = setTimeout(sendPreviewEdit, previewSendDelay);
+});
+connectLivePreview();
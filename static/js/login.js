@@ -1,4 +1,7 @@
 const totpInputDiv = document.getElementById("totp-input-div");
+const loginForm = document.getElementById("login-form");
+const loginErrorAlert = document.getElementById("error-alert");
+const passkeyUrl = loginForm.dataset.passkeyUrl;
 
 /**
  * @typedef {Object} detail
@@ -17,11 +20,73 @@ const handleLoginRequest = (e) => {
     const detail = e.detail;
     const xhr = detail.xhr;
     const loginError = xhr.getResponseHeader("X-Login-Error");
-    if (loginError === "MissingTotp" || loginError === "InvalidTotp") {
+    if (
+        loginError === "MissingTotp" ||
+        loginError === "InvalidTotp" ||
+        (loginError === "MissingPasskey" && xhr.getResponseHeader("X-Totp-Enabled") === "true")
+    ) {
         totpInputDiv.classList.remove("hidden")
     } else {
         totpInputDiv.classList.add("hidden")
     }
 
     turnstile.reset();
+    if (loginError === "MissingPasskey" && isPasskeySupported()) {
+        confirmWithPasskey(JSON.parse(xhr.getResponseHeader("X-Passkey-Options")));
+    }
 }
+
+/**
+ * Uses a passkey as the second factor after the password has been verified
+ *
+ * @param {PasskeyRequestOptions} options
+ */
+const confirmWithPasskey = async (options) => {
+    const assertion = await getPasskeyAssertion(options);
+    if (assertion === null) {
+        return;
+    }
+    await finishPasskeyLogin(passkeyUrl, assertion, loginErrorAlert);
+};
+
+/**
+ * Signs in with a discoverable passkey without a password
+ *
+ * @param {HTMLButtonElement} btn
+ */
+const signInWithPasskey = async (btn) => {
+    if (!isPasskeySupported()) {
+        return;
+    }
+
+    btn.disabled = true;
+    try {
+        const formData = new FormData(loginForm);
+        const body = new URLSearchParams({
+            "cf-turnstile-response": formData.get("cf-turnstile-response") || "",
+        });
+        if (formData.get("remember")) {
+            body.append("remember", "on");
+        }
+        const optionsRes = await fetch(`${passkeyUrl}/options`, {
+            method: "POST",
+            headers: {
+                [csrfHeaderName]: csrfValue,
+            },
+            body: body,
+        });
+        if (!optionsRes.ok) {
+            loginErrorAlert.innerHTML = await optionsRes.text();
+            return;
+        }
+
+        const assertion = await getPasskeyAssertion(await optionsRes.json());
+        if (assertion === null) {
+            return;
+        }
+        await finishPasskeyLogin(passkeyUrl, assertion, loginErrorAlert);
+    } finally {
+        btn.disabled = false;
+        turnstile.reset();
+    }
+};
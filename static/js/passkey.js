@@ -0,0 +1,219 @@
+// Requires the csrfHeaderName and csrfValue to be set before calling the functions below.
+
+/**
+ * @param {string} value
+ * @returns {ArrayBuffer}
+ */
+const base64UrlToBuffer = (value) => {
+    const base64 = value.replace(/-/g, "+").replace(/_/g, "/");
+    const padded = base64.padEnd(base64.length + (4 - (base64.length % 4)) % 4, "=");
+    return Uint8Array.from(atob(padded), (c) => c.charCodeAt(0)).buffer;
+};
+
+/**
+ * @param {ArrayBuffer} buffer
+ * @returns {string}
+ */
+const bufferToBase64Url = (buffer) => {
+    const bytes = new Uint8Array(buffer);
+    let binary = "";
+    for (const byte of bytes) {
+        binary += String.fromCharCode(byte);
+    }
+    return btoa(binary).replace(/\+/g, "-").replace(/\//g, "_").replace(/=+$/, "");
+};
+
+/**
+ * @typedef {Object} PasskeyDescriptor
+ * @property {string} id
+ * @property {string[]} transports
+ */
+/**
+ * @param {PasskeyDescriptor[]} descriptors
+ */
+const toCredentialDescriptors = (descriptors) => {
+    return descriptors.map((descriptor) => ({
+        type: "public-key",
+        id: base64UrlToBuffer(descriptor.id),
+        transports: descriptor.transports,
+    }));
+};
+
+/**
+ * @param {string} msg
+ */
+const showPasskeyError = (msg) => {
+    Swal.fire({
+        icon: "error",
+        title: "Oops...",
+        text: msg,
+    });
+};
+
+/**
+ * Checks if the browser supports passkeys and shows an error if it does not
+ *
+ * @returns {boolean}
+ */
+const isPasskeySupported = () => {
+    if (window.PublicKeyCredential && navigator.credentials) {
+        return true;
+    }
+    showPasskeyError("Your browser does not support passkeys!");
+    return false;
+};
+
+/**
+ * Asks the authenticator to sign the challenge from the server
+ *
+ * @typedef {Object} PasskeyRequestOptions
+ * @property {string} challenge_id
+ * @property {string} challenge
+ * @property {string} rp_id
+ * @property {number} timeout
+ * @property {string} user_verification
+ * @property {PasskeyDescriptor[]} allow_credentials
+ *
+ * @param {PasskeyRequestOptions} options
+ * @returns {Promise<Object|null>} the assertion to send to the server or null if cancelled
+ */
+const getPasskeyAssertion = async (options) => {
+    let credential;
+    try {
+        credential = await navigator.credentials.get({
+            publicKey: {
+                challenge: base64UrlToBuffer(options.challenge),
+                rpId: options.rp_id,
+                timeout: options.timeout,
+                userVerification: options.user_verification,
+                allowCredentials: toCredentialDescriptors(options.allow_credentials),
+            },
+        });
+    } catch (err) {
+        console.error("Failed to get passkey assertion", err);
+        return null;
+    }
+
+    const response = credential.response;
+    return {
+        challenge_id: options.challenge_id,
+        id: credential.id,
+        client_data_json: bufferToBase64Url(response.clientDataJSON),
+        authenticator_data: bufferToBase64Url(response.authenticatorData),
+        signature: bufferToBase64Url(response.signature),
+        user_handle: response.userHandle ? bufferToBase64Url(response.userHandle) : null,
+    };
+};
+
+/**
+ * Sends the signed assertion to the server to sign in
+ *
+ * @param {string} passkeyUrl
+ * @param {Object} assertion
+ * @param {HTMLElement} errorEl
+ */
+const finishPasskeyLogin = async (passkeyUrl, assertion, errorEl) => {
+    const response = await fetch(passkeyUrl, {
+        method: "POST",
+        headers: {
+            [csrfHeaderName]: csrfValue,
+            "Content-Type": "application/json",
+        },
+        body: JSON.stringify(assertion),
+    });
+    if (!response.ok) {
+        errorEl.innerHTML = await response.text();
+        return;
+    }
+    window.location.href = response.headers.get("HX-Redirect") || "/";
+};
+
+/**
+ * Registers a new passkey for the logged in user from the profile page
+ *
+ * @param {SubmitEvent} e
+ */
+const registerPasskey = async (e) => {
+    e.preventDefault();
+    if (!isPasskeySupported()) {
+        return;
+    }
+
+    const form = e.target;
+    const alertEl = document.getElementById("passkey-alert");
+    const submitBtn = form.querySelector("button[type='submit']");
+    submitBtn.disabled = true;
+    try {
+        const optionsRes = await fetch("/api/admin/passkeys/options", {
+            method: "POST",
+            headers: {
+                [csrfHeaderName]: csrfValue,
+            },
+        });
+        if (!optionsRes.ok) {
+            alertEl.innerHTML = await optionsRes.text();
+            return;
+        }
+
+        const options = await optionsRes.json();
+        let credential;
+        try {
+            credential = await navigator.credentials.create({
+                publicKey: {
+                    challenge: base64UrlToBuffer(options.challenge),
+                    rp: { id: options.rp_id, name: options.rp_name },
+                    user: {
+                        id: base64UrlToBuffer(options.user_id),
+                        name: options.user_name,
+                        displayName: options.user_name,
+                    },
+                    pubKeyCredParams: [{ type: "public-key", alg: options.alg }],
+                    timeout: options.timeout,
+                    attestation: "none",
+                    authenticatorSelection: {
+                        residentKey: "preferred",
+                        userVerification: "preferred",
+                    },
+                    excludeCredentials: toCredentialDescriptors(options.exclude_credentials),
+                },
+            });
+        } catch (err) {
+            console.error("Failed to create passkey", err);
+            showPasskeyError("The passkey was not created. Please try again.");
+            return;
+        }
+
+        const formData = new FormData(form);
+        const response = credential.response;
+        const registerRes = await fetch("/api/admin/passkeys", {
+            method: "POST",
+            headers: {
+                [csrfHeaderName]: csrfValue,
+                "Content-Type": "application/json",
+            },
+            body: JSON.stringify({
+                "cf-turnstile-response": formData.get("cf-turnstile-response") || "",
+                "current-password": formData.get("current-password"),
+                challenge_id: options.challenge_id,
+                name: formData.get("name"),
+                id: credential.id,
+                client_data_json: bufferToBase64Url(response.clientDataJSON),
+                attestation_object: bufferToBase64Url(response.attestationObject),
+                transports: response.getTransports ? response.getTransports() : [],
+            }),
+        });
+        if (!registerRes.ok) {
+            alertEl.innerHTML = await registerRes.text();
+            return;
+        }
+
+        alertEl.innerHTML = "";
+        const listEl = document.getElementById("passkey-list");
+        listEl.innerHTML = await registerRes.text();
+        htmx.process(listEl);
+        form.reset();
+    } finally {
+        submitBtn.disabled = false;
+        turnstile.reset(form.querySelector(".cf-turnstile"));
+    }
+};
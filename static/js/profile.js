@@ -21,3 +21,149 @@ const handleChangePasswordRes = (e) => {
         changePasswordForm.reset();
     }
 };
+
+/**
+ * Shows the JSON report returned by the backup APIs
+ *
+ * @param {Object} report
+ */
+const showBackupReport = (report) => {
+    const reportEl = document.getElementById("backup-report");
+    reportEl.textContent = JSON.stringify(report, null, 2);
+    reportEl.classList.remove("hidden");
+};
+
+/**
+ * @param {Response} response
+ * @param {string} defaultMsg
+ */
+const showBackupError = async (response, defaultMsg) => {
+    const text = await response.text();
+    Swal.fire({
+        icon: "error",
+        title: "Oops...",
+        text: text || defaultMsg,
+    });
+    console.error(defaultMsg, response);
+};
+
+/**
+ * Downloads a zip archive of the whole site
+ *
+ * @param {HTMLButtonElement} btn
+ */
+const exportSite = async (btn) => {
+    btn.disabled = true;
+    try {
+        const response = await fetch("/api/admin/export", {
+            method: "GET",
+            headers: {
+                [csrfHeaderName]: csrfValue,
+            },
+        });
+        if (!response.ok) {
+            await showBackupError(response, "Failed to export site!");
+            return;
+        }
+
+        const disposition = response.headers.get("Content-Disposition") || "";
+        const match = disposition.match(/filename="?([^"]+)"?/);
+        const url = URL.createObjectURL(await response.blob());
+        const link = document.createElement("a");
+        link.href = url;
+        link.download = match ? match[1] : "export.zip";
+        link.click();
+        URL.revokeObjectURL(url);
+    } finally {
+        btn.disabled = false;
+    }
+};
+
+/**
+ * Restores every blog post from its latest backup
+ *
+ * @param {HTMLButtonElement} btn
+ */
+const restoreBlogBackups = async (btn) => {
+    const result = await Swal.fire({
+        icon: "warning",
+        title: "Restore all blog posts?",
+        text: "Blog posts will be overwritten by their backups.",
+        showCancelButton: true,
+        confirmButtonText: "Restore",
+    });
+    if (!result.isConfirmed) {
+        return;
+    }
+
+    btn.disabled = true;
+    try {
+        const response = await fetch("/api/admin/backups/restore", {
+            method: "POST",
+            headers: {
+                [csrfHeaderName]: csrfValue,
+            },
+        });
+        if (!response.ok) {
+            await showBackupError(response, "Failed to restore blog backups!");
+            return;
+        }
+        showBackupReport(await response.json());
+    } finally {
+        btn.disabled = false;
+    }
+};
+
+/**
+ * Uploads a zip archive created by the site export
+ *
+ * @param {SubmitEvent} e
+ */
+const importSite = async (e) => {
+    e.preventDefault();
+    const form = e.target;
+    const submitBtn = form.querySelector("button[type='submit']");
+    submitBtn.disabled = true;
+    try {
+        const response = await fetch("/api/admin/import", {
+            method: "POST",
+            headers: {
+                [csrfHeaderName]: csrfValue,
+            },
+            body: new FormData(form),
+        });
+        if (!response.ok) {
+            await showBackupError(response, "Failed to import site!");
+            return;
+        }
+        form.reset();
+        showBackupReport(await response.json());
+    } finally {
+        submitBtn.disabled = false;
+    }
+};
+
+/**
+ * Copies the invite link of the button to the clipboard
+ *
+ * @param {HTMLButtonElement} btn
+ */
+const copyInviteLink = (btn) => {
+    navigator.clipboard.writeText(btn.dataset.url);
+    Swal.fire({
+        icon: "success",
+        title: "Copied!",
+        text: "Invite link copied to clipboard!",
+        timer: 1000,
+        timerProgressBar: true,
+    });
+};
+
+const parseInviteDates = () => {
+    document.querySelectorAll(".invite-date").forEach((date) => {
+        if (date.innerText !== "") {
+            date.innerText = parseDateToLocal(date.innerText, true);
+        }
+    });
+};
+parseInviteDates();
@@ -47,7 +47,13 @@ const get2FaSecretFromServer = async () => {
         return;
     }
 
-    const response = await fetch("/api/admin/generate-2fa", {
+    const algorithmEl = document.getElementById("two-fa-algorithm");
+    const digitsEl = document.getElementById("two-fa-digits");
+    const params = new URLSearchParams({
+        algorithm: algorithmEl ? algorithmEl.value : "SHA1",
+        digits: digitsEl ? digitsEl.value : "6",
+    });
+    const response = await fetch(`/api/admin/generate-2fa?${params}`, {
         method: "GET",
         headers: {
             [csrfHeaderName]: csrfValue,
@@ -70,3 +76,12 @@ const get2FaSecretFromServer = async () => {
     twoFaInpEl.value = data.secret;
     hasGenerated2FaSecret = true;
 };
+
+/**
+ * Generates a new secret when the algorithm or the number of digits
+ * is changed since the QR code contains both of them.
+ */
+const regenerate2FaSecret = async () => {
+    hasGenerated2FaSecret = false;
+    await get2FaSecretFromServer();
+};